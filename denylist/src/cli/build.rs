@@ -0,0 +1,42 @@
+use crate::{
+    cli::{print_json, read_entries},
+    Error, Result,
+};
+use serde_json::json;
+use std::{fs, path::PathBuf};
+use xorf_generator::Filter;
+
+/// Build an unsigned filter from a csv of gateway keys and edges.
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Path to csv with one key, or two comma separated keys for an edge, per
+    /// line
+    input: PathBuf,
+    /// Path to write the filter to
+    #[clap(short, long, default_value = "filter.bin")]
+    output: PathBuf,
+    /// Serial of the filter, usually the tag it is released under
+    #[clap(short, long)]
+    serial: u32,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result {
+        let entries = read_entries(&self.input)?;
+        let mut hashes: Vec<u64> = entries.iter().map(|entry| entry.hash()).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        let filter = Filter::new(self.serial, xorf_generator::xorf::Xor32::from(&hashes))
+            .map_err(|_| Error::invalid_filter("filter"))?;
+        let bin = filter
+            .to_bytes()
+            .map_err(|_| Error::invalid_filter("filter"))?;
+        fs::write(&self.output, bin)?;
+        print_json(&json!({
+            "output": self.output,
+            "serial": self.serial,
+            "entries": entries.len(),
+            "hashes": hashes.len(),
+        }))
+    }
+}
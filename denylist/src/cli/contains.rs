@@ -0,0 +1,72 @@
+use crate::{
+    cli::{denied_since, print_json, Entry, SignKeys},
+    Result,
+};
+use helium_crypto::PublicKeyBinary;
+use serde_json::json;
+use std::path::PathBuf;
+use xorf_generator::Filter;
+
+/// Check whether a gateway key is denied.
+#[derive(Debug, clap::Args)]
+pub struct Key {
+    /// b58 encoded gateway key
+    key: PublicKeyBinary,
+    #[clap(flatten)]
+    filters: Filters,
+}
+
+/// Check whether an edge between two gateway keys is denied.
+#[derive(Debug, clap::Args)]
+pub struct Edge {
+    /// b58 encoded key of one side of the edge
+    a: PublicKeyBinary,
+    /// b58 encoded key of the other side of the edge
+    b: PublicKeyBinary,
+    #[clap(flatten)]
+    filters: Filters,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Filters {
+    /// Paths to filters to check. When given several filters, for example
+    /// every released tag, the serial from which the entry has been
+    /// continuously denied is reported
+    #[clap(short, long = "filter", required = true)]
+    filters: Vec<PathBuf>,
+    #[clap(flatten)]
+    sign_keys: SignKeys,
+}
+
+impl Key {
+    pub fn run(&self) -> Result {
+        self.filters.run(Entry::Key(self.key.clone()))
+    }
+}
+
+impl Edge {
+    pub fn run(&self) -> Result {
+        self.filters
+            .run(Entry::Edge(self.a.clone(), self.b.clone()))
+    }
+}
+
+impl Filters {
+    fn run(&self, entry: Entry) -> Result {
+        let mut filters = self
+            .filters
+            .iter()
+            .map(|path| self.sign_keys.read_filter(path))
+            .collect::<Result<Vec<Filter>>>()?;
+        filters.sort_by_key(|filter| filter.serial);
+
+        let latest_serial = filters.last().map(|filter| filter.serial);
+        let since = denied_since(&entry, &filters);
+        print_json(&json!({
+            "entry": entry.to_string(),
+            "denied": since.is_some(),
+            "since": since,
+            "latest": latest_serial,
+        }))
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    cli::{diff_entries, print_json, read_entries, Entry, SignKeys},
+    Error, Result,
+};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Compare two filters.
+///
+/// Xor filters can not be enumerated, so added and removed entries are
+/// reported for a given csv of candidate keys and edges, for example the
+/// csvs the two filters were built from.
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Path to the old filter
+    old: PathBuf,
+    /// Path to the new filter
+    new: PathBuf,
+    /// Path to a csv of candidate keys and edges to compare
+    #[clap(short, long)]
+    candidates: Option<PathBuf>,
+    #[clap(flatten)]
+    sign_keys: SignKeys,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result {
+        let old = self.sign_keys.read_filter(&self.old)?;
+        let new = self.sign_keys.read_filter(&self.new)?;
+        let identical = old
+            .signing_bytes()
+            .and_then(|old| new.signing_bytes().map(|new| old == new))
+            .map_err(|_| Error::invalid_filter("filter"))?;
+
+        let (added, removed) = match &self.candidates {
+            Some(candidates) => {
+                let entries = read_entries(candidates)?;
+                let (added, removed) = diff_entries(&entries, &old, &new);
+                let to_strings = |entries: Vec<&Entry>| -> Vec<String> {
+                    entries.iter().map(ToString::to_string).collect()
+                };
+                (Some(to_strings(added)), Some(to_strings(removed)))
+            }
+            None => (None, None),
+        };
+        print_json(&json!({
+            "old_serial": old.serial,
+            "new_serial": new.serial,
+            "identical": identical,
+            "added": added,
+            "removed": removed,
+        }))
+    }
+}
@@ -0,0 +1,286 @@
+pub mod build;
+pub mod contains;
+pub mod diff;
+pub mod sign;
+pub mod verify;
+
+use crate::{denylist::filter_from_bin, Error, Result};
+use helium_crypto::{PublicKey, PublicKeyBinary};
+use std::{fs, path::Path, str::FromStr};
+use xorf_generator::{edge_hash, public_key_hash, Filter};
+
+pub(crate) fn print_json<T: ?Sized + serde::Serialize>(value: &T) -> Result {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// A single line of a denylist csv, either a gateway key or an edge between
+/// two gateway keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Key(PublicKeyBinary),
+    Edge(PublicKeyBinary, PublicKeyBinary),
+}
+
+impl Entry {
+    pub fn hash(&self) -> u64 {
+        match self {
+            Self::Key(key) => public_key_hash(key),
+            Self::Edge(a, b) => edge_hash(a, b),
+        }
+    }
+
+    pub fn contained_in(&self, filter: &Filter) -> bool {
+        match self {
+            Self::Key(key) => filter.contains(key),
+            Self::Edge(a, b) => filter.contains_edge(a, b),
+        }
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{key}"),
+            Self::Edge(a, b) => write!(f, "{a},{b}"),
+        }
+    }
+}
+
+impl FromStr for Entry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let keys = s
+            .split(',')
+            .map(|key| PublicKeyBinary::from_str(key.trim()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        match keys.as_slice() {
+            [key] => Ok(Self::Key(key.clone())),
+            [a, b] => Ok(Self::Edge(a.clone(), b.clone())),
+            _ => Err(Error::invalid_filter(format!("invalid csv line: {s}"))),
+        }
+    }
+}
+
+/// Read keys and edges from a csv file with one key, or two comma separated
+/// keys for an edge, per line. Empty lines and lines starting with `#` are
+/// ignored
+pub fn read_entries(path: &Path) -> Result<Vec<Entry>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Entry::from_str)
+        .collect()
+}
+
+/// The serial of the oldest filter from which the entry has been
+/// continuously denied up to the latest filter, given filters sorted by
+/// serial
+pub fn denied_since(entry: &Entry, filters: &[Filter]) -> Option<u32> {
+    filters
+        .iter()
+        .rev()
+        .take_while(|filter| entry.contained_in(filter))
+        .last()
+        .map(|filter| filter.serial)
+}
+
+/// The entries added to and removed from the old filter by the new one
+pub fn diff_entries<'a>(
+    entries: &'a [Entry],
+    old: &Filter,
+    new: &Filter,
+) -> (Vec<&'a Entry>, Vec<&'a Entry>) {
+    let added = entries
+        .iter()
+        .filter(|entry| !entry.contained_in(old) && entry.contained_in(new))
+        .collect();
+    let removed = entries
+        .iter()
+        .filter(|entry| entry.contained_in(old) && !entry.contained_in(new))
+        .collect();
+    (added, removed)
+}
+
+/// Keys filters read from disk must be signed by
+#[derive(Debug, clap::Args)]
+pub struct SignKeys {
+    /// b58 encoded public key allowed to sign the filters, may be repeated.
+    /// Required unless --no-verify is given
+    #[clap(short, long = "sign-key", required_unless_present = "no_verify")]
+    sign_keys: Vec<PublicKey>,
+    /// Read filters without verifying their signatures
+    #[clap(long, conflicts_with = "sign_keys")]
+    no_verify: bool,
+}
+
+impl SignKeys {
+    /// Load a filter from disk, verifying its signature unless verification
+    /// was explicitly disabled
+    pub fn read_filter(&self, path: &Path) -> Result<Filter> {
+        if self.no_verify {
+            read_unverified_filter(path)
+        } else {
+            read_filter(path, &self.sign_keys)
+        }
+    }
+}
+
+/// Load a filter from disk, verifying it is signed by one of the given keys
+pub fn read_filter(path: &Path, sign_keys: &[PublicKey]) -> Result<Filter> {
+    filter_from_bin(&fs::read(path)?, sign_keys)
+}
+
+/// Load a filter from disk without verifying its signature
+pub fn read_unverified_filter(path: &Path) -> Result<Filter> {
+    Filter::from_bytes(&fs::read(path)?).map_err(|_| Error::invalid_filter("filter"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use helium_crypto::{KeyTag, KeyType, Keypair, Network, Sign};
+
+    #[derive(Debug, clap::Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        sign_keys: SignKeys,
+    }
+
+    fn keypair() -> Keypair {
+        Keypair::generate(
+            KeyTag {
+                network: Network::MainNet,
+                key_type: KeyType::Ed25519,
+            },
+            &mut rand::rngs::OsRng,
+        )
+    }
+
+    fn write_filter(dir: &Path, signer: Option<&Keypair>) -> std::path::PathBuf {
+        let entry =
+            Entry::from_str("112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf").expect("entry");
+        let mut filter =
+            Filter::new(1, xorf_generator::xorf::Xor32::from(&[entry.hash()][..])).expect("filter");
+        if let Some(keypair) = signer {
+            let signing_bytes = filter.signing_bytes().expect("signing bytes");
+            filter.signature = keypair.sign(&signing_bytes).expect("signature");
+        }
+        let path = dir.join("filter.bin");
+        fs::write(&path, filter.to_bytes().expect("bytes")).expect("write filter");
+        path
+    }
+
+    const DENIED: &str = "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf";
+    const ALLOWED: &str = "11z69eJ3czc92k6snrfR9ek7g2uRWXosFbnG9v4bXgwhfUCivUo";
+
+    fn entry(line: &str) -> Entry {
+        Entry::from_str(line).expect("entry")
+    }
+
+    fn filter_of(serial: u32, lines: &[&str]) -> Filter {
+        let hashes: Vec<u64> = lines.iter().map(|line| entry(line).hash()).collect();
+        Filter::new(serial, xorf_generator::xorf::Xor32::from(&hashes[..])).expect("filter")
+    }
+
+    #[test]
+    fn denied_since_without_filters() {
+        assert_eq!(denied_since(&entry(DENIED), &[]), None);
+    }
+
+    #[test]
+    fn denied_since_missing_entry() {
+        let filters = [filter_of(1, &[DENIED]), filter_of(2, &[DENIED])];
+        assert_eq!(denied_since(&entry(ALLOWED), &filters), None);
+        let edge = format!("{DENIED},{ALLOWED}");
+        assert_eq!(denied_since(&entry(&edge), &filters), None);
+    }
+
+    #[test]
+    fn denied_since_changed_entry() {
+        let filters = [
+            filter_of(1, &[DENIED]),
+            filter_of(2, &[ALLOWED]),
+            filter_of(3, &[DENIED, ALLOWED]),
+            filter_of(4, &[DENIED, ALLOWED]),
+        ];
+        assert_eq!(denied_since(&entry(DENIED), &filters), Some(3));
+        assert_eq!(denied_since(&entry(ALLOWED), &filters), Some(2));
+        assert_eq!(denied_since(&entry(DENIED), &filters[..2]), None);
+    }
+
+    #[test]
+    fn diff_without_entries() {
+        let (added, removed) =
+            diff_entries(&[], &filter_of(1, &[DENIED]), &filter_of(2, &[ALLOWED]));
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn diff_missing_entry() {
+        let entries = [entry(ALLOWED)];
+        let (added, removed) =
+            diff_entries(&entries, &filter_of(1, &[DENIED]), &filter_of(2, &[DENIED]));
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn diff_changed_entries() {
+        let entries = [entry(DENIED), entry(ALLOWED)];
+        let (added, removed) = diff_entries(
+            &entries,
+            &filter_of(1, &[DENIED]),
+            &filter_of(2, &[ALLOWED]),
+        );
+        assert_eq!(added, vec![&entry(ALLOWED)]);
+        assert_eq!(removed, vec![&entry(DENIED)]);
+
+        let (added, removed) = diff_entries(
+            &entries,
+            &filter_of(1, &[DENIED]),
+            &filter_of(2, &[DENIED, ALLOWED]),
+        );
+        assert_eq!(added, vec![&entry(ALLOWED)]);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn requires_sign_keys_or_no_verify() {
+        let signer = keypair().public_key().to_string();
+        assert!(Cli::try_parse_from(["denylist"]).is_err());
+        assert!(Cli::try_parse_from(["denylist", "--no-verify", "--sign-key", &signer]).is_err());
+        assert!(Cli::try_parse_from(["denylist", "--no-verify"]).is_ok());
+        assert!(Cli::try_parse_from(["denylist", "--sign-key", &signer]).is_ok());
+    }
+
+    #[test]
+    fn verifies_filter_signature() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let signer = keypair();
+        let other = keypair();
+        let path = write_filter(dir.path(), Some(&signer));
+
+        assert!(read_filter(&path, &[signer.public_key().clone()]).is_ok());
+        assert!(read_filter(&path, &[other.public_key().clone()]).is_err());
+        assert!(read_filter(&path, &[]).is_err());
+
+        let cli = Cli::try_parse_from(["denylist", "--sign-key", &other.public_key().to_string()])
+            .expect("cli");
+        assert!(cli.sign_keys.read_filter(&path).is_err());
+        let cli = Cli::try_parse_from(["denylist", "--no-verify"]).expect("cli");
+        assert!(cli.sign_keys.read_filter(&path).is_ok());
+    }
+
+    #[test]
+    fn no_verify_reads_unsigned_filter() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = write_filter(dir.path(), None);
+        assert!(read_unverified_filter(&path).is_ok());
+        assert!(read_filter(&path, &[keypair().public_key().clone()]).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{cli::print_json, Error, Result};
+use helium_crypto::{Keypair, Sign};
+use serde_json::json;
+use std::{fs, path::PathBuf};
+use xorf_generator::Filter;
+
+/// Sign a filter in place with the given keypair.
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Path to the filter to sign
+    filter: PathBuf,
+    /// Path to the keypair file to sign with
+    #[clap(short, long)]
+    keypair: PathBuf,
+    /// Path to write the signed filter to. Defaults to overwriting the input
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result {
+        let keypair = Keypair::try_from(&fs::read(&self.keypair)?[..])?;
+        let mut filter = Filter::from_bytes(&fs::read(&self.filter)?)
+            .map_err(|_| Error::invalid_filter("filter"))?;
+        let signing_bytes = filter
+            .signing_bytes()
+            .map_err(|_| Error::invalid_filter("filter"))?;
+        filter.signature = keypair.sign(&signing_bytes)?;
+        let bin = filter
+            .to_bytes()
+            .map_err(|_| Error::invalid_filter("filter"))?;
+        let output = self.output.as_ref().unwrap_or(&self.filter);
+        fs::write(output, bin)?;
+        print_json(&json!({
+            "output": output,
+            "serial": filter.serial,
+            "signer": keypair.public_key().to_string(),
+        }))
+    }
+}
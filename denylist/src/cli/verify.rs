@@ -0,0 +1,25 @@
+use crate::{cli::print_json, denylist::filter_from_bin, Result};
+use helium_crypto::PublicKey;
+use serde_json::json;
+use std::{fs, path::PathBuf};
+
+/// Verify a filter is signed by one of the given keys.
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Path to the filter to verify
+    filter: PathBuf,
+    /// b58 encoded public key allowed to sign the filter, may be repeated
+    #[clap(short, long = "sign-key", required = true)]
+    sign_keys: Vec<PublicKey>,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result {
+        let filter = filter_from_bin(&fs::read(&self.filter)?, &self.sign_keys)?;
+        print_json(&json!({
+            "filter": self.filter,
+            "serial": filter.serial,
+            "verified": true,
+        }))
+    }
+}
@@ -1,23 +1,201 @@
-use crate::{client::DenyListClient, models::metadata::Asset, Error, Result, Settings};
+use crate::{
+    client::DenyListClient,
+    settings::SourceSettings,
+    source::{DenyListSource, FetchedFilter},
+    Error, Result, Settings,
+};
+use chrono::{DateTime, Utc};
 use helium_crypto::{PublicKey, PublicKeyBinary};
-use serde::Serialize;
-use std::{fs, path};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use xorf_generator::{edge_hash, public_key_hash, Filter};
 
 pub const SERIAL_SIZE: usize = 32;
-/// a copy of the last saved filter bin downloaded from github
-/// if present will be used to initialise the denylist upon verifier startup
-// TODO: look at using the tempfile crate to handle this
-const FILTER_BIN_PATH: &str = "./tmp/last_saved_filter.bin";
 
 #[derive(Serialize)]
 pub struct DenyList {
+    /// tag of the first, primary, source
     pub tag_name: u64,
     #[serde(skip_serializing)]
     pub client: DenyListClient,
+    pub filters: Vec<SourceFilter>,
+    pub sign_keys: Vec<PublicKey>,
+}
+
+/// max number of tag activations retained per source
+const MAX_TAG_HISTORY: usize = 100;
+
+/// The filter currently loaded from a single denylist source
+#[derive(Serialize)]
+pub struct SourceFilter {
+    pub name: String,
+    /// url or path of the source
+    pub location: String,
+    pub tag_name: u64,
     #[serde(skip_serializing)]
     pub filter: Option<Filter>,
-    pub sign_keys: Vec<PublicKey>,
+    /// tags activated for this source, oldest first
+    pub history: Vec<TagActivation>,
+    #[serde(skip_serializing)]
+    source: Option<DenyListSource>,
+    /// a copy of the last filter bin fetched from the source
+    /// if present will be used to initialise the filter upon verifier startup
+    #[serde(skip_serializing)]
+    cache_path: Option<PathBuf>,
+    #[serde(skip_serializing)]
+    history_path: Option<PathBuf>,
+}
+
+/// A denylist tag and the time from which the verifier applied it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagActivation {
+    pub tag_name: u64,
+    pub activated_at: DateTime<Utc>,
+}
+
+/// The source and tag of the filter which denied a key or edge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeniedBy {
+    pub source: String,
+    pub location: String,
+    pub tag_name: u64,
+    pub activated_at: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Display for DeniedBy {
+    /// the tag followed by the url or path of the source it was loaded
+    /// from, `<tag>@<location>`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.tag_name, self.location)
+    }
+}
+
+impl SourceFilter {
+    fn from_settings(settings: &SourceSettings, cache_dir: &Path, sign_keys: &[PublicKey]) -> Self {
+        let name = settings.name().to_string();
+        let cache_path = cache_dir.join(format!("{name}_filter.bin"));
+        let history_path = cache_dir.join(format!("{name}_history.json"));
+        // if exists default to the local saved filter bin, otherwise default to
+        // empty filter a local filter should always be present after the
+        // verifier has been run at least once in the current dir and has
+        // previously successfully downloaded a filter from the source
+        let filter = fs::read(&cache_path)
+            .map_err(Error::from)
+            .and_then(|bytes| filter_from_bin(&bytes, sign_keys))
+            .map(Some)
+            .unwrap_or_else(|_| {
+                tracing::warn!(
+                    source = %name,
+                    "failed to initialise with a denylist filter, filter is currently empty"
+                );
+                None
+            });
+        let history: Vec<TagActivation> = fs::read(&history_path)
+            .map_err(Error::from)
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(Error::from))
+            .unwrap_or_default();
+        // the tag of the saved filter is the last activated tag, without a
+        // saved filter default tag to 0, proper tag name will be set on
+        // first call to update_to_latest
+        let restored = filter.as_ref().and(history.last());
+        let tag_name = restored
+            .map(|activation| activation.tag_name)
+            .unwrap_or_default();
+        let mut source = DenyListSource::from(settings);
+        if let Some(activation) = restored {
+            source.restore(activation.activated_at.into());
+        }
+        Self {
+            name,
+            location: settings.location(),
+            tag_name,
+            filter,
+            history,
+            source: Some(source),
+            cache_path: Some(cache_path),
+            history_path: Some(history_path),
+        }
+    }
+
+    fn from_filter(filter: Filter) -> Self {
+        Self {
+            name: "static".to_string(),
+            location: "static".to_string(),
+            tag_name: 0,
+            filter: Some(filter),
+            history: vec![],
+            source: None,
+            cache_path: None,
+            history_path: None,
+        }
+    }
+
+    async fn update_to_latest(
+        &mut self,
+        client: &mut DenyListClient,
+        sign_keys: &[PublicKey],
+    ) -> Result {
+        let Some(source) = &mut self.source else {
+            return Ok(());
+        };
+        if let Some(FetchedFilter {
+            tag_name,
+            filter,
+            bin,
+        }) = source.fetch(client, sign_keys, self.tag_name).await?
+        {
+            tracing::info!(
+                source = %self.name,
+                "source is newer, updating denylist to {:?}",
+                tag_name
+            );
+            self.filter = Some(filter);
+            self.tag_name = tag_name;
+            if let Some(cache_path) = &self.cache_path {
+                save_local_copy(&bin, cache_path)?;
+            }
+            // a reloaded filter under the active tag is not a new activation
+            if self.history.last().map(|activation| activation.tag_name) == Some(tag_name) {
+                return Ok(());
+            }
+            self.history.push(TagActivation {
+                tag_name,
+                activated_at: Utc::now(),
+            });
+            if self.history.len() > MAX_TAG_HISTORY {
+                self.history.drain(..self.history.len() - MAX_TAG_HISTORY);
+            }
+            if let Some(history_path) = &self.history_path {
+                save_local_copy(&serde_json::to_vec(&self.history)?, history_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The tag the verifier applied at `at`, if the retained history reaches
+    /// back that far
+    pub fn tag_at(&self, at: DateTime<Utc>) -> Option<u64> {
+        self.history
+            .iter()
+            .rev()
+            .find(|activation| activation.activated_at <= at)
+            .map(|activation| activation.tag_name)
+    }
+
+    fn denied_by(&self) -> DeniedBy {
+        DeniedBy {
+            source: self.name.clone(),
+            location: self.location.clone(),
+            tag_name: self.tag_name,
+            activated_at: self
+                .history
+                .last()
+                .map(|activation| activation.activated_at),
+        }
+    }
 }
 
 impl TryFrom<Vec<PublicKeyBinary>> for DenyList {
@@ -30,7 +208,7 @@ impl TryFrom<Vec<PublicKeyBinary>> for DenyList {
         Ok(Self {
             tag_name: 0,
             client,
-            filter: Some(filter),
+            filters: vec![SourceFilter::from_filter(filter)],
             sign_keys: vec![],
         })
     }
@@ -46,7 +224,7 @@ impl TryFrom<Vec<(PublicKeyBinary, PublicKeyBinary)>> for DenyList {
         Ok(Self {
             tag_name: 0,
             client,
-            filter: Some(filter),
+            filters: vec![SourceFilter::from_filter(filter)],
             sign_keys: vec![],
         })
     }
@@ -55,88 +233,111 @@ impl TryFrom<Vec<(PublicKeyBinary, PublicKeyBinary)>> for DenyList {
 impl DenyList {
     pub fn new(settings: &Settings) -> Result<Self> {
         tracing::debug!("initializing new denylist");
-        // if exists default to the local saved filter bin, otherwise default to
-        // empty filter a local filter should always be present after the
-        // verifier has been run at least once in the current dir and has
-        // previously successfully downloaded a filter from github
         let sign_keys = settings.sign_keys()?;
-        let filter = fs::read(FILTER_BIN_PATH)
-            .map_err(Error::from)
-            .and_then(|bytes| filter_from_bin(&bytes, &sign_keys))
-            .map(Some)
-            .unwrap_or_else(|_| {
-                tracing::warn!(
-                    "failed to initialise with a denylist filter, filter is currently empty"
-                );
-                None
-            });
-
+        let filters: Vec<SourceFilter> = settings
+            .sources()
+            .iter()
+            .map(|source| SourceFilter::from_settings(source, &settings.cache_dir, &sign_keys))
+            .collect();
         let client = DenyListClient::new()?;
         Ok(Self {
-            // default tag to 0, proper tag name will be set on first call to
-            // update_to_latest
-            tag_name: 0,
+            // the tag of the primary source restored from its saved filter,
+            // if any, otherwise 0 until the first call to update_to_latest
+            tag_name: primary_tag_name(&filters),
             client,
-            filter,
+            filters,
             sign_keys,
         })
     }
 
-    pub async fn update_to_latest(&mut self, metadata_url: &String) -> Result {
-        tracing::info!("checking for updated denylist, url: {metadata_url} ");
-
-        let metadata = self.client.get_metadata(metadata_url).await?;
-        let new_tag_name = metadata.tag_name.parse::<u64>()?;
-        tracing::info!(
-            "local denylist tag: {:?}, remote denylist tag: {:?}",
-            self.tag_name,
-            new_tag_name
-        );
-        if new_tag_name > self.tag_name {
-            tracing::info!(
-                "remote tag is newer, updating denylist to {:?}",
-                new_tag_name
-            );
-            // get the asset
-            // filter out any assets which do not have a name == "filter.bin"
-            let assets: Vec<Asset> = metadata
-                .assets
-                .into_iter()
-                .filter(|a| a.name == "filter.bin")
-                .collect();
-            // we should be left with a single asset
-            // at least this is the assumption the erlang implementation followed
-            if let Some(asset) = assets.first() {
-                tracing::debug!("found asset for tag");
-                let asset_url = &asset.browser_download_url;
-                let bin = self.client.get_bin(asset_url).await?;
-                if let Ok(filter) = filter_from_bin(&bin, &self.sign_keys) {
-                    self.filter = Some(filter);
-                    self.tag_name = new_tag_name;
-                    save_local_filter_bin(&bin, FILTER_BIN_PATH)?;
-                }
+    /// Update every source to its latest filter. A failing source does not
+    /// prevent the remaining sources from updating, the errors of all
+    /// failing sources are returned once all sources have been tried
+    pub async fn update_to_latest(&mut self) -> Result {
+        let mut errors = vec![];
+        for source_filter in self.filters.iter_mut() {
+            tracing::info!(source = %source_filter.name, "checking for updated denylist");
+            if let Err(err) = source_filter
+                .update_to_latest(&mut self.client, &self.sign_keys)
+                .await
+            {
+                tracing::warn!(
+                    source = %source_filter.name,
+                    "failed to update denylist source: {err:?}"
+                );
+                errors.push((source_filter.name.clone(), err));
             }
         }
-        Ok(())
+        self.tag_name = primary_tag_name(&self.filters);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::SourceUpdates(errors))
+        }
     }
 
     pub fn contains_key(&self, key: &PublicKeyBinary) -> bool {
-        if let Some(filter) = &self.filter {
-            filter.contains(key)
-        } else {
-            tracing::warn!("empty denylist filter, rejecting key");
-            true
-        }
+        self.key_denied_by(key).is_some()
     }
 
     pub fn contains_edge(&self, beaconer: &PublicKeyBinary, witness: &PublicKeyBinary) -> bool {
-        if let Some(filter) = &self.filter {
-            filter.contains_edge(beaconer, witness)
-        } else {
-            tracing::warn!("empty denylist filter, rejecting edge");
-            true
+        self.edge_denied_by(beaconer, witness).is_some()
+    }
+
+    /// The source and tag of the first filter containing the key. Until
+    /// every configured source has loaded a filter all keys are denied by
+    /// the first source without one
+    pub fn key_denied_by(&self, key: &PublicKeyBinary) -> Option<DeniedBy> {
+        if let Some(missing) = self.missing_filter() {
+            tracing::warn!(source = %missing.source, "missing denylist filter, rejecting key");
+            return Some(missing);
         }
+        self.denied_by(|filter| filter.contains(key))
     }
+
+    /// The source and tag of the first filter containing the edge
+    pub fn edge_denied_by(
+        &self,
+        beaconer: &PublicKeyBinary,
+        witness: &PublicKeyBinary,
+    ) -> Option<DeniedBy> {
+        if let Some(missing) = self.missing_filter() {
+            tracing::warn!(source = %missing.source, "missing denylist filter, rejecting edge");
+            return Some(missing);
+        }
+        self.denied_by(|filter| filter.contains_edge(beaconer, witness))
+    }
+
+    /// The first source which has yet to load a filter, if any
+    fn missing_filter(&self) -> Option<DeniedBy> {
+        if self.filters.is_empty() {
+            return Some(DeniedBy {
+                source: String::new(),
+                location: String::new(),
+                tag_name: self.tag_name,
+                activated_at: None,
+            });
+        }
+        self.filters
+            .iter()
+            .find(|sf| sf.filter.is_none())
+            .map(SourceFilter::denied_by)
+    }
+
+    fn denied_by<F>(&self, contains: F) -> Option<DeniedBy>
+    where
+        F: Fn(&Filter) -> bool,
+    {
+        self.filters
+            .iter()
+            .find(|sf| sf.filter.as_ref().map_or(false, &contains))
+            .map(SourceFilter::denied_by)
+    }
+}
+
+/// the tag of the first, primary, source
+fn primary_tag_name(filters: &[SourceFilter]) -> u64 {
+    filters.first().map(|sf| sf.tag_name).unwrap_or_default()
 }
 
 /// deconstruct bytes into the filter component parts
@@ -158,12 +359,184 @@ pub fn filter_from_bin(bin: &[u8], sign_keys: &[PublicKey]) -> Result<Filter> {
     Ok(filter)
 }
 
-/// save a copy of the xor file locally
-// the local copy will be used should during init github be unreachable
-pub fn save_local_filter_bin(bin: &Vec<u8>, path: &str) -> Result {
-    if let Some(parent) = path::PathBuf::from(path).parent() {
+/// save a local copy of the xor file or tag history of a source
+// the local copy will be used should during init the source be unreachable
+pub fn save_local_copy(bin: &[u8], path: &Path) -> Result {
+    if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
         fs::write(path, bin)?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use helium_crypto::{KeyTag, KeyType, Keypair, Network, Sign};
+    use std::str::FromStr;
+
+    const DENIED: &str = "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf";
+    const ALLOWED: &str = "11z69eJ3czc92k6snrfR9ek7g2uRWXosFbnG9v4bXgwhfUCivUo";
+
+    fn key(b58: &str) -> PublicKeyBinary {
+        PublicKeyBinary::from_str(b58).expect("valid key")
+    }
+
+    fn local_source(name: &str, filter: Option<Filter>) -> SourceFilter {
+        SourceFilter {
+            name: name.to_string(),
+            location: format!("./does_not_exist/{name}_filter.bin"),
+            tag_name: filter.as_ref().map_or(0, |filter| filter.serial as u64),
+            filter,
+            history: vec![],
+            source: Some(DenyListSource::Local {
+                path: PathBuf::from(format!("./does_not_exist/{name}_filter.bin")),
+                modified: None,
+            }),
+            cache_path: None,
+            history_path: None,
+        }
+    }
+
+    fn filter_of(serial: u32, keys: &[&str]) -> Filter {
+        let hashes: Vec<u64> = keys.iter().map(|k| public_key_hash(&key(k))).collect();
+        Filter::new(serial, xorf_generator::xorf::Xor32::from(&hashes)).expect("filter")
+    }
+
+    fn with_sources(filters: Vec<SourceFilter>) -> DenyList {
+        DenyList {
+            tag_name: 0,
+            client: DenyListClient::new().expect("client"),
+            filters,
+            sign_keys: vec![],
+        }
+    }
+
+    #[test]
+    fn denies_key_contained_in_any_source() {
+        let deny_list = with_sources(vec![
+            local_source("primary", Some(filter_of(1, &[ALLOWED]))),
+            local_source("override", Some(filter_of(7, &[DENIED]))),
+        ]);
+        let denied_by = deny_list.key_denied_by(&key(DENIED)).expect("denied");
+        assert_eq!(denied_by.source, "override");
+        assert_eq!(denied_by.tag_name, 7);
+    }
+
+    #[test]
+    fn fails_closed_per_configured_source() {
+        let deny_list = with_sources(vec![
+            local_source("primary", Some(filter_of(1, &[DENIED]))),
+            local_source("override", None),
+        ]);
+        let denied_by = deny_list.key_denied_by(&key(ALLOWED)).expect("denied");
+        assert_eq!(denied_by.source, "override");
+        assert!(deny_list.contains_edge(&key(ALLOWED), &key(ALLOWED)));
+
+        let deny_list = with_sources(vec![
+            local_source("primary", Some(filter_of(1, &[DENIED]))),
+            local_source("override", Some(filter_of(7, &[DENIED]))),
+        ]);
+        assert!(!deny_list.contains_key(&key(ALLOWED)));
+        assert!(deny_list.contains_key(&key(DENIED)));
+    }
+
+    #[test]
+    fn fails_closed_without_sources() {
+        assert!(with_sources(vec![]).contains_key(&key(ALLOWED)));
+    }
+
+    #[test]
+    fn tag_at_time_of_activation() {
+        let activated_at = |secs| Utc.timestamp_opt(secs, 0).unwrap();
+        let mut source = local_source("primary", None);
+        source.history = vec![
+            TagActivation {
+                tag_name: 3,
+                activated_at: activated_at(100),
+            },
+            TagActivation {
+                tag_name: 5,
+                activated_at: activated_at(200),
+            },
+        ];
+        assert_eq!(source.tag_at(activated_at(99)), None);
+        assert_eq!(source.tag_at(activated_at(100)), Some(3));
+        assert_eq!(source.tag_at(activated_at(199)), Some(3));
+        assert_eq!(source.tag_at(activated_at(300)), Some(5));
+    }
+
+    #[tokio::test]
+    async fn update_to_latest_reports_every_failed_source() {
+        let mut deny_list = with_sources(vec![
+            local_source("primary", None),
+            local_source("override", None),
+        ]);
+        match deny_list.update_to_latest().await {
+            Err(Error::SourceUpdates(errors)) => {
+                let sources: Vec<&str> = errors.iter().map(|(name, _)| name.as_str()).collect();
+                assert_eq!(sources, vec!["primary", "override"]);
+            }
+            other => panic!("expected source update errors, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn restores_tag_and_history_of_saved_filter() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let keypair = Keypair::generate(
+            KeyTag {
+                network: Network::MainNet,
+                key_type: KeyType::Ed25519,
+            },
+            &mut rand::rngs::OsRng,
+        );
+        let mut filter = filter_of(3, &[DENIED]);
+        filter.signature = keypair
+            .sign(&filter.signing_bytes().expect("signing bytes"))
+            .expect("signature");
+        let filter_path = dir.path().join("override.bin");
+        fs::write(&filter_path, filter.to_bytes().expect("bytes")).expect("write filter");
+
+        let settings = Settings {
+            log: "denylist=debug".to_string(),
+            denylist_url: String::new(),
+            trigger: 0,
+            sign_keys: vec![keypair.public_key().to_string()],
+            cache_dir: dir.path().join("cache"),
+            sources: vec![SourceSettings::Local {
+                name: "override".to_string(),
+                path: filter_path.clone(),
+            }],
+        };
+        let mut deny_list = DenyList::new(&settings).expect("deny list");
+        assert_eq!(deny_list.tag_name, 0);
+        deny_list.update_to_latest().await.expect("updated");
+        assert_eq!(deny_list.tag_name, 3);
+        let activated_at = deny_list.filters[0].history[0].activated_at;
+
+        let mut restored = DenyList::new(&settings).expect("deny list");
+        assert_eq!(restored.tag_name, 3);
+        // the unchanged local file is neither reloaded nor activated again
+        restored.update_to_latest().await.expect("updated");
+        assert_eq!(
+            restored.filters[0].history,
+            vec![TagActivation {
+                tag_name: 3,
+                activated_at
+            }]
+        );
+        let denied_by = restored.key_denied_by(&key(DENIED)).expect("denied");
+        assert_eq!(
+            denied_by,
+            DeniedBy {
+                source: "override".to_string(),
+                location: filter_path.display().to_string(),
+                tag_name: 3,
+                activated_at: Some(activated_at),
+            }
+        );
+        assert!(!restored.contains_key(&key(ALLOWED)));
+    }
+}
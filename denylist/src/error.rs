@@ -7,6 +7,8 @@ pub enum Error {
     Request(#[from] reqwest::Error),
     #[error("filter error")]
     InvalidFilter(String),
+    #[error("json error")]
+    Json(#[from] serde_json::Error),
     #[error("unexpected value")]
     Value(serde_json::Value),
     #[error("invalid decimals in {0}, only 8 allowed")]
@@ -25,6 +27,20 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("config error")]
     Config(#[from] config::ConfigError),
+    #[error("file store error")]
+    FileStore(#[from] file_store::Error),
+    #[error("byte stream error: {0}")]
+    ByteStream(String),
+    #[error("failed to update denylist sources: {}", source_names(.0))]
+    SourceUpdates(Vec<(String, Error)>),
+}
+
+fn source_names(errors: &[(String, Error)]) -> String {
+    errors
+        .iter()
+        .map(|(source, err)| format!("{source}: {err}"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl Error {
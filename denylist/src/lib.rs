@@ -1,9 +1,11 @@
 mod error;
 pub use error::{Error, Result};
+pub mod cli;
 pub mod client;
 pub mod denylist;
 pub mod models;
 pub mod settings;
+pub mod source;
 
 pub use crate::denylist::DenyList;
 pub use crate::settings::Settings;
@@ -0,0 +1,41 @@
+use clap::Parser;
+use denylist::{
+    cli::{build, contains, diff, sign, verify},
+    Result,
+};
+
+#[derive(Debug, clap::Parser)]
+#[clap(version = env!("CARGO_PKG_VERSION"))]
+#[clap(about = "Helium Denylist Filter Tooling")]
+pub struct Cli {
+    #[clap(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Cmd {
+    Build(build::Cmd),
+    Sign(sign::Cmd),
+    Verify(verify::Cmd),
+    Contains(contains::Key),
+    ContainsEdge(contains::Edge),
+    Diff(diff::Cmd),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result {
+        match self {
+            Self::Build(cmd) => cmd.run(),
+            Self::Sign(cmd) => cmd.run(),
+            Self::Verify(cmd) => cmd.run(),
+            Self::Contains(cmd) => cmd.run(),
+            Self::ContainsEdge(cmd) => cmd.run(),
+            Self::Diff(cmd) => cmd.run(),
+        }
+    }
+}
+
+fn main() -> Result {
+    let cli = Cli::parse();
+    cli.cmd.run()
+}
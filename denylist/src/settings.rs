@@ -2,7 +2,11 @@ use crate::{Error, Result};
 use config::{Config, Environment, File};
 use helium_crypto::PublicKey;
 use serde::Deserialize;
-use std::{path::Path, str::FromStr, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -10,7 +14,9 @@ pub struct Settings {
     /// "denylist=debug"
     #[serde(default = "default_log")]
     pub log: String,
-    /// Listen address for http requests for entropy. Default "0.0.0.0:8080"
+    /// Github release metadata url of the denylist. Used as the single
+    /// denylist source when no `sources` are configured.
+    /// Default "https://api.github.com/repos/helium/denylist/releases/latest"
     #[serde(default = "default_denylist_url")]
     pub denylist_url: String,
     /// Cadence at which we poll for an updated denylist (secs)
@@ -20,6 +26,34 @@ pub struct Settings {
     // used to verify signature of denylist filters
     #[serde(default)]
     pub sign_keys: Vec<String>,
+    /// Directory in which a copy of the last downloaded filter of each
+    /// source is saved. Default "./tmp"
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: PathBuf,
+    /// Signed filter sources combined into the denylist. A key or edge is
+    /// denied if it is contained in any of the loaded filters. Defaults to
+    /// a single github source using `denylist_url`
+    #[serde(default)]
+    pub sources: Vec<SourceSettings>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SourceSettings {
+    /// Github release with a `filter.bin` asset. `url` is the release
+    /// metadata url
+    Github { name: String, url: String },
+    /// Plain https url serving a signed filter
+    Https { name: String, url: String },
+    /// Signed filter stored as an object in an S3 bucket
+    S3 {
+        name: String,
+        key: String,
+        store: file_store::Settings,
+    },
+    /// Signed filter on the local filesystem, reloaded whenever the file is
+    /// modified
+    Local { name: String, path: PathBuf },
 }
 
 pub fn default_log() -> String {
@@ -34,6 +68,14 @@ fn default_trigger_interval() -> u64 {
     21600
 }
 
+fn default_cache_dir() -> PathBuf {
+    PathBuf::from("./tmp")
+}
+
+/// name of the source created from `denylist_url` when no sources are
+/// configured. Keeps the cache file name of earlier releases.
+pub const DEFAULT_SOURCE_NAME: &str = "last_saved";
+
 impl Settings {
     /// Load Settings from a given path. Settings are loaded from a given
     /// optional path and can be overriden with environment variables.
@@ -68,4 +110,37 @@ impl Settings {
             .map(|pubkey| PublicKey::from_str(pubkey))
             .collect()
     }
+
+    /// The configured sources, falling back to a single github source built
+    /// from `denylist_url`
+    pub fn sources(&self) -> Vec<SourceSettings> {
+        if self.sources.is_empty() {
+            vec![SourceSettings::Github {
+                name: DEFAULT_SOURCE_NAME.to_string(),
+                url: self.denylist_url.clone(),
+            }]
+        } else {
+            self.sources.clone()
+        }
+    }
+}
+
+impl SourceSettings {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Github { name, .. }
+            | Self::Https { name, .. }
+            | Self::S3 { name, .. }
+            | Self::Local { name, .. } => name,
+        }
+    }
+
+    /// url or path the filter of the source is loaded from
+    pub fn location(&self) -> String {
+        match self {
+            Self::Github { url, .. } | Self::Https { url, .. } => url.clone(),
+            Self::S3 { key, store, .. } => format!("s3://{}/{key}", store.bucket),
+            Self::Local { path, .. } => path.display().to_string(),
+        }
+    }
 }
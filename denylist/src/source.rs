@@ -0,0 +1,181 @@
+use crate::{
+    client::DenyListClient, denylist::filter_from_bin, models::metadata::Asset,
+    settings::SourceSettings, Error, Result,
+};
+use file_store::FileStore;
+use helium_crypto::PublicKey;
+use std::{fs, path::PathBuf, time::SystemTime};
+use xorf_generator::Filter;
+
+/// A newly fetched and verified filter along with the tag it was
+/// published under
+pub struct FetchedFilter {
+    pub tag_name: u64,
+    pub filter: Filter,
+    pub bin: Vec<u8>,
+}
+
+/// Location from which a signed denylist filter is pulled
+pub enum DenyListSource {
+    Github {
+        url: String,
+    },
+    Https {
+        url: String,
+    },
+    S3 {
+        settings: file_store::Settings,
+        key: String,
+        store: Option<FileStore>,
+    },
+    Local {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+    },
+}
+
+impl From<&SourceSettings> for DenyListSource {
+    fn from(settings: &SourceSettings) -> Self {
+        match settings {
+            SourceSettings::Github { url, .. } => Self::Github { url: url.clone() },
+            SourceSettings::Https { url, .. } => Self::Https { url: url.clone() },
+            SourceSettings::S3 { key, store, .. } => Self::S3 {
+                settings: store.clone(),
+                key: key.clone(),
+                store: None,
+            },
+            SourceSettings::Local { path, .. } => Self::Local {
+                path: path.clone(),
+                modified: None,
+            },
+        }
+    }
+}
+
+impl DenyListSource {
+    /// Restore the state of a source whose filter was loaded from the saved
+    /// copy of a tag activated at `activated_at`. A local file which has not
+    /// been modified since is not reloaded
+    pub fn restore(&mut self, activated_at: SystemTime) {
+        if let Self::Local { path, modified } = self {
+            match fs::metadata(path.as_path()).and_then(|metadata| metadata.modified()) {
+                Ok(last_modified) if last_modified <= activated_at => {
+                    *modified = Some(last_modified)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Fetch the filter from the source if it is newer than `current_tag`.
+    ///
+    /// Github sources are versioned by the release tag, all others by the
+    /// serial of the signed filter. A local file is reloaded whenever its
+    /// modification time changes, regardless of serial, so operators can
+    /// roll back an override by replacing the file.
+    pub async fn fetch(
+        &mut self,
+        client: &mut DenyListClient,
+        sign_keys: &[PublicKey],
+        current_tag: u64,
+    ) -> Result<Option<FetchedFilter>> {
+        match self {
+            Self::Github { url } => {
+                let metadata = client.get_metadata(url).await?;
+                let new_tag_name = metadata.tag_name.parse::<u64>()?;
+                tracing::info!(
+                    "local denylist tag: {:?}, remote denylist tag: {:?}",
+                    current_tag,
+                    new_tag_name
+                );
+                if new_tag_name <= current_tag {
+                    return Ok(None);
+                }
+                // filter out any assets which do not have a name == "filter.bin"
+                let assets: Vec<Asset> = metadata
+                    .assets
+                    .into_iter()
+                    .filter(|a| a.name == "filter.bin")
+                    .collect();
+                // we should be left with a single asset
+                // at least this is the assumption the erlang implementation followed
+                match assets.first() {
+                    Some(asset) => {
+                        tracing::debug!("found asset for tag");
+                        let bin = client.get_bin(&asset.browser_download_url).await?;
+                        let filter = filter_from_bin(&bin, sign_keys)?;
+                        Ok(Some(FetchedFilter {
+                            tag_name: new_tag_name,
+                            filter,
+                            bin,
+                        }))
+                    }
+                    None => Ok(None),
+                }
+            }
+            Self::Https { url } => {
+                let bin = client.get_bin(url).await?;
+                newer_than(bin, sign_keys, current_tag)
+            }
+            Self::S3 {
+                settings,
+                key,
+                store,
+            } => {
+                let file_store = match store {
+                    Some(file_store) => file_store.clone(),
+                    None => {
+                        let file_store = FileStore::from_settings(settings).await?;
+                        *store = Some(file_store.clone());
+                        file_store
+                    }
+                };
+                let bin = file_store
+                    .get_raw(key.clone())
+                    .await?
+                    .collect()
+                    .await
+                    .map_err(|err| Error::ByteStream(err.to_string()))?
+                    .into_bytes()
+                    .to_vec();
+                newer_than(bin, sign_keys, current_tag)
+            }
+            Self::Local { path, modified } => {
+                let last_modified = fs::metadata(path.as_path())?.modified()?;
+                if Some(last_modified) == *modified {
+                    return Ok(None);
+                }
+                let bin = fs::read(path.as_path())?;
+                let filter = filter_from_bin(&bin, sign_keys)?;
+                *modified = Some(last_modified);
+                Ok(Some(FetchedFilter {
+                    tag_name: filter.serial as u64,
+                    filter,
+                    bin,
+                }))
+            }
+        }
+    }
+}
+
+fn newer_than(
+    bin: Vec<u8>,
+    sign_keys: &[PublicKey],
+    current_tag: u64,
+) -> Result<Option<FetchedFilter>> {
+    let filter = filter_from_bin(&bin, sign_keys)?;
+    let tag_name = filter.serial as u64;
+    tracing::info!(
+        "local denylist tag: {:?}, remote denylist tag: {:?}",
+        current_tag,
+        tag_name
+    );
+    if tag_name <= current_tag {
+        return Ok(None);
+    }
+    Ok(Some(FetchedFilter {
+        tag_name,
+        filter,
+        bin,
+    }))
+}
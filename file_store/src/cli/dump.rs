@@ -3,8 +3,10 @@ use crate::{
     file_source,
     heartbeat::{CbrsHeartbeat, CbrsHeartbeatIngestReport},
     iot_packet::IotValidPacket,
+    iot_poc_diagnostics::IotPocDiagnostics,
     mobile_session::{DataTransferSessionIngestReport, InvalidDataTransferIngestReport},
     mobile_subscriber::{SubscriberLocationIngestReport, VerifiedSubscriberLocationIngestReport},
+    price_sources_report::PriceSourcesReportV1,
     speedtest::{CellSpeedtest, CellSpeedtestIngestReport},
     traits::MsgDecode,
     wifi_heartbeat::WifiHeartbeatIngestReport,
@@ -157,6 +159,10 @@ impl Cmd {
                     print_json(&json)?;
                     // wtr.serialize(IotWitnessIngestReport::try_from(dec_msg)?)?;
                 }
+                FileType::IotPocDiagnostics => {
+                    let diagnostics = IotPocDiagnostics::decode(msg)?;
+                    print_json(&diagnostics)?;
+                }
                 FileType::IotPoc => {
                     let dec_msg = LoraPocV1::decode(msg)?;
                     let json = json!({
@@ -276,6 +282,26 @@ impl Cmd {
                         "token_type": manifest.token_type(),
                     }))?;
                 }
+                FileType::PriceSourcesReport => {
+                    let report = PriceSourcesReportV1::decode(msg)?;
+                    let sources: Vec<serde_json::Value> = report
+                        .sources
+                        .iter()
+                        .map(|source| {
+                            json!({
+                                "source": source.source,
+                                "price": source.price,
+                                "timestamp": source.timestamp,
+                            })
+                        })
+                        .collect();
+                    print_json(&json!({
+                        "price": report.price,
+                        "timestamp": report.timestamp,
+                        "token_type": report.token_type(),
+                        "sources": sources,
+                    }))?;
+                }
                 FileType::IotValidPacket => {
                     let manifest = IotValidPacket::decode(msg)?;
                     print_json(&json!({
@@ -124,6 +124,7 @@ pub const IOT_WITNESS_INGEST_REPORT: &str = "iot_witness_ingest_report";
 pub const IOT_POC: &str = "iot_poc";
 pub const IOT_INVALID_BEACON_REPORT: &str = "iot_invalid_beacon";
 pub const IOT_INVALID_WITNESS_REPORT: &str = "iot_invalid_witness";
+pub const IOT_POC_DIAGNOSTICS: &str = "iot_poc_diagnostics";
 pub const SPEEDTEST_AVG: &str = "speedtest_avg";
 pub const VALIDATED_HEARTBEAT: &str = "validated_heartbeat";
 pub const SIGNED_POC_RECEIPT_TXN: &str = "signed_poc_receipt_txn";
@@ -139,6 +140,7 @@ pub const INVALID_DATA_TRANSFER_SESSION_INGEST_REPORT: &str =
     "invalid_data_transfer_session_ingest_report";
 pub const VALID_DATA_TRANSFER_SESSION: &str = "valid_data_transfer_session";
 pub const PRICE_REPORT: &str = "price_report";
+pub const PRICE_SOURCES_REPORT: &str = "price_sources_report";
 pub const MOBILE_REWARD_SHARE: &str = "mobile_reward_share";
 pub const MAPPER_MSG: &str = "mapper_msg";
 pub const COVERAGE_OBJECT: &str = "coverage_object";
@@ -160,6 +162,7 @@ pub enum FileType {
     IotPoc,
     IotInvalidBeaconReport,
     IotInvalidWitnessReport,
+    IotPocDiagnostics,
     SpeedtestAvg,
     ValidatedHeartbeat,
     SignedPocReceiptTxn,
@@ -174,6 +177,7 @@ pub enum FileType {
     InvalidDataTransferSessionIngestReport,
     ValidDataTransferSession,
     PriceReport,
+    PriceSourcesReport,
     MobileRewardShare,
     SubscriberLocationReq,
     SubscriberLocationIngestReport,
@@ -210,6 +214,7 @@ impl fmt::Display for FileType {
             Self::IotPoc => IOT_POC,
             Self::IotInvalidBeaconReport => IOT_INVALID_BEACON_REPORT,
             Self::IotInvalidWitnessReport => IOT_INVALID_WITNESS_REPORT,
+            Self::IotPocDiagnostics => IOT_POC_DIAGNOSTICS,
             Self::SpeedtestAvg => SPEEDTEST_AVG,
             Self::ValidatedHeartbeat => VALIDATED_HEARTBEAT,
             Self::SignedPocReceiptTxn => SIGNED_POC_RECEIPT_TXN,
@@ -226,6 +231,7 @@ impl fmt::Display for FileType {
             }
             Self::ValidDataTransferSession => VALID_DATA_TRANSFER_SESSION,
             Self::PriceReport => PRICE_REPORT,
+            Self::PriceSourcesReport => PRICE_SOURCES_REPORT,
             Self::MobileRewardShare => MOBILE_REWARD_SHARE,
             Self::MapperMsg => MAPPER_MSG,
             Self::CoverageObject => COVERAGE_OBJECT,
@@ -259,6 +265,7 @@ impl FileType {
             Self::IotPoc => IOT_POC,
             Self::IotInvalidBeaconReport => IOT_INVALID_BEACON_REPORT,
             Self::IotInvalidWitnessReport => IOT_INVALID_WITNESS_REPORT,
+            Self::IotPocDiagnostics => IOT_POC_DIAGNOSTICS,
             Self::SpeedtestAvg => SPEEDTEST_AVG,
             Self::ValidatedHeartbeat => VALIDATED_HEARTBEAT,
             Self::SignedPocReceiptTxn => SIGNED_POC_RECEIPT_TXN,
@@ -275,6 +282,7 @@ impl FileType {
             }
             Self::ValidDataTransferSession => VALID_DATA_TRANSFER_SESSION,
             Self::PriceReport => PRICE_REPORT,
+            Self::PriceSourcesReport => PRICE_SOURCES_REPORT,
             Self::MobileRewardShare => MOBILE_REWARD_SHARE,
             Self::MapperMsg => MAPPER_MSG,
             Self::CoverageObject => COVERAGE_OBJECT,
@@ -308,6 +316,7 @@ impl FromStr for FileType {
             IOT_POC => Self::IotPoc,
             IOT_INVALID_BEACON_REPORT => Self::IotInvalidBeaconReport,
             IOT_INVALID_WITNESS_REPORT => Self::IotInvalidWitnessReport,
+            IOT_POC_DIAGNOSTICS => Self::IotPocDiagnostics,
             SPEEDTEST_AVG => Self::SpeedtestAvg,
             VALIDATED_HEARTBEAT => Self::ValidatedHeartbeat,
             SIGNED_POC_RECEIPT_TXN => Self::SignedPocReceiptTxn,
@@ -324,6 +333,7 @@ impl FromStr for FileType {
             }
             VALID_DATA_TRANSFER_SESSION => Self::ValidDataTransferSession,
             PRICE_REPORT => Self::PriceReport,
+            PRICE_SOURCES_REPORT => Self::PriceSourcesReport,
             MOBILE_REWARD_SHARE => Self::MobileRewardShare,
             MAPPER_MSG => Self::MapperMsg,
             COVERAGE_OBJECT => Self::CoverageObject,
@@ -361,7 +361,7 @@ impl FileInfoPollerState for sqlx::Pool<sqlx::Postgres> {
     async fn clean(&self, process_name: &str, file_type: &str) -> Result {
         sqlx::query(
             r#"
-                DELETE FROM files_processed where file_name in (
+                DELETE FROM files_processed where process_name = $1 and file_name in (
                     SELECT file_name
                     FROM files_processed
                     WHERE process_name = $1 and file_type = $2
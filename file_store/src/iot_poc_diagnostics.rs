@@ -0,0 +1,207 @@
+//! Diagnostics of the verification of a poc, written by the iot verifier for
+//! the pocs it explains.
+
+use crate::{
+    error::DecodeError,
+    iot_beacon_report::IotBeaconReport,
+    iot_valid_poc::IotVerifiedWitnessReport,
+    traits::{MsgDecode, MsgTimestamp, TimestampDecode, TimestampEncode},
+    Error, Result,
+};
+use chrono::{DateTime, Utc};
+use helium_proto::services::poc_lora::{
+    InvalidReason, LoraBeaconReportReqV1, LoraVerifiedWitnessReportV1, VerificationStatus,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerificationCheckV1 {
+    #[prost(string, tag = "1")]
+    pub check: String,
+    #[prost(bool, tag = "2")]
+    pub passed: bool,
+    #[prost(enumeration = "InvalidReason", tag = "3")]
+    pub reason: i32,
+    #[prost(btree_map = "string, string", tag = "4")]
+    pub values: BTreeMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WitnessDiagnosticsV1 {
+    #[prost(message, optional, tag = "1")]
+    pub witness: Option<LoraVerifiedWitnessReportV1>,
+    #[prost(bool, tag = "2")]
+    pub selected: bool,
+    #[prost(message, repeated, tag = "3")]
+    pub checks: Vec<VerificationCheckV1>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IotPocDiagnosticsV1 {
+    #[prost(bytes = "vec", tag = "1")]
+    pub poc_id: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub received_timestamp: u64,
+    #[prost(message, optional, tag = "3")]
+    pub beacon: Option<LoraBeaconReportReqV1>,
+    #[prost(enumeration = "VerificationStatus", tag = "4")]
+    pub status: i32,
+    #[prost(enumeration = "InvalidReason", tag = "5")]
+    pub invalid_reason: i32,
+    #[prost(message, repeated, tag = "6")]
+    pub beacon_checks: Vec<VerificationCheckV1>,
+    #[prost(message, repeated, tag = "7")]
+    pub witnesses: Vec<WitnessDiagnosticsV1>,
+}
+
+/// The outcome of a single check of a beacon or witness verification and the
+/// values it was evaluated with
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct VerificationCheck {
+    pub check: String,
+    pub passed: bool,
+    pub reason: InvalidReason,
+    pub values: BTreeMap<String, String>,
+}
+
+/// A verified witness of a poc along with the outcome of every check it was
+/// verified against
+#[derive(Serialize, Clone, Debug)]
+pub struct WitnessDiagnostics {
+    pub witness: IotVerifiedWitnessReport,
+    pub selected: bool,
+}
+
+/// The beacon of a poc and its witnesses along with the outcome of every
+/// check they were verified against. The witnesses of an invalid beacon are
+/// not verified and so are not included
+#[derive(Serialize, Clone, Debug)]
+pub struct IotPocDiagnostics {
+    pub poc_id: Vec<u8>,
+    pub received_timestamp: DateTime<Utc>,
+    pub beacon: IotBeaconReport,
+    pub status: VerificationStatus,
+    pub invalid_reason: InvalidReason,
+    pub beacon_checks: Vec<VerificationCheck>,
+    pub witnesses: Vec<WitnessDiagnostics>,
+}
+
+impl MsgDecode for IotPocDiagnostics {
+    type Msg = IotPocDiagnosticsV1;
+}
+
+impl MsgTimestamp<Result<DateTime<Utc>>> for IotPocDiagnosticsV1 {
+    fn timestamp(&self) -> Result<DateTime<Utc>> {
+        self.received_timestamp.to_timestamp_millis()
+    }
+}
+
+impl MsgTimestamp<u64> for IotPocDiagnostics {
+    fn timestamp(&self) -> u64 {
+        self.received_timestamp.encode_timestamp_millis()
+    }
+}
+
+impl TryFrom<VerificationCheckV1> for VerificationCheck {
+    type Error = Error;
+    fn try_from(v: VerificationCheckV1) -> Result<Self> {
+        let reason = InvalidReason::from_i32(v.reason).ok_or_else(|| {
+            DecodeError::unsupported_invalid_reason("verification_check_v1", v.reason)
+        })?;
+        Ok(Self {
+            check: v.check,
+            passed: v.passed,
+            reason,
+            values: v.values,
+        })
+    }
+}
+
+impl From<VerificationCheck> for VerificationCheckV1 {
+    fn from(v: VerificationCheck) -> Self {
+        Self {
+            check: v.check,
+            passed: v.passed,
+            reason: v.reason as i32,
+            values: v.values,
+        }
+    }
+}
+
+impl TryFrom<WitnessDiagnosticsV1> for WitnessDiagnostics {
+    type Error = Error;
+    fn try_from(v: WitnessDiagnosticsV1) -> Result<Self> {
+        let mut witness: IotVerifiedWitnessReport = v
+            .witness
+            .ok_or_else(|| Error::not_found("iot witness diagnostics v1"))?
+            .try_into()?;
+        witness.checks = decode_checks(v.checks)?;
+        Ok(Self {
+            witness,
+            selected: v.selected,
+        })
+    }
+}
+
+impl From<WitnessDiagnostics> for WitnessDiagnosticsV1 {
+    fn from(v: WitnessDiagnostics) -> Self {
+        let checks = v.witness.checks.iter().cloned().map(From::from).collect();
+        Self {
+            witness: Some(v.witness.into()),
+            selected: v.selected,
+            checks,
+        }
+    }
+}
+
+impl TryFrom<IotPocDiagnosticsV1> for IotPocDiagnostics {
+    type Error = Error;
+    fn try_from(v: IotPocDiagnosticsV1) -> Result<Self> {
+        let received_timestamp = v.timestamp()?;
+        let status = VerificationStatus::from_i32(v.status).ok_or_else(|| {
+            DecodeError::unsupported_status_reason("iot_poc_diagnostics_v1", v.status)
+        })?;
+        let invalid_reason = InvalidReason::from_i32(v.invalid_reason).ok_or_else(|| {
+            DecodeError::unsupported_invalid_reason("iot_poc_diagnostics_v1", v.invalid_reason)
+        })?;
+        Ok(Self {
+            poc_id: v.poc_id,
+            received_timestamp,
+            beacon: v
+                .beacon
+                .ok_or_else(|| Error::not_found("iot poc diagnostics v1"))?
+                .try_into()?,
+            status,
+            invalid_reason,
+            beacon_checks: decode_checks(v.beacon_checks)?,
+            witnesses: v
+                .witnesses
+                .into_iter()
+                .map(WitnessDiagnostics::try_from)
+                .collect::<Result<Vec<WitnessDiagnostics>>>()?,
+        })
+    }
+}
+
+impl From<IotPocDiagnostics> for IotPocDiagnosticsV1 {
+    fn from(v: IotPocDiagnostics) -> Self {
+        let received_timestamp = v.timestamp();
+        Self {
+            poc_id: v.poc_id,
+            received_timestamp,
+            beacon: Some(v.beacon.into()),
+            status: v.status as i32,
+            invalid_reason: v.invalid_reason as i32,
+            beacon_checks: v.beacon_checks.into_iter().map(From::from).collect(),
+            witnesses: v.witnesses.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+fn decode_checks(checks: Vec<VerificationCheckV1>) -> Result<Vec<VerificationCheck>> {
+    checks
+        .into_iter()
+        .map(VerificationCheck::try_from)
+        .collect()
+}
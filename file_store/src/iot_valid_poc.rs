@@ -1,6 +1,7 @@
 use crate::{
     error::DecodeError,
     iot_beacon_report::IotBeaconReport,
+    iot_poc_diagnostics::VerificationCheck,
     iot_witness_report::IotWitnessReport,
     traits::{MsgDecode, MsgTimestamp, TimestampDecode, TimestampEncode},
     Error, Result,
@@ -52,6 +53,10 @@ pub struct IotVerifiedWitnessReport {
     pub invalid_reason: InvalidReason,
     pub participant_side: InvalidParticipantSide,
     pub invalid_details: Option<InvalidDetails>,
+    /// the outcome of every verification check, recorded only when the
+    /// verifier explains its verifications. These are not part of
+    /// LoraVerifiedWitnessReportV1 and are written as witness diagnostics
+    pub checks: Vec<VerificationCheck>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -199,6 +204,7 @@ impl TryFrom<LoraVerifiedWitnessReportV1> for IotVerifiedWitnessReport {
             invalid_reason,
             participant_side,
             invalid_details: v.invalid_details,
+            checks: vec![],
         })
     }
 }
@@ -246,6 +252,7 @@ impl IotVerifiedWitnessReport {
             reward_unit: Decimal::ZERO,
             participant_side: InvalidParticipantSide::SideNone,
             invalid_details: None,
+            checks: vec![],
         }
     }
 
@@ -274,6 +281,7 @@ impl IotVerifiedWitnessReport {
             // valid, non-failed witnesses for the final validated poc report
             reward_unit: Decimal::ZERO,
             participant_side,
+            checks: vec![],
         }
     }
 }
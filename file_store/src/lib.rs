@@ -1,3 +1,12 @@
+//! Reading, writing and uploading of the files exchanged by the oracles.
+//!
+//! Most files hold messages of the published helium protos. The
+//! `price_sources_report` and `iot_poc_diagnostics` files carry data those
+//! protos have no messages for, so their messages are defined in the modules
+//! of the same name. These are internal formats of the oracles which may
+//! change between releases and should only be read through those modules,
+//! e.g. with `file-store dump`.
+
 pub mod cli;
 pub mod coverage;
 pub mod entropy_report;
@@ -12,11 +21,13 @@ pub mod heartbeat;
 pub mod iot_beacon_report;
 pub mod iot_invalid_poc;
 pub mod iot_packet;
+pub mod iot_poc_diagnostics;
 pub mod iot_valid_poc;
 pub mod iot_witness_report;
 pub mod mobile_session;
 pub mod mobile_subscriber;
 pub mod mobile_transfer;
+pub mod price_sources_report;
 pub mod reward_manifest;
 mod settings;
 pub mod speedtest;
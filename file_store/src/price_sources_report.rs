@@ -0,0 +1,31 @@
+use helium_proto::BlockchainTokenTypeV1;
+
+// the per source breakdown of an aggregated price, written alongside each
+// price_report
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SourcePriceV1 {
+    /// Name of the configured price source
+    #[prost(string, tag = "1")]
+    pub source: String,
+    #[prost(uint64, tag = "2")]
+    pub price: u64,
+    /// Unix timestamp (seconds) the source reported the price at
+    #[prost(uint64, tag = "3")]
+    pub timestamp: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PriceSourcesReportV1 {
+    /// Unix timestamp (seconds) of the price report the sources contributed to
+    #[prost(uint64, tag = "1")]
+    pub timestamp: u64,
+    #[prost(enumeration = "BlockchainTokenTypeV1", tag = "2")]
+    pub token_type: i32,
+    /// The aggregated price
+    #[prost(uint64, tag = "3")]
+    pub price: u64,
+    /// Every source price which contributed to the aggregated price
+    #[prost(message, repeated, tag = "4")]
+    pub sources: Vec<SourcePriceV1>,
+}
@@ -71,6 +71,13 @@ impl_msg_verify!(iot_config::RegionParamsReqV1, signature);
 impl_msg_verify!(iot_config::GatewayInfoResV1, signature);
 impl_msg_verify!(iot_config::GatewayInfoStreamResV1, signature);
 impl_msg_verify!(iot_config::RegionParamsResV1, signature);
+impl_msg_verify!(iot_config::RouteResV1, signature);
+impl_msg_verify!(iot_config::RouteListResV1, signature);
+impl_msg_verify!(iot_config::RouteEuisResV1, signature);
+impl_msg_verify!(iot_config::RouteDevaddrRangesResV1, signature);
+impl_msg_verify!(iot_config::RouteSkfUpdateResV1, signature);
+impl_msg_verify!(iot_config::AdminKeyResV1, signature);
+impl_msg_verify!(iot_config::AdminLoadRegionResV1, signature);
 impl_msg_verify!(mobile_config::AdminAddKeyReqV1, signature);
 impl_msg_verify!(mobile_config::AdminRemoveKeyReqV1, signature);
 impl_msg_verify!(mobile_config::AuthorizationVerifyReqV1, signature);
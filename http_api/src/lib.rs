@@ -0,0 +1,148 @@
+use base64::Engine;
+use helium_crypto::{Keypair, PublicKey, Sign, Verify};
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, StatusCode,
+};
+use std::{convert::Infallible, future::Future, net::SocketAddr, str::FromStr};
+
+pub use hyper;
+
+/// Header holding the b58 encoded public key of the response signer
+pub const SIGNER_HEADER: &str = "x-signer";
+/// Header holding the base64 encoded signature of the response body
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Serve every request on the given address with the handler until shutdown
+pub async fn serve<H, F>(
+    socket_addr: &SocketAddr,
+    handler: H,
+    shutdown: triggered::Listener,
+) -> hyper::Result<()>
+where
+    H: Fn(Request<Body>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    let make_service = make_service_fn(move |_conn| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let response = handler(req);
+                async move { Ok::<_, Infallible>(response.await) }
+            }))
+        }
+    });
+    hyper::Server::bind(socket_addr)
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+/// An unsigned json response
+pub fn json(json: Vec<u8>) -> Result<Response<Body>, StatusCode> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// A json response signed by the given keypair. The signature of the exact
+/// response body is in the `x-signature` header, the signer in `x-signer`
+pub fn signed(signing_key: &Keypair, json: Vec<u8>) -> Result<Response<Body>, StatusCode> {
+    let signature = signing_key
+        .sign(&json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let signer = HeaderValue::from_str(&signing_key.public_key().to_string())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let signature =
+        HeaderValue::from_str(&base64::engine::general_purpose::STANDARD.encode(signature))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(SIGNER_HEADER, signer)
+        .header(SIGNATURE_HEADER, signature)
+        .body(Body::from(json))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Verify the signature headers of a signed response against its body,
+/// returning the signer
+pub fn verify_signed(headers: &hyper::HeaderMap, body: &[u8]) -> Option<PublicKey> {
+    let signer = headers
+        .get(SIGNER_HEADER)
+        .and_then(|signer| signer.to_str().ok())
+        .and_then(|signer| PublicKey::from_str(signer).ok())?;
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|signature| {
+        base64::engine::general_purpose::STANDARD
+            .decode(signature.as_bytes())
+            .ok()
+    })?;
+    signer.verify(body, &signature).ok()?;
+    Some(signer)
+}
+
+/// The value of the first query parameter with the given name
+pub fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// An empty response with the given status
+pub fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_crypto::{KeyTag, KeyType, Network};
+
+    #[test]
+    fn finds_query_params() {
+        let query = "limit=10&cursor=abc&flag&limit=20";
+        assert_eq!(query_param(query, "limit"), Some("10"));
+        assert_eq!(query_param(query, "cursor"), Some("abc"));
+        assert_eq!(query_param(query, "flag"), None);
+        assert_eq!(query_param(query, "missing"), None);
+        assert_eq!(query_param("", "limit"), None);
+    }
+
+    #[test]
+    fn empty_status_response() {
+        let response = status(StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn signs_response_body() {
+        let keypair = Keypair::generate(
+            KeyTag {
+                network: Network::MainNet,
+                key_type: KeyType::Ed25519,
+            },
+            &mut rand::rngs::OsRng,
+        );
+        let response = signed(&keypair, b"{\"total\":1}".to_vec()).expect("signed response");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body");
+        assert_eq!(
+            verify_signed(&headers, &body).as_ref(),
+            Some(keypair.public_key())
+        );
+        assert_eq!(verify_signed(&headers, b"{\"total\":2}"), None);
+    }
+}
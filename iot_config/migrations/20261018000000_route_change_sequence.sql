@@ -0,0 +1,70 @@
+-- Monotonic sequence of routing configuration changes. Every insert or update
+-- of a route, eui pair, devaddr range or session key filter is stamped with
+-- the next value so route stream subscribers can resume after the last change
+-- they saw.
+create sequence route_change_seq;
+
+-- Values are handed out while holding a transaction level advisory lock so
+-- change sequences are committed in order: once the lock is acquired, every
+-- sequence at or below the current value is committed.
+create or replace function set_change_seq()
+    returns trigger as
+$$
+begin
+    perform pg_advisory_xact_lock(7265676);
+    NEW.change_seq = nextval('route_change_seq');
+    return NEW;
+end;
+$$ language plpgsql;
+
+create or replace function trigger_change_seq(tablename regclass)
+    returns void as
+$$
+begin
+    execute format('ALTER TABLE %s ADD COLUMN change_seq bigint NOT NULL DEFAULT nextval(''route_change_seq'');', tablename);
+    execute format('CREATE INDEX %s_change_seq_idx ON %s (change_seq);', tablename, tablename);
+    execute format('CREATE TRIGGER set_change_seq_insert
+        BEFORE INSERT
+        ON %s
+        FOR EACH ROW
+    EXECUTE FUNCTION set_change_seq();', tablename);
+    execute format('CREATE TRIGGER set_change_seq_update
+        BEFORE UPDATE
+        ON %s
+        FOR EACH ROW
+        WHEN (OLD is distinct from NEW)
+    EXECUTE FUNCTION set_change_seq();', tablename);
+end;
+$$ language plpgsql;
+
+select trigger_change_seq('routes');
+select trigger_change_seq('route_eui_pairs');
+select trigger_change_seq('route_devaddr_ranges');
+select trigger_change_seq('route_session_key_filters');
+
+-- A route's locked flag comes from its org, so locking or unlocking an org is
+-- a change to each of its routes
+create or replace function org_locked_change_seq()
+    returns trigger as
+$$
+begin
+    update routes set change_seq = nextval('route_change_seq') where oui = NEW.oui;
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger org_locked_change_seq
+    after update of locked
+    on organizations
+    for each row
+    when (OLD.locked is distinct from NEW.locked)
+execute function org_locked_change_seq();
+
+-- Highest change sequence of the soft deleted rows purged by the db cleaner.
+-- Subscribers resuming from before it may have missed removals and must resync.
+create table route_change_horizon (
+    singleton bool primary key default true check (singleton),
+    purged_change_seq bigint not null default 0
+);
+
+insert into route_change_horizon default values;
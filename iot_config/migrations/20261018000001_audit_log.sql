@@ -0,0 +1,40 @@
+create type audit_signer_type as enum (
+    'administrator',
+    'packet_router',
+    'oracle',
+    'org'
+);
+
+-- Append-only record of every configuration change and the key that signed it.
+-- `before` and `after` hold the changed record; for eui pair, devaddr range and
+-- session key filter updates they hold the removed and added entries.
+create table audit_log (
+    id bigserial primary key,
+    signer text not null,
+    signer_type audit_signer_type not null,
+    rpc text not null,
+    oui bigint,
+    route_id uuid,
+    before jsonb,
+    after jsonb,
+
+    inserted_at timestamptz not null default now()
+);
+
+create index audit_log_oui_idx on audit_log (oui, id);
+create index audit_log_route_id_idx on audit_log (route_id, id);
+create index audit_log_inserted_at_idx on audit_log (inserted_at);
+
+create or replace function reject_audit_log_change()
+    returns trigger as
+$$
+begin
+    raise exception 'audit_log is append-only';
+end;
+$$ language plpgsql;
+
+create trigger audit_log_append_only
+    before update or delete or truncate
+    on audit_log
+    for each statement
+execute function reject_audit_log_change();
@@ -0,0 +1,21 @@
+-- Time an org was last disabled, so the devaddrs of orgs disabled for longer
+-- than a grace period can be reclaimed
+alter table organizations add column locked_at timestamptz;
+
+update organizations set locked_at = updated_at where locked;
+
+create or replace function set_org_locked_at()
+    returns trigger as
+$$
+begin
+    NEW.locked_at = case when NEW.locked then now() else null end;
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger set_org_locked_at
+    before update of locked
+    on organizations
+    for each row
+    when (OLD.locked is distinct from NEW.locked)
+execute function set_org_locked_at();
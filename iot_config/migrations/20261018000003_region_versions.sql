@@ -0,0 +1,18 @@
+-- Every set of params and h3 indexes loaded for a region, from the time it
+-- became active. The regions table keeps the active version of each region.
+create table region_versions (
+    id bigserial primary key,
+    region text not null,
+    params bytea not null,
+    indexes bytea,
+    active_from timestamptz not null default now(),
+
+    inserted_at timestamptz not null default now()
+);
+
+create index region_versions_region_active_from_idx on region_versions (region, active_from);
+
+-- The activation time of the regions loaded before versioning is unknown, so
+-- they're taken to have always been active
+insert into region_versions (region, params, indexes, active_from)
+select region, params, indexes, 'epoch'::timestamptz from regions;
@@ -0,0 +1,42 @@
+-- Time the devaddrs claimed by helium orgs were left outside the constraints
+-- of every org, so orphaned devaddrs are only reclaimed once they have been
+-- orphaned for longer than a grace period
+alter table helium_used_devaddrs add column orphaned_at timestamptz;
+
+update helium_used_devaddrs used set orphaned_at = now()
+where not exists (
+    select 1 from organization_devaddr_constraints org_const
+    where org_const.net_id = used.net_id
+        and used.devaddr between org_const.start_addr and org_const.end_addr
+);
+
+create or replace function set_devaddrs_orphaned_at()
+    returns trigger as
+$$
+begin
+    if TG_OP in ('UPDATE', 'DELETE') then
+        update helium_used_devaddrs used set orphaned_at = now()
+        where used.net_id = OLD.net_id
+            and used.devaddr between OLD.start_addr and OLD.end_addr
+            and used.orphaned_at is null
+            and not exists (
+                select 1 from organization_devaddr_constraints org_const
+                where org_const.net_id = used.net_id
+                    and used.devaddr between org_const.start_addr and org_const.end_addr
+            );
+    end if;
+    if TG_OP in ('INSERT', 'UPDATE') then
+        update helium_used_devaddrs set orphaned_at = null
+        where net_id = NEW.net_id
+            and devaddr between NEW.start_addr and NEW.end_addr
+            and orphaned_at is not null;
+    end if;
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger set_devaddrs_orphaned_at
+    after insert or update or delete
+    on organization_devaddr_constraints
+    for each row
+execute function set_devaddrs_orphaned_at();
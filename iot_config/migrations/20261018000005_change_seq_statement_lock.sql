@@ -0,0 +1,38 @@
+-- Take the change sequence lock once per statement rather than once per row
+-- stamped, so a batch of eui pairs, devaddr ranges or session key filters
+-- acquires it once. The lock is still held until the transaction ends, which
+-- keeps change sequences committed in order.
+create or replace function lock_change_seq()
+    returns trigger as
+$$
+begin
+    perform pg_advisory_xact_lock(7265676);
+    return null;
+end;
+$$ language plpgsql;
+
+create or replace function set_change_seq()
+    returns trigger as
+$$
+begin
+    NEW.change_seq = nextval('route_change_seq');
+    return NEW;
+end;
+$$ language plpgsql;
+
+create or replace function trigger_change_seq_lock(tablename regclass)
+    returns void as
+$$
+begin
+    execute format('CREATE TRIGGER lock_change_seq
+        BEFORE INSERT OR UPDATE
+        ON %s
+        FOR EACH STATEMENT
+    EXECUTE FUNCTION lock_change_seq();', tablename);
+end;
+$$ language plpgsql;
+
+select trigger_change_seq_lock('routes');
+select trigger_change_seq_lock('route_eui_pairs');
+select trigger_change_seq_lock('route_devaddr_ranges');
+select trigger_change_seq_lock('route_session_key_filters');
@@ -61,6 +61,10 @@ impl AuthCache {
         }
     }
 
+    pub fn get_key_type(&self, key: &PublicKey) -> Option<KeyType> {
+        self.cache_receiver.borrow().get(key).copied()
+    }
+
     pub fn get_keys(&self) -> Vec<(PublicKey, KeyType)> {
         self.cache_receiver
             .borrow()
@@ -1,9 +1,10 @@
 use crate::{
     admin::{self, AuthCache, CacheKeys, KeyType},
+    audit::{self, Signer},
     region_map::{self, RegionMap, RegionMapReader},
     telemetry, verify_public_key, GrpcResult, Settings,
 };
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use chrono::Utc;
 use file_store::traits::{MsgVerify, TimestampEncode};
 use futures::future::TryFutureExt;
@@ -15,6 +16,7 @@ use helium_proto::{
     },
     Message, Region,
 };
+use serde_json::json;
 use sqlx::{Pool, Postgres};
 use tokio::sync::watch;
 use tonic::{Request, Response, Status};
@@ -51,14 +53,14 @@ impl AdminService {
         &self,
         signer: &PublicKey,
         request: &R,
-    ) -> Result<(), Status>
+    ) -> Result<Signer, Status>
     where
         R: MsgVerify,
     {
         self.auth_cache
             .verify_signature_with_type(KeyType::Administrator, signer, request)
             .map_err(|_| Status::permission_denied("invalid admin signature"))?;
-        Ok(())
+        Ok(Signer::new(signer.clone(), KeyType::Administrator))
     }
 
     fn verify_request_signature<R>(&self, signer: &PublicKey, request: &R) -> Result<(), Status>
@@ -85,36 +87,44 @@ impl iot_config::Admin for AdminService {
         telemetry::count_request("admin", "add-key");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_admin_request_signature(&signer, &request)?;
+        let signer = self.verify_admin_request_signature(&signer, &request)?;
 
         let key_type = request.key_type().into();
         let pubkey = verify_public_key(request.pubkey.as_ref())
             .map_err(|_| Status::invalid_argument("invalid pubkey supplied"))?;
+        let added = json!({ "pubkey": pubkey.to_string(), "key_type": key_type.to_string() });
 
-        admin::insert_key(request.pubkey.clone().into(), key_type, &self.pool)
-            .and_then(|_| async move {
-                if self.auth_updater.send_if_modified(|cache| {
-                    if let std::collections::hash_map::Entry::Vacant(key) =
-                        cache.entry(pubkey.clone())
-                    {
-                        key.insert(key_type);
-                        true
-                    } else {
-                        false
-                    }
-                }) {
-                    tracing::info!(%pubkey, %key_type, "key authorized");
-                    Ok(())
-                } else {
-                    Err(anyhow!("key already registered"))
-                }
-            })
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error saving requested key"))?;
+        admin::insert_key(request.pubkey.clone().into(), key_type, &mut txn)
             .map_err(|err| {
-                let pubkey: PublicKeyBinary = request.pubkey.into();
+                let pubkey: PublicKeyBinary = request.pubkey.clone().into();
                 tracing::error!(%pubkey, "pubkey add failed");
                 Status::internal(format!("error saving requested key: {pubkey}, {err:?}"))
             })
             .await?;
+        audit::record(
+            audit::Entry::new(&signer, "admin_add_key").after(&added),
+            &mut txn,
+        )
+        .await?;
+        txn.commit()
+            .await
+            .map_err(|_| Status::internal("error saving requested key"))?;
+
+        if self.auth_updater.send_if_modified(|cache| {
+            if let std::collections::hash_map::Entry::Vacant(key) = cache.entry(pubkey.clone()) {
+                key.insert(key_type);
+                true
+            } else {
+                false
+            }
+        }) {
+            tracing::info!(%pubkey, %key_type, "key authorized");
+        }
 
         let timestamp = Utc::now().encode_timestamp();
         let signer = self.signing_key.public_key().into();
@@ -133,27 +143,39 @@ impl iot_config::Admin for AdminService {
         telemetry::count_request("admin", "remove-key");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_admin_request_signature(&signer, &request)?;
-
-        admin::remove_key(request.pubkey.clone().into(), &self.pool)
-            .and_then(|deleted| async move {
-                match deleted {
-                    Some((pubkey, key_type)) => {
-                        self.auth_updater.send_modify(|cache| {
-                            cache.remove(&pubkey);
-                        });
-                        tracing::info!(%pubkey, %key_type,"key de-authorized");
-                        Ok(())
-                    }
-                    None => Ok(()),
-                }
-            })
+        let signer = self.verify_admin_request_signature(&signer, &request)?;
+
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error removing requested key"))?;
+        let removed = admin::remove_key(request.pubkey.clone().into(), &mut txn)
             .map_err(|_| {
-                let pubkey: PublicKeyBinary = request.pubkey.into();
+                let pubkey: PublicKeyBinary = request.pubkey.clone().into();
                 tracing::error!(%pubkey, "pubkey remove failed");
                 Status::internal(format!("error removing request key: {pubkey}"))
             })
             .await?;
+        if let Some((pubkey, key_type)) = &removed {
+            audit::record(
+                audit::Entry::new(&signer, "admin_remove_key").before(
+                    &json!({ "pubkey": pubkey.to_string(), "key_type": key_type.to_string() }),
+                ),
+                &mut txn,
+            )
+            .await?;
+        }
+        txn.commit()
+            .await
+            .map_err(|_| Status::internal("error removing requested key"))?;
+
+        if let Some((pubkey, key_type)) = removed {
+            self.auth_updater.send_modify(|cache| {
+                cache.remove(&pubkey);
+            });
+            tracing::info!(%pubkey, %key_type, "key de-authorized");
+        }
 
         let timestamp = Utc::now().encode_timestamp();
         let signer = self.signing_key.public_key().into();
@@ -175,7 +197,7 @@ impl iot_config::Admin for AdminService {
         telemetry::count_request("admin", "load-region");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_admin_request_signature(&signer, &request)?;
+        let signer = self.verify_admin_request_signature(&signer, &request)?;
 
         let region = Region::from_i32(request.region).ok_or_else(|| {
             Status::invalid_argument(format!("invalid lora region {}", request.region))
@@ -192,34 +214,51 @@ impl iot_config::Admin for AdminService {
             None => return Err(Status::invalid_argument("missing region")),
         };
 
-        let idz = if !request.hex_indexes.is_empty() {
+        let hex_indexes_updated = !request.hex_indexes.is_empty();
+        let idz = if hex_indexes_updated {
             Some(request.hex_indexes.as_ref())
         } else {
             None
         };
 
-        region_map::update_region(region, &params.clone(), idz, &self.pool)
-            .and_then(|updated_region| async move {
-                self.region_updater.send_modify(|region_map| {
-                    region_map.insert_params(region, params);
-                });
-                if let Some(region_tree) = updated_region {
-                    let region_tree_size = region_tree.len();
-                    tracing::debug!(region_cells = region_tree_size, "new compacted region map");
-                    telemetry::gauge_hexes(region_tree_size);
-                    self.region_updater
-                        .send_modify(|region_map| region_map.replace_tree(region_tree));
-                };
-                Ok(())
-            })
-            .map_err(|err| {
-                tracing::error!(
-                    region = region.to_string(),
-                    "failed to update region: {err:?}"
-                );
-                Status::internal("region update failed")
-            })
-            .await?;
+        let region_update_failed = |err: anyhow::Error| {
+            tracing::error!(
+                region = region.to_string(),
+                "failed to update region: {err:?}"
+            );
+            Status::internal("region update failed")
+        };
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| region_update_failed(err.into()))?;
+        let update = region_map::update_region(region, &params.clone(), idz, &mut txn)
+            .await
+            .map_err(region_update_failed)?;
+        audit::record(
+            audit::Entry::new(&signer, "admin_load_region").after(&json!({
+                "region": region.to_string(),
+                "hex_indexes_updated": hex_indexes_updated,
+            })),
+            &mut txn,
+        )
+        .await?;
+        txn.commit()
+            .await
+            .map_err(|err| region_update_failed(err.into()))?;
+
+        self.region_updater.send_modify(|region_map| {
+            region_map.insert_params(region, params.clone());
+            region_map.insert_version(region, update.active_from, params, update.cells);
+        });
+        if let Some(region_tree) = update.region_tree {
+            let region_tree_size = region_tree.len();
+            tracing::debug!(region_cells = region_tree_size, "new compacted region map");
+            telemetry::gauge_hexes(region_tree_size);
+            self.region_updater
+                .send_modify(|region_map| region_map.replace_tree(region_tree));
+        };
 
         let timestamp = Utc::now().encode_timestamp();
         let signer = self.signing_key.public_key().into();
@@ -0,0 +1,642 @@
+use crate::{
+    admin::{AuthCache, KeyType},
+    audit::{self, AuditError, AuditQuery, Signer, SignerType},
+    devaddr_slabs::{self, DevAddrSlabError, ReclaimRequest, SlabRequest, UsageQuery},
+    lora_field::{DevAddrRange, EuiPair, Skf},
+    org::{self, OrgStoreError},
+    quota::{QuotaError, Quotas},
+    region_lookup::{self, RegionLookupError, RegionLookupQuery},
+    region_map::RegionMapReader,
+    route::StreamUpdate,
+    route_diff::{self, RouteDiffError, RouteEntry, RouteUpdate},
+    snapshot::{self, Snapshot, SnapshotError},
+};
+use base64::Engine;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::future::LocalBoxFuture;
+use helium_crypto::{Keypair, PublicKey, Sign, Verify};
+use helium_proto::{Message, Region};
+use http_api::{
+    hyper::{self, Body, Method, Request, Response, StatusCode},
+    signed,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+use task_manager::ManagedTask;
+use tokio::sync::broadcast;
+
+pub const AUDIT_PATH: &str = "/audit";
+pub const ROUTE_EUIS_PATH: &str = "/routes/euis";
+pub const ROUTE_DEVADDR_RANGES_PATH: &str = "/routes/devaddr_ranges";
+pub const ROUTE_SKFS_PATH: &str = "/routes/skfs";
+pub const SNAPSHOT_EXPORT_PATH: &str = "/snapshot/export";
+pub const SNAPSHOT_IMPORT_PATH: &str = "/snapshot/import";
+pub const DEVADDR_USAGE_PATH: &str = "/devaddrs/usage";
+pub const DEVADDR_RECLAIM_PATH: &str = "/devaddrs/reclaim";
+pub const DEVADDR_ALLOCATE_PATH: &str = "/devaddrs/allocate";
+pub const REGION_PARAMS_PATH: &str = "/regions/params";
+pub const REGION_LOOKUP_PATH: &str = "/regions/lookup";
+
+pub use http_api::{SIGNATURE_HEADER, SIGNER_HEADER};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignatureError {
+    #[error("unable to encode request: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error("invalid request signer: {0}")]
+    Signer(#[from] helium_crypto::Error),
+    #[error("invalid request signature")]
+    Signature,
+    #[error("request signed at {0} is outside the accepted window")]
+    Expired(u64),
+}
+
+/// How far the time a request was signed at may be from the server's clock
+/// before the request is rejected, limiting how long a request can be replayed
+pub const REQUEST_WINDOW_SECS: u64 = 60;
+
+/// A signed api request. The base64 encoded signature covers the json
+/// encoding of the request with an empty signature.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Signed<T> {
+    #[serde(flatten)]
+    pub request: T,
+    pub signer: String,
+    /// Unix timestamp (seconds) the request was signed at
+    pub signed_at: u64,
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl<T: Serialize> Signed<T> {
+    pub fn sign(request: T, keypair: &Keypair) -> Result<Self, SignatureError> {
+        let mut signed = Self {
+            request,
+            signer: keypair.public_key().to_string(),
+            signed_at: Utc::now().timestamp() as u64,
+            signature: String::new(),
+        };
+        let signature = keypair.sign(&signed.signing_bytes()?)?;
+        signed.signature = base64::engine::general_purpose::STANDARD.encode(signature);
+        Ok(signed)
+    }
+
+    /// Verify the request signature, returning the signing key
+    pub fn verify(&self) -> Result<PublicKey, SignatureError> {
+        let signer = PublicKey::from_str(&self.signer)?;
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|_| SignatureError::Signature)?;
+        signer
+            .verify(&self.signing_bytes()?, &signature)
+            .map_err(|_| SignatureError::Signature)?;
+        Ok(signer)
+    }
+
+    /// Verify the request signature and that the request was signed within
+    /// [`REQUEST_WINDOW_SECS`] of `now`, returning the signing key
+    pub fn verify_at(&self, now: DateTime<Utc>) -> Result<PublicKey, SignatureError> {
+        let signer = self.verify()?;
+        if self.signed_at.abs_diff(now.timestamp() as u64) > REQUEST_WINDOW_SECS {
+            return Err(SignatureError::Expired(self.signed_at));
+        }
+        Ok(signer)
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(&SigningView {
+            request: &self.request,
+            signer: &self.signer,
+            signed_at: self.signed_at,
+            signature: "",
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct SigningView<'a, T> {
+    #[serde(flatten)]
+    request: &'a T,
+    signer: &'a str,
+    signed_at: u64,
+    signature: &'a str,
+}
+
+/// Request for a snapshot of the routing configuration
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SnapshotExport {}
+
+/// Request to restore a snapshot of the routing configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SnapshotImport {
+    /// Snapshot signed by a config service or an administrator key
+    pub snapshot: Signed<Snapshot>,
+    /// Apply the changes. If false the snapshot is only validated and the
+    /// changes it would make are returned
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// Request for the params of a region as of a point in time
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionParamsQuery {
+    /// Region name, eg: US915
+    pub region: String,
+    /// Unix timestamp (seconds) the params were active at
+    pub at: i64,
+}
+
+/// Params of a region as of a point in time
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionParamsAt {
+    pub region: String,
+    /// Unix timestamp (seconds) the region's params were loaded at
+    pub active_from: i64,
+    /// Base64 of the protobuf encoded `BlockchainRegionParamsV1`
+    pub params: String,
+}
+
+/// Serves the config service's http apis.
+///
+/// Requests are json [`Signed`] by the requesting key and are rejected if
+/// their `signed_at` is more than [`REQUEST_WINDOW_SECS`] from the server's
+/// clock. Responses are signed by the config service keypair. The signature of the exact response body
+/// is in the `x-signature` header, the signer in `x-signer`.
+///
+/// * `POST /audit` with an [`AuditQuery`] signed by an administrator key
+///   returns an [`audit::AuditPage`] of the matching entries, most recent
+///   first.
+/// * `POST /routes/euis`, `/routes/devaddr_ranges` and `/routes/skfs` with a
+///   [`RouteUpdate`] signed by an administrator key or a key of the route's
+///   org returns the [`route_diff::RouteDiff`] of the update, applied only if
+///   the update is committed.
+/// * `POST /snapshot/export` with a [`SnapshotExport`] signed by an
+///   administrator key returns the [`Snapshot`] of the routing configuration,
+///   [`Signed`] by the config service keypair.
+/// * `POST /snapshot/import` with a [`SnapshotImport`] signed by an
+///   administrator key returns the [`snapshot::SnapshotDiff`] restoring the
+///   snapshot makes, applied only if the import is committed. The snapshot
+///   must be signed by the config service keypair, an administrator key or
+///   one of the trusted snapshot signers.
+/// * `POST /devaddrs/usage`, `/devaddrs/reclaim` and `/devaddrs/allocate`
+///   with a [`UsageQuery`], [`ReclaimRequest`] or [`SlabRequest`] signed by an
+///   administrator key report the used and free devaddrs of each helium net
+///   id, release the devaddrs of disabled and deleted orgs and allocate an
+///   org a contiguous slab of devaddrs.
+/// * `POST /regions/params` with a [`RegionParamsQuery`] signed by any key
+///   known to the config service returns the [`RegionParamsAt`] of the region
+///   as they were at the given time.
+/// * `POST /regions/lookup` with a [`RegionLookupQuery`] signed by an
+///   administrator key returns the [`region_lookup::RegionLookup`] of the
+///   region, params and neighbouring regions of an h3 cell or lat/lng.
+pub struct ApiServer {
+    socket_addr: SocketAddr,
+    pool: Pool<Postgres>,
+    auth_cache: AuthCache,
+    signing_key: Arc<Keypair>,
+    /// Keys of other config services whose snapshots may be imported
+    snapshot_signers: Vec<PublicKey>,
+    update_tx: broadcast::Sender<StreamUpdate>,
+    quotas: Quotas,
+    region_map: RegionMapReader,
+}
+
+impl ApiServer {
+    pub fn new(
+        socket_addr: SocketAddr,
+        pool: Pool<Postgres>,
+        auth_cache: AuthCache,
+        signing_key: Arc<Keypair>,
+        snapshot_signers: Vec<PublicKey>,
+        update_tx: broadcast::Sender<StreamUpdate>,
+        quotas: Quotas,
+        region_map: RegionMapReader,
+    ) -> Self {
+        Self {
+            socket_addr,
+            pool,
+            auth_cache,
+            signing_key,
+            snapshot_signers,
+            update_tx,
+            quotas,
+            region_map,
+        }
+    }
+
+    async fn run(self, shutdown: triggered::Listener) -> anyhow::Result<()> {
+        tracing::info!(listen = self.socket_addr.to_string(), "starting http api");
+        let socket_addr = self.socket_addr;
+        let api = Arc::new(self);
+        http_api::serve(
+            &socket_addr,
+            move |req| {
+                let api = api.clone();
+                async move { api.handle(req).await }
+            },
+            shutdown,
+        )
+        .await?;
+        tracing::info!("stopping http api");
+        Ok(())
+    }
+
+    async fn handle(&self, req: Request<Body>) -> Response<Body> {
+        let result = if req.method() != Method::POST {
+            Err(StatusCode::NOT_FOUND.into())
+        } else if req.uri().path() == AUDIT_PATH {
+            self.audit_query(req).await
+        } else if req.uri().path() == ROUTE_EUIS_PATH {
+            self.route_update::<EuiPair>(req).await
+        } else if req.uri().path() == ROUTE_DEVADDR_RANGES_PATH {
+            self.route_update::<DevAddrRange>(req).await
+        } else if req.uri().path() == ROUTE_SKFS_PATH {
+            self.route_update::<Skf>(req).await
+        } else if req.uri().path() == SNAPSHOT_EXPORT_PATH {
+            self.snapshot_export(req).await
+        } else if req.uri().path() == SNAPSHOT_IMPORT_PATH {
+            self.snapshot_import(req).await
+        } else if req.uri().path() == DEVADDR_USAGE_PATH {
+            self.devaddr_usage(req).await
+        } else if req.uri().path() == DEVADDR_RECLAIM_PATH {
+            self.devaddr_reclaim(req).await
+        } else if req.uri().path() == DEVADDR_ALLOCATE_PATH {
+            self.devaddr_allocate(req).await
+        } else if req.uri().path() == REGION_PARAMS_PATH {
+            self.region_params(req).await
+        } else if req.uri().path() == REGION_LOOKUP_PATH {
+            self.region_lookup(req).await
+        } else {
+            Err(StatusCode::NOT_FOUND.into())
+        };
+        match result.and_then(|json| Ok(signed(&self.signing_key, json)?)) {
+            Ok(response) => response,
+            Err(err) => err.into_response(),
+        }
+    }
+
+    async fn audit_query(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let query: Signed<AuditQuery> = read_json(req).await?;
+        let signer = self.admin_signer(&query)?;
+        tracing::debug!(signer = signer.to_string(), query = ?query.request, "audit log query");
+
+        let page = audit::query(&query.request, &self.pool)
+            .await
+            .map_err(|err| match err {
+                AuditError::RouteIdParse(_) | AuditError::InvalidTimestamp(_) => {
+                    StatusCode::BAD_REQUEST
+                }
+                err => {
+                    tracing::error!(reason = ?err, "audit log query failed");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            })?;
+        Ok(serde_json::to_vec(&page).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn route_update<T>(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError>
+    where
+        T: RouteEntry + DeserializeOwned,
+    {
+        let update: Signed<RouteUpdate<T>> = read_json(req).await?;
+        let pubkey = update
+            .verify_at(Utc::now())
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        let signer = self.route_signer(&update.request.route_id, pubkey).await?;
+        let update = update.request;
+        tracing::debug!(
+            signer = %signer.pubkey,
+            route_id = update.route_id,
+            mode = ?update.mode,
+            commit = update.commit,
+            entries = update.entries.len(),
+            "route update"
+        );
+
+        let diff = route_diff::apply(
+            &update,
+            &signer,
+            &self.quotas,
+            &self.pool,
+            self.signing_key.clone(),
+            self.update_tx.clone(),
+        )
+        .await
+        .map_err(diff_error)?;
+        Ok(serde_json::to_vec(&diff).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn snapshot_export(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let request: Signed<SnapshotExport> = read_json(req).await?;
+        let signer = self.admin_signer(&request)?;
+        tracing::info!(signer = %signer, "exporting routing snapshot");
+
+        let snapshot = snapshot::export(&self.pool).await.map_err(snapshot_error)?;
+        let snapshot = Signed::sign(snapshot, &self.signing_key)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(serde_json::to_vec(&snapshot).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn snapshot_import(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let request: Signed<SnapshotImport> = read_json(req).await?;
+        let signer = self.admin_signer(&request)?;
+        let request = request.request;
+        let snapshot_signer = request
+            .snapshot
+            .verify()
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        if snapshot_signer != *self.signing_key.public_key()
+            && !self.snapshot_signers.contains(&snapshot_signer)
+            && !self
+                .auth_cache
+                .get_keys_by_type(KeyType::Administrator)
+                .contains(&snapshot_signer)
+        {
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+        tracing::info!(
+            signer = %signer,
+            snapshot_signer = %snapshot_signer,
+            change_seq = request.snapshot.request.change_seq,
+            commit = request.commit,
+            "importing routing snapshot"
+        );
+
+        let mut diff = snapshot::diff(&request.snapshot.request, &self.pool)
+            .await
+            .map_err(snapshot_error)?;
+        if request.commit {
+            snapshot::apply(
+                &mut diff,
+                &Signer::new(signer, KeyType::Administrator),
+                &self.pool,
+                &self.signing_key,
+                self.update_tx.clone(),
+            )
+            .await
+            .map_err(snapshot_error)?;
+        }
+        Ok(serde_json::to_vec(&diff).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn devaddr_usage(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let query: Signed<UsageQuery> = read_json(req).await?;
+        let signer = self.admin_signer(&query)?;
+        tracing::debug!(signer = %signer, "devaddr usage query");
+
+        let usage = devaddr_slabs::usage(&self.pool)
+            .await
+            .map_err(devaddr_slab_error)?;
+        Ok(serde_json::to_vec(&usage).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn devaddr_reclaim(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let request: Signed<ReclaimRequest> = read_json(req).await?;
+        let signer = self.admin_signer(&request)?;
+        let request = request.request;
+        tracing::info!(
+            signer = %signer,
+            grace_period_secs = request.grace_period_secs,
+            commit = request.commit,
+            "reclaiming devaddrs"
+        );
+
+        let reclaim = devaddr_slabs::reclaim(
+            &request,
+            &Signer::new(signer, KeyType::Administrator),
+            &self.pool,
+            &self.signing_key,
+            self.update_tx.clone(),
+        )
+        .await
+        .map_err(devaddr_slab_error)?;
+        Ok(serde_json::to_vec(&reclaim).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn devaddr_allocate(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let request: Signed<SlabRequest> = read_json(req).await?;
+        let signer = self.admin_signer(&request)?;
+        let request = request.request;
+        tracing::info!(
+            signer = %signer,
+            oui = request.oui,
+            count = request.count,
+            commit = request.commit,
+            "allocating devaddr slab"
+        );
+
+        let allocation = devaddr_slabs::allocate_slab(
+            &request,
+            &Signer::new(signer, KeyType::Administrator),
+            &self.pool,
+        )
+        .await
+        .map_err(devaddr_slab_error)?;
+        Ok(serde_json::to_vec(&allocation).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn region_params(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let query: Signed<RegionParamsQuery> = read_json(req).await?;
+        let signer = query
+            .verify_at(Utc::now())
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        if self.auth_cache.get_key_type(&signer).is_none() {
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+        let query = query.request;
+        let region = Region::from_str(&query.region).map_err(|_| ApiError {
+            status: StatusCode::BAD_REQUEST,
+            reason: Some(format!("unknown lora region {}", query.region)),
+        })?;
+        let at = Utc
+            .timestamp_opt(query.at, 0)
+            .single()
+            .ok_or_else(|| ApiError {
+                status: StatusCode::BAD_REQUEST,
+                reason: Some(format!("invalid timestamp {}", query.at)),
+            })?;
+        tracing::debug!(signer = %signer, %region, %at, "region params query");
+
+        let version = self
+            .region_map
+            .get_version_at(&region, at)
+            .ok_or_else(|| ApiError {
+                status: StatusCode::NOT_FOUND,
+                reason: Some(format!("no {region} params loaded as of {at}")),
+            })?;
+        let params = RegionParamsAt {
+            region: region.to_string(),
+            active_from: version.active_from.timestamp(),
+            params: base64::engine::general_purpose::STANDARD
+                .encode(version.params.encode_to_vec()),
+        };
+        Ok(serde_json::to_vec(&params).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    async fn region_lookup(&self, req: Request<Body>) -> Result<Vec<u8>, ApiError> {
+        let query: Signed<RegionLookupQuery> = read_json(req).await?;
+        let signer = self.admin_signer(&query)?;
+        tracing::debug!(signer = %signer, "region lookup");
+
+        let lookup =
+            region_lookup::lookup(&query.request, &self.region_map).map_err(region_lookup_error)?;
+        Ok(serde_json::to_vec(&lookup).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+    }
+
+    fn admin_signer<T: Serialize>(&self, request: &Signed<T>) -> Result<PublicKey, StatusCode> {
+        let signer = request
+            .verify_at(Utc::now())
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        if !self
+            .auth_cache
+            .get_keys_by_type(KeyType::Administrator)
+            .contains(&signer)
+        {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(signer)
+    }
+
+    /// Route entries may be updated by administrators and the keys of the
+    /// org owning the route
+    async fn route_signer(&self, route_id: &str, pubkey: PublicKey) -> Result<Signer, StatusCode> {
+        if self
+            .auth_cache
+            .get_keys_by_type(KeyType::Administrator)
+            .contains(&pubkey)
+        {
+            return Ok(Signer::new(pubkey, KeyType::Administrator));
+        }
+        let org_keys = org::get_org_pubkeys_by_route(route_id, &self.pool)
+            .await
+            .map_err(|err| match err {
+                OrgStoreError::RouteIdParse(_) => StatusCode::BAD_REQUEST,
+                OrgStoreError::FetchOrg(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+                err => {
+                    tracing::error!(reason = ?err, "failed to retrieve route org keys");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            })?;
+        if org_keys.contains(&pubkey) {
+            Ok(Signer::new(pubkey, SignerType::Org))
+        } else {
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+impl ManagedTask for ApiServer {
+    fn start_task(
+        self: Box<Self>,
+        shutdown: triggered::Listener,
+    ) -> LocalBoxFuture<'static, anyhow::Result<()>> {
+        Box::pin(self.run(shutdown))
+    }
+}
+
+/// An error response, with the reason in a plain text body for errors the
+/// requester can fix
+struct ApiError {
+    status: StatusCode,
+    reason: Option<String>,
+}
+
+impl ApiError {
+    fn into_response(self) -> Response<Body> {
+        let mut response = Response::new(self.reason.map(Body::from).unwrap_or_default());
+        *response.status_mut() = self.status;
+        response
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self {
+            status,
+            reason: None,
+        }
+    }
+}
+
+async fn read_json<T: DeserializeOwned>(req: Request<Body>) -> Result<T, ApiError> {
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    serde_json::from_slice(&body).map_err(|err| ApiError {
+        status: StatusCode::BAD_REQUEST,
+        reason: Some(err.to_string()),
+    })
+}
+
+fn diff_error(err: RouteDiffError) -> ApiError {
+    let status = match err {
+        RouteDiffError::RouteNotFound(_) => StatusCode::NOT_FOUND,
+        RouteDiffError::Invalid(_) => StatusCode::BAD_REQUEST,
+        RouteDiffError::Quota(err) => return quota_error(err),
+        err => {
+            tracing::error!(reason = ?err, "route update failed");
+            // Updates are applied in a single transaction
+            return ApiError {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                reason: Some("route update failed, no changes were applied".to_string()),
+            };
+        }
+    };
+    ApiError {
+        status,
+        reason: Some(err.to_string()),
+    }
+}
+
+fn quota_error(err: QuotaError) -> ApiError {
+    if let QuotaError::Db(err) = err {
+        tracing::error!(reason = ?err, "quota check failed");
+        return StatusCode::INTERNAL_SERVER_ERROR.into();
+    }
+    ApiError {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        reason: Some(err.to_string()),
+    }
+}
+
+fn snapshot_error(err: SnapshotError) -> ApiError {
+    let status = match err {
+        SnapshotError::Invalid(_) | SnapshotError::Version(_) => StatusCode::BAD_REQUEST,
+        SnapshotError::ExportConflict | SnapshotError::ImportConflict(_) => StatusCode::CONFLICT,
+        err => {
+            tracing::error!(reason = ?err, "routing snapshot failed");
+            return StatusCode::INTERNAL_SERVER_ERROR.into();
+        }
+    };
+    ApiError {
+        status,
+        reason: Some(err.to_string()),
+    }
+}
+
+fn devaddr_slab_error(err: DevAddrSlabError) -> ApiError {
+    let status = match err {
+        DevAddrSlabError::OrgNotFound(_) => StatusCode::NOT_FOUND,
+        DevAddrSlabError::Invalid(_) => StatusCode::BAD_REQUEST,
+        DevAddrSlabError::Unavailable(_) => StatusCode::CONFLICT,
+        err => {
+            tracing::error!(reason = ?err, "devaddr slab request failed");
+            return StatusCode::INTERNAL_SERVER_ERROR.into();
+        }
+    };
+    ApiError {
+        status,
+        reason: Some(err.to_string()),
+    }
+}
+
+fn region_lookup_error(err: RegionLookupError) -> ApiError {
+    let status = match err {
+        RegionLookupError::Invalid(_) => StatusCode::BAD_REQUEST,
+    };
+    ApiError {
+        status,
+        reason: Some(err.to_string()),
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{admin::KeyType, org_service::UpdateAuthorizer, telemetry};
+use chrono::{DateTime, TimeZone, Utc};
+use helium_crypto::PublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{types::Uuid, Postgres, Transaction};
+use tonic::Status;
+
+pub const DEFAULT_QUERY_LIMIT: i64 = 100;
+pub const MAX_QUERY_LIMIT: i64 = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuditError {
+    #[error("audit log query error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("unable to parse route id: {0}")]
+    RouteIdParse(#[from] sqlx::types::uuid::Error),
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(i64),
+}
+
+impl From<AuditError> for Status {
+    fn from(err: AuditError) -> Self {
+        match err {
+            AuditError::Db(_) => Status::internal("audit log record failed"),
+            err => Status::invalid_argument(err.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, sqlx::Type)]
+#[sqlx(type_name = "audit_signer_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SignerType {
+    Administrator,
+    PacketRouter,
+    Oracle,
+    Org,
+}
+
+impl From<KeyType> for SignerType {
+    fn from(key_type: KeyType) -> Self {
+        match key_type {
+            KeyType::Administrator => Self::Administrator,
+            KeyType::PacketRouter => Self::PacketRouter,
+            KeyType::Oracle => Self::Oracle,
+        }
+    }
+}
+
+impl From<&UpdateAuthorizer> for SignerType {
+    fn from(authorizer: &UpdateAuthorizer) -> Self {
+        match authorizer {
+            UpdateAuthorizer::Admin => Self::Administrator,
+            UpdateAuthorizer::Org => Self::Org,
+        }
+    }
+}
+
+/// The verified key that signed a configuration change
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signer {
+    pub pubkey: PublicKey,
+    pub signer_type: SignerType,
+}
+
+impl Signer {
+    pub fn new(pubkey: PublicKey, signer_type: impl Into<SignerType>) -> Self {
+        Self {
+            pubkey,
+            signer_type: signer_type.into(),
+        }
+    }
+}
+
+/// A configuration change to record in the audit log
+#[derive(Debug)]
+pub struct Entry {
+    signer: Signer,
+    rpc: &'static str,
+    oui: Option<u64>,
+    route_id: Option<String>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl Entry {
+    pub fn new(signer: &Signer, rpc: &'static str) -> Self {
+        Self {
+            signer: signer.clone(),
+            rpc,
+            oui: None,
+            route_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn oui(self, oui: u64) -> Self {
+        Self {
+            oui: Some(oui),
+            ..self
+        }
+    }
+
+    pub fn route_id(self, route_id: &str) -> Self {
+        Self {
+            route_id: Some(route_id.to_string()),
+            ..self
+        }
+    }
+
+    pub fn before<T: Serialize>(self, before: &T) -> Self {
+        Self {
+            before: Some(json!(before)),
+            ..self
+        }
+    }
+
+    pub fn after<T: Serialize>(self, after: &T) -> Self {
+        Self {
+            after: Some(json!(after)),
+            ..self
+        }
+    }
+}
+
+/// Record a configuration change in the transaction making it, so a change is
+/// only ever committed along with its audit log entry
+pub async fn record(
+    entry: Entry,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), AuditError> {
+    insert(&entry, transaction).await.map_err(|err| {
+        tracing::error!(
+            rpc = entry.rpc,
+            signer = %entry.signer.pubkey,
+            reason = ?err,
+            "failed to record audit log entry"
+        );
+        telemetry::count_audit_failure(entry.rpc);
+        err
+    })
+}
+
+/// Insert an audit log entry. Entries for a route without an oui are recorded
+/// under the oui of the route.
+async fn insert(entry: &Entry, db: impl sqlx::PgExecutor<'_>) -> Result<(), AuditError> {
+    let route_id = entry.route_id.as_deref().map(Uuid::try_parse).transpose()?;
+    sqlx::query(
+        r#"
+        insert into audit_log (signer, signer_type, rpc, oui, route_id, before, after)
+        values ($1, $2, $3, coalesce($4, (select oui from routes where id = $5)), $5, $6, $7)
+        "#,
+    )
+    .bind(entry.signer.pubkey.to_string())
+    .bind(entry.signer.signer_type)
+    .bind(entry.rpc)
+    .bind(entry.oui.map(|oui| oui as i64))
+    .bind(route_id)
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Audit log query. Only administrator keys may query the audit log.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AuditQuery {
+    pub oui: Option<u64>,
+    pub route_id: Option<String>,
+    /// Unix timestamp (seconds) of the earliest entry to return, inclusive
+    pub start: Option<i64>,
+    /// Unix timestamp (seconds) of the latest entry to return, exclusive
+    pub end: Option<i64>,
+    /// Only return entries older than this entry id, to page through results
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub signer: String,
+    pub signer_type: SignerType,
+    pub rpc: String,
+    pub oui: Option<i64>,
+    pub route_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub inserted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditRecord>,
+    /// `before_id` of the next page of entries, if the page is full
+    pub next_before_id: Option<i64>,
+}
+
+/// List the audit log entries matching a query, most recent first
+pub async fn query(
+    query: &AuditQuery,
+    db: impl sqlx::PgExecutor<'_>,
+) -> Result<AuditPage, AuditError> {
+    let route_id = query.route_id.as_deref().map(Uuid::try_parse).transpose()?;
+    let start = query.start.map(timestamp).transpose()?;
+    let end = query.end.map(timestamp).transpose()?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .clamp(1, MAX_QUERY_LIMIT);
+
+    let entries: Vec<AuditRecord> = sqlx::query_as(
+        r#"
+        select id, signer, signer_type, rpc, oui, route_id::text, before, after, inserted_at
+        from audit_log
+        where ($1::bigint is null or oui = $1)
+            and ($2::uuid is null or route_id = $2)
+            and ($3::timestamptz is null or inserted_at >= $3)
+            and ($4::timestamptz is null or inserted_at < $4)
+            and ($5::bigint is null or id < $5)
+        order by id desc
+        limit $6
+        "#,
+    )
+    .bind(query.oui.map(|oui| oui as i64))
+    .bind(route_id)
+    .bind(start)
+    .bind(end)
+    .bind(query.before_id)
+    .bind(limit)
+    .fetch_all(db)
+    .await?;
+
+    let next_before_id = if entries.len() as i64 == limit {
+        entries.last().map(|entry| entry.id)
+    } else {
+        None
+    };
+
+    Ok(AuditPage {
+        entries,
+        next_before_id,
+    })
+}
+
+/// Audit log entries after an entry id, along with the entries of `ids`,
+/// oldest first. Entry ids are taken when an entry is inserted but only seen
+/// once its transaction commits, so ids below one already seen may still
+/// appear; readers following the log pass the ids they skipped as `ids`.
+pub async fn entries_after(
+    after_id: i64,
+    ids: &[i64],
+    limit: i64,
+    db: impl sqlx::PgExecutor<'_>,
+) -> Result<Vec<AuditRecord>, AuditError> {
+    Ok(sqlx::query_as(
+        r#"
+        select id, signer, signer_type, rpc, oui, route_id::text, before, after, inserted_at
+        from audit_log
+        where id > $1 or id = any($2)
+        order by id
+        limit $3
+        "#,
+    )
+    .bind(after_id)
+    .bind(ids)
+    .bind(limit)
+    .fetch_all(db)
+    .await?)
+}
+
+/// Id of the latest audit log entry, 0 if there are none
+pub async fn latest_id(db: impl sqlx::PgExecutor<'_>) -> Result<i64, AuditError> {
+    Ok(
+        sqlx::query_scalar("select coalesce(max(id), 0)::bigint from audit_log")
+            .fetch_one(db)
+            .await?,
+    )
+}
+
+fn timestamp(secs: i64) -> Result<DateTime<Utc>, AuditError> {
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .ok_or(AuditError::InvalidTimestamp(secs))
+}
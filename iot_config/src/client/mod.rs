@@ -9,9 +9,11 @@ use helium_proto::{
 use std::{sync::Arc, time::Duration};
 
 pub mod org_client;
+pub mod route_client;
 mod settings;
 
 pub use org_client::OrgClient;
+pub use route_client::RouteClient;
 pub use settings::Settings;
 
 #[derive(thiserror::Error, Debug)]
@@ -24,6 +26,10 @@ pub enum ClientError {
     Verification(#[from] file_store::Error),
     #[error("error resolving region params: {0}")]
     UndefinedRegionParams(String),
+    #[error("route missing from response")]
+    UndefinedRoute,
+    #[error("error decoding validated diff: {0}")]
+    ValidatedDiff(#[from] serde_json::Error),
 }
 
 #[async_trait::async_trait]
@@ -6,6 +6,7 @@ use async_trait::async_trait;
 use chrono::Utc;
 use file_store::traits::TimestampEncode;
 use helium_proto::services::iot_config::{
+    org_create_helium_req_v1::HeliumNetId, OrgCreateHeliumReqV1, OrgCreateRoamerReqV1,
     OrgDisableReqV1, OrgEnableReqV1, OrgGetReqV1, OrgListReqV1, OrgResV1, OrgV1,
 };
 
@@ -38,6 +39,59 @@ impl OrgClient {
             config_pubkey: settings.config_pubkey()?,
         })
     }
+
+    /// Create an org under a helium net id, checking out `devaddrs` addresses
+    /// from the net id's devaddr space
+    pub async fn create_helium(
+        &mut self,
+        owner: &PublicKey,
+        payer: &PublicKey,
+        devaddrs: u64,
+        net_id: HeliumNetId,
+        delegate_keys: &[PublicKey],
+    ) -> Result<OrgResV1, ClientError> {
+        tracing::info!(%owner, %devaddrs, ?net_id, "creating helium org");
+
+        let mut req = OrgCreateHeliumReqV1 {
+            owner: owner.into(),
+            payer: payer.into(),
+            devaddrs,
+            timestamp: Utc::now().encode_timestamp(),
+            signature: vec![],
+            delegate_keys: delegate_keys.iter().map(|key| key.into()).collect(),
+            signer: self.signing_key.public_key().into(),
+            net_id: net_id.into(),
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.create_helium(req.clone()))?.into_inner();
+        res.verify(&self.config_pubkey)?;
+        Ok(res)
+    }
+
+    /// Create an org for a roaming partner under its own net id
+    pub async fn create_roamer(
+        &mut self,
+        owner: &PublicKey,
+        payer: &PublicKey,
+        net_id: u32,
+        delegate_keys: &[PublicKey],
+    ) -> Result<OrgResV1, ClientError> {
+        tracing::info!(%owner, %net_id, "creating roamer org");
+
+        let mut req = OrgCreateRoamerReqV1 {
+            owner: owner.into(),
+            payer: payer.into(),
+            net_id,
+            timestamp: Utc::now().encode_timestamp(),
+            signature: vec![],
+            delegate_keys: delegate_keys.iter().map(|key| key.into()).collect(),
+            signer: self.signing_key.public_key().into(),
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.create_roamer(req.clone()))?.into_inner();
+        res.verify(&self.config_pubkey)?;
+        Ok(res)
+    }
 }
 
 #[async_trait]
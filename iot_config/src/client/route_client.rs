@@ -0,0 +1,327 @@
+use super::{
+    call_with_retry, iot_config, Arc, Channel, ClientError, Duration, Endpoint, Keypair, Message,
+    MsgVerify, PublicKey, Settings, Sign,
+};
+use crate::{
+    lora_field::{DevAddrRange, EuiPair, Skf},
+    route::Route,
+    route_service::{ValidatedDiff, VALIDATE_ONLY_DIFF_METADATA_KEY, VALIDATE_ONLY_METADATA_KEY},
+};
+use chrono::Utc;
+use file_store::traits::TimestampEncode;
+use futures::stream::{self, TryStreamExt};
+use helium_proto::services::iot_config::{
+    route_skf_update_req_v1::RouteSkfUpdateV1, ActionV1, RouteCreateReqV1, RouteDeleteReqV1,
+    RouteGetDevaddrRangesReqV1, RouteGetEuisReqV1, RouteGetReqV1, RouteListReqV1, RouteResV1,
+    RouteSkfListReqV1, RouteSkfUpdateReqV1, RouteUpdateDevaddrRangesReqV1, RouteUpdateEuisReqV1,
+    RouteUpdateReqV1,
+};
+use serde::de::DeserializeOwned;
+
+/// Maximum number of session key filter updates accepted per request
+pub const SKF_UPDATE_BATCH_SIZE: usize = 100;
+
+/// Signed client of the iot config `RouteService`.
+///
+/// Every request is signed with the configured keypair and every signed
+/// response is verified against the config service public key. The eui,
+/// devaddr range and session key filter listings are streamed back by the
+/// service unsigned.
+#[derive(Clone)]
+pub struct RouteClient {
+    client: iot_config::route_client::RouteClient<Channel>,
+    signing_key: Arc<Keypair>,
+    config_pubkey: PublicKey,
+}
+
+impl RouteClient {
+    pub fn from_settings(settings: &Settings) -> Result<Self, Box<helium_crypto::Error>> {
+        let channel = Endpoint::from(settings.url.clone())
+            .connect_timeout(Duration::from_secs(settings.connect_timeout))
+            .timeout(Duration::from_secs(settings.rpc_timeout))
+            .connect_lazy();
+        Ok(Self {
+            client: iot_config::route_client::RouteClient::new(channel),
+            signing_key: settings.signing_keypair()?,
+            config_pubkey: settings.config_pubkey()?,
+        })
+    }
+
+    pub async fn list(&mut self, oui: u64) -> Result<Vec<Route>, ClientError> {
+        tracing::debug!(%oui, "retrieving route list");
+
+        let mut req = RouteListReqV1 {
+            oui,
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.list(req.clone()))?.into_inner();
+        res.verify(&self.config_pubkey)?;
+        Ok(res.routes.into_iter().map(Route::from).collect())
+    }
+
+    pub async fn get(&mut self, id: &str) -> Result<Route, ClientError> {
+        tracing::debug!(%id, "retrieving route");
+
+        let mut req = RouteGetReqV1 {
+            id: id.to_string(),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.get(req.clone()))?.into_inner();
+        self.verify_route(res)
+    }
+
+    pub async fn create(&mut self, oui: u64, route: Route) -> Result<Route, ClientError> {
+        tracing::info!(%oui, "creating route");
+
+        let mut req = RouteCreateReqV1 {
+            oui,
+            route: Some(route.into()),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.create(req.clone()))?.into_inner();
+        self.verify_route(res)
+    }
+
+    pub async fn update(&mut self, route: Route) -> Result<Route, ClientError> {
+        tracing::info!(id = route.id, "updating route");
+
+        let mut req = RouteUpdateReqV1 {
+            route: Some(route.into()),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.update(req.clone()))?.into_inner();
+        self.verify_route(res)
+    }
+
+    pub async fn delete(&mut self, id: &str) -> Result<Route, ClientError> {
+        tracing::info!(%id, "deleting route");
+
+        let mut req = RouteDeleteReqV1 {
+            id: id.to_string(),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let res = call_with_retry!(self.client.delete(req.clone()))?.into_inner();
+        self.verify_route(res)
+    }
+
+    pub async fn get_euis(&mut self, route_id: &str) -> Result<Vec<EuiPair>, ClientError> {
+        tracing::debug!(%route_id, "retrieving route euis");
+
+        let mut req = RouteGetEuisReqV1 {
+            route_id: route_id.to_string(),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let euis = call_with_retry!(self.client.get_euis(req.clone()))?
+            .into_inner()
+            .map_ok(EuiPair::from)
+            .try_collect()
+            .await?;
+        Ok(euis)
+    }
+
+    /// Add or remove eui pairs, streamed to the service as one signed request
+    /// per pair. With `validate_only` the service checks and rolls back the
+    /// update without committing it, returning the changes it would make.
+    pub async fn update_euis(
+        &mut self,
+        action: ActionV1,
+        euis: &[EuiPair],
+        validate_only: bool,
+    ) -> Result<Option<ValidatedDiff<EuiPair>>, ClientError> {
+        tracing::info!(?action, count = euis.len(), "updating route euis");
+
+        let reqs = euis
+            .iter()
+            .map(|eui_pair| {
+                let mut req = RouteUpdateEuisReqV1 {
+                    action: action.into(),
+                    eui_pair: Some(eui_pair.into()),
+                    timestamp: Utc::now().encode_timestamp(),
+                    signer: self.signing_key.public_key().into(),
+                    signature: vec![],
+                };
+                req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+                Ok(req)
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+        let res = self
+            .client
+            .update_euis(update_request(stream::iter(reqs), validate_only))
+            .await?;
+        let validated = validated_diff(res.metadata())?;
+        res.into_inner().verify(&self.config_pubkey)?;
+        Ok(validated)
+    }
+
+    pub async fn get_devaddr_ranges(
+        &mut self,
+        route_id: &str,
+    ) -> Result<Vec<DevAddrRange>, ClientError> {
+        tracing::debug!(%route_id, "retrieving route devaddr ranges");
+
+        let mut req = RouteGetDevaddrRangesReqV1 {
+            route_id: route_id.to_string(),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let ranges = call_with_retry!(self.client.get_devaddr_ranges(req.clone()))?
+            .into_inner()
+            .map_ok(DevAddrRange::from)
+            .try_collect()
+            .await?;
+        Ok(ranges)
+    }
+
+    /// Add or remove devaddr ranges, streamed to the service as one signed
+    /// request per range. With `validate_only` the service checks and rolls
+    /// back the update without committing it, returning the changes it would
+    /// make.
+    pub async fn update_devaddr_ranges(
+        &mut self,
+        action: ActionV1,
+        ranges: &[DevAddrRange],
+        validate_only: bool,
+    ) -> Result<Option<ValidatedDiff<DevAddrRange>>, ClientError> {
+        tracing::info!(
+            ?action,
+            count = ranges.len(),
+            "updating route devaddr ranges"
+        );
+
+        let reqs = ranges
+            .iter()
+            .map(|devaddr_range| {
+                let mut req = RouteUpdateDevaddrRangesReqV1 {
+                    action: action.into(),
+                    devaddr_range: Some(devaddr_range.into()),
+                    timestamp: Utc::now().encode_timestamp(),
+                    signer: self.signing_key.public_key().into(),
+                    signature: vec![],
+                };
+                req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+                Ok(req)
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+        let res = self
+            .client
+            .update_devaddr_ranges(update_request(stream::iter(reqs), validate_only))
+            .await?;
+        let validated = validated_diff(res.metadata())?;
+        res.into_inner().verify(&self.config_pubkey)?;
+        Ok(validated)
+    }
+
+    pub async fn list_skfs(&mut self, route_id: &str) -> Result<Vec<Skf>, ClientError> {
+        tracing::debug!(%route_id, "retrieving route session key filters");
+
+        let mut req = RouteSkfListReqV1 {
+            route_id: route_id.to_string(),
+            timestamp: Utc::now().encode_timestamp(),
+            signer: self.signing_key.public_key().into(),
+            signature: vec![],
+        };
+        req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+        let skfs = call_with_retry!(self.client.list_skfs(req.clone()))?
+            .into_inner()
+            .map_ok(Skf::from)
+            .try_collect()
+            .await?;
+        Ok(skfs)
+    }
+
+    /// Add or remove session key filters of a route, sent in batches of
+    /// `SKF_UPDATE_BATCH_SIZE` updates per signed request. With
+    /// `validate_only` each batch is checked and rolled back by the service
+    /// without being committed, returning the changes the batches would make.
+    pub async fn update_skfs(
+        &mut self,
+        route_id: &str,
+        updates: &[(ActionV1, Skf)],
+        validate_only: bool,
+    ) -> Result<Option<ValidatedDiff<Skf>>, ClientError> {
+        tracing::info!(%route_id, count = updates.len(), "updating route session key filters");
+
+        let mut validated: Option<ValidatedDiff<Skf>> = None;
+        for batch in updates.chunks(SKF_UPDATE_BATCH_SIZE) {
+            let mut req = RouteSkfUpdateReqV1 {
+                route_id: route_id.to_string(),
+                updates: batch
+                    .iter()
+                    .map(|(action, skf)| RouteSkfUpdateV1 {
+                        devaddr: skf.devaddr.into(),
+                        session_key: skf.session_key.clone(),
+                        action: (*action).into(),
+                        max_copies: skf.max_copies,
+                    })
+                    .collect(),
+                timestamp: Utc::now().encode_timestamp(),
+                signer: self.signing_key.public_key().into(),
+                signature: vec![],
+            };
+            req.signature = self.signing_key.sign(&req.encode_to_vec())?;
+            let res = call_with_retry!(self
+                .client
+                .update_skfs(update_request(req.clone(), validate_only)))?;
+            if let Some(diff) = validated_diff(res.metadata())? {
+                let validated = validated.get_or_insert_with(ValidatedDiff::default);
+                validated.adds.extend(diff.adds);
+                validated.removes.extend(diff.removes);
+            }
+            res.into_inner().verify(&self.config_pubkey)?;
+        }
+        Ok(validated)
+    }
+
+    fn verify_route(&self, res: RouteResV1) -> Result<Route, ClientError> {
+        res.verify(&self.config_pubkey)?;
+        res.route
+            .map(Route::from)
+            .ok_or(ClientError::UndefinedRoute)
+    }
+}
+
+fn update_request<T>(message: T, validate_only: bool) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    if validate_only {
+        request.metadata_mut().insert(
+            VALIDATE_ONLY_METADATA_KEY,
+            tonic::metadata::MetadataValue::from_static("true"),
+        );
+    }
+    request
+}
+
+/// The changes of a validate-only update, from the response metadata
+fn validated_diff<T: DeserializeOwned>(
+    metadata: &tonic::metadata::MetadataMap,
+) -> Result<Option<ValidatedDiff<T>>, ClientError> {
+    metadata
+        .get_bin(VALIDATE_ONLY_DIFF_METADATA_KEY)
+        .map(|diff| -> Result<ValidatedDiff<T>, ClientError> {
+            let diff = diff
+                .to_bytes()
+                .map_err(|_| tonic::Status::internal("invalid validated diff metadata"))?;
+            Ok(serde_json::from_slice(&diff)?)
+        })
+        .transpose()
+}
@@ -43,6 +43,7 @@ impl DbCleaner {
                     let mut tx = self.pool.begin().await?;
                     let timestamp = Utc::now() - self.deleted_entry_retention;
 
+                    record_purged_change_seq(&mut tx, timestamp).await?;
                     delete_skfs(&mut tx, timestamp).await?;
                     delete_devaddr_ranges(&mut tx, timestamp).await?;
                     delete_euis(&mut tx, timestamp).await?;
@@ -55,6 +56,35 @@ impl DbCleaner {
     }
 }
 
+/// Advance the route change horizon past the deleted entries about to be
+/// purged so streams can no longer resume from before them
+async fn record_purged_change_seq(
+    tx: &mut Transaction<'_, Postgres>,
+    timestamp: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        update route_change_horizon
+        set purged_change_seq = greatest(purged_change_seq, (
+            select coalesce(max(change_seq), 0) from (
+                select change_seq from routes where deleted = true and updated_at < $1
+                union all
+                select change_seq from route_eui_pairs where deleted = true and updated_at < $1
+                union all
+                select change_seq from route_devaddr_ranges where deleted = true and updated_at < $1
+                union all
+                select change_seq from route_session_key_filters where deleted = true and updated_at < $1
+            ) purged
+        ))
+    "#,
+    )
+    .bind(timestamp)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
 async fn delete_routes(
     tx: &mut Transaction<'_, Postgres>,
     timestamp: DateTime<Utc>,
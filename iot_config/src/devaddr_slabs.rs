@@ -0,0 +1,398 @@
+use crate::{
+    audit::{self, AuditError, Signer},
+    helium_netids::{self, is_helium_netid, AddressStore, DevAddrConstraintsError, HeliumNetId},
+    lora_field::{DevAddrConstraint, DevAddrRange, NetIdField},
+    org,
+    route::{self, proto, PendingUpdates, StreamUpdate},
+};
+use chrono::{DateTime, Duration, Utc};
+use helium_crypto::Keypair;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres, Row, Transaction};
+use std::ops::RangeInclusive;
+use tokio::sync::broadcast::Sender;
+
+pub use crate::helium_netids::NetIdUsage;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DevAddrSlabError {
+    #[error("org not found: {0}")]
+    OrgNotFound(u64),
+    #[error("invalid request: {0}")]
+    Invalid(String),
+    #[error("no devaddr slab available: {0}")]
+    Unavailable(String),
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("audit log error: {0}")]
+    Audit(#[from] AuditError),
+    #[error("devaddr range error: {0}")]
+    Ranges(#[from] anyhow::Error),
+}
+
+/// Request for the devaddr usage of the helium net ids
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UsageQuery {}
+
+/// Request to reclaim the devaddrs of disabled and deleted orgs
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReclaimRequest {
+    /// Only devaddrs of orgs disabled, or orphaned by the removal of their
+    /// org's constraints, for longer than this many seconds are reclaimed
+    pub grace_period_secs: u64,
+    /// Release the devaddrs. If false the devaddrs that would be reclaimed
+    /// are only listed
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// Request for a contiguous slab of devaddrs for an org
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SlabRequest {
+    pub oui: u64,
+    /// Number of devaddrs in the slab, a positive even number
+    pub count: u64,
+    /// Helium net id of the slab, required only if the org has no devaddr
+    /// constraints to take it from
+    #[serde(default)]
+    pub net_id: Option<NetIdField>,
+    /// Add the slab to the org's constraints. If false the slab that would
+    /// be allocated is only returned
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// The slab allocated to an org, or that would be if not committed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SlabAllocation {
+    pub committed: bool,
+    pub oui: u64,
+    pub net_id: NetIdField,
+    pub constraint: DevAddrConstraint,
+}
+
+/// A run of claimed devaddrs not within any org's constraints
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrphanedSlab {
+    pub net_id: NetIdField,
+    pub slab: DevAddrConstraint,
+}
+
+/// A disabled org whose devaddrs are reclaimed, along with the devaddr ranges
+/// of its routes that are removed with them
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReclaimedOrg {
+    pub oui: u64,
+    pub net_id: NetIdField,
+    /// Unix timestamp (seconds) the org was disabled
+    pub locked_at: i64,
+    pub constraints: Vec<DevAddrConstraint>,
+    pub devaddr_ranges: Vec<DevAddrRange>,
+}
+
+/// The devaddrs a reclaim released, or would release if not committed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Reclaim {
+    pub committed: bool,
+    pub orphaned: Vec<OrphanedSlab>,
+    pub orgs: Vec<ReclaimedOrg>,
+}
+
+impl Reclaim {
+    pub fn is_empty(&self) -> bool {
+        self.orphaned.is_empty() && self.orgs.is_empty()
+    }
+}
+
+/// Used and free devaddrs of each helium net id
+pub async fn usage(db: &Pool<Postgres>) -> Result<Vec<NetIdUsage>, DevAddrSlabError> {
+    let mut transaction = db.begin().await?;
+    let mut usage = Vec::with_capacity(HeliumNetId::ALL.len());
+    for net_id in HeliumNetId::ALL {
+        let used_addrs = transaction.get_used_addrs(net_id).await?;
+        let mut net_id_usage = NetIdUsage::new(net_id, &used_addrs);
+        net_id_usage.orphaned_addrs = orphaned_addrs(net_id, Utc::now(), &mut transaction)
+            .await?
+            .len() as u64;
+        usage.push(net_id_usage);
+    }
+    transaction.commit().await?;
+    Ok(usage)
+}
+
+/// Release the devaddrs of orgs disabled for longer than the grace period,
+/// removing the devaddr ranges of their routes, and the claimed devaddrs
+/// orphaned, no longer within any org's constraints, for longer than the grace
+/// period. Disabled orgs are left without
+/// constraints; re-enabling one requires allocating it a new slab.
+pub async fn reclaim(
+    request: &ReclaimRequest,
+    signer: &Signer,
+    db: &Pool<Postgres>,
+    signing_key: &Keypair,
+    update_tx: Sender<StreamUpdate>,
+) -> Result<Reclaim, DevAddrSlabError> {
+    let cutoff = Duration::from_std(std::time::Duration::from_secs(request.grace_period_secs))
+        .ok()
+        .and_then(|grace_period| Utc::now().checked_sub_signed(grace_period))
+        .ok_or_else(|| {
+            DevAddrSlabError::Invalid(format!(
+                "grace period out of range: {}",
+                request.grace_period_secs
+            ))
+        })?;
+    let mut transaction = db.begin().await?;
+
+    let mut orphaned = vec![];
+    for net_id in HeliumNetId::ALL {
+        let addrs = orphaned_addrs(net_id, cutoff, &mut transaction).await?;
+        orphaned.extend(addr_runs(&addrs).into_iter().map(|run| OrphanedSlab {
+            net_id: net_id.id(),
+            slab: DevAddrConstraint {
+                start_addr: (*run.start()).into(),
+                end_addr: (*run.end()).into(),
+            },
+        }));
+    }
+
+    let mut orgs = disabled_orgs(cutoff, &mut transaction).await?;
+    for org in orgs.iter_mut() {
+        org.devaddr_ranges = org_devaddr_ranges(org.oui, &mut transaction).await?;
+    }
+
+    if !request.commit {
+        return Ok(Reclaim {
+            committed: false,
+            orphaned,
+            orgs,
+        });
+    }
+
+    for orphan in orphaned.iter() {
+        release_slab(orphan.net_id, &orphan.slab, &mut transaction).await?;
+    }
+    let mut removed_ranges = vec![];
+    for org in orgs.iter() {
+        removed_ranges
+            .extend(route::remove_devaddr_ranges(&org.devaddr_ranges, &mut transaction).await?);
+        for constraint in org.constraints.iter() {
+            release_slab(org.net_id, constraint, &mut transaction).await?;
+        }
+        sqlx::query(" delete from organization_devaddr_constraints where oui = $1 ")
+            .bind(org.oui as i64)
+            .execute(&mut transaction)
+            .await?;
+    }
+
+    let reclaim = Reclaim {
+        committed: true,
+        orphaned,
+        orgs,
+    };
+    if !reclaim.is_empty() {
+        audit::record(
+            audit::Entry::new(signer, "devaddr_reclaim").before(&reclaim),
+            &mut transaction,
+        )
+        .await?;
+    }
+    PendingUpdates::devaddr_ranges(proto::ActionV1::Remove, removed_ranges)
+        .commit(transaction, signing_key, &update_tx)
+        .await?;
+
+    Ok(reclaim)
+}
+
+/// Allocate an org a contiguous slab of devaddrs from its net id, as close
+/// to its existing constraints as the free devaddrs allow
+pub async fn allocate_slab(
+    request: &SlabRequest,
+    signer: &Signer,
+    db: &Pool<Postgres>,
+) -> Result<SlabAllocation, DevAddrSlabError> {
+    let mut transaction = db.begin().await?;
+
+    let org = org::get(request.oui, &mut transaction)
+        .await?
+        .ok_or(DevAddrSlabError::OrgNotFound(request.oui))?;
+    let constraints = org.constraints.unwrap_or_default();
+    let net_id = if constraints.is_empty() {
+        request.net_id.ok_or_else(|| {
+            DevAddrSlabError::Invalid(format!(
+                "org {} has no devaddr constraints, a net id is required",
+                request.oui
+            ))
+        })?
+    } else {
+        let net_id = org::get_org_netid(request.oui, &mut transaction).await?;
+        if matches!(request.net_id, Some(requested) if requested != net_id) {
+            return Err(DevAddrSlabError::Invalid(format!(
+                "org {} devaddrs are from net id {net_id}",
+                request.oui
+            )));
+        }
+        net_id
+    };
+    let helium_net_id = HeliumNetId::try_from(net_id)
+        .map_err(|_| DevAddrSlabError::Invalid(format!("{net_id} is not a helium net id")))?;
+
+    let constraint = helium_netids::checkout_devaddr_slab(
+        &mut transaction,
+        request.count,
+        helium_net_id,
+        &constraints,
+    )
+    .await
+    .map_err(|err| match err {
+        DevAddrConstraintsError::AddressStore(err) => DevAddrSlabError::Db(err),
+        DevAddrConstraintsError::NoAvailableAddrs => DevAddrSlabError::Unavailable(format!(
+            "no slab of {} devaddrs free in net id {net_id}",
+            request.count
+        )),
+        err => DevAddrSlabError::Invalid(err.to_string()),
+    })?;
+
+    if request.commit {
+        org::insert_helium_constraints(
+            request.oui,
+            net_id,
+            std::slice::from_ref(&constraint),
+            &mut transaction,
+        )
+        .await?;
+        audit::record(
+            audit::Entry::new(signer, "devaddr_allocate")
+                .oui(request.oui)
+                .after(&constraint),
+            &mut transaction,
+        )
+        .await?;
+        transaction.commit().await?;
+    }
+
+    Ok(SlabAllocation {
+        committed: request.commit,
+        oui: request.oui,
+        net_id,
+        constraint,
+    })
+}
+
+/// Claimed devaddrs of the net id orphaned before `before`, no longer within
+/// the constraints of any org
+async fn orphaned_addrs(
+    net_id: HeliumNetId,
+    before: DateTime<Utc>,
+    db: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<u32>, sqlx::Error> {
+    Ok(sqlx::query_scalar::<_, i32>(
+        r#"
+        select used.devaddr from helium_used_devaddrs used
+        where used.net_id = $1 and used.orphaned_at < $2
+            and not exists (
+                select 1 from organization_devaddr_constraints org_const
+                where org_const.net_id = used.net_id
+                    and used.devaddr between org_const.start_addr and org_const.end_addr
+            )
+        order by used.devaddr asc
+        "#,
+    )
+    .bind(i32::from(net_id.id()))
+    .bind(before)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|addr| addr as u32)
+    .collect())
+}
+
+/// Helium orgs disabled before the cutoff that still hold devaddr constraints.
+/// The org rows are locked until the transaction ends so an org can't be
+/// re-enabled while its devaddrs are reclaimed.
+async fn disabled_orgs(
+    cutoff: DateTime<Utc>,
+    db: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<ReclaimedOrg>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        select org.oui, org.locked_at, org_const.net_id, org_const.start_addr, org_const.end_addr
+        from organizations org
+        join organization_devaddr_constraints org_const on org_const.oui = org.oui
+        where org.locked and org.locked_at < $1
+        order by org.oui, org_const.start_addr
+        for update of org
+        "#,
+    )
+    .bind(cutoff)
+    .fetch_all(db)
+    .await?;
+
+    let mut orgs: Vec<ReclaimedOrg> = vec![];
+    for row in rows {
+        let oui = row.get::<i64, &str>("oui") as u64;
+        let net_id: NetIdField = row.get::<i32, &str>("net_id").into();
+        if !is_helium_netid(&net_id) {
+            continue;
+        }
+        let constraint = DevAddrConstraint {
+            start_addr: row.get::<i32, &str>("start_addr").into(),
+            end_addr: row.get::<i32, &str>("end_addr").into(),
+        };
+        match orgs.last_mut() {
+            Some(org) if org.oui == oui => org.constraints.push(constraint),
+            _ => orgs.push(ReclaimedOrg {
+                oui,
+                net_id,
+                locked_at: row.get::<DateTime<Utc>, &str>("locked_at").timestamp(),
+                constraints: vec![constraint],
+                devaddr_ranges: vec![],
+            }),
+        }
+    }
+    Ok(orgs)
+}
+
+async fn org_devaddr_ranges(
+    oui: u64,
+    db: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<DevAddrRange>, sqlx::Error> {
+    sqlx::query_as::<_, DevAddrRange>(
+        r#"
+        select ranges.route_id, ranges.start_addr, ranges.end_addr
+        from route_devaddr_ranges ranges
+        join routes on routes.id = ranges.route_id
+        where routes.oui = $1 and ranges.deleted = false
+        "#,
+    )
+    .bind(oui as i64)
+    .fetch_all(db)
+    .await
+}
+
+async fn release_slab(
+    net_id: NetIdField,
+    slab: &DevAddrConstraint,
+    db: &mut Transaction<'_, Postgres>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        " delete from helium_used_devaddrs where net_id = $1 and devaddr between $2 and $3 ",
+    )
+    .bind(i32::from(net_id))
+    .bind(i32::from(slab.start_addr))
+    .bind(i32::from(slab.end_addr))
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+/// Runs of consecutive addresses in sorted addresses
+fn addr_runs(addrs: &[u32]) -> Vec<RangeInclusive<u32>> {
+    let mut runs: Vec<RangeInclusive<u32>> = vec![];
+    for &addr in addrs {
+        match runs.last_mut() {
+            Some(run) if *run.end() + 1 == addr => *run = *run.start()..=addr,
+            _ => runs.push(addr..=addr),
+        }
+    }
+    runs
+}
@@ -1,5 +1,6 @@
 use crate::lora_field::{self, DevAddrConstraint, LoraField, NetIdField};
 use helium_proto::services::iot_config::org_create_helium_req_v1::HeliumNetId as ProtoNetId;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, ops::RangeInclusive};
 
 const TYPE_0_ID: NetIdField = LoraField(0x00003c);
@@ -17,6 +18,12 @@ pub enum HeliumNetId {
 }
 
 impl HeliumNetId {
+    pub const ALL: [HeliumNetId; 3] = [
+        HeliumNetId::Type0_0x00003c,
+        HeliumNetId::Type3_0x60002d,
+        HeliumNetId::Type6_0xc00053,
+    ];
+
     pub fn id(&self) -> NetIdField {
         match *self {
             HeliumNetId::Type0_0x00003c => TYPE_0_ID,
@@ -48,6 +55,55 @@ impl TryFrom<NetIdField> for HeliumNetId {
     }
 }
 
+/// Claimed and free addresses of a helium net id
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NetIdUsage {
+    pub net_id: NetIdField,
+    pub total_addrs: u64,
+    pub used_addrs: u64,
+    pub free_addrs: u64,
+    /// Number of runs of contiguous free addresses
+    pub free_slabs: u64,
+    pub largest_free_slab: u64,
+    /// Share of the free addresses outside of the largest free slab, 0 when
+    /// all free addresses are contiguous
+    pub fragmentation: f64,
+    /// Claimed addresses not within the devaddr constraints of any org, as
+    /// left behind by deleted orgs
+    #[serde(default)]
+    pub orphaned_addrs: u64,
+}
+
+impl NetIdUsage {
+    /// Usage of a net id from its sorted used addresses
+    pub fn new(net_id: HeliumNetId, used_addrs: &[u32]) -> Self {
+        let addr_range = net_id.addr_range();
+        let total_addrs = (addr_range.end() - addr_range.start()) as u64 + 1;
+        let free_slabs = free_slabs(net_id, used_addrs);
+        let free_addrs: u64 = free_slabs.iter().map(slab_len).sum();
+        let largest_free_slab = free_slabs.iter().map(slab_len).max().unwrap_or(0);
+        let fragmentation = if free_addrs == 0 {
+            0.0
+        } else {
+            (free_addrs - largest_free_slab) as f64 / free_addrs as f64
+        };
+        Self {
+            net_id: net_id.id(),
+            total_addrs,
+            used_addrs: total_addrs - free_addrs,
+            free_addrs,
+            free_slabs: free_slabs.len() as u64,
+            largest_free_slab,
+            fragmentation,
+            orphaned_addrs: 0,
+        }
+    }
+}
+
+fn slab_len(slab: &RangeInclusive<u32>) -> u64 {
+    (slab.end() - slab.start()) as u64 + 1
+}
+
 #[async_trait::async_trait]
 pub trait AddressStore {
     type Error;
@@ -195,6 +251,93 @@ where
         .map_err(DevAddrConstraintsError::AddressStore)
 }
 
+/// Claim a contiguous slab of `count` addresses as close as possible to the
+/// `near` constraints, usually the existing constraints of the org the slab is
+/// for. Without constraints to be near the lowest free slab is used.
+pub async fn checkout_devaddr_slab<S>(
+    addr_store: &mut S,
+    count: u64,
+    net_id: HeliumNetId,
+    near: &[DevAddrConstraint],
+) -> Result<DevAddrConstraint, DevAddrConstraintsError<S::Error>>
+where
+    S: AddressStore,
+{
+    if count == 0 || count % 2 != 0 {
+        return Err(DevAddrConstraintsError::InvalidSlabSize(count));
+    }
+    let used_addrs = addr_store
+        .get_used_addrs(net_id)
+        .await
+        .map_err(DevAddrConstraintsError::AddressStore)?;
+    let slab = nearest_free_slab(net_id, &used_addrs, near, count)
+        .ok_or(DevAddrConstraintsError::NoAvailableAddrs)?;
+    let constraint = DevAddrConstraint::new((*slab.start()).into(), (*slab.end()).into())
+        .map_err(ConstraintsBuildError::from)?;
+
+    addr_store
+        .claim_addrs(net_id, &slab.collect::<Vec<u32>>())
+        .await
+        .map_err(DevAddrConstraintsError::AddressStore)?;
+    Ok(constraint)
+}
+
+/// Runs of unclaimed addresses of the net id, from its sorted used addresses
+pub fn free_slabs(net_id: HeliumNetId, used_addrs: &[u32]) -> Vec<RangeInclusive<u32>> {
+    let addr_range = net_id.addr_range();
+    let mut slabs = vec![];
+    let mut next_free = *addr_range.start();
+    for &addr in used_addrs.iter().filter(|addr| addr_range.contains(addr)) {
+        if addr > next_free {
+            slabs.push(next_free..=addr - 1);
+        }
+        next_free = next_free.max(addr + 1);
+    }
+    if next_free <= *addr_range.end() {
+        slabs.push(next_free..=*addr_range.end());
+    }
+    slabs
+}
+
+/// The free slab of `count` addresses closest to the `near` constraints,
+/// taken from the start or the end of a run of free addresses. Slabs start on
+/// an even address so they form a valid devaddr constraint.
+fn nearest_free_slab(
+    net_id: HeliumNetId,
+    used_addrs: &[u32],
+    near: &[DevAddrConstraint],
+    count: u64,
+) -> Option<RangeInclusive<u32>> {
+    let count = u32::try_from(count).ok()?;
+    free_slabs(net_id, used_addrs)
+        .into_iter()
+        .filter_map(|slab| {
+            let start = slab.start() + slab.start() % 2;
+            let end = slab.end() - (1 - slab.end() % 2);
+            (end > start && end - start + 1 >= count).then_some((start, end))
+        })
+        .flat_map(|(start, end)| [start..=start + count - 1, end + 1 - count..=end])
+        .min_by_key(|slab| (distance(slab, near), *slab.start()))
+}
+
+/// Number of addresses between a slab and the closest constraint
+fn distance(slab: &RangeInclusive<u32>, near: &[DevAddrConstraint]) -> u32 {
+    near.iter()
+        .map(|constraint| {
+            let start = u32::from(constraint.start_addr);
+            let end = u32::from(constraint.end_addr);
+            if *slab.start() > end {
+                slab.start() - end
+            } else if *slab.end() < start {
+                start - slab.end()
+            } else {
+                0
+            }
+        })
+        .min()
+        .unwrap_or(0)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum DevAddrConstraintsError<AS> {
     #[error("AddressStore error: {0}")]
@@ -205,6 +348,8 @@ pub enum DevAddrConstraintsError<AS> {
     InvalidConstraint(#[from] ConstraintsBuildError),
     #[error("Requested constraint in use {0}")]
     ConstraintAddrInUse(String),
+    #[error("Slab size must be a positive even number of addrs: {0}")]
+    InvalidSlabSize(u64),
 }
 
 fn constraints_from_addrs(
@@ -433,6 +578,77 @@ mod test {
         );
     }
 
+    #[test]
+    fn usage_counts_free_slabs() {
+        let start = *HeliumNetId::Type6_0xc00053.addr_range().start();
+        let used_addrs = (start..start + 4)
+            .chain(start + 8..start + 10)
+            .collect::<Vec<_>>();
+        let usage = NetIdUsage::new(HeliumNetId::Type6_0xc00053, &used_addrs);
+        assert_eq!(usage.total_addrs, 1024);
+        assert_eq!(usage.used_addrs, 6);
+        assert_eq!(usage.free_addrs, 1018);
+        assert_eq!(usage.free_slabs, 2);
+        assert_eq!(usage.largest_free_slab, 1014);
+        assert_eq!(usage.fragmentation, 4.0 / 1018.0);
+    }
+
+    #[tokio::test]
+    async fn allocate_slab_near_org_constraints() {
+        let start = *HeliumNetId::Type0_0x00003c.addr_range().start();
+        let org_a = DevAddrConstraint::new(start.into(), (start + 7).into()).expect("org a");
+        let org_b =
+            DevAddrConstraint::new((start + 32).into(), (start + 39).into()).expect("org b");
+        let mut addr_store = HashMap::new();
+        addr_store.insert(
+            HeliumNetId::Type0_0x00003c.id(),
+            (start..start + 8).chain(start + 32..start + 40).collect(),
+        );
+
+        let slab = checkout_devaddr_slab(
+            &mut addr_store,
+            8,
+            HeliumNetId::Type0_0x00003c,
+            &[org_b.clone()],
+        )
+        .await
+        .expect("slab near org b");
+        assert_eq!(
+            slab,
+            DevAddrConstraint::new((start + 24).into(), (start + 31).into()).expect("slab")
+        );
+
+        let slab = checkout_devaddr_slab(&mut addr_store, 8, HeliumNetId::Type0_0x00003c, &[org_a])
+            .await
+            .expect("slab near org a");
+        assert_eq!(
+            slab,
+            DevAddrConstraint::new((start + 8).into(), (start + 15).into()).expect("slab")
+        );
+
+        let slab =
+            checkout_devaddr_slab(&mut addr_store, 10, HeliumNetId::Type0_0x00003c, &[org_b])
+                .await
+                .expect("slab too large for gap");
+        assert_eq!(
+            slab,
+            DevAddrConstraint::new((start + 40).into(), (start + 49).into()).expect("slab")
+        );
+    }
+
+    #[tokio::test]
+    async fn error_when_slab_size_invalid_or_unavailable() {
+        let mut addr_store = HashMap::new();
+        assert!(matches!(
+            checkout_devaddr_slab(&mut addr_store, 7, HeliumNetId::Type6_0xc00053, &[]).await,
+            Err(DevAddrConstraintsError::InvalidSlabSize(7))
+        ));
+        assert!(matches!(
+            checkout_devaddr_slab(&mut addr_store, 2048, HeliumNetId::Type6_0xc00053, &[]).await,
+            Err(DevAddrConstraintsError::NoAvailableAddrs)
+        ));
+    }
+
     #[tokio::test]
     async fn allocate_across_net_id() {
         let mut addr_store = HashMap::new();
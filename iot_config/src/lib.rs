@@ -1,18 +1,26 @@
 pub mod admin;
 pub mod admin_service;
+pub mod api_server;
+pub mod audit;
 pub mod client;
 pub mod db_cleaner;
+pub mod devaddr_slabs;
 pub mod gateway_info;
 pub mod gateway_service;
 mod helium_netids;
 pub mod lora_field;
 pub mod org;
 pub mod org_service;
+pub mod quota;
+pub mod region_lookup;
 pub mod region_map;
 pub mod route;
+pub mod route_diff;
 pub mod route_service;
 pub mod settings;
+pub mod snapshot;
 pub mod telemetry;
+pub mod webhooks;
 
 pub use admin_service::AdminService;
 pub use client::{Client, Settings as ClientSettings};
@@ -37,25 +45,6 @@ pub fn update_channel<T: Clone>() -> broadcast::Sender<T> {
     update_tx
 }
 
-pub async fn broadcast_update<T: std::fmt::Debug>(
-    message: T,
-    sender: broadcast::Sender<T>,
-) -> Result<(), broadcast::error::SendError<T>> {
-    while !enqueue_update(sender.len()) {
-        tokio::time::sleep(tokio::time::Duration::from_millis(25)).await
-    }
-    sender.send(message).map(|_| ()).map_err(|err| {
-        tracing::error!(error = ?err, "failed to broadcast routing update");
-        err
-    })
-}
-
-fn enqueue_update(queue_size: usize) -> bool {
-    // enqueue the message for broadcast if
-    // the current queue is <= 80% full
-    (queue_size * 100) / BROADCAST_CHANNEL_QUEUE <= 80
-}
-
 pub fn verify_public_key(bytes: &[u8]) -> Result<PublicKey, Status> {
     PublicKey::try_from(bytes)
         .map_err(|_| Status::invalid_argument(format!("invalid public key: {bytes:?}")))
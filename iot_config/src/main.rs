@@ -4,9 +4,10 @@ use futures::future::LocalBoxFuture;
 use futures_util::TryFutureExt;
 use helium_proto::services::iot_config::{AdminServer, GatewayServer, OrgServer, RouteServer};
 use iot_config::{
-    admin::AuthCache, admin_service::AdminService, db_cleaner::DbCleaner,
-    gateway_service::GatewayService, org, org_service::OrgService, region_map::RegionMapReader,
-    route_service::RouteService, settings::Settings, telemetry,
+    admin::AuthCache, admin_service::AdminService, api_server::ApiServer, db_cleaner::DbCleaner,
+    gateway_service::GatewayService, org, org_service::OrgService, quota::Quotas,
+    region_map::RegionMapReader, route_service::RouteService, settings::Settings, telemetry,
+    webhooks::WebhookNotifier,
 };
 use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use task_manager::{ManagedTask, TaskManager};
@@ -84,8 +85,13 @@ impl Daemon {
             delegate_key_cache,
         )?;
 
-        let route_svc =
-            RouteService::new(signing_keypair.clone(), auth_cache.clone(), pool.clone());
+        let quotas = Quotas::new(settings.quotas.clone());
+        let route_svc = RouteService::new(
+            signing_keypair.clone(),
+            auth_cache.clone(),
+            pool.clone(),
+            quotas.clone(),
+        );
 
         let org_svc = OrgService::new(
             signing_keypair.clone(),
@@ -95,6 +101,25 @@ impl Daemon {
             delegate_key_updater,
         )?;
 
+        // Route and org changes are posted to webhooks if any are configured
+        let webhook_notifier = (!settings.webhooks.is_empty())
+            .then(|| WebhookNotifier::new(pool.clone(), settings.webhooks.clone()));
+
+        // Http api, served alongside the grpc server if configured
+        let api_server = match &settings.api {
+            Some(api) => Some(ApiServer::new(
+                api.listen_addr()?,
+                pool.clone(),
+                auth_cache.clone(),
+                signing_keypair.clone(),
+                api.snapshot_signers()?,
+                route_svc.clone_update_channel(),
+                quotas,
+                region_map.clone(),
+            )),
+            None => None,
+        };
+
         let admin_svc = AdminService::new(
             settings,
             auth_cache.clone(),
@@ -120,11 +145,16 @@ impl Daemon {
 
         let db_cleaner = DbCleaner::new(pool.clone(), settings.deleted_entry_retention());
 
-        TaskManager::builder()
+        let mut task_manager = TaskManager::builder()
             .add_task(grpc_server)
-            .add_task(db_cleaner)
-            .start()
-            .await
+            .add_task(db_cleaner);
+        if let Some(api_server) = api_server {
+            task_manager = task_manager.add_task(api_server);
+        }
+        if let Some(webhook_notifier) = webhook_notifier {
+            task_manager = task_manager.add_task(webhook_notifier);
+        }
+        task_manager.start().await
     }
 }
 
@@ -151,37 +151,37 @@ pub async fn create_org(
     Ok(org)
 }
 
+/// Apply updates to an org within a transaction. Once the transaction is
+/// committed the delegate key cache is brought up to date with
+/// [`update_delegate_cache`].
 pub async fn update_org(
     oui: u64,
     authorizer: UpdateAuthorizer,
-    updates: Vec<proto::UpdateV1>,
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres>,
-    delegate_cache: &watch::Sender<DelegateCache>,
+    updates: &[proto::UpdateV1],
+    txn: &mut sqlx::Transaction<'_, sqlx::Postgres>,
 ) -> Result<Org, OrgStoreError> {
-    let mut txn = db.begin().await?;
-
-    let current_org = get(oui, &mut txn)
+    let current_org = get(oui, &mut *txn)
         .await?
         .ok_or_else(|| OrgStoreError::NotFound(format!("{oui}")))?;
-    let net_id = get_org_netid(oui, &mut txn).await?;
+    let net_id = get_org_netid(oui, &mut *txn).await?;
     let is_helium_org = is_helium_netid(&net_id);
 
     for update in updates.iter() {
         match update.update {
             Some(proto::Update::Owner(ref pubkeybin)) if authorizer == UpdateAuthorizer::Admin => {
                 let pubkeybin: PublicKeyBinary = pubkeybin.clone().into();
-                update_owner(oui, &pubkeybin, &mut txn).await?;
+                update_owner(oui, &pubkeybin, &mut *txn).await?;
                 tracing::info!(oui, pubkey = %pubkeybin, "owner pubkey updated");
             }
             Some(proto::Update::Payer(ref pubkeybin)) if authorizer == UpdateAuthorizer::Admin => {
                 let pubkeybin: PublicKeyBinary = pubkeybin.clone().into();
-                update_payer(oui, &pubkeybin, &mut txn).await?;
+                update_payer(oui, &pubkeybin, &mut *txn).await?;
                 tracing::info!(oui, pubkey = %pubkeybin, "payer pubkey updated");
             }
             Some(proto::Update::Devaddrs(addr_count))
                 if authorizer == UpdateAuthorizer::Admin && is_helium_org =>
             {
-                add_devaddr_slab(oui, net_id, addr_count, &mut txn).await?;
+                add_devaddr_slab(oui, net_id, addr_count, &mut *txn).await?;
                 tracing::info!(oui, addrs = addr_count, "new devaddr slab assigned");
             }
             Some(proto::Update::Constraint(ref constraint_update))
@@ -190,12 +190,12 @@ pub async fn update_org(
                 match (constraint_update.action(), &constraint_update.constraint) {
                     (proto::ActionV1::Add, Some(ref constraint)) => {
                         let constraint: DevAddrConstraint = constraint.into();
-                        add_constraint_update(oui, net_id, constraint.clone(), &mut txn).await?;
+                        add_constraint_update(oui, net_id, constraint.clone(), &mut *txn).await?;
                         tracing::info!(oui, %net_id, ?constraint, "devaddr constraint added");
                     }
                     (proto::ActionV1::Remove, Some(ref constraint)) => {
                         let constraint: DevAddrConstraint = constraint.into();
-                        remove_constraint_update(oui, net_id, current_org.constraints.as_ref(), constraint.clone(), &mut txn).await?;
+                        remove_constraint_update(oui, net_id, current_org.constraints.as_ref(), constraint.clone(), &mut *txn).await?;
                         tracing::info!(oui, %net_id, ?constraint, "devaddr constraint removed");
                     }
                     _ => return Err(OrgStoreError::InvalidUpdate(format!("invalid action or missing devaddr constraint update: {constraint_update:?}")))
@@ -205,12 +205,12 @@ pub async fn update_org(
                 match delegate_key_update.action() {
                     proto::ActionV1::Add => {
                         let delegate = delegate_key_update.delegate_key.clone().into();
-                        add_delegate_key(oui, &delegate, &mut txn).await?;
+                        add_delegate_key(oui, &delegate, &mut *txn).await?;
                         tracing::info!(oui, %delegate, "delegate key authorized");
                     }
                     proto::ActionV1::Remove => {
                         let delegate = delegate_key_update.delegate_key.clone().into();
-                        remove_delegate_key(oui, &delegate, &mut txn).await?;
+                        remove_delegate_key(oui, &delegate, &mut *txn).await?;
                         tracing::info!(oui, %delegate, "delegate key de-authorized");
                     }
                 }
@@ -223,12 +223,16 @@ pub async fn update_org(
         };
     }
 
-    let updated_org = get(oui, &mut txn)
+    get(oui, &mut *txn)
         .await?
-        .ok_or_else(|| OrgStoreError::SaveOrg(format!("{oui}")))?;
-
-    txn.commit().await?;
+        .ok_or_else(|| OrgStoreError::SaveOrg(format!("{oui}")))
+}
 
+/// Authorize and de-authorize the delegate keys of committed org updates
+pub fn update_delegate_cache(
+    updates: &[proto::UpdateV1],
+    delegate_cache: &watch::Sender<DelegateCache>,
+) {
     for update in updates.iter() {
         if let Some(proto::Update::DelegateKey(ref delegate_key_update)) = update.update {
             match delegate_key_update.action() {
@@ -245,8 +249,6 @@ pub async fn update_org(
             }
         }
     }
-
-    Ok(updated_org)
 }
 
 pub async fn get_org_netid(
@@ -380,7 +382,7 @@ async fn add_devaddr_slab(
     Ok(())
 }
 
-async fn insert_helium_constraints(
+pub async fn insert_helium_constraints(
     oui: u64,
     net_id: NetIdField,
     devaddr_ranges: &[DevAddrConstraint],
@@ -465,11 +467,7 @@ const GET_ORG_SQL: &str = r#"
         "#;
 
 pub async fn list(db: impl sqlx::PgExecutor<'_>) -> Result<Vec<Org>, sqlx::Error> {
-    Ok(sqlx::query_as::<_, Org>(GET_ORG_SQL)
-        .fetch(db)
-        .filter_map(|row| async move { row.ok() })
-        .collect::<Vec<Org>>()
-        .await)
+    sqlx::query_as::<_, Org>(GET_ORG_SQL).fetch_all(db).await
 }
 
 pub async fn get(oui: u64, db: impl sqlx::PgExecutor<'_>) -> Result<Option<Org>, sqlx::Error> {
@@ -2,8 +2,9 @@ use std::sync::Arc;
 
 use crate::{
     admin::{AuthCache, KeyType},
-    broadcast_update, helium_netids, lora_field, org,
-    route::list_routes,
+    audit::{self, Signer},
+    helium_netids, lora_field, org,
+    route::{list_routes, PendingUpdates, StreamUpdate},
     telemetry, verify_public_key, GrpcResult,
 };
 use anyhow::Result;
@@ -12,20 +13,21 @@ use file_store::traits::{MsgVerify, TimestampEncode};
 use helium_crypto::{Keypair, PublicKey, Sign};
 use helium_proto::{
     services::iot_config::{
-        self, route_stream_res_v1, ActionV1, DevaddrConstraintV1, OrgCreateHeliumReqV1,
-        OrgCreateRoamerReqV1, OrgDisableReqV1, OrgDisableResV1, OrgEnableReqV1, OrgEnableResV1,
-        OrgGetReqV1, OrgListReqV1, OrgListResV1, OrgResV1, OrgUpdateReqV1, OrgV1, RouteStreamResV1,
+        self, ActionV1, DevaddrConstraintV1, OrgCreateHeliumReqV1, OrgCreateRoamerReqV1,
+        OrgDisableReqV1, OrgDisableResV1, OrgEnableReqV1, OrgEnableResV1, OrgGetReqV1,
+        OrgListReqV1, OrgListResV1, OrgResV1, OrgUpdateReqV1, OrgV1,
     },
     Message,
 };
-use sqlx::{Pool, Postgres};
+use serde_json::json;
+use sqlx::{Pool, Postgres, Transaction};
 use tokio::sync::{broadcast, watch};
 use tonic::{Request, Response, Status};
 
 pub struct OrgService {
     auth_cache: AuthCache,
     pool: Pool<Postgres>,
-    route_update_tx: broadcast::Sender<RouteStreamResV1>,
+    route_update_tx: broadcast::Sender<StreamUpdate>,
     signing_key: Arc<Keypair>,
     delegate_updater: watch::Sender<org::DelegateCache>,
 }
@@ -41,7 +43,7 @@ impl OrgService {
         signing_key: Arc<Keypair>,
         auth_cache: AuthCache,
         pool: Pool<Postgres>,
-        route_update_tx: broadcast::Sender<RouteStreamResV1>,
+        route_update_tx: broadcast::Sender<StreamUpdate>,
         delegate_updater: watch::Sender<org::DelegateCache>,
     ) -> Result<Self> {
         Ok(Self {
@@ -57,24 +59,27 @@ impl OrgService {
         &self,
         signer: &PublicKey,
         request: &R,
-    ) -> Result<(), Status>
+    ) -> Result<Signer, Status>
     where
         R: MsgVerify,
     {
         self.auth_cache
             .verify_signature_with_type(KeyType::Administrator, signer, request)
             .map_err(|_| Status::permission_denied("invalid admin signature"))?;
-        Ok(())
+        Ok(Signer::new(signer.clone(), KeyType::Administrator))
     }
 
-    fn verify_request_signature<R>(&self, signer: &PublicKey, request: &R) -> Result<(), Status>
+    fn verify_request_signature<R>(&self, signer: &PublicKey, request: &R) -> Result<Signer, Status>
     where
         R: MsgVerify,
     {
         self.auth_cache
             .verify_signature(signer, request)
             .map_err(|_| Status::permission_denied("invalid request signature"))?;
-        Ok(())
+        self.auth_cache
+            .get_key_type(signer)
+            .map(|key_type| Signer::new(signer.clone(), key_type))
+            .ok_or_else(|| Status::permission_denied("invalid request signature"))
     }
 
     async fn verify_update_request_signature(
@@ -114,35 +119,24 @@ impl OrgService {
             .map_err(|_| Status::internal("response signing error"))
     }
 
-    async fn stream_org_routes_enable_disable(&self, oui: u64) -> Result<(), Status> {
-        let routes = list_routes(oui, &self.pool).await.map_err(|err| {
+    /// Commits a change to an org's locked flag, streaming its routes to
+    /// route stream subscribers as updated
+    async fn commit_org_routes(
+        &self,
+        oui: u64,
+        mut txn: Transaction<'_, Postgres>,
+    ) -> Result<(), Status> {
+        let routes = list_routes(oui, &mut txn).await.map_err(|err| {
             tracing::error!(org = oui, reason = ?err, "failed to list org routes for streaming update");
             Status::internal(format!("error retrieving routes for updated org: {}", oui))
         })?;
-        let timestamp = Utc::now().encode_timestamp();
-        let signer: Vec<u8> = self.signing_key.public_key().into();
-        for route in routes {
-            let route_id = route.id.clone();
-            let mut update = RouteStreamResV1 {
-                action: ActionV1::Add.into(),
-                data: Some(route_stream_res_v1::Data::Route(route.into())),
-                timestamp,
-                signer: signer.clone(),
-                signature: vec![],
-            };
-            update.signature = self.sign_response(&update.encode_to_vec())?;
-            if broadcast_update(update, self.route_update_tx.clone())
-                .await
-                .is_err()
-            {
-                tracing::info!(
-                    route_id,
-                    "all subscribers disconnected; org routes update incomplete"
-                );
-                break;
-            };
-            tracing::debug!(route_id, "route updated");
-        }
+        PendingUpdates::routes(ActionV1::Add, routes)
+            .commit(txn, &self.signing_key, &self.route_update_tx)
+            .await
+            .map_err(|err| {
+                tracing::error!(org = oui, reason = ?err, "failed to commit org routes update");
+                Status::internal(format!("error updating routes of org: {}", oui))
+            })?;
         Ok(())
     }
 }
@@ -216,7 +210,7 @@ impl iot_config::Org for OrgService {
         telemetry::count_request("org", "create-helium");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_admin_request_signature(&signer, &request)?;
+        let signer = self.verify_admin_request_signature(&signer, &request)?;
 
         let mut verify_keys: Vec<&[u8]> = vec![request.owner.as_ref(), request.payer.as_ref()];
         let mut verify_delegates: Vec<&[u8]> = request
@@ -279,6 +273,14 @@ impl iot_config::Org for OrgService {
             Status::internal(format!("org save failed: {err:?}"))
         })?;
 
+        audit::record(
+            audit::Entry::new(&signer, "org_create_helium")
+                .oui(org.oui)
+                .after(&org),
+            &mut txn,
+        )
+        .await?;
+
         txn.commit()
             .await
             .map_err(|_| Status::internal("error saving org record"))?;
@@ -321,7 +323,7 @@ impl iot_config::Org for OrgService {
         telemetry::count_request("org", "create-roamer");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_admin_request_signature(&signer, &request)?;
+        let signer = self.verify_admin_request_signature(&signer, &request)?;
 
         let mut verify_keys: Vec<&[u8]> = vec![request.owner.as_ref(), request.payer.as_ref()];
         let mut verify_delegates: Vec<&[u8]> = request
@@ -347,6 +349,11 @@ impl iot_config::Org for OrgService {
             .map_err(|_| Status::invalid_argument("invalid net_id"))?;
         tracing::info!(constraints = ?devaddr_range, "roaming devaddr range");
 
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error saving org record"))?;
         let org = org::create_org(
             request.owner.into(),
             request.payer.into(),
@@ -357,7 +364,7 @@ impl iot_config::Org for OrgService {
                 .collect(),
             net_id,
             &[devaddr_range],
-            &self.pool,
+            &mut txn,
         )
         .await
         .map_err(|err| {
@@ -365,6 +372,18 @@ impl iot_config::Org for OrgService {
             Status::internal(format!("org save failed: {err:?}"))
         })?;
 
+        audit::record(
+            audit::Entry::new(&signer, "org_create_roamer")
+                .oui(org.oui)
+                .after(&org),
+            &mut txn,
+        )
+        .await?;
+
+        txn.commit()
+            .await
+            .map_err(|_| Status::internal("error saving org record"))?;
+
         org.delegate_keys.as_ref().map(|keys| {
             self.delegate_updater.send_if_modified(|cache| {
                 keys.iter().fold(false, |acc, key| {
@@ -406,19 +425,37 @@ impl iot_config::Org for OrgService {
         let authorizer = self
             .verify_update_request_signature(&signer, &request)
             .await?;
+        let signer = Signer::new(signer, &authorizer);
 
-        let org = org::update_org(
-            request.oui,
-            authorizer,
-            request.updates,
-            &self.pool,
-            &self.delegate_updater,
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error saving org record"))?;
+        let previous_org = org::get(request.oui, &mut txn)
+            .await
+            .map_err(|_| Status::internal("error retrieving current org"))?;
+
+        let org = org::update_org(request.oui, authorizer, &request.updates, &mut txn)
+            .await
+            .map_err(|err| {
+                tracing::error!(reason = ?err, "org update failed");
+                Status::internal(format!("org update failed: {err:?}"))
+            })?;
+
+        audit::record(
+            audit::Entry::new(&signer, "org_update")
+                .oui(org.oui)
+                .before(&previous_org)
+                .after(&org),
+            &mut txn,
         )
-        .await
-        .map_err(|err| {
-            tracing::error!(reason = ?err, "org update failed");
-            Status::internal(format!("org update failed: {err:?}"))
-        })?;
+        .await?;
+
+        txn.commit()
+            .await
+            .map_err(|_| Status::internal("error saving org record"))?;
+        org::update_delegate_cache(&request.updates, &self.delegate_updater);
 
         let net_id = org::get_org_netid(org.oui, &self.pool)
             .await
@@ -452,25 +489,38 @@ impl iot_config::Org for OrgService {
         telemetry::count_request("org", "disable");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request)?;
+        let signer = self.verify_request_signature(&signer, &request)?;
 
-        if !org::is_locked(request.oui, &self.pool)
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error retrieving current status"))?;
+        if !org::is_locked(request.oui, &mut txn)
             .await
             .map_err(|_| Status::internal("error retrieving current status"))?
         {
-            org::toggle_locked(request.oui, &self.pool)
+            let org_disable_failed = |err: sqlx::Error| {
+                tracing::error!(
+                    org = request.oui,
+                    reason = ?err,
+                    "failed to disable org with reason"
+                );
+                Status::internal(format!("org disable failed for: {}", request.oui))
+            };
+            org::toggle_locked(request.oui, &mut txn)
                 .await
-                .map_err(|err| {
-                    tracing::error!(
-                        org = request.oui,
-                        reason = ?err,
-                        "failed to disable org with reason"
-                    );
-                    Status::internal(format!("org disable failed for: {}", request.oui))
-                })?;
+                .map_err(org_disable_failed)?;
+            audit::record(
+                audit::Entry::new(&signer, "org_disable")
+                    .oui(request.oui)
+                    .before(&json!({ "locked": false }))
+                    .after(&json!({ "locked": true })),
+                &mut txn,
+            )
+            .await?;
+            self.commit_org_routes(request.oui, txn).await?;
             tracing::info!(oui = request.oui, "org locked");
-
-            self.stream_org_routes_enable_disable(request.oui).await?
         }
 
         let mut resp = OrgDisableResV1 {
@@ -489,25 +539,38 @@ impl iot_config::Org for OrgService {
         telemetry::count_request("org", "enable");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request)?;
+        let signer = self.verify_request_signature(&signer, &request)?;
 
-        if org::is_locked(request.oui, &self.pool)
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|_| Status::internal("error retrieving current status"))?;
+        if org::is_locked(request.oui, &mut txn)
             .await
             .map_err(|_| Status::internal("error retrieving current status"))?
         {
-            org::toggle_locked(request.oui, &self.pool)
+            let org_enable_failed = |err: sqlx::Error| {
+                tracing::error!(
+                    org = request.oui,
+                    reason = ?err,
+                    "failed to enable org with reason"
+                );
+                Status::internal(format!("org enable failed for: {}", request.oui))
+            };
+            org::toggle_locked(request.oui, &mut txn)
                 .await
-                .map_err(|err| {
-                    tracing::error!(
-                        org = request.oui,
-                        reason = ?err,
-                        "failed to enable org with reason"
-                    );
-                    Status::internal(format!("org enable failed for: {}", request.oui))
-                })?;
+                .map_err(org_enable_failed)?;
+            audit::record(
+                audit::Entry::new(&signer, "org_enable")
+                    .oui(request.oui)
+                    .before(&json!({ "locked": true }))
+                    .after(&json!({ "locked": false })),
+                &mut txn,
+            )
+            .await?;
+            self.commit_org_routes(request.oui, txn).await?;
             tracing::info!(oui = request.oui, "org unlocked");
-
-            self.stream_org_routes_enable_disable(request.oui).await?
         }
 
         let mut resp = OrgEnableResV1 {
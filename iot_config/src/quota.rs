@@ -0,0 +1,308 @@
+use crate::{
+    audit::{Signer, SignerType},
+    lora_field::{EuiPair, Skf},
+    settings::QuotaSettings,
+    telemetry,
+};
+use helium_crypto::PublicKey;
+use sqlx::PgConnection;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tonic::Status;
+
+/// Namespace of the transaction level advisory locks serializing the quota
+/// checks of an org's routes with their inserts
+const ORG_ROUTES_LOCK: i32 = 7265677;
+/// Namespace of the transaction level advisory locks serializing the quota
+/// checks of a route's entries with their inserts
+const ROUTE_ENTRIES_LOCK: i32 = 7265678;
+
+#[derive(thiserror::Error, Debug)]
+pub enum QuotaError {
+    #[error("org {oui} is at its quota of {limit} routes")]
+    Routes { oui: u64, limit: u64 },
+    #[error("route {route_id} would exceed its quota of {limit} {entries}")]
+    Entries {
+        route_id: String,
+        entries: &'static str,
+        limit: u64,
+    },
+    #[error("signer {signer} exceeded its quota of {limit} route mutations per minute")]
+    Rate { signer: PublicKey, limit: u64 },
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+}
+
+impl From<QuotaError> for Status {
+    fn from(err: QuotaError) -> Self {
+        match err {
+            QuotaError::Db(err) => {
+                tracing::error!(reason = ?err, "quota check failed");
+                Status::internal("quota check failed")
+            }
+            err => Status::resource_exhausted(err.to_string()),
+        }
+    }
+}
+
+/// Limits on the number of routes of an org and entries of a route, and on
+/// how many route mutations a signer can make per minute. Administrators are
+/// not rate limited.
+///
+/// The route and entry checks lock the org or routes they count for the rest
+/// of the transaction, so they must run in the transaction making the
+/// inserts they check; concurrent inserts then wait for it to commit.
+#[derive(Clone, Debug)]
+pub struct Quotas {
+    settings: QuotaSettings,
+    buckets: Arc<Mutex<HashMap<PublicKey, TokenBucket>>>,
+}
+
+/// Mutations a signer can make, refilled continuously up to a minute's worth
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Default for Quotas {
+    fn default() -> Self {
+        Self::new(QuotaSettings::default())
+    }
+}
+
+impl Quotas {
+    pub fn new(settings: QuotaSettings) -> Self {
+        Self {
+            settings,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Take `mutations` from the signer's allowance, failing without taking
+    /// any if the signer doesn't have enough left
+    pub fn check_rate(&self, signer: &Signer, mutations: usize) -> Result<(), QuotaError> {
+        if signer.signer_type == SignerType::Administrator {
+            return Ok(());
+        }
+        let limit = self.settings.mutations_per_minute;
+        let per_sec = limit as f64 / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let bucket = buckets
+            .entry(signer.pubkey.clone())
+            .or_insert_with(|| TokenBucket {
+                tokens: limit as f64,
+                refilled_at: now,
+            });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(limit as f64);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < mutations as f64 {
+            telemetry::count_quota_exceeded("mutation_rate");
+            return Err(QuotaError::Rate {
+                signer: signer.pubkey.clone(),
+                limit,
+            });
+        }
+        bucket.tokens -= mutations as f64;
+        Ok(())
+    }
+
+    /// Check the org has room for another route, locking its routes
+    pub async fn check_routes(&self, oui: u64, db: &mut PgConnection) -> Result<(), QuotaError> {
+        lock(ORG_ROUTES_LOCK, &oui.to_string(), &mut *db).await?;
+        let routes: i64 =
+            sqlx::query_scalar(" select count(*) from routes where oui = $1 and deleted = false ")
+                .bind(oui as i64)
+                .fetch_one(&mut *db)
+                .await?;
+        let limit = self.settings.max_routes_per_org;
+        if routes as u64 >= limit {
+            telemetry::count_quota_exceeded("routes");
+            return Err(QuotaError::Routes { oui, limit });
+        }
+        Ok(())
+    }
+
+    /// Lock the routes' entries against inserts of other transactions until
+    /// this one ends. Locks are taken in order of route id.
+    pub async fn lock_routes<'a>(
+        &self,
+        route_ids: impl IntoIterator<Item = &'a str>,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        for route_id in route_ids.into_iter().collect::<BTreeSet<_>>() {
+            lock(ROUTE_ENTRIES_LOCK, route_id, &mut *db).await?;
+        }
+        Ok(())
+    }
+
+    /// Check the routes of the eui pairs have room for them, counting every
+    /// added pair as new, locking the routes
+    pub async fn check_euis(
+        &self,
+        adds: &[EuiPair],
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        let added = count_by_route(adds.iter().map(|pair| pair.route_id.as_str()));
+        self.check_stored_euis(added, db).await
+    }
+
+    /// Check the eui pairs stored on each route, plus the count to be added to
+    /// it, are within quota, locking the routes. Pairs inserted earlier in the
+    /// transaction, under the route's lock, are counted as stored.
+    pub async fn check_stored_euis(
+        &self,
+        added: BTreeMap<&str, usize>,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        self.lock_routes(added.keys().copied(), &mut *db).await?;
+        for (route_id, count) in added {
+            let stored: i64 = sqlx::query_scalar(
+                r#"
+                select count(*) from route_eui_pairs
+                where route_id = $1::uuid and deleted = false
+                "#,
+            )
+            .bind(route_id)
+            .fetch_one(&mut *db)
+            .await?;
+            self.check_entries(
+                route_id,
+                ("euis", "eui pairs"),
+                stored,
+                count,
+                self.settings.max_euis_per_route,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check the routes of the session key filters have room for them,
+    /// counting every added filter as new, locking the routes
+    pub async fn check_skfs(&self, adds: &[Skf], db: &mut PgConnection) -> Result<(), QuotaError> {
+        let added = count_by_route(adds.iter().map(|skf| skf.route_id.as_str()));
+        self.check_stored_skfs(added, db).await
+    }
+
+    /// Check the session key filters stored on each route, plus the count to
+    /// be added to it, are within quota, locking the routes. Filters inserted
+    /// earlier in the transaction, under the route's lock, are counted as
+    /// stored.
+    pub async fn check_stored_skfs(
+        &self,
+        added: BTreeMap<&str, usize>,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        self.lock_routes(added.keys().copied(), &mut *db).await?;
+        for (route_id, count) in added {
+            let stored: i64 = sqlx::query_scalar(
+                r#"
+                select count(*) from route_session_key_filters
+                where route_id = $1::uuid and deleted = false
+                "#,
+            )
+            .bind(route_id)
+            .fetch_one(&mut *db)
+            .await?;
+            self.check_entries(
+                route_id,
+                ("skfs", "session key filters"),
+                stored,
+                count,
+                self.settings.max_skfs_per_route,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check_entries(
+        &self,
+        route_id: &str,
+        (quota, entries): (&'static str, &'static str),
+        stored: i64,
+        added: usize,
+        limit: u64,
+    ) -> Result<(), QuotaError> {
+        if stored as u64 + added as u64 > limit {
+            telemetry::count_quota_exceeded(quota);
+            return Err(QuotaError::Entries {
+                route_id: route_id.to_string(),
+                entries,
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+pub fn count_by_route<'a>(route_ids: impl Iterator<Item = &'a str>) -> BTreeMap<&'a str, usize> {
+    let mut counts = BTreeMap::new();
+    for route_id in route_ids {
+        *counts.entry(route_id).or_default() += 1;
+    }
+    counts
+}
+
+async fn lock(namespace: i32, key: &str, db: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("select pg_advisory_xact_lock($1, hashtext($2))")
+        .bind(namespace)
+        .bind(key)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helium_crypto::{KeyTag, KeyType, Keypair, Network};
+
+    fn signer(signer_type: SignerType) -> Signer {
+        let keypair = Keypair::generate(
+            KeyTag {
+                network: Network::MainNet,
+                key_type: KeyType::Ed25519,
+            },
+            &mut rand::rngs::OsRng,
+        );
+        Signer::new(keypair.public_key().clone(), signer_type)
+    }
+
+    #[test]
+    fn rate_limits_each_signer() {
+        let quotas = Quotas::new(QuotaSettings {
+            mutations_per_minute: 10,
+            ..Default::default()
+        });
+        let org = signer(SignerType::Org);
+        let other_org = signer(SignerType::Org);
+
+        quotas.check_rate(&org, 8).expect("within rate");
+        assert!(matches!(
+            quotas.check_rate(&org, 5),
+            Err(QuotaError::Rate { limit: 10, .. })
+        ));
+        quotas.check_rate(&org, 2).expect("remaining allowance");
+        quotas
+            .check_rate(&other_org, 10)
+            .expect("separate allowance");
+    }
+
+    #[test]
+    fn administrators_are_not_rate_limited() {
+        let quotas = Quotas::new(QuotaSettings {
+            mutations_per_minute: 1,
+            ..Default::default()
+        });
+        let admin = signer(SignerType::Administrator);
+        quotas.check_rate(&admin, 100).expect("admin not limited");
+        quotas.check_rate(&admin, 100).expect("admin not limited");
+    }
+}
@@ -0,0 +1,254 @@
+use crate::region_map::RegionMapReader;
+use h3o::{CellIndex, LatLng, Resolution};
+use helium_proto::Region;
+use hextree::Cell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Resolution of asserted gateway locations, used for lat/lng lookups by
+/// default
+pub const DEFAULT_RESOLUTION: u8 = 12;
+/// Distance around a location searched for other regions by default, in
+/// meters
+pub const DEFAULT_RADIUS_M: u32 = 25_000;
+/// Farthest around a location a lookup may search, in meters
+pub const MAX_RADIUS_M: u32 = 500_000;
+/// The search is made at the finest resolution, no finer than the location's,
+/// at which the radius is covered by this many rings of cells
+const SEARCH_RINGS: u32 = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RegionLookupError {
+    #[error("invalid lookup: {0}")]
+    Invalid(String),
+}
+
+/// Request for the region and params that apply to a location, given as
+/// either an h3 cell or a lat/lng
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RegionLookupQuery {
+    /// Hex encoded h3 cell, eg: 8c2ab38f1ee67ff
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub lat: Option<f64>,
+    #[serde(default)]
+    pub lng: Option<f64>,
+    /// Resolution of the cell a lat/lng is looked up at, defaults to the
+    /// resolution of asserted locations
+    #[serde(default)]
+    pub resolution: Option<u8>,
+    /// Distance around the location to search for other regions, in meters
+    #[serde(default)]
+    pub radius_m: Option<u32>,
+}
+
+/// The region of a location as the config service resolves it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionLookup {
+    /// Hex encoded h3 cell looked up
+    pub location: String,
+    pub resolution: u8,
+    /// Region of the location, none if it's outside of every region
+    pub region: Option<String>,
+    /// Cell of the region tree containing the location, the location itself
+    /// or one of its compacted parents
+    pub matched_cell: Option<String>,
+    pub matched_resolution: Option<u8>,
+    /// Channels of the region's params
+    pub channels: Vec<Channel>,
+    /// Resolution of the cells searched for other regions
+    pub search_resolution: u8,
+    /// Other regions, and cells outside of every region, nearest first
+    pub neighbors: Vec<NeighborRegion>,
+}
+
+/// A channel of a region's `BlockchainRegionParamsV1`, in its units
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Channel {
+    pub channel_frequency: u64,
+    pub bandwidth: u64,
+    pub max_eirp: u64,
+}
+
+/// The nearest cell of another region around a location
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NeighborRegion {
+    /// Region of the cell, none if it's outside of every region
+    pub region: Option<String>,
+    /// Hex encoded h3 cell at the search resolution
+    pub cell: String,
+    /// Distance from the location to the center of the cell, in meters
+    pub distance_m: u32,
+}
+
+/// Resolve the region, params and neighbouring regions of a location from
+/// the current region map
+pub fn lookup(
+    query: &RegionLookupQuery,
+    region_map: &RegionMapReader,
+) -> Result<RegionLookup, RegionLookupError> {
+    let location = query_cell(query)?;
+    let radius_m = query.radius_m.unwrap_or(DEFAULT_RADIUS_M);
+    if radius_m > MAX_RADIUS_M {
+        return Err(RegionLookupError::Invalid(format!(
+            "at most {MAX_RADIUS_M} meters can be searched"
+        )));
+    }
+
+    let matched = region_map.get_region_cell(to_cell(location)?);
+    let region = matched.map(|(_, region)| region);
+    let channels = region
+        .and_then(|region| region_map.get_params(&region))
+        .map(|params| {
+            params
+                .region_params
+                .iter()
+                .map(|param| Channel {
+                    channel_frequency: param.channel_frequency,
+                    bandwidth: param.bandwidth,
+                    max_eirp: param.max_eirp,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let search_resolution = search_resolution(location.resolution(), radius_m);
+    let origin = location.parent(search_resolution).unwrap_or(location);
+    let center = LatLng::from(location);
+    let mut nearest: HashMap<Option<Region>, (CellIndex, u32)> = HashMap::new();
+    for cell in origin.grid_disk::<Vec<_>>(rings(search_resolution, radius_m)) {
+        // Coarser cells are sampled at their center, at the location's
+        // resolution
+        let sample = cell.center_child(location.resolution()).unwrap_or(cell);
+        let distance = center.distance_m(LatLng::from(sample)).round() as u32;
+        if distance > radius_m {
+            continue;
+        }
+        let cell_region = region_map.get_region(to_cell(sample)?);
+        if cell_region == region {
+            continue;
+        }
+        let entry = nearest.entry(cell_region).or_insert((cell, distance));
+        if distance < entry.1 {
+            *entry = (cell, distance);
+        }
+    }
+    let mut neighbors: Vec<NeighborRegion> = nearest
+        .into_iter()
+        .map(|(region, (cell, distance))| NeighborRegion {
+            region: region.map(|region| region.to_string()),
+            cell: cell.to_string(),
+            distance_m: distance,
+        })
+        .collect();
+    neighbors.sort_by(|a, b| {
+        a.distance_m
+            .cmp(&b.distance_m)
+            .then(a.region.cmp(&b.region))
+    });
+
+    Ok(RegionLookup {
+        location: location.to_string(),
+        resolution: u8::from(location.resolution()),
+        region: region.map(|region| region.to_string()),
+        matched_cell: matched.map(|(cell, _)| cell.to_string()),
+        matched_resolution: matched.map(|(cell, _)| cell.res()),
+        channels,
+        search_resolution: u8::from(search_resolution),
+        neighbors,
+    })
+}
+
+/// Finest resolution, no finer than the location's, at which the radius is
+/// covered by `SEARCH_RINGS` rings of cells
+fn search_resolution(location: Resolution, radius_m: u32) -> Resolution {
+    (0..=u8::from(location))
+        .rev()
+        .filter_map(|resolution| Resolution::try_from(resolution).ok())
+        .find(|resolution| rings(*resolution, radius_m) <= SEARCH_RINGS)
+        .unwrap_or(Resolution::Zero)
+}
+
+/// Rings of cells at a resolution covering the radius. The centers of
+/// neighbouring cells are about `sqrt(3)` edge lengths apart.
+fn rings(resolution: Resolution, radius_m: u32) -> u32 {
+    let spacing_m = resolution.edge_length_m() * 3f64.sqrt();
+    (radius_m as f64 / spacing_m).ceil() as u32
+}
+
+fn query_cell(query: &RegionLookupQuery) -> Result<CellIndex, RegionLookupError> {
+    match (&query.location, query.lat, query.lng) {
+        (Some(location), None, None) => u64::from_str_radix(location, 16)
+            .ok()
+            .and_then(|location| CellIndex::try_from(location).ok())
+            .ok_or_else(|| RegionLookupError::Invalid(format!("invalid h3 cell {location}"))),
+        (None, Some(lat), Some(lng)) => {
+            let resolution = query.resolution.unwrap_or(DEFAULT_RESOLUTION);
+            let resolution = Resolution::try_from(resolution).map_err(|_| {
+                RegionLookupError::Invalid(format!("invalid h3 resolution {resolution}"))
+            })?;
+            LatLng::new(lat, lng)
+                .map(|lat_lng| lat_lng.to_cell(resolution))
+                .map_err(|_| RegionLookupError::Invalid(format!("invalid lat/lng {lat}, {lng}")))
+        }
+        _ => Err(RegionLookupError::Invalid(
+            "either an h3 cell or a lat and lng is required".to_string(),
+        )),
+    }
+}
+
+fn to_cell(cell: CellIndex) -> Result<Cell, RegionLookupError> {
+    Cell::from_raw(u64::from(cell))
+        .map_err(|_| RegionLookupError::Invalid(format!("invalid h3 cell {cell}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_takes_a_cell_or_a_lat_lng() {
+        let cell = query_cell(&RegionLookupQuery {
+            location: Some("8a1fb46622dffff".to_string()),
+            ..Default::default()
+        })
+        .expect("cell query");
+        assert_eq!("8a1fb46622dffff", cell.to_string());
+
+        let cell = query_cell(&RegionLookupQuery {
+            lat: Some(37.7749),
+            lng: Some(-122.4194),
+            ..Default::default()
+        })
+        .expect("lat/lng query");
+        assert_eq!(DEFAULT_RESOLUTION, u8::from(cell.resolution()));
+
+        assert!(query_cell(&RegionLookupQuery::default()).is_err());
+        assert!(query_cell(&RegionLookupQuery {
+            location: Some("8a1fb46622dffff".to_string()),
+            lat: Some(37.7749),
+            lng: Some(-122.4194),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(query_cell(&RegionLookupQuery {
+            location: Some("not a cell".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn search_resolution_covers_radius() {
+        let resolution = search_resolution(Resolution::try_from(12).unwrap(), DEFAULT_RADIUS_M);
+        assert!(resolution < Resolution::try_from(12).unwrap());
+        assert!(rings(resolution, DEFAULT_RADIUS_M) <= SEARCH_RINGS);
+        let finer = Resolution::try_from(u8::from(resolution) + 1).unwrap();
+        assert!(rings(finer, DEFAULT_RADIUS_M) > SEARCH_RINGS);
+
+        // A small radius is searched at the location's resolution
+        let resolution = Resolution::try_from(8).unwrap();
+        assert_eq!(resolution, search_resolution(resolution, 1_000));
+    }
+}
@@ -1,15 +1,29 @@
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use futures::stream::TryStreamExt;
 use helium_proto::{BlockchainRegionParamsV1, Message, Region};
-use hextree::{compaction::EqCompactor, Cell, HexTreeMap};
+use hextree::{
+    compaction::{EqCompactor, SetCompactor},
+    Cell, HexTreeMap, HexTreeSet,
+};
 use libflate::gzip::Decoder;
-use std::{collections::HashMap, io::Read, str::FromStr};
+use std::{collections::HashMap, io::Read, str::FromStr, sync::Arc};
 use tokio::sync::watch;
 
 #[derive(Clone, Debug)]
 pub struct RegionMap {
     region_hextree: HexTreeMap<Region, EqCompactor>,
     params_map: HashMap<Region, BlockchainRegionParamsV1>,
+    versions: HashMap<Region, Vec<RegionVersion>>,
+}
+
+/// The params and hexes of a region from the time they were loaded until the
+/// region is next loaded
+#[derive(Clone, Debug)]
+pub struct RegionVersion {
+    pub active_from: DateTime<Utc>,
+    pub params: BlockchainRegionParamsV1,
+    pub cells: Arc<HexTreeSet>,
 }
 
 #[derive(Clone, Debug)]
@@ -33,15 +47,46 @@ impl RegionMapReader {
     pub fn get_params(&self, region: &Region) -> Option<BlockchainRegionParamsV1> {
         self.map_receiver.borrow().get_params(region)
     }
+
+    pub fn get_region_cell(&self, location: Cell) -> Option<(Cell, Region)> {
+        self.map_receiver.borrow().get_region_cell(location)
+    }
+
+    pub fn get_region_at(&self, location: Cell, timestamp: DateTime<Utc>) -> Option<Region> {
+        self.map_receiver
+            .borrow()
+            .get_region_at(location, timestamp)
+    }
+
+    pub fn get_params_at(
+        &self,
+        region: &Region,
+        timestamp: DateTime<Utc>,
+    ) -> Option<BlockchainRegionParamsV1> {
+        self.map_receiver.borrow().get_params_at(region, timestamp)
+    }
+
+    pub fn get_version_at(
+        &self,
+        region: &Region,
+        timestamp: DateTime<Utc>,
+    ) -> Option<RegionVersion> {
+        self.map_receiver
+            .borrow()
+            .get_version_at(region, timestamp)
+            .cloned()
+    }
 }
 
 impl RegionMap {
     pub async fn new(db: impl sqlx::PgExecutor<'_> + Copy) -> anyhow::Result<Self> {
         let region_hextree = build_region_tree(db).await?;
         let params_map = build_params_map(db).await?;
+        let versions = build_region_versions(db).await?;
         Ok(Self {
             region_hextree,
             params_map,
+            versions,
         })
     }
 
@@ -49,6 +94,14 @@ impl RegionMap {
         self.region_hextree.get(location).unzip().1.copied()
     }
 
+    /// Region of the location along with the cell of the region tree
+    /// containing it, the location itself or one of its compacted parents
+    pub fn get_region_cell(&self, location: Cell) -> Option<(Cell, Region)> {
+        self.region_hextree
+            .get(location)
+            .map(|(cell, region)| (cell, *region))
+    }
+
     pub fn get_params(&self, region: &Region) -> Option<BlockchainRegionParamsV1> {
         self.params_map.get(region).cloned()
     }
@@ -60,6 +113,73 @@ impl RegionMap {
     pub fn replace_tree(&mut self, new_map: HexTreeMap<Region, EqCompactor>) {
         self.region_hextree = new_map
     }
+
+    /// Region of the location as of the timestamp. Where the hexes of regions
+    /// overlap the location resolves, as in the current region tree, to the
+    /// first of the regions by name
+    pub fn get_region_at(&self, location: Cell, timestamp: DateTime<Utc>) -> Option<Region> {
+        self.versions
+            .iter()
+            .filter(|(_, versions)| {
+                version_at(versions, timestamp)
+                    .map_or(false, |version| version.cells.contains(location))
+            })
+            .map(|(region, _)| *region)
+            .min_by_key(|region| region.to_string())
+    }
+
+    /// Params of the region as of the timestamp, none if the region hadn't
+    /// been loaded yet
+    pub fn get_params_at(
+        &self,
+        region: &Region,
+        timestamp: DateTime<Utc>,
+    ) -> Option<BlockchainRegionParamsV1> {
+        if *region == Region::Unknown {
+            return self.get_params(region);
+        }
+        self.get_version_at(region, timestamp)
+            .map(|version| version.params.clone())
+    }
+
+    pub fn get_version_at(
+        &self,
+        region: &Region,
+        timestamp: DateTime<Utc>,
+    ) -> Option<&RegionVersion> {
+        self.versions
+            .get(region)
+            .and_then(|versions| version_at(versions, timestamp))
+    }
+
+    /// Add a version of the region active from `active_from`, which must be
+    /// later than the region's other versions. Without cells the version keeps
+    /// the hexes of the version before it
+    pub fn insert_version(
+        &mut self,
+        region: Region,
+        active_from: DateTime<Utc>,
+        params: BlockchainRegionParamsV1,
+        cells: Option<HexTreeSet>,
+    ) {
+        let versions = self.versions.entry(region).or_default();
+        let cells = match (cells, versions.last()) {
+            (Some(cells), _) => Arc::new(cells),
+            (None, Some(previous)) => previous.cells.clone(),
+            (None, None) => Arc::new(HexTreeSet::with_compactor(SetCompactor)),
+        };
+        versions.push(RegionVersion {
+            active_from,
+            params,
+            cells,
+        });
+    }
+}
+
+/// Version active at the timestamp of versions ordered by activation
+fn version_at(versions: &[RegionVersion], timestamp: DateTime<Utc>) -> Option<&RegionVersion> {
+    let active = versions.partition_point(|version| version.active_from <= timestamp);
+    active.checked_sub(1).map(|idx| &versions[idx])
 }
 
 #[derive(sqlx::FromRow)]
@@ -80,28 +200,8 @@ pub async fn build_region_tree(
     while let Some(region_row) = regions.try_next().await? {
         if let Some(indexes) = region_row.indexes {
             let region = Region::from_str(&region_row.region)?;
-            let mut h3_idx_decoder = Decoder::new(&indexes[..])?;
-            let mut raw_h3_indices = Vec::new();
-            h3_idx_decoder.read_to_end(&mut raw_h3_indices)?;
-
-            if raw_h3_indices.len() % std::mem::size_of::<u64>() != 0 {
-                tracing::error!("h3 index list malformed; indices are not an index-byte-size multiple; region: {region}");
-                return Err(anyhow!("malformed h3 indices"));
-            }
-
-            let mut h3_idx_buf = [0_u8; 8];
-            for (chunk_num, chunk) in raw_h3_indices.chunks(8).enumerate() {
-                h3_idx_buf.as_mut_slice().copy_from_slice(chunk);
-                let h3_idx = u64::from_le_bytes(h3_idx_buf);
-                match Cell::from_raw(h3_idx) {
-                    Ok(cell) => region_tree.insert(cell, region),
-                    Err(_) => {
-                        tracing::error!(
-                            "h3 index list malformed; region, chunk, bits: {region}, {chunk_num}, {h3_idx:x}"
-                        );
-                        return Err(anyhow!("malformed h3 indices"));
-                    }
-                }
+            for cell in decode_indexes(region, &indexes)? {
+                region_tree.insert(cell, region);
             }
         }
     }
@@ -109,6 +209,35 @@ pub async fn build_region_tree(
     Ok(region_tree)
 }
 
+/// Decode the gzip compressed, little endian h3 indexes of a region's hexes
+fn decode_indexes(region: Region, indexes: &[u8]) -> anyhow::Result<Vec<Cell>> {
+    let mut h3_idx_decoder = Decoder::new(indexes)?;
+    let mut raw_h3_indices = Vec::new();
+    h3_idx_decoder.read_to_end(&mut raw_h3_indices)?;
+
+    if raw_h3_indices.len() % std::mem::size_of::<u64>() != 0 {
+        tracing::error!("h3 index list malformed; indices are not an index-byte-size multiple; region: {region}");
+        return Err(anyhow!("malformed h3 indices"));
+    }
+
+    let mut cells = Vec::with_capacity(raw_h3_indices.len() / std::mem::size_of::<u64>());
+    let mut h3_idx_buf = [0_u8; 8];
+    for (chunk_num, chunk) in raw_h3_indices.chunks(8).enumerate() {
+        h3_idx_buf.as_mut_slice().copy_from_slice(chunk);
+        let h3_idx = u64::from_le_bytes(h3_idx_buf);
+        match Cell::from_raw(h3_idx) {
+            Ok(cell) => cells.push(cell),
+            Err(_) => {
+                tracing::error!(
+                    "h3 index list malformed; region, chunk, bits: {region}, {chunk_num}, {h3_idx:x}"
+                );
+                return Err(anyhow!("malformed h3 indices"));
+            }
+        }
+    }
+    Ok(cells)
+}
+
 pub async fn build_params_map(
     db: impl sqlx::PgExecutor<'_>,
 ) -> anyhow::Result<HashMap<Region, BlockchainRegionParamsV1>> {
@@ -132,12 +261,72 @@ pub async fn build_params_map(
     Ok(params_map)
 }
 
+#[derive(sqlx::FromRow)]
+struct RegionVersionRow {
+    region: String,
+    params: Vec<u8>,
+    indexes: Option<Vec<u8>>,
+    active_from: DateTime<Utc>,
+}
+
+/// Every version of each region, ordered by activation. Versions sharing the
+/// indexes of the version before them share its hexes
+pub async fn build_region_versions(
+    db: impl sqlx::PgExecutor<'_>,
+) -> anyhow::Result<HashMap<Region, Vec<RegionVersion>>> {
+    let mut versions: HashMap<Region, Vec<RegionVersion>> = HashMap::new();
+    let mut previous_indexes: HashMap<Region, Vec<u8>> = HashMap::new();
+
+    let mut rows = sqlx::query_as::<_, RegionVersionRow>(
+        r#"
+        select region, params, indexes, active_from from region_versions
+        order by active_from, id
+        "#,
+    )
+    .fetch(db);
+
+    while let Some(row) = rows.try_next().await? {
+        let region = Region::from_str(&row.region)?;
+        let params = BlockchainRegionParamsV1::decode(row.params.as_slice())?;
+        let region_versions = versions.entry(region).or_default();
+        let cells = match (row.indexes, region_versions.last()) {
+            (Some(indexes), Some(previous)) if previous_indexes.get(&region) == Some(&indexes) => {
+                previous.cells.clone()
+            }
+            (Some(indexes), _) => {
+                let cells: HexTreeSet = decode_indexes(region, &indexes)?.iter().collect();
+                previous_indexes.insert(region, indexes);
+                Arc::new(cells)
+            }
+            (None, _) => Arc::new(HexTreeSet::with_compactor(SetCompactor)),
+        };
+        region_versions.push(RegionVersion {
+            active_from: row.active_from,
+            params,
+            cells,
+        });
+    }
+
+    Ok(versions)
+}
+
+/// A region loaded as a new version
+pub struct RegionUpdate {
+    pub active_from: DateTime<Utc>,
+    /// Hexes of the region, if its indexes were updated
+    pub cells: Option<HexTreeSet>,
+    /// Region tree of all regions, if the region's indexes were updated
+    pub region_tree: Option<HexTreeMap<Region, EqCompactor>>,
+}
+
+/// Store the params and, if given, the indexes of the region and record them
+/// as a version of the region active from now
 pub async fn update_region(
     region: Region,
     params: &BlockchainRegionParamsV1,
     indexes: Option<&[u8]>,
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-) -> anyhow::Result<Option<HexTreeMap<Region, EqCompactor>>> {
+    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres>,
+) -> anyhow::Result<RegionUpdate> {
     let mut transaction = db.begin().await?;
 
     sqlx::query(
@@ -158,14 +347,134 @@ pub async fn update_region(
     .execute(&mut transaction)
     .await?;
 
-    let updated_region = if indexes.is_some() {
-        Some(build_region_tree(&mut transaction).await?)
+    let active_from: DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        insert into region_versions (region, params, indexes)
+        select region, params, indexes from regions where region = $1
+        returning active_from
+        "#,
+    )
+    .bind(region.to_string())
+    .fetch_one(&mut transaction)
+    .await?;
+
+    let (cells, region_tree) = if let Some(indexes) = indexes {
+        let cells: HexTreeSet = decode_indexes(region, indexes)?.iter().collect();
+        (
+            Some(cells),
+            Some(build_region_tree(&mut transaction).await?),
+        )
     } else {
         tracing::debug!("h3 region index update skipped");
-        None
+        (None, None)
     };
 
     transaction.commit().await?;
 
-    Ok(updated_region)
+    Ok(RegionUpdate {
+        active_from,
+        cells,
+        region_tree,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use helium_proto::BlockchainRegionParamV1;
+
+    fn params(channel_frequency: u64) -> BlockchainRegionParamsV1 {
+        BlockchainRegionParamsV1 {
+            region_params: vec![BlockchainRegionParamV1 {
+                channel_frequency,
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn lookups_resolve_the_version_active_at_the_timestamp() {
+        let first_cell = Cell::from_raw(0x8a1fb46622dffff).expect("valid cell");
+        let second_cell = Cell::from_raw(0x8a1fb46632dffff).expect("valid cell");
+        let mut region_map = RegionMap {
+            region_hextree: HexTreeMap::with_compactor(EqCompactor),
+            params_map: HashMap::new(),
+            versions: HashMap::new(),
+        };
+
+        let loaded = Utc::now() - Duration::days(2);
+        let reloaded = loaded + Duration::days(1);
+        region_map.insert_version(
+            Region::Us915,
+            loaded,
+            params(903_900_000),
+            Some([first_cell].iter().collect()),
+        );
+        region_map.insert_version(
+            Region::Us915,
+            reloaded,
+            params(904_100_000),
+            Some([second_cell].iter().collect()),
+        );
+        region_map.insert_version(Region::Eu868, reloaded, params(867_100_000), None);
+
+        let before = loaded - Duration::hours(1);
+        let between = loaded + Duration::hours(1);
+        let after = reloaded + Duration::hours(1);
+
+        assert_eq!(None, region_map.get_region_at(first_cell, before));
+        assert_eq!(None, region_map.get_params_at(&Region::Us915, before));
+        assert_eq!(
+            Some(Region::Us915),
+            region_map.get_region_at(first_cell, between)
+        );
+        assert_eq!(None, region_map.get_region_at(second_cell, between));
+        assert_eq!(
+            Some(params(903_900_000)),
+            region_map.get_params_at(&Region::Us915, between)
+        );
+        assert_eq!(None, region_map.get_params_at(&Region::Eu868, between));
+        assert_eq!(None, region_map.get_region_at(first_cell, after));
+        assert_eq!(
+            Some(Region::Us915),
+            region_map.get_region_at(second_cell, after)
+        );
+        assert_eq!(
+            Some(params(904_100_000)),
+            region_map.get_params_at(&Region::Us915, reloaded)
+        );
+        assert_eq!(
+            Some(params(867_100_000)),
+            region_map.get_params_at(&Region::Eu868, after)
+        );
+    }
+
+    #[test]
+    fn versions_without_cells_keep_the_previous_hexes() {
+        let cell = Cell::from_raw(0x8a1fb46622dffff).expect("valid cell");
+        let mut region_map = RegionMap {
+            region_hextree: HexTreeMap::with_compactor(EqCompactor),
+            params_map: HashMap::new(),
+            versions: HashMap::new(),
+        };
+
+        let loaded = Utc::now() - Duration::days(1);
+        region_map.insert_version(
+            Region::Us915,
+            loaded,
+            params(903_900_000),
+            Some([cell].iter().collect()),
+        );
+        region_map.insert_version(Region::Us915, Utc::now(), params(904_100_000), None);
+
+        assert_eq!(
+            Some(Region::Us915),
+            region_map.get_region_at(cell, Utc::now())
+        );
+        assert_eq!(
+            Some(params(904_100_000)),
+            region_map.get_params_at(&Region::Us915, Utc::now())
+        );
+    }
 }
@@ -1,20 +1,14 @@
-use crate::{
-    broadcast_update,
-    lora_field::{DevAddrField, DevAddrRange, EuiPair, NetIdField, Skf},
-};
+use crate::lora_field::{DevAddrField, DevAddrRange, EuiPair, NetIdField, Skf};
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 use file_store::traits::TimestampEncode;
-use futures::{
-    future::TryFutureExt,
-    stream::{self, Stream, StreamExt, TryStreamExt},
-};
+use futures::stream::{Stream, StreamExt, TryStreamExt};
 use helium_crypto::{Keypair, Sign};
 use helium_proto::Message;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{types::Uuid, FromRow, Row};
-use std::{collections::BTreeMap, sync::Arc};
+use sqlx::{types::Uuid, FromRow, Postgres, Row, Transaction};
+use std::collections::BTreeMap;
 use tokio::sync::broadcast::Sender;
 
 pub mod proto {
@@ -97,12 +91,147 @@ pub enum RouteStorageError {
     ServerProtocol(String),
 }
 
+/// A signed route stream update and the change sequence of the transaction
+/// that committed it, as broadcast to route stream subscribers
+#[derive(Clone, Debug)]
+pub struct StreamUpdate {
+    pub change_seq: i64,
+    pub update: proto::RouteStreamResV1,
+}
+
+/// Held from committing a transaction until its updates are broadcast, so
+/// updates are broadcast in the order of their change sequences
+static COMMIT_ORDER: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Route stream updates for changes made within a transaction, signed and
+/// broadcast to route stream subscribers once the transaction is committed
+#[must_use = "pending updates must be committed"]
+#[derive(Debug, Default)]
+pub struct PendingUpdates(Vec<(proto::ActionV1, proto::route_stream_res_v1::Data)>);
+
+impl PendingUpdates {
+    pub fn routes(action: proto::ActionV1, routes: impl IntoIterator<Item = Route>) -> Self {
+        Self::new(action, routes, |route| {
+            proto::route_stream_res_v1::Data::Route(route.into())
+        })
+    }
+
+    pub fn euis(action: proto::ActionV1, euis: impl IntoIterator<Item = EuiPair>) -> Self {
+        Self::new(action, euis, |eui| {
+            proto::route_stream_res_v1::Data::EuiPair(eui.into())
+        })
+    }
+
+    pub fn devaddr_ranges(
+        action: proto::ActionV1,
+        ranges: impl IntoIterator<Item = DevAddrRange>,
+    ) -> Self {
+        Self::new(action, ranges, |range| {
+            proto::route_stream_res_v1::Data::DevaddrRange(range.into())
+        })
+    }
+
+    pub fn skfs(action: proto::ActionV1, skfs: impl IntoIterator<Item = Skf>) -> Self {
+        Self::new(action, skfs, |skf| {
+            proto::route_stream_res_v1::Data::Skf(skf.into())
+        })
+    }
+
+    fn new<T>(
+        action: proto::ActionV1,
+        entries: impl IntoIterator<Item = T>,
+        data: fn(T) -> proto::route_stream_res_v1::Data,
+    ) -> Self {
+        Self(
+            entries
+                .into_iter()
+                .map(|entry| (action, data(entry)))
+                .collect(),
+        )
+    }
+
+    pub fn push(&mut self, action: proto::ActionV1, data: proto::route_stream_res_v1::Data) {
+        self.0.push((action, data))
+    }
+
+    pub fn extend(&mut self, other: Self) {
+        self.0.extend(other.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The entries the updates add and remove, of the updates `entry` takes
+    pub fn entries<T>(
+        &self,
+        entry: impl Fn(&proto::route_stream_res_v1::Data) -> Option<T>,
+    ) -> (Vec<T>, Vec<T>) {
+        let mut adds = vec![];
+        let mut removes = vec![];
+        for (action, data) in &self.0 {
+            match (action, entry(data)) {
+                (proto::ActionV1::Add, Some(entry)) => adds.push(entry),
+                (proto::ActionV1::Remove, Some(entry)) => removes.push(entry),
+                _ => {}
+            }
+        }
+        (adds, removes)
+    }
+
+    /// Commit the transaction that made the updates, then broadcast them
+    /// signed and stamped with the change sequence it committed at, returning
+    /// that sequence. Broadcasts never wait on subscribers: one falling more
+    /// than the channel capacity behind lags and resyncs from the database.
+    pub async fn commit(
+        self,
+        mut transaction: Transaction<'_, Postgres>,
+        signing_key: &Keypair,
+        update_tx: &Sender<StreamUpdate>,
+    ) -> anyhow::Result<i64> {
+        // The lock is already held by a transaction that changed anything, so
+        // the last sequence handed out is the highest of this transaction
+        let change_seq = lock_change_seq(&mut transaction).await?;
+        let updates = self.sign(signing_key, change_seq)?;
+
+        let _order = COMMIT_ORDER.lock().await;
+        transaction.commit().await?;
+        for update in updates {
+            // Sending only fails without subscribers
+            if update_tx.send(update).is_err() {
+                break;
+            }
+        }
+        Ok(change_seq)
+    }
+
+    fn sign(self, signing_key: &Keypair, change_seq: i64) -> anyhow::Result<Vec<StreamUpdate>> {
+        let timestamp = Utc::now().encode_timestamp();
+        let signer: Vec<u8> = signing_key.public_key().into();
+        self.0
+            .into_iter()
+            .map(|(action, data)| {
+                let mut update = proto::RouteStreamResV1 {
+                    action: i32::from(action),
+                    data: Some(data),
+                    timestamp,
+                    signer: signer.clone(),
+                    signature: vec![],
+                };
+                update.signature = signing_key.sign(&update.encode_to_vec()).map_err(|err| {
+                    tracing::error!(error = ?err, "error signing route stream update");
+                    anyhow!("error signing route stream update")
+                })?;
+                Ok(StreamUpdate { change_seq, update })
+            })
+            .collect()
+    }
+}
+
 pub async fn create_route(
     route: Route,
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: &Keypair,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<Route> {
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<(Route, PendingUpdates)> {
     let net_id: i32 = route.net_id.into();
     let protocol_opts = route
         .server
@@ -111,8 +240,6 @@ pub async fn create_route(
         .ok_or("no protocol defined")
         .map_err(|e| RouteStorageError::ServerProtocol(e.to_string()))?;
 
-    let mut transaction = db.begin().await?;
-
     let row = sqlx::query(
             r#"
             insert into routes (oui, net_id, max_copies, server_host, server_port, server_protocol_opts, active, ignore_empty_skf)
@@ -128,49 +255,21 @@ pub async fn create_route(
         .bind(json!(&protocol_opts))
         .bind(route.active)
         .bind(route.ignore_empty_skf)
-        .fetch_one(&mut transaction)
+        .fetch_one(&mut *transaction)
         .await?;
 
     let route_id = row.get::<Uuid, &str>("id").to_string();
 
-    let new_route = get_route(&route_id, &mut transaction).await?;
-
-    transaction.commit().await?;
+    let new_route = get_route(&route_id, &mut *transaction).await?;
+    let updates = PendingUpdates::routes(proto::ActionV1::Add, [new_route.clone()]);
 
-    let timestamp = Utc::now().encode_timestamp();
-    let signer = signing_key.public_key().into();
-    let mut update = proto::RouteStreamResV1 {
-        action: proto::ActionV1::Add.into(),
-        data: Some(proto::route_stream_res_v1::Data::Route(
-            new_route.clone().into(),
-        )),
-        timestamp,
-        signer,
-        signature: vec![],
-    };
-    _ = futures::future::ready(signing_key.sign(&update.encode_to_vec()))
-        .map_err(|err| {
-            tracing::error!(error = ?err, "error signing route create");
-            anyhow!("error signing route create")
-        })
-        .and_then(|signature| {
-            update.signature = signature;
-            broadcast_update(update, update_tx).map_err(|_| {
-                tracing::error!("failed broadcasting route create");
-                anyhow!("failed broadcasting route create")
-            })
-        })
-        .await;
-
-    Ok(new_route)
+    Ok((new_route, updates))
 }
 
 pub async fn update_route(
     route: Route,
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: &Keypair,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<Route> {
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<(Route, PendingUpdates)> {
     let protocol_opts = route
         .server
         .protocol
@@ -180,8 +279,6 @@ pub async fn update_route(
 
     let uuid = Uuid::try_parse(&route.id)?;
 
-    let mut transaction = db.begin().await?;
-
     sqlx::query(
         r#"
         update routes
@@ -196,40 +293,13 @@ pub async fn update_route(
     .bind(json!(&protocol_opts))
     .bind(route.active)
     .bind(route.ignore_empty_skf)
-    .execute(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
 
-    let updated_route = get_route(&route.id, &mut transaction).await?;
-
-    transaction.commit().await?;
-
-    let timestamp = Utc::now().encode_timestamp();
-    let signer = signing_key.public_key().into();
-    let mut update_res = proto::RouteStreamResV1 {
-        action: proto::ActionV1::Add.into(),
-        data: Some(proto::route_stream_res_v1::Data::Route(
-            updated_route.clone().into(),
-        )),
-        timestamp,
-        signer,
-        signature: vec![],
-    };
-
-    _ = futures::future::ready(signing_key.sign(&update_res.encode_to_vec()))
-        .map_err(|err| {
-            tracing::error!(error = ?err, "error signing route update");
-            anyhow!("error signing route update")
-        })
-        .and_then(|signature| {
-            update_res.signature = signature;
-            broadcast_update(update_res, update_tx).map_err(|_| {
-                tracing::error!("failed broadcasting route update");
-                anyhow!("failed broadcasting route update")
-            })
-        })
-        .await;
+    let updated_route = get_route(&route.id, &mut *transaction).await?;
+    let updates = PendingUpdates::routes(proto::ActionV1::Add, [updated_route.clone()]);
 
-    Ok(updated_route)
+    Ok((updated_route, updates))
 }
 
 async fn insert_euis(
@@ -295,51 +365,14 @@ async fn remove_euis(
 pub async fn update_euis(
     to_add: &[EuiPair],
     to_remove: &[EuiPair],
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: Arc<Keypair>,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<()> {
-    let mut transaction = db.begin().await?;
-
-    let added_euis: Vec<(EuiPair, proto::ActionV1)> = insert_euis(to_add, &mut transaction)
-        .await?
-        .into_iter()
-        .map(|added_eui| (added_eui, proto::ActionV1::Add))
-        .collect();
-
-    let removed_euis: Vec<(EuiPair, proto::ActionV1)> = remove_euis(to_remove, &mut transaction)
-        .await?
-        .into_iter()
-        .map(|removed_eui| (removed_eui, proto::ActionV1::Remove))
-        .collect();
-
-    transaction.commit().await?;
-
-    tokio::spawn(async move {
-        let timestamp = Utc::now().encode_timestamp();
-        let signer: Vec<u8> = signing_key.public_key().into();
-        stream::iter([added_euis, removed_euis].concat())
-            .map(Ok)
-            .try_for_each(|(update, action)| {
-                let mut update_res = proto::RouteStreamResV1 {
-                    action: i32::from(action),
-                    data: Some(proto::route_stream_res_v1::Data::EuiPair(update.into())),
-                    timestamp,
-                    signer: signer.clone(),
-                    signature: vec![],
-                };
-                futures::future::ready(signing_key.sign(&update_res.encode_to_vec()))
-                    .map_err(|_| anyhow!("failed signing eui pair update"))
-                    .and_then(|signature| {
-                        update_res.signature = signature;
-                        broadcast_update::<proto::RouteStreamResV1>(update_res, update_tx.clone())
-                            .map_err(|_| anyhow!("failed broadcasting eui pair update"))
-                    })
-            })
-            .await
-    });
-
-    Ok(())
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<PendingUpdates> {
+    let added_euis = insert_euis(to_add, &mut *transaction).await?;
+    let removed_euis = remove_euis(to_remove, &mut *transaction).await?;
+
+    let mut updates = PendingUpdates::euis(proto::ActionV1::Add, added_euis);
+    updates.extend(PendingUpdates::euis(proto::ActionV1::Remove, removed_euis));
+    Ok(updates)
 }
 
 async fn insert_devaddr_ranges(
@@ -373,7 +406,7 @@ async fn insert_devaddr_ranges(
         .await?)
 }
 
-async fn remove_devaddr_ranges(
+pub async fn remove_devaddr_ranges(
     ranges: &[DevAddrRange],
     db: impl sqlx::PgExecutor<'_>,
 ) -> anyhow::Result<Vec<DevAddrRange>> {
@@ -407,56 +440,17 @@ async fn remove_devaddr_ranges(
 pub async fn update_devaddr_ranges(
     to_add: &[DevAddrRange],
     to_remove: &[DevAddrRange],
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: Arc<Keypair>,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<()> {
-    let mut transaction = db.begin().await?;
-
-    let added_devaddrs: Vec<(DevAddrRange, proto::ActionV1)> =
-        insert_devaddr_ranges(to_add, &mut transaction)
-            .await?
-            .into_iter()
-            .map(|added_range| (added_range, proto::ActionV1::Add))
-            .collect();
-
-    let removed_devaddrs: Vec<(DevAddrRange, proto::ActionV1)> =
-        remove_devaddr_ranges(to_remove, &mut transaction)
-            .await?
-            .into_iter()
-            .map(|removed_range| (removed_range, proto::ActionV1::Remove))
-            .collect();
-
-    transaction.commit().await?;
-
-    tokio::spawn(async move {
-        let timestamp = Utc::now().encode_timestamp();
-        let signer: Vec<u8> = signing_key.public_key().into();
-        stream::iter([added_devaddrs, removed_devaddrs].concat())
-            .map(Ok)
-            .try_for_each(|(update, action)| {
-                let mut devaddr_res = proto::RouteStreamResV1 {
-                    action: i32::from(action),
-                    data: Some(proto::route_stream_res_v1::Data::DevaddrRange(
-                        update.into(),
-                    )),
-                    timestamp,
-                    signer: signer.clone(),
-                    signature: vec![],
-                };
-
-                futures::future::ready(signing_key.sign(&devaddr_res.encode_to_vec()))
-                    .map_err(|_| anyhow!("failed to sign devaddr range update"))
-                    .and_then(|signature| {
-                        devaddr_res.signature = signature;
-                        broadcast_update::<proto::RouteStreamResV1>(devaddr_res, update_tx.clone())
-                            .map_err(|_| anyhow!("failed to broadcast devaddr range update"))
-                    })
-            })
-            .await
-    });
-
-    Ok(())
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<PendingUpdates> {
+    let added_ranges = insert_devaddr_ranges(to_add, &mut *transaction).await?;
+    let removed_ranges = remove_devaddr_ranges(to_remove, &mut *transaction).await?;
+
+    let mut updates = PendingUpdates::devaddr_ranges(proto::ActionV1::Add, added_ranges);
+    updates.extend(PendingUpdates::devaddr_ranges(
+        proto::ActionV1::Remove,
+        removed_ranges,
+    ));
+    Ok(updates)
 }
 
 pub async fn list_routes(oui: u64, db: impl sqlx::PgExecutor<'_>) -> anyhow::Result<Vec<Route>> {
@@ -489,7 +483,7 @@ pub async fn list_routes(oui: u64, db: impl sqlx::PgExecutor<'_>) -> anyhow::Res
 
 pub fn list_euis_for_route<'a>(
     id: &str,
-    db: impl sqlx::PgExecutor<'a> + 'a + Copy,
+    db: impl sqlx::PgExecutor<'a> + 'a,
 ) -> Result<impl Stream<Item = Result<EuiPair, sqlx::Error>> + 'a, RouteStorageError> {
     let id = Uuid::try_parse(id)?;
     const EUI_SELECT_SQL: &str = r#"
@@ -521,20 +515,85 @@ pub fn list_devaddr_ranges_for_route<'a>(
         .boxed())
 }
 
+/// Key of the transaction level advisory lock held while routing configuration
+/// change sequences are handed out
+const CHANGE_SEQ_LOCK: i64 = 7265676;
+
+/// Where a route stream picks up the stored routing configuration
+#[derive(Clone, Copy, Debug)]
+pub enum StreamFrom {
+    /// Everything updated at or after a timestamp
+    Timestamp(DateTime<Utc>),
+    /// Everything changed after a change sequence
+    Sequence(i64),
+}
+
+impl StreamFrom {
+    fn since(&self) -> DateTime<Utc> {
+        match self {
+            Self::Timestamp(since) => *since,
+            Self::Sequence(_) => DateTime::<Utc>::from(std::time::UNIX_EPOCH),
+        }
+    }
+
+    fn after_seq(&self) -> i64 {
+        match self {
+            Self::Timestamp(_) => 0,
+            Self::Sequence(seq) => *seq,
+        }
+    }
+}
+
+/// The change sequence at or below which every routing configuration change
+/// is committed. Changes committed later are guaranteed a higher sequence.
+pub async fn committed_change_seq(db: &sqlx::Pool<sqlx::Postgres>) -> Result<i64, sqlx::Error> {
+    let mut transaction = db.begin().await?;
+    let seq = lock_change_seq(&mut transaction).await?;
+    transaction.commit().await?;
+    Ok(seq)
+}
+
+/// Hold the change sequence lock for the rest of a transaction, returning the
+/// committed change sequence. Other routing configuration changes wait for the
+/// transaction to end.
+pub async fn lock_change_seq(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query("select pg_advisory_xact_lock($1)")
+        .bind(CHANGE_SEQ_LOCK)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query_scalar::<_, i64>(
+        "select case when is_called then last_value else 0 end from route_change_seq",
+    )
+    .fetch_one(&mut *transaction)
+    .await
+}
+
+/// Highest change sequence of the deleted routing configuration purged from
+/// the database. Resuming a stream from before it could miss removals.
+pub async fn purged_change_seq(db: impl sqlx::PgExecutor<'_>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("select purged_change_seq from route_change_horizon")
+        .fetch_one(db)
+        .await
+}
+
 pub fn route_stream<'a>(
     db: impl sqlx::PgExecutor<'a> + 'a,
-    since: DateTime<Utc>,
-) -> impl Stream<Item = (Route, bool)> + 'a {
+    from: StreamFrom,
+) -> impl Stream<Item = Result<(Route, bool), RouteStorageError>> + 'a {
     sqlx::query(
         r#"
         select r.id, r.oui, r.net_id, r.max_copies, r.server_host, r.server_port, r.server_protocol_opts, r.active, r.ignore_empty_skf, o.locked, r.deleted
             from routes r
             join organizations o on r.oui = o.oui
-            where r.updated_at >= $1
+            where r.updated_at >= $1 and r.change_seq > $2
             group by r.id, o.locked
+            order by r.change_seq
         "#,
     )
-    .bind(since)
+    .bind(from.since())
+    .bind(from.after_seq())
     .fetch(db)
     .and_then(|row| async move { StorageRoute::from_row(&row).map(|sr| (sr, row.get("deleted"))) })
     .map_err(RouteStorageError::from)
@@ -548,66 +607,71 @@ pub fn route_stream<'a>(
             locked: route.locked,
             ignore_empty_skf: route.ignore_empty_skf,
         }, deleted))})
-    .filter_map(|result| async move { result.ok() })
     .boxed()
 }
 
 pub fn eui_stream<'a>(
     db: impl sqlx::PgExecutor<'a> + 'a + Copy,
-    since: DateTime<Utc>,
-) -> impl Stream<Item = (EuiPair, bool)> + 'a {
+    from: StreamFrom,
+) -> impl Stream<Item = Result<(EuiPair, bool), RouteStorageError>> + 'a {
     sqlx::query(
         r#"
         select eui.route_id, eui.app_eui, eui.dev_eui, eui.deleted
         from route_eui_pairs eui
         join routes r on eui.route_id = r.id
-        where eui.updated_at >= $1 and r.deleted = false
+        where eui.updated_at >= $1 and eui.change_seq > $2 and r.deleted = false
+        order by eui.change_seq
         "#,
     )
-    .bind(since)
+    .bind(from.since())
+    .bind(from.after_seq())
     .fetch(db)
     .and_then(|row| async move { EuiPair::from_row(&row).map(|eui| (eui, row.get("deleted"))) })
-    .filter_map(|result| async move { result.ok() })
+    .map_err(RouteStorageError::from)
     .boxed()
 }
 
 pub fn devaddr_range_stream<'a>(
     db: impl sqlx::PgExecutor<'a> + 'a + Copy,
-    since: DateTime<Utc>,
-) -> impl Stream<Item = (DevAddrRange, bool)> + 'a {
+    from: StreamFrom,
+) -> impl Stream<Item = Result<(DevAddrRange, bool), RouteStorageError>> + 'a {
     sqlx::query(
         r#"
         select devaddr.route_id, devaddr.start_addr, devaddr.end_addr, devaddr.deleted
         from route_devaddr_ranges devaddr
         join routes r on devaddr.route_id = r.id
-        where devaddr.updated_at >= $1 and r.deleted = false
+        where devaddr.updated_at >= $1 and devaddr.change_seq > $2 and r.deleted = false
+        order by devaddr.change_seq
         "#,
     )
-    .bind(since)
+    .bind(from.since())
+    .bind(from.after_seq())
     .fetch(db)
     .and_then(
         |row| async move { DevAddrRange::from_row(&row).map(|dar| (dar, row.get("deleted"))) },
     )
-    .filter_map(|result| async move { result.ok() })
+    .map_err(RouteStorageError::from)
     .boxed()
 }
 
 pub fn skf_stream<'a>(
     db: impl sqlx::PgExecutor<'a> + 'a + Copy,
-    since: DateTime<Utc>,
-) -> impl Stream<Item = (Skf, bool)> + 'a {
+    from: StreamFrom,
+) -> impl Stream<Item = Result<(Skf, bool), RouteStorageError>> + 'a {
     sqlx::query(
         r#"
         select skf.route_id, skf.devaddr, skf.session_key, skf.max_copies, skf.deleted
         from route_session_key_filters skf
         join routes r on skf.route_id = r.id
-        where skf.updated_at >= $1 and r.deleted = false
+        where skf.updated_at >= $1 and skf.change_seq > $2 and r.deleted = false
+        order by skf.change_seq
         "#,
     )
-    .bind(since)
+    .bind(from.since())
+    .bind(from.after_seq())
     .fetch(db)
     .and_then(|row| async move { Skf::from_row(&row).map(|skf| (skf, row.get("deleted"))) })
-    .filter_map(|result| async move { result.ok() })
+    .map_err(RouteStorageError::from)
     .boxed()
 }
 
@@ -646,14 +710,11 @@ pub async fn get_route(id: &str, db: impl sqlx::PgExecutor<'_>) -> anyhow::Resul
 
 pub async fn delete_route(
     id: &str,
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: &Keypair,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<()> {
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<(Route, PendingUpdates)> {
     let uuid = Uuid::try_parse(id)?;
-    let mut transaction = db.begin().await?;
 
-    let route = get_route(id, &mut transaction).await?;
+    let route = get_route(id, &mut *transaction).await?;
 
     sqlx::query(
         r#"
@@ -663,40 +724,17 @@ pub async fn delete_route(
         "#,
     )
     .bind(uuid)
-    .execute(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
 
-    transaction.commit().await?;
-
-    let timestamp = Utc::now().encode_timestamp();
-    let signer = signing_key.public_key().into();
-    let mut delete_res = proto::RouteStreamResV1 {
-        action: proto::ActionV1::Remove.into(),
-        data: Some(proto::route_stream_res_v1::Data::Route(
-            route.clone().into(),
-        )),
-        timestamp,
-        signer,
-        signature: vec![],
-    };
+    let updates = PendingUpdates::routes(proto::ActionV1::Remove, [route.clone()]);
 
-    _ = signing_key
-        .sign(&delete_res.encode_to_vec())
-        .map_err(|_| anyhow!("failed to sign route delete update"))
-        .and_then(|signature| {
-            delete_res.signature = signature;
-            update_tx.send(delete_res).map_err(|_| {
-                tracing::error!("failed to broadcast route delete update");
-                anyhow!("failed to broadcast route delete update")
-            })
-        });
-
-    Ok(())
+    Ok((route, updates))
 }
 
 pub fn list_skfs_for_route<'a>(
     id: &str,
-    db: impl sqlx::PgExecutor<'a> + 'a + Copy,
+    db: impl sqlx::PgExecutor<'a> + 'a,
 ) -> Result<impl Stream<Item = Result<Skf, sqlx::Error>> + 'a, RouteStorageError> {
     let id = Uuid::try_parse(id)?;
     const SKF_SELECT_SQL: &str = r#"
@@ -734,53 +772,16 @@ pub fn list_skfs_for_route_and_devaddr<'a>(
 pub async fn update_skfs(
     to_add: &[Skf],
     to_remove: &[Skf],
-    db: impl sqlx::PgExecutor<'_> + sqlx::Acquire<'_, Database = sqlx::Postgres> + Copy,
-    signing_key: Arc<Keypair>,
-    update_tx: Sender<proto::RouteStreamResV1>,
-) -> anyhow::Result<()> {
-    let mut transaction = db.begin().await?;
-
+    transaction: &mut Transaction<'_, Postgres>,
+) -> anyhow::Result<PendingUpdates> {
     // Always process removes before adds to ensure updating existing values doesn't result in
     // removing a value that was just added
-    let removed_updates: Vec<(Skf, proto::ActionV1)> = remove_skfs(to_remove, &mut transaction)
-        .await?
-        .into_iter()
-        .map(|removed_skf| (removed_skf, proto::ActionV1::Remove))
-        .collect();
-
-    let added_updates: Vec<(Skf, proto::ActionV1)> = insert_skfs(to_add, &mut transaction)
-        .await?
-        .into_iter()
-        .map(|added_skf| (added_skf, proto::ActionV1::Add))
-        .collect();
-
-    transaction.commit().await?;
-
-    tokio::spawn(async move {
-        let timestamp = Utc::now().encode_timestamp();
-        let signer: Vec<u8> = signing_key.public_key().into();
-        stream::iter([added_updates, removed_updates].concat())
-            .map(Ok)
-            .try_for_each(|(update, action)| {
-                let mut skf_update = proto::RouteStreamResV1 {
-                    action: i32::from(action),
-                    data: Some(proto::route_stream_res_v1::Data::Skf(update.into())),
-                    timestamp,
-                    signer: signer.clone(),
-                    signature: vec![],
-                };
-                futures::future::ready(signing_key.sign(&skf_update.encode_to_vec()))
-                    .map_err(|_| anyhow!("failed to sign session key filter update"))
-                    .and_then(|signature| {
-                        skf_update.signature = signature;
-                        broadcast_update::<proto::RouteStreamResV1>(skf_update, update_tx.clone())
-                            .map_err(|_| anyhow!("failed to broadcast session key filter update"))
-                    })
-            })
-            .await
-    });
+    let removed_skfs = remove_skfs(to_remove, &mut *transaction).await?;
+    let added_skfs = insert_skfs(to_add, &mut *transaction).await?;
 
-    Ok(())
+    let mut updates = PendingUpdates::skfs(proto::ActionV1::Add, added_skfs);
+    updates.extend(PendingUpdates::skfs(proto::ActionV1::Remove, removed_skfs));
+    Ok(updates)
 }
 
 async fn insert_skfs(skfs: &[Skf], db: impl sqlx::PgExecutor<'_>) -> anyhow::Result<Vec<Skf>> {
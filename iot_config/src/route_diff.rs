@@ -0,0 +1,397 @@
+use crate::{
+    audit::{self, AuditError, Signer},
+    lora_field::{DevAddrField, DevAddrRange, EuiField, EuiPair, Skf},
+    org::{self, OrgStoreError},
+    quota::{QuotaError, Quotas},
+    route::{self, PendingUpdates, RouteStorageError, StreamUpdate},
+    route_service::{SKF_UPDATE_LIMIT, UPDATE_BATCH_LIMIT},
+};
+use futures::stream::TryStreamExt;
+use helium_crypto::Keypair;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Pool, Postgres, Transaction};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::Arc,
+};
+use tokio::sync::broadcast::Sender;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateMode {
+    /// Add the entries to the route
+    Add,
+    /// Remove the entries from the route
+    Remove,
+    /// Make the entries the complete set of the route's entries, removing
+    /// any others
+    Set,
+}
+
+/// An update of a route's eui pairs, devaddr ranges or session key filters
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RouteUpdate<T> {
+    pub route_id: String,
+    pub mode: UpdateMode,
+    /// Apply the changes. If false the update is only validated and the
+    /// changes it would make are returned
+    #[serde(default)]
+    pub commit: bool,
+    pub entries: Vec<T>,
+}
+
+/// The changes an update made, or would make if not committed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RouteDiff<T> {
+    pub route_id: String,
+    pub committed: bool,
+    pub adds: Vec<T>,
+    pub removes: Vec<T>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RouteDiffError {
+    #[error("route not found: {0}")]
+    RouteNotFound(String),
+    #[error("invalid update: {0}")]
+    Invalid(String),
+    #[error("route storage error: {0}")]
+    Storage(#[from] RouteStorageError),
+    #[error("org storage error: {0}")]
+    OrgStore(#[from] OrgStoreError),
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("audit log error: {0}")]
+    Audit(#[from] AuditError),
+    #[error("quota exceeded: {0}")]
+    Quota(#[from] QuotaError),
+    #[error("update failed: {0}")]
+    Update(#[from] anyhow::Error),
+}
+
+/// Eui pairs, devaddr ranges and session key filters of a route
+#[async_trait::async_trait]
+pub trait RouteEntry: Clone + std::fmt::Debug + PartialEq + Send + Sync + Serialize {
+    /// Identity of an entry on its route. Adding an entry replaces any entry
+    /// with the same key.
+    type Key: Eq + Hash + Send;
+
+    /// Rpc name under which committed updates are audited
+    const AUDIT_RPC: &'static str;
+    /// Number of entries applied per transaction
+    const BATCH_SIZE: usize;
+
+    fn route_id(&self) -> &str;
+
+    fn key(&self) -> Self::Key;
+
+    /// The entries currently stored for a route
+    async fn current(route_id: &str, db: &mut PgConnection) -> Result<Vec<Self>, RouteDiffError>;
+
+    /// Check the route has room for the entries to be added under its quota,
+    /// locking it against other inserts for the rest of the transaction
+    async fn check_quota(
+        adds: &[Self],
+        quotas: &Quotas,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError>;
+
+    /// Check the constraints of the entries to be added to a route
+    async fn validate_adds(
+        route_id: &str,
+        adds: &[Self],
+        db: &mut PgConnection,
+    ) -> Result<(), RouteDiffError>;
+
+    async fn apply(
+        adds: &[Self],
+        removes: &[Self],
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> anyhow::Result<PendingUpdates>;
+}
+
+#[async_trait::async_trait]
+impl RouteEntry for EuiPair {
+    type Key = (EuiField, EuiField);
+
+    const AUDIT_RPC: &'static str = "route_sync_euis";
+    const BATCH_SIZE: usize = UPDATE_BATCH_LIMIT;
+
+    fn route_id(&self) -> &str {
+        &self.route_id
+    }
+
+    fn key(&self) -> Self::Key {
+        (self.app_eui, self.dev_eui)
+    }
+
+    async fn current(route_id: &str, db: &mut PgConnection) -> Result<Vec<Self>, RouteDiffError> {
+        Ok(route::list_euis_for_route(route_id, db)?
+            .try_collect()
+            .await?)
+    }
+
+    async fn check_quota(
+        adds: &[Self],
+        quotas: &Quotas,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        quotas.check_euis(adds, db).await
+    }
+
+    async fn validate_adds(
+        _route_id: &str,
+        _adds: &[Self],
+        _db: &mut PgConnection,
+    ) -> Result<(), RouteDiffError> {
+        Ok(())
+    }
+
+    async fn apply(
+        adds: &[Self],
+        removes: &[Self],
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> anyhow::Result<PendingUpdates> {
+        route::update_euis(adds, removes, transaction).await
+    }
+}
+
+#[async_trait::async_trait]
+impl RouteEntry for DevAddrRange {
+    type Key = (DevAddrField, DevAddrField);
+
+    const AUDIT_RPC: &'static str = "route_sync_devaddr_ranges";
+    const BATCH_SIZE: usize = UPDATE_BATCH_LIMIT;
+
+    fn route_id(&self) -> &str {
+        &self.route_id
+    }
+
+    fn key(&self) -> Self::Key {
+        (self.start_addr, self.end_addr)
+    }
+
+    async fn current(route_id: &str, db: &mut PgConnection) -> Result<Vec<Self>, RouteDiffError> {
+        Ok(route::list_devaddr_ranges_for_route(route_id, db)?
+            .try_collect()
+            .await?)
+    }
+
+    /// Devaddr ranges are bounded by the org's constraints rather than a quota
+    async fn check_quota(
+        _adds: &[Self],
+        _quotas: &Quotas,
+        _db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        Ok(())
+    }
+
+    async fn validate_adds(
+        route_id: &str,
+        adds: &[Self],
+        db: &mut PgConnection,
+    ) -> Result<(), RouteDiffError> {
+        let constraints = org::get_constraints_by_route(route_id, db).await?;
+        for range in adds {
+            if range.end_addr < range.start_addr {
+                return Err(RouteDiffError::Invalid(format!(
+                    "devaddr range ends before it starts {range:?}"
+                )));
+            }
+            if !constraints
+                .iter()
+                .any(|constraint| constraint.contains_range(range))
+            {
+                return Err(RouteDiffError::Invalid(format!(
+                    "devaddr range outside of constraint bounds {range:?}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply(
+        adds: &[Self],
+        removes: &[Self],
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> anyhow::Result<PendingUpdates> {
+        route::update_devaddr_ranges(adds, removes, transaction).await
+    }
+}
+
+#[async_trait::async_trait]
+impl RouteEntry for Skf {
+    type Key = (DevAddrField, String);
+
+    const AUDIT_RPC: &'static str = "route_sync_skfs";
+    const BATCH_SIZE: usize = SKF_UPDATE_LIMIT;
+
+    fn route_id(&self) -> &str {
+        &self.route_id
+    }
+
+    fn key(&self) -> Self::Key {
+        (self.devaddr, self.session_key.clone())
+    }
+
+    async fn current(route_id: &str, db: &mut PgConnection) -> Result<Vec<Self>, RouteDiffError> {
+        Ok(route::list_skfs_for_route(route_id, db)?
+            .try_collect()
+            .await?)
+    }
+
+    async fn check_quota(
+        adds: &[Self],
+        quotas: &Quotas,
+        db: &mut PgConnection,
+    ) -> Result<(), QuotaError> {
+        quotas.check_skfs(adds, db).await
+    }
+
+    /// Session key filters must be for devaddrs within the route's current
+    /// devaddr ranges
+    async fn validate_adds(
+        route_id: &str,
+        adds: &[Self],
+        db: &mut PgConnection,
+    ) -> Result<(), RouteDiffError> {
+        let ranges = DevAddrRange::current(route_id, db).await?;
+        for skf in adds {
+            if !ranges.iter().any(|range| range.contains_addr(skf.devaddr)) {
+                return Err(RouteDiffError::Invalid(format!(
+                    "devaddr {} not within registered ranges for route {route_id}",
+                    skf.devaddr
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply(
+        adds: &[Self],
+        removes: &[Self],
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> anyhow::Result<PendingUpdates> {
+        route::update_skfs(adds, removes, transaction).await
+    }
+}
+
+/// Validate an update against the route's stored entries, returning the
+/// entries it adds and removes. Entries already stored are not added again
+/// and entries not stored are not removed.
+async fn diff<T: RouteEntry>(
+    update: &RouteUpdate<T>,
+    db: &mut PgConnection,
+) -> Result<RouteDiff<T>, RouteDiffError> {
+    route::get_route(&update.route_id, &mut *db)
+        .await
+        .map_err(|_| RouteDiffError::RouteNotFound(update.route_id.clone()))?;
+
+    let mut keys = HashSet::with_capacity(update.entries.len());
+    for entry in &update.entries {
+        if entry.route_id() != update.route_id {
+            return Err(RouteDiffError::Invalid(format!(
+                "entry not for route {}: {entry:?}",
+                update.route_id
+            )));
+        }
+        if !keys.insert(entry.key()) {
+            return Err(RouteDiffError::Invalid(format!(
+                "duplicate entry {entry:?}"
+            )));
+        }
+    }
+
+    let current: HashMap<T::Key, T> = T::current(&update.route_id, &mut *db)
+        .await?
+        .into_iter()
+        .map(|entry| (entry.key(), entry))
+        .collect();
+
+    let not_stored = |entry: &&T| current.get(&entry.key()) != Some(*entry);
+    let (adds, removes) = match update.mode {
+        UpdateMode::Add => (
+            update.entries.iter().filter(not_stored).cloned().collect(),
+            vec![],
+        ),
+        UpdateMode::Remove => (
+            vec![],
+            update
+                .entries
+                .iter()
+                .filter_map(|entry| current.get(&entry.key()).cloned())
+                .collect(),
+        ),
+        UpdateMode::Set => (
+            update.entries.iter().filter(not_stored).cloned().collect(),
+            current
+                .into_iter()
+                .filter(|(key, _)| !keys.contains(key))
+                .map(|(_, entry)| entry)
+                .collect(),
+        ),
+    };
+
+    T::validate_adds(&update.route_id, &adds, db).await?;
+
+    Ok(RouteDiff {
+        route_id: update.route_id.clone(),
+        committed: false,
+        adds,
+        removes,
+    })
+}
+
+/// Validate an update and apply its changes, along with its audit log entry,
+/// in a single transaction, returning the changes. The route is locked before
+/// its stored entries are read, so the changes are computed against the
+/// entries they are applied to. The changes are written in batches, removes
+/// first, and the quota is checked once the removes are written. Either all
+/// of them are committed or, on failure, none are. An update that is not to
+/// be committed is rolled back once checked.
+pub async fn apply<T: RouteEntry>(
+    update: &RouteUpdate<T>,
+    signer: &Signer,
+    quotas: &Quotas,
+    db: &Pool<Postgres>,
+    signing_key: Arc<Keypair>,
+    update_tx: Sender<StreamUpdate>,
+) -> Result<RouteDiff<T>, RouteDiffError> {
+    let mut transaction = db.begin().await?;
+    quotas
+        .lock_routes([update.route_id.as_str()], &mut transaction)
+        .await?;
+    let mut diff = diff(update, &mut transaction).await?;
+    if update.commit {
+        quotas.check_rate(signer, diff.adds.len() + diff.removes.len())?;
+    }
+
+    let mut updates = PendingUpdates::default();
+    for removes in diff.removes.chunks(T::BATCH_SIZE) {
+        updates.extend(T::apply(&[], removes, &mut transaction).await?);
+    }
+    T::check_quota(&diff.adds, quotas, &mut transaction).await?;
+    for adds in diff.adds.chunks(T::BATCH_SIZE) {
+        updates.extend(T::apply(adds, &[], &mut transaction).await?);
+    }
+    if !update.commit {
+        transaction.rollback().await?;
+        return Ok(diff);
+    }
+
+    audit::record(
+        audit::Entry::new(signer, T::AUDIT_RPC)
+            .route_id(&diff.route_id)
+            .before(&diff.removes)
+            .after(&diff.adds),
+        &mut transaction,
+    )
+    .await?;
+    updates
+        .commit(transaction, &signing_key, &update_tx)
+        .await?;
+
+    diff.committed = true;
+    Ok(diff)
+}
@@ -1,12 +1,14 @@
 use crate::{
     admin::{AuthCache, KeyType},
+    audit::{self, AuditError, Signer, SignerType},
     lora_field::{DevAddrConstraint, DevAddrRange, EuiPair, Skf},
     org::{self, OrgStoreError},
-    route::{self, Route, RouteStorageError},
+    quota::{QuotaError, Quotas},
+    route::{self, PendingUpdates, Route, RouteStorageError, StreamFrom, StreamUpdate},
     telemetry, update_channel, verify_public_key, GrpcResult, GrpcStreamRequest, GrpcStreamResult,
 };
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{TimeZone, Utc};
 use file_store::traits::{MsgVerify, TimestampEncode};
 use futures::{
     future::TryFutureExt,
@@ -24,19 +26,53 @@ use helium_proto::{
     },
     Message,
 };
-use sqlx::{Pool, Postgres};
-use std::{pin::Pin, sync::Arc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres, Transaction};
+use std::{collections::BTreeSet, pin::Pin, sync::Arc};
 use tokio::sync::{broadcast, mpsc};
-use tonic::{Request, Response, Status};
-
-const UPDATE_BATCH_LIMIT: usize = 5_000;
-const SKF_UPDATE_LIMIT: usize = 100;
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+
+/// Request and response metadata key of route stream change sequences. Sent
+/// on a stream request to resume after a sequence, returned with the stream
+/// as the sequence the subscriber is synced to once existing changes are sent.
+///
+/// Updates are broadcast in change sequence order and the stream tracks the
+/// sequence of the last update it sent, skipping updates already sent from
+/// the database. `RouteStreamResV1` has no field for the sequence, so it is
+/// only sent to the subscriber in the trailers when the server ends the
+/// stream, on a failed resync or on shutdown. A subscriber that disconnects
+/// otherwise resumes from the sequence it subscribed with, replaying every
+/// change since, which only works while those changes have not been purged.
+pub const CHANGE_SEQ_METADATA_KEY: &str = "x-route-change-seq";
+
+/// Request metadata key marking an eui pair, devaddr range or session key
+/// filter update as validate-only when set to `true`. The update is checked
+/// and applied within a transaction that is then rolled back, so nothing is
+/// committed, audited or broadcast. The key is returned on the response of a
+/// validated update.
+pub const VALIDATE_ONLY_METADATA_KEY: &str = "x-validate-only";
+
+/// Response metadata key of the [`ValidatedDiff`] of a validate-only update,
+/// as json
+pub const VALIDATE_ONLY_DIFF_METADATA_KEY: &str = "x-validate-only-diff-bin";
+
+pub const UPDATE_BATCH_LIMIT: usize = 5_000;
+pub const SKF_UPDATE_LIMIT: usize = 100;
+
+/// The eui pairs, devaddr ranges or session key filters a validate-only
+/// update adds and removes
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ValidatedDiff<T> {
+    pub adds: Vec<T>,
+    pub removes: Vec<T>,
+}
 
 pub struct RouteService {
     auth_cache: AuthCache,
     pool: Pool<Postgres>,
-    update_channel: broadcast::Sender<RouteStreamResV1>,
+    update_channel: broadcast::Sender<StreamUpdate>,
     signing_key: Arc<Keypair>,
+    quotas: Quotas,
 }
 
 #[derive(Clone, Debug)]
@@ -46,20 +82,26 @@ enum OrgId<'a> {
 }
 
 impl RouteService {
-    pub fn new(signing_key: Arc<Keypair>, auth_cache: AuthCache, pool: Pool<Postgres>) -> Self {
+    pub fn new(
+        signing_key: Arc<Keypair>,
+        auth_cache: AuthCache,
+        pool: Pool<Postgres>,
+        quotas: Quotas,
+    ) -> Self {
         Self {
             auth_cache,
             pool,
             update_channel: update_channel(),
             signing_key,
+            quotas,
         }
     }
 
-    fn subscribe_to_routes(&self) -> broadcast::Receiver<RouteStreamResV1> {
+    fn subscribe_to_routes(&self) -> broadcast::Receiver<StreamUpdate> {
         self.update_channel.subscribe()
     }
 
-    pub fn clone_update_channel(&self) -> broadcast::Sender<RouteStreamResV1> {
+    pub fn clone_update_channel(&self) -> broadcast::Sender<StreamUpdate> {
         self.update_channel.clone()
     }
 
@@ -68,7 +110,7 @@ impl RouteService {
         signer: &PublicKey,
         request: &R,
         id: OrgId<'a>,
-    ) -> Result<(), Status>
+    ) -> Result<Signer, Status>
     where
         R: MsgVerify,
     {
@@ -78,7 +120,7 @@ impl RouteService {
             .is_ok()
         {
             tracing::debug!(signer = signer.to_string(), "request authorized by admin");
-            return Ok(());
+            return Ok(Signer::new(signer.clone(), KeyType::Administrator));
         }
 
         let org_keys = match id {
@@ -95,7 +137,7 @@ impl RouteService {
                 signer = signer.to_string(),
                 "request authorized by delegate"
             );
-            return Ok(());
+            return Ok(Signer::new(signer.clone(), SignerType::Org));
         }
 
         Err(Status::permission_denied("unauthorized request signature"))
@@ -132,7 +174,9 @@ impl RouteService {
         {
             return Ok(());
         }
-        self.verify_request_signature(signer, request, id).await
+        self.verify_request_signature(signer, request, id)
+            .await
+            .map(|_| ())
     }
 
     fn sign_response(&self, response: &[u8]) -> Result<Vec<u8>, Status> {
@@ -141,6 +185,42 @@ impl RouteService {
             .map_err(|_| Status::internal("response signing error"))
     }
 
+    async fn begin_update(&self) -> Result<Transaction<'static, Postgres>, Status> {
+        self.pool.begin().await.map_err(|err| {
+            tracing::error!("route update failed: {err:?}");
+            Status::internal("route update failed")
+        })
+    }
+
+    /// Commits an update and broadcasts its changes, or rolls it back when
+    /// the update was only being validated, returning the entries `entry`
+    /// takes of the changes it would make
+    async fn finish_update<T>(
+        &self,
+        transaction: Transaction<'_, Postgres>,
+        updates: PendingUpdates,
+        validate_only: bool,
+        entry: fn(&route_stream_res_v1::Data) -> Option<T>,
+    ) -> Result<Option<ValidatedDiff<T>>, Status> {
+        let result = if validate_only {
+            let (adds, removes) = updates.entries(entry);
+            transaction
+                .rollback()
+                .await
+                .map(|_| Some(ValidatedDiff { adds, removes }))
+                .map_err(anyhow::Error::from)
+        } else {
+            updates
+                .commit(transaction, &self.signing_key, &self.update_channel)
+                .await
+                .map(|_| None)
+        };
+        result.map_err(|err| {
+            tracing::error!("route update failed: {err:?}");
+            Status::internal("route update failed")
+        })
+    }
+
     async fn update_validator(
         &self,
         route_id: &str,
@@ -248,7 +328,8 @@ impl iot_config::Route for RouteService {
         telemetry::count_request("route", "create");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request, OrgId::Oui(request.oui))
+        let signer = self
+            .verify_request_signature(&signer, &request, OrgId::Oui(request.oui))
             .await?;
 
         let route: Route = request
@@ -269,17 +350,37 @@ impl iot_config::Route for RouteService {
             ));
         }
 
-        let new_route: Route = route::create_route(
-            route,
-            &self.pool,
-            &self.signing_key,
-            self.clone_update_channel(),
-        )
-        .await
-        .map_err(|err| {
+        self.quotas.check_rate(&signer, 1)?;
+
+        let mut transaction = self.pool.begin().await.map_err(|err| {
             tracing::error!("route create failed {err:?}");
             Status::internal("route create failed")
         })?;
+        self.quotas
+            .check_routes(request.oui, &mut transaction)
+            .await?;
+        let (new_route, updates) =
+            route::create_route(route, &mut transaction)
+                .await
+                .map_err(|err| {
+                    tracing::error!("route create failed {err:?}");
+                    Status::internal("route create failed")
+                })?;
+        audit::record(
+            audit::Entry::new(&signer, "route_create")
+                .oui(new_route.oui)
+                .route_id(&new_route.id)
+                .after(&new_route),
+            &mut transaction,
+        )
+        .await?;
+        updates
+            .commit(transaction, &self.signing_key, &self.update_channel)
+            .await
+            .map_err(|err| {
+                tracing::error!("route create failed {err:?}");
+                Status::internal("route create failed")
+            })?;
 
         let mut resp = RouteResV1 {
             route: Some(new_route.into()),
@@ -309,20 +410,41 @@ impl iot_config::Route for RouteService {
         );
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request, OrgId::RouteId(&route.id))
+        let signer = self
+            .verify_request_signature(&signer, &request, OrgId::RouteId(&route.id))
             .await?;
+        self.quotas.check_rate(&signer, 1)?;
 
-        let updated_route = route::update_route(
-            route,
-            &self.pool,
-            &self.signing_key,
-            self.clone_update_channel(),
-        )
-        .await
-        .map_err(|err| {
+        let mut transaction = self.pool.begin().await.map_err(|err| {
             tracing::error!("route update failed {err:?}");
             Status::internal("update route failed")
         })?;
+        let previous_route = route::get_route(&route.id, &mut transaction)
+            .await
+            .map_err(|_| Status::internal("fetch route failed"))?;
+        let (updated_route, updates) =
+            route::update_route(route, &mut transaction)
+                .await
+                .map_err(|err| {
+                    tracing::error!("route update failed {err:?}");
+                    Status::internal("update route failed")
+                })?;
+        audit::record(
+            audit::Entry::new(&signer, "route_update")
+                .oui(updated_route.oui)
+                .route_id(&updated_route.id)
+                .before(&previous_route)
+                .after(&updated_route),
+            &mut transaction,
+        )
+        .await?;
+        updates
+            .commit(transaction, &self.signing_key, &self.update_channel)
+            .await
+            .map_err(|err| {
+                tracing::error!("route update failed {err:?}");
+                Status::internal("update route failed")
+            })?;
 
         let mut resp = RouteResV1 {
             route: Some(updated_route.into()),
@@ -340,26 +462,38 @@ impl iot_config::Route for RouteService {
         telemetry::count_request("route", "delete");
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request, OrgId::RouteId(&request.id))
+        let signer = self
+            .verify_request_signature(&signer, &request, OrgId::RouteId(&request.id))
             .await?;
+        self.quotas.check_rate(&signer, 1)?;
 
         tracing::debug!(route_id = request.id, "route delete");
 
-        let route = route::get_route(&request.id, &self.pool)
-            .await
-            .map_err(|_| Status::internal("fetch route failed"))?;
-
-        route::delete_route(
-            &request.id,
-            &self.pool,
-            &self.signing_key,
-            self.clone_update_channel(),
-        )
-        .await
-        .map_err(|err| {
+        let mut transaction = self.pool.begin().await.map_err(|err| {
             tracing::error!("route delete failed {err:?}");
             Status::internal("delete route failed")
         })?;
+        let (route, updates) = route::delete_route(&request.id, &mut transaction)
+            .await
+            .map_err(|err| {
+                tracing::error!("route delete failed {err:?}");
+                Status::internal("delete route failed")
+            })?;
+        audit::record(
+            audit::Entry::new(&signer, "route_delete")
+                .oui(route.oui)
+                .route_id(&route.id)
+                .before(&route),
+            &mut transaction,
+        )
+        .await?;
+        updates
+            .commit(transaction, &self.signing_key, &self.update_channel)
+            .await
+            .map_err(|err| {
+                tracing::error!("route delete failed {err:?}");
+                Status::internal("delete route failed")
+            })?;
 
         let mut resp = RouteResV1 {
             route: Some(route.into()),
@@ -374,31 +508,50 @@ impl iot_config::Route for RouteService {
 
     type streamStream = GrpcStreamResult<RouteStreamResV1>;
     async fn stream(&self, request: Request<RouteStreamReqV1>) -> GrpcResult<Self::streamStream> {
+        let resume_seq = resume_change_seq(&request)?;
         let request = request.into_inner();
         telemetry::count_request("route", "stream");
 
         let signer = verify_public_key(&request.signer)?;
         self.verify_stream_request_signature(&signer, &request)?;
 
-        let since = Utc
-            .timestamp_opt(request.since as i64, 0)
-            .single()
-            .ok_or_else(|| Status::invalid_argument("unable to parse since timestamp"))?;
+        let from = match resume_seq {
+            Some(seq) => {
+                let purged_seq = route::purged_change_seq(&self.pool).await.map_err(|err| {
+                    tracing::error!(reason = ?err, "failed to retrieve purged change sequence");
+                    Status::internal("resume failed")
+                })?;
+                if seq < purged_seq {
+                    return Err(Status::out_of_range(format!(
+                        "changes up to sequence {purged_seq} purged; resync with since 0"
+                    )));
+                }
+                StreamFrom::Sequence(seq)
+            }
+            None => StreamFrom::Timestamp(
+                Utc.timestamp_opt(request.since as i64, 0)
+                    .single()
+                    .ok_or_else(|| Status::invalid_argument("unable to parse since timestamp"))?,
+            ),
+        };
 
-        tracing::info!("client subscribed to route stream");
+        // Subscribe before reading the committed sequence so every change
+        // after it is either broadcast or replayed on lag
+        let mut route_updates = self.subscribe_to_routes();
+        let mut synced_seq = route::committed_change_seq(&self.pool)
+            .await
+            .map_err(|err| {
+                tracing::error!(reason = ?err, "failed to retrieve committed change sequence");
+                Status::internal("stream failed")
+            })?;
+
+        tracing::info!(?from, synced_seq, "client subscribed to route stream");
         let pool = self.pool.clone();
         let (tx, rx) = tokio::sync::mpsc::channel(20);
         let signing_key = self.signing_key.clone();
 
-        let mut route_updates = self.subscribe_to_routes();
-
         tokio::spawn(async move {
-            let broadcast = stream_existing_routes(&pool, since, &signing_key, tx.clone())
-                .and_then(|_| stream_existing_euis(&pool, since, &signing_key, tx.clone()))
-                .and_then(|_| stream_existing_devaddrs(&pool, since, &signing_key, tx.clone()))
-                .and_then(|_| stream_existing_skfs(&pool, since, &signing_key, tx.clone()))
-                .await;
-            if let Err(error) = broadcast {
+            if let Err(error) = stream_existing(&pool, from, &signing_key, &tx).await {
                 tracing::error!(
                     ?error,
                     "Error occurred streaming current routing configuration"
@@ -408,15 +561,51 @@ impl iot_config::Route for RouteService {
 
             tracing::info!("existing routes sent; streaming updates as available");
             telemetry::route_stream_subscribe();
-            while let Ok(update) = route_updates.recv().await {
-                if tx.send(Ok(update)).await.is_err() {
-                    telemetry::route_stream_unsubscribe();
-                    return;
+            loop {
+                match route_updates.recv().await {
+                    // Changes at or below the synced sequence were already
+                    // sent from the database
+                    Ok(StreamUpdate { change_seq, .. }) if change_seq <= synced_seq => continue,
+                    Ok(StreamUpdate { change_seq, update }) => {
+                        if tx.send(Ok(update)).await.is_err() {
+                            break;
+                        }
+                        synced_seq = change_seq;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            skipped,
+                            synced_seq,
+                            "route stream lagged; resyncing from stored changes"
+                        );
+                        telemetry::route_stream_lagged();
+                        match resync(&pool, synced_seq, &signing_key, &tx).await {
+                            Ok(seq) => synced_seq = seq,
+                            Err(error) => {
+                                tracing::error!(
+                                    ?error,
+                                    synced_seq,
+                                    "failed to resync route stream"
+                                );
+                                let _ = tx.send(Err(lagged_status(synced_seq))).await;
+                                break;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = tx.send(Err(closed_status(synced_seq))).await;
+                        break;
+                    }
                 }
             }
+            telemetry::route_stream_unsubscribe();
         });
 
-        Ok(Response::new(GrpcStreamResult::new(rx)))
+        let mut response = Response::new(GrpcStreamResult::new(rx));
+        response
+            .metadata_mut()
+            .insert(CHANGE_SEQ_METADATA_KEY, synced_seq.into());
+        Ok(response)
     }
 
     type get_euisStream = GrpcStreamResult<EuiPairV1>;
@@ -478,6 +667,7 @@ impl iot_config::Route for RouteService {
         &self,
         request: GrpcStreamRequest<RouteUpdateEuisReqV1>,
     ) -> GrpcResult<RouteEuisResV1> {
+        let validate_only = validate_only(&request)?;
         let request = request.into_inner();
         telemetry::count_request("route", "update-euis");
 
@@ -502,9 +692,18 @@ impl iot_config::Route for RouteService {
             .ok_or_else(|| Status::invalid_argument("no eui pairs provided"))?
             .await?;
 
-        incoming_stream
+        let mut transaction = self.begin_update().await?;
+        // The org's routes are locked until the eui pair quota is checked,
+        // once every update is applied
+        self.quotas
+            .lock_routes(
+                validator.route_ids.iter().map(String::as_str),
+                &mut transaction,
+            )
+            .await?;
+        let (mut transaction, updates, added_routes) = incoming_stream
             .map_ok(|update| match validator.validate_update(&update) {
-                Ok(()) => Ok(update),
+                Ok(signer) => Ok((update, signer)),
                 Err(reason) => Err(Status::invalid_argument(format!(
                     "invalid update request: {reason:?}"
                 ))),
@@ -514,49 +713,71 @@ impl iot_config::Route for RouteService {
             .and_then(|batch| async move {
                 batch
                     .into_iter()
-                    .collect::<Result<Vec<RouteUpdateEuisReqV1>, Status>>()
+                    .collect::<Result<Vec<(RouteUpdateEuisReqV1, Signer)>, Status>>()
             })
             .and_then(|batch| async move {
                 batch
                     .into_iter()
                     .map(
-                        |update: RouteUpdateEuisReqV1| match (update.action(), update.eui_pair) {
-                            (ActionV1::Add, Some(eui_pair)) => Ok((ActionV1::Add, eui_pair)),
-                            (ActionV1::Remove, Some(eui_pair)) => Ok((ActionV1::Remove, eui_pair)),
+                        |(update, signer)| match (update.action(), update.eui_pair) {
+                            (ActionV1::Add, Some(eui_pair)) => {
+                                Ok((ActionV1::Add, eui_pair.into(), signer))
+                            }
+                            (ActionV1::Remove, Some(eui_pair)) => {
+                                Ok((ActionV1::Remove, eui_pair.into(), signer))
+                            }
                             _ => Err(Status::invalid_argument("invalid eui pair update request")),
                         },
                     )
-                    .collect::<Result<Vec<(ActionV1, EuiPairV1)>, Status>>()
+                    .collect::<Result<Vec<(ActionV1, EuiPair, Signer)>, Status>>()
             })
-            .try_for_each(|batch: Vec<(ActionV1, EuiPairV1)>| async move {
-                let (to_add, to_remove): (Vec<(ActionV1, EuiPairV1)>, Vec<(ActionV1, EuiPairV1)>) =
-                    batch
-                        .into_iter()
-                        .partition(|(action, _update)| action == &ActionV1::Add);
-                telemetry::count_eui_updates(to_add.len(), to_remove.len());
-                tracing::debug!(
-                    adding = to_add.len(),
-                    removing = to_remove.len(),
-                    "updating eui pairs"
-                );
-                let adds_update: Vec<EuiPair> =
-                    to_add.into_iter().map(|(_, add)| add.into()).collect();
-                let removes_update: Vec<EuiPair> = to_remove
-                    .into_iter()
-                    .map(|(_, remove)| remove.into())
-                    .collect();
-                route::update_euis(
-                    &adds_update,
-                    &removes_update,
-                    &self.pool,
-                    self.signing_key.clone(),
-                    self.clone_update_channel(),
-                )
-                .await
-                .map_err(|err| {
-                    tracing::error!("eui pair update failed: {err:?}");
-                    Status::internal(format!("eui pair update failed: {err:?}"))
-                })
+            .try_fold(
+                (transaction, PendingUpdates::default(), BTreeSet::new()),
+                |(mut transaction, mut updates, mut added_routes),
+                 batch: Vec<(ActionV1, EuiPair, Signer)>| async move {
+                    let (adds_update, removes_update) = partition_updates(&batch);
+                    telemetry::count_eui_updates(adds_update.len(), removes_update.len());
+                    tracing::debug!(
+                        adding = adds_update.len(),
+                        removing = removes_update.len(),
+                        "updating eui pairs"
+                    );
+                    if !validate_only {
+                        check_batch_rate(&self.quotas, &batch)?;
+                    }
+                    added_routes.extend(adds_update.iter().map(|pair| pair.route_id.clone()));
+                    let batch_updates =
+                        route::update_euis(&adds_update, &removes_update, &mut transaction)
+                            .await
+                            .map_err(|err| {
+                                tracing::error!("eui pair update failed: {err:?}");
+                                Status::internal(format!("eui pair update failed: {err:?}"))
+                            })?;
+                    updates.extend(batch_updates);
+                    record_updates(
+                        "route_update_euis",
+                        batch,
+                        |pair| pair.route_id.as_str(),
+                        &mut transaction,
+                    )
+                    .await?;
+                    Ok((transaction, updates, added_routes))
+                },
+            )
+            .await?;
+        self.quotas
+            .check_stored_euis(
+                added_routes
+                    .iter()
+                    .map(|route_id| (route_id.as_str(), 0))
+                    .collect(),
+                &mut transaction,
+            )
+            .await?;
+        let validated = self
+            .finish_update(transaction, updates, validate_only, |data| match data {
+                route_stream_res_v1::Data::EuiPair(entry) => Some(EuiPair::from(entry)),
+                _ => None,
             })
             .await?;
 
@@ -567,7 +788,7 @@ impl iot_config::Route for RouteService {
         };
         resp.signature = self.sign_response(&resp.encode_to_vec())?;
 
-        Ok(Response::new(resp))
+        update_response(resp, validated)
     }
 
     type get_devaddr_rangesStream = GrpcStreamResult<DevaddrRangeV1>;
@@ -630,6 +851,7 @@ impl iot_config::Route for RouteService {
         &self,
         request: GrpcStreamRequest<RouteUpdateDevaddrRangesReqV1>,
     ) -> GrpcResult<RouteDevaddrRangesResV1> {
+        let validate_only = validate_only(&request)?;
         let request = request.into_inner();
         telemetry::count_request("route", "update-devaddr-ranges");
 
@@ -654,67 +876,82 @@ impl iot_config::Route for RouteService {
             .ok_or_else(|| Status::invalid_argument("no devaddr range provided"))?
             .await?;
 
-        incoming_stream
-            .map_ok(|update| match validator.validate_update(&update) {
-                Ok(()) => Ok(update),
-                Err(reason) => Err(Status::invalid_argument(format!(
-                    "invalid update request: {reason:?}"
-                ))),
-            })
-            .try_chunks(UPDATE_BATCH_LIMIT)
-            .map_err(|err| {
-                Status::internal(format!("devaddr range update failed to batch: {err:?}"))
-            })
-            .and_then(|batch| async move {
-                batch
-                    .into_iter()
-                    .collect::<Result<Vec<RouteUpdateDevaddrRangesReqV1>, Status>>()
-            })
-            .and_then(|batch| async move {
-                batch
-                    .into_iter()
-                    .map(|update: RouteUpdateDevaddrRangesReqV1| {
-                        match (update.action(), update.devaddr_range) {
-                            (ActionV1::Add, Some(range)) => Ok((ActionV1::Add, range)),
-                            (ActionV1::Remove, Some(range)) => Ok((ActionV1::Remove, range)),
-                            _ => Err(Status::invalid_argument(
-                                "invalid devaddr range update request",
-                            )),
-                        }
-                    })
-                    .collect::<Result<Vec<(ActionV1, DevaddrRangeV1)>, Status>>()
-            })
-            .try_for_each(|batch: Vec<(ActionV1, DevaddrRangeV1)>| async move {
-                let (to_add, to_remove): (
-                    Vec<(ActionV1, DevaddrRangeV1)>,
-                    Vec<(ActionV1, DevaddrRangeV1)>,
-                ) = batch
-                    .into_iter()
-                    .partition(|(action, _update)| action == &ActionV1::Add);
-                telemetry::count_devaddr_updates(to_add.len(), to_remove.len());
-                tracing::debug!(
-                    adding = to_add.len(),
-                    removing = to_remove.len(),
-                    "updating devaddr ranges"
-                );
-                let adds_update: Vec<DevAddrRange> =
-                    to_add.into_iter().map(|(_, add)| add.into()).collect();
-                let removes_update: Vec<DevAddrRange> = to_remove
-                    .into_iter()
-                    .map(|(_, remove)| remove.into())
-                    .collect();
-                route::update_devaddr_ranges(
-                    &adds_update,
-                    &removes_update,
-                    &self.pool,
-                    self.signing_key.clone(),
-                    self.clone_update_channel(),
-                )
-                .await
+        let transaction = self.begin_update().await?;
+        let (transaction, updates) =
+            incoming_stream
+                .map_ok(|update| match validator.validate_update(&update) {
+                    Ok(signer) => Ok((update, signer)),
+                    Err(reason) => Err(Status::invalid_argument(format!(
+                        "invalid update request: {reason:?}"
+                    ))),
+                })
+                .try_chunks(UPDATE_BATCH_LIMIT)
                 .map_err(|err| {
-                    tracing::error!("devaddr range update failed: {err:?}");
-                    Status::internal("devaddr range update failed")
+                    Status::internal(format!("devaddr range update failed to batch: {err:?}"))
+                })
+                .and_then(|batch| async move {
+                    batch
+                        .into_iter()
+                        .collect::<Result<Vec<(RouteUpdateDevaddrRangesReqV1, Signer)>, Status>>()
                 })
+                .and_then(|batch| async move {
+                    batch
+                        .into_iter()
+                        .map(
+                            |(update, signer)| match (update.action(), update.devaddr_range) {
+                                (ActionV1::Add, Some(range)) => {
+                                    Ok((ActionV1::Add, range.into(), signer))
+                                }
+                                (ActionV1::Remove, Some(range)) => {
+                                    Ok((ActionV1::Remove, range.into(), signer))
+                                }
+                                _ => Err(Status::invalid_argument(
+                                    "invalid devaddr range update request",
+                                )),
+                            },
+                        )
+                        .collect::<Result<Vec<(ActionV1, DevAddrRange, Signer)>, Status>>()
+                })
+                .try_fold(
+                    (transaction, PendingUpdates::default()),
+                    |(mut transaction, mut updates),
+                     batch: Vec<(ActionV1, DevAddrRange, Signer)>| async move {
+                        let (adds_update, removes_update) = partition_updates(&batch);
+                        telemetry::count_devaddr_updates(adds_update.len(), removes_update.len());
+                        tracing::debug!(
+                            adding = adds_update.len(),
+                            removing = removes_update.len(),
+                            "updating devaddr ranges"
+                        );
+                        if !validate_only {
+                            check_batch_rate(&self.quotas, &batch)?;
+                        }
+                        let batch_updates = route::update_devaddr_ranges(
+                            &adds_update,
+                            &removes_update,
+                            &mut transaction,
+                        )
+                        .await
+                        .map_err(|err| {
+                            tracing::error!("devaddr range update failed: {err:?}");
+                            Status::internal("devaddr range update failed")
+                        })?;
+                        updates.extend(batch_updates);
+                        record_updates(
+                            "route_update_devaddr_ranges",
+                            batch,
+                            |range| range.route_id.as_str(),
+                            &mut transaction,
+                        )
+                        .await?;
+                        Ok((transaction, updates))
+                    },
+                )
+                .await?;
+        let validated = self
+            .finish_update(transaction, updates, validate_only, |data| match data {
+                route_stream_res_v1::Data::DevaddrRange(entry) => Some(DevAddrRange::from(entry)),
+                _ => None,
             })
             .await?;
 
@@ -725,7 +962,7 @@ impl iot_config::Route for RouteService {
         };
         resp.signature = self.sign_response(&resp.encode_to_vec())?;
 
-        Ok(Response::new(resp))
+        update_response(resp, validated)
     }
 
     type list_skfsStream = GrpcStreamResult<SkfV1>;
@@ -848,6 +1085,7 @@ impl iot_config::Route for RouteService {
         &self,
         request: Request<RouteSkfUpdateReqV1>,
     ) -> GrpcResult<RouteSkfUpdateResV1> {
+        let validate_only = validate_only(&request)?;
         let request = request.into_inner();
         telemetry::count_request("route", "update-skfs");
 
@@ -858,7 +1096,8 @@ impl iot_config::Route for RouteService {
         };
 
         let signer = verify_public_key(&request.signer)?;
-        self.verify_request_signature(&signer, &request, OrgId::RouteId(&request.route_id))
+        let signer = self
+            .verify_request_signature(&signer, &request, OrgId::RouteId(&request.route_id))
             .await?;
 
         self.validate_skf_devaddrs(&request.route_id, &request.updates)
@@ -887,18 +1126,45 @@ impl iot_config::Route for RouteService {
         );
         let adds_update: Vec<Skf> = to_add.into_iter().map(|(_, add)| add).collect();
         let removes_update: Vec<Skf> = to_remove.into_iter().map(|(_, remove)| remove).collect();
-        route::update_skfs(
-            &adds_update,
-            &removes_update,
-            &self.pool,
-            self.signing_key.clone(),
-            self.clone_update_channel(),
+        if !validate_only {
+            self.quotas
+                .check_rate(&signer, adds_update.len() + removes_update.len())?;
+        }
+        let mut transaction = self.begin_update().await?;
+        // The route is locked until the session key filter quota is checked,
+        // once the updates are applied. Re-added filters are upserted, so
+        // only the stored filters count against the quota.
+        self.quotas
+            .lock_routes([request.route_id.as_str()], &mut transaction)
+            .await?;
+        let updates = route::update_skfs(&adds_update, &removes_update, &mut transaction)
+            .await
+            .map_err(|err| {
+                tracing::error!("session key update failed: {err:?}");
+                Status::internal(format!("session key update failed {err:?}"))
+            })?;
+        if !adds_update.is_empty() {
+            self.quotas
+                .check_stored_skfs(
+                    [(request.route_id.as_str(), 0)].into_iter().collect(),
+                    &mut transaction,
+                )
+                .await?;
+        }
+        audit::record(
+            audit::Entry::new(&signer, "route_update_skfs")
+                .route_id(&request.route_id)
+                .before(&removes_update)
+                .after(&adds_update),
+            &mut transaction,
         )
-        .await
-        .map_err(|err| {
-            tracing::error!("session key update failed: {err:?}");
-            Status::internal(format!("session key update failed {err:?}"))
-        })?;
+        .await?;
+        let validated = self
+            .finish_update(transaction, updates, validate_only, |data| match data {
+                route_stream_res_v1::Data::Skf(entry) => Some(Skf::from(entry)),
+                _ => None,
+            })
+            .await?;
 
         let mut resp = RouteSkfUpdateResV1 {
             timestamp: Utc::now().encode_timestamp(),
@@ -906,14 +1172,14 @@ impl iot_config::Route for RouteService {
             signature: vec![],
         };
         resp.signature = self.sign_response(&resp.encode_to_vec())?;
-        Ok(Response::new(resp))
+        update_response(resp, validated)
     }
 }
 
 struct DevAddrEuiValidator {
     route_ids: Vec<String>,
     constraints: Option<Vec<DevAddrConstraint>>,
-    signing_keys: Vec<PublicKey>,
+    signing_keys: Vec<Signer>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -931,7 +1197,7 @@ enum DevAddrEuiValidationError {
 impl DevAddrEuiValidator {
     async fn new(
         route_id: &str,
-        mut admin_keys: Vec<PublicKey>,
+        admin_keys: Vec<PublicKey>,
         db: impl sqlx::PgExecutor<'_> + Copy,
         check_constraints: bool,
     ) -> Result<Self, OrgStoreError> {
@@ -941,25 +1207,32 @@ impl DevAddrEuiValidator {
             None
         };
 
-        let mut org_keys = org::get_org_pubkeys_by_route(route_id, db).await?;
-        org_keys.append(&mut admin_keys);
+        let signing_keys = org::get_org_pubkeys_by_route(route_id, db)
+            .await?
+            .into_iter()
+            .map(|key| Signer::new(key, SignerType::Org))
+            .chain(
+                admin_keys
+                    .into_iter()
+                    .map(|key| Signer::new(key, KeyType::Administrator)),
+            )
+            .collect();
 
         Ok(Self {
             route_ids: org::get_route_ids_by_route(route_id, db).await?,
             constraints,
-            signing_keys: org_keys,
+            signing_keys,
         })
     }
 
-    fn validate_update<'a, R>(&'a mut self, request: &'a R) -> Result<(), Status>
+    fn validate_update<'a, R>(&'a mut self, request: &'a R) -> Result<Signer, Status>
     where
         R: MsgVerify + ValidateRouteComponent<'a> + std::fmt::Debug,
     {
         validate_owned_route(request, &self.route_ids)
             .and_then(|update| validate_range_bounds(update, self.constraints.as_ref()))
             .and_then(|update| validate_signature(update, &mut self.signing_keys))
-            .map_err(|err| Status::invalid_argument(format!("{err:?}")))?;
-        Ok(())
+            .map_err(|err| Status::invalid_argument(format!("{err:?}")))
     }
 }
 
@@ -1050,15 +1323,15 @@ where
 
 fn validate_signature<'a, R>(
     request: &'a R,
-    signing_keys: &mut [PublicKey],
-) -> Result<&'a R, DevAddrEuiValidationError>
+    signing_keys: &mut [Signer],
+) -> Result<Signer, DevAddrEuiValidationError>
 where
     R: MsgVerify + ValidateRouteComponent<'a> + std::fmt::Debug,
 {
-    for (idx, pubkey) in signing_keys.iter().enumerate() {
-        if request.verify(pubkey).is_ok() {
+    for (idx, signer) in signing_keys.iter().enumerate() {
+        if request.verify(&signer.pubkey).is_ok() {
             signing_keys.swap(idx, 0);
-            return Ok(request);
+            return Ok(signing_keys[0].clone());
         }
     }
     Err(DevAddrEuiValidationError::UnauthorizedSignature(format!(
@@ -1066,17 +1339,181 @@ where
     )))
 }
 
+/// Split a batch of route component updates into the entries to add and the
+/// entries to remove
+fn partition_updates<T: Clone>(batch: &[(ActionV1, T, Signer)]) -> (Vec<T>, Vec<T>) {
+    let mut adds = vec![];
+    let mut removes = vec![];
+    for (action, update, _signer) in batch {
+        match action {
+            ActionV1::Add => adds.push(update.clone()),
+            ActionV1::Remove => removes.push(update.clone()),
+        }
+    }
+    (adds, removes)
+}
+
+/// Take each signer's updates in a batch from its mutation rate quota
+fn check_batch_rate<T>(quotas: &Quotas, batch: &[(ActionV1, T, Signer)]) -> Result<(), QuotaError> {
+    let mut mutations: Vec<(&Signer, usize)> = vec![];
+    for (_action, _update, signer) in batch {
+        match mutations.iter_mut().find(|(s, _)| *s == signer) {
+            Some((_, count)) => *count += 1,
+            None => mutations.push((signer, 1)),
+        }
+    }
+    mutations
+        .into_iter()
+        .try_for_each(|(signer, count)| quotas.check_rate(signer, count))
+}
+
+/// Record a batch of route component updates in the audit log, in the
+/// transaction applying them, one entry per signer and route with the removed
+/// entries before and the added entries after
+async fn record_updates<T: Serialize>(
+    rpc: &'static str,
+    batch: Vec<(ActionV1, T, Signer)>,
+    route_id: fn(&T) -> &str,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), AuditError> {
+    let mut entries: Vec<(Signer, String, Vec<T>, Vec<T>)> = vec![];
+    for (action, update, signer) in batch {
+        let update_route_id = route_id(&update);
+        let idx = match entries
+            .iter()
+            .position(|(s, r, _, _)| s == &signer && r == update_route_id)
+        {
+            Some(idx) => idx,
+            None => {
+                entries.push((signer, update_route_id.to_string(), vec![], vec![]));
+                entries.len() - 1
+            }
+        };
+        match action {
+            ActionV1::Add => entries[idx].3.push(update),
+            ActionV1::Remove => entries[idx].2.push(update),
+        }
+    }
+
+    for (signer, route_id, removed, added) in entries {
+        audit::record(
+            audit::Entry::new(&signer, rpc)
+                .route_id(&route_id)
+                .before(&removed)
+                .after(&added),
+            &mut *transaction,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Parse the change sequence a route stream subscriber resumes after, if any
+fn resume_change_seq<T>(request: &Request<T>) -> Result<Option<i64>, Status> {
+    request
+        .metadata()
+        .get(CHANGE_SEQ_METADATA_KEY)
+        .map(|seq| {
+            seq.to_str()
+                .ok()
+                .and_then(|seq| seq.parse::<i64>().ok())
+                .ok_or_else(|| Status::invalid_argument("unable to parse resume change sequence"))
+        })
+        .transpose()
+}
+
+fn validate_only<T>(request: &Request<T>) -> Result<bool, Status> {
+    request
+        .metadata()
+        .get(VALIDATE_ONLY_METADATA_KEY)
+        .map(|flag| {
+            flag.to_str()
+                .ok()
+                .and_then(|flag| flag.parse::<bool>().ok())
+                .ok_or_else(|| Status::invalid_argument("unable to parse validate-only flag"))
+        })
+        .transpose()
+        .map(|flag| flag.unwrap_or(false))
+}
+
+fn update_response<T, D: Serialize>(resp: T, validated: Option<ValidatedDiff<D>>) -> GrpcResult<T> {
+    let mut response = Response::new(resp);
+    if let Some(diff) = validated {
+        let diff = serde_json::to_vec(&diff).map_err(|err| {
+            tracing::error!("validated diff encoding failed: {err:?}");
+            Status::internal("validated diff encoding failed")
+        })?;
+        let metadata = response.metadata_mut();
+        metadata.insert(
+            VALIDATE_ONLY_METADATA_KEY,
+            MetadataValue::from_static("true"),
+        );
+        metadata.insert_bin(
+            VALIDATE_ONLY_DIFF_METADATA_KEY,
+            MetadataValue::from_bytes(&diff),
+        );
+    }
+    Ok(response)
+}
+
+/// Signals a lagging subscriber that could not be resynced to resubscribe,
+/// resuming after the last change sequence it is known to have received
+fn lagged_status(synced_seq: i64) -> Status {
+    let mut status = Status::data_loss("route stream lagged; resubscribe to resync");
+    status
+        .metadata_mut()
+        .insert(CHANGE_SEQ_METADATA_KEY, synced_seq.into());
+    status
+}
+
+/// Ends the stream of a subscriber cleanly on shutdown, with the change
+/// sequence to resume after in the trailers
+fn closed_status(synced_seq: i64) -> Status {
+    let mut status = Status::ok("route stream closed; resubscribe to resume");
+    status
+        .metadata_mut()
+        .insert(CHANGE_SEQ_METADATA_KEY, synced_seq.into());
+    status
+}
+
+/// Replay the changes after `synced_seq` that a lagging subscriber may have
+/// missed, returning the change sequence the subscriber is then synced to
+async fn resync(
+    pool: &Pool<Postgres>,
+    synced_seq: i64,
+    signing_key: &Keypair,
+    tx: &mpsc::Sender<Result<RouteStreamResV1, Status>>,
+) -> Result<i64> {
+    let committed_seq = route::committed_change_seq(pool).await?;
+    stream_existing(pool, StreamFrom::Sequence(synced_seq), signing_key, tx).await?;
+    Ok(committed_seq)
+}
+
+async fn stream_existing(
+    pool: &Pool<Postgres>,
+    from: StreamFrom,
+    signing_key: &Keypair,
+    tx: &mpsc::Sender<Result<RouteStreamResV1, Status>>,
+) -> Result<()> {
+    stream_existing_routes(pool, from, signing_key, tx.clone())
+        .and_then(|_| stream_existing_euis(pool, from, signing_key, tx.clone()))
+        .and_then(|_| stream_existing_devaddrs(pool, from, signing_key, tx.clone()))
+        .and_then(|_| stream_existing_skfs(pool, from, signing_key, tx.clone()))
+        .await
+}
+
 async fn stream_existing_routes(
     pool: &Pool<Postgres>,
-    since: DateTime<Utc>,
+    from: StreamFrom,
     signing_key: &Keypair,
     tx: mpsc::Sender<Result<RouteStreamResV1, Status>>,
 ) -> Result<()> {
     let timestamp = Utc::now().encode_timestamp();
     let signer: Vec<u8> = signing_key.public_key().into();
     let tx = &tx;
-    route::route_stream(pool, since)
-        .then(move |(route, deleted)| {
+    route::route_stream(pool, from)
+        .map_err(|err| anyhow!(err))
+        .and_then(move |(route, deleted)| {
             let mut route_res = RouteStreamResV1 {
                 action: if deleted {
                     ActionV1::Remove
@@ -1089,29 +1526,30 @@ async fn stream_existing_routes(
                 signer: signer.clone(),
                 signature: vec![],
             };
-            if let Ok(signature) = signing_key.sign(&route_res.encode_to_vec()) {
+            let send = if let Ok(signature) = signing_key.sign(&route_res.encode_to_vec()) {
                 route_res.signature = signature;
                 tx.send(Ok(route_res))
             } else {
                 tx.send(Err(Status::internal("failed to sign route")))
-            }
+            };
+            send.map_err(|err| anyhow!(err))
         })
-        .map_err(|err| anyhow!(err))
         .try_fold((), |acc, _| async move { Ok(acc) })
         .await
 }
 
 async fn stream_existing_euis(
     pool: &Pool<Postgres>,
-    since: DateTime<Utc>,
+    from: StreamFrom,
     signing_key: &Keypair,
     tx: mpsc::Sender<Result<RouteStreamResV1, Status>>,
 ) -> Result<()> {
     let timestamp = Utc::now().encode_timestamp();
     let signer: Vec<u8> = signing_key.public_key().into();
     let tx = &tx;
-    route::eui_stream(pool, since)
-        .then(move |(eui_pair, deleted)| {
+    route::eui_stream(pool, from)
+        .map_err(|err| anyhow!(err))
+        .and_then(move |(eui_pair, deleted)| {
             let mut eui_pair_res = RouteStreamResV1 {
                 action: if deleted {
                     ActionV1::Remove
@@ -1124,29 +1562,30 @@ async fn stream_existing_euis(
                 signer: signer.clone(),
                 signature: vec![],
             };
-            if let Ok(signature) = signing_key.sign(&eui_pair_res.encode_to_vec()) {
+            let send = if let Ok(signature) = signing_key.sign(&eui_pair_res.encode_to_vec()) {
                 eui_pair_res.signature = signature;
                 tx.send(Ok(eui_pair_res))
             } else {
                 tx.send(Err(Status::internal("failed to sign eui pair")))
-            }
+            };
+            send.map_err(|err| anyhow!(err))
         })
-        .map_err(|err| anyhow!(err))
         .try_fold((), |acc, _| async move { Ok(acc) })
         .await
 }
 
 async fn stream_existing_devaddrs(
     pool: &Pool<Postgres>,
-    since: DateTime<Utc>,
+    from: StreamFrom,
     signing_key: &Keypair,
     tx: mpsc::Sender<Result<RouteStreamResV1, Status>>,
 ) -> Result<()> {
     let timestamp = Utc::now().encode_timestamp();
     let signer: Vec<u8> = signing_key.public_key().into();
     let tx = &tx;
-    route::devaddr_range_stream(pool, since)
-        .then(move |(devaddr_range, deleted)| {
+    route::devaddr_range_stream(pool, from)
+        .map_err(|err| anyhow!(err))
+        .and_then(move |(devaddr_range, deleted)| {
             let mut devaddr_range_res = RouteStreamResV1 {
                 action: if deleted {
                     ActionV1::Remove
@@ -1161,28 +1600,29 @@ async fn stream_existing_devaddrs(
                 signer: signer.clone(),
                 signature: vec![],
             };
-            if let Ok(signature) = signing_key.sign(&devaddr_range_res.encode_to_vec()) {
+            let send = if let Ok(signature) = signing_key.sign(&devaddr_range_res.encode_to_vec()) {
                 devaddr_range_res.signature = signature;
                 tx.send(Ok(devaddr_range_res))
             } else {
                 tx.send(Err(Status::internal("failed to sign devaddr range")))
-            }
+            };
+            send.map_err(|err| anyhow!(err))
         })
-        .map_err(|err| anyhow!(err))
         .try_fold((), |acc, _| async move { Ok(acc) })
         .await
 }
 
 async fn stream_existing_skfs(
     pool: &Pool<Postgres>,
-    since: DateTime<Utc>,
+    from: StreamFrom,
     signing_key: &Keypair,
     tx: mpsc::Sender<Result<RouteStreamResV1, Status>>,
 ) -> Result<()> {
     let timestamp = Utc::now().encode_timestamp();
     let signer: Vec<u8> = signing_key.public_key().into();
-    route::skf_stream(pool, since)
-        .then(|(skf, deleted)| {
+    route::skf_stream(pool, from)
+        .map_err(|err| anyhow!(err))
+        .and_then(|(skf, deleted)| {
             let mut skf_res = RouteStreamResV1 {
                 action: if deleted {
                     ActionV1::Remove
@@ -1195,14 +1635,14 @@ async fn stream_existing_skfs(
                 signer: signer.clone(),
                 signature: vec![],
             };
-            if let Ok(signature) = signing_key.sign(&skf_res.encode_to_vec()) {
+            let send = if let Ok(signature) = signing_key.sign(&skf_res.encode_to_vec()) {
                 skf_res.signature = signature;
                 tx.send(Ok(skf_res))
             } else {
                 tx.send(Err(Status::internal("failed to sign session key filter")))
-            }
+            };
+            send.map_err(|err| anyhow!(err))
         })
-        .map_err(|err| anyhow!(err))
         .try_fold((), |acc, _| async move { Ok(acc) })
         .await
 }
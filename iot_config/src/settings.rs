@@ -2,6 +2,7 @@ use chrono::Duration;
 use config::{Config, Environment, File};
 use serde::Deserialize;
 use std::{
+    fmt,
     net::{AddrParseError, SocketAddr},
     path::Path,
     str::FromStr,
@@ -27,6 +28,94 @@ pub struct Settings {
     /// the database for Solana on-chain data
     pub metadata: db_store::Settings,
     pub metrics: poc_metrics::Settings,
+    /// Http api serving the audit log and route updates. Disabled if not
+    /// present
+    pub api: Option<ApiSettings>,
+    /// Limits on routes, route entries and route mutations of orgs
+    #[serde(default)]
+    pub quotas: QuotaSettings,
+    /// Http endpoints notified of org and route changes
+    #[serde(default)]
+    pub webhooks: Vec<WebhookSettings>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiSettings {
+    /// Listen address for http requests. Default "0.0.0.0:8081"
+    #[serde(default = "default_api_listen")]
+    pub listen: String,
+    /// B58 encoded public keys of other config services whose exported
+    /// snapshots may be imported, eg: to restore a snapshot of another
+    /// deployment. Snapshots signed by this service or an administrator key
+    /// are always accepted. Default none
+    #[serde(default)]
+    pub snapshot_signers: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuotaSettings {
+    /// Max routes of an org. Default 100
+    #[serde(default = "default_max_routes_per_org")]
+    pub max_routes_per_org: u64,
+    /// Max eui pairs of a route. Default 500,000
+    #[serde(default = "default_max_entries_per_route")]
+    pub max_euis_per_route: u64,
+    /// Max session key filters of a route. Default 500,000
+    #[serde(default = "default_max_entries_per_route")]
+    pub max_skfs_per_route: u64,
+    /// Route, eui pair, devaddr range and session key filter changes a
+    /// non-administrator signer can make per minute. Default 60,000
+    #[serde(default = "default_mutations_per_minute")]
+    pub mutations_per_minute: u64,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct WebhookSettings {
+    /// Url events are posted to
+    pub url: String,
+    /// Shared secret the events are signed with, by hmac-sha256
+    pub secret: String,
+    /// Only notify of the changes of these orgs. Default all orgs
+    #[serde(default)]
+    pub ouis: Vec<u64>,
+    /// Times delivery of an event is attempted before it's dropped. Default 5
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+}
+
+impl fmt::Debug for WebhookSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookSettings")
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .field("ouis", &self.ouis)
+            .field("max_attempts", &self.max_attempts)
+            .finish()
+    }
+}
+
+impl Default for QuotaSettings {
+    fn default() -> Self {
+        Self {
+            max_routes_per_org: default_max_routes_per_org(),
+            max_euis_per_route: default_max_entries_per_route(),
+            max_skfs_per_route: default_max_entries_per_route(),
+            mutations_per_minute: default_mutations_per_minute(),
+        }
+    }
+}
+
+impl ApiSettings {
+    pub fn listen_addr(&self) -> Result<SocketAddr, AddrParseError> {
+        SocketAddr::from_str(&self.listen)
+    }
+
+    pub fn snapshot_signers(&self) -> Result<Vec<helium_crypto::PublicKey>, helium_crypto::Error> {
+        self.snapshot_signers
+            .iter()
+            .map(|key| helium_crypto::PublicKey::from_str(key))
+            .collect()
+    }
 }
 
 pub fn default_log() -> String {
@@ -37,6 +126,26 @@ pub fn default_listen_addr() -> String {
     "0.0.0.0:8080".to_string()
 }
 
+pub fn default_api_listen() -> String {
+    "0.0.0.0:8081".to_string()
+}
+
+pub fn default_max_routes_per_org() -> u64 {
+    100
+}
+
+pub fn default_max_entries_per_route() -> u64 {
+    500_000
+}
+
+pub fn default_mutations_per_minute() -> u64 {
+    60_000
+}
+
+pub fn default_webhook_max_attempts() -> u32 {
+    5
+}
+
 pub fn default_deleted_entry_retention() -> u64 {
     // 48 hours
     48 * 60 * 60
@@ -0,0 +1,687 @@
+use crate::{
+    audit::{self, AuditError, Signer},
+    helium_netids::{is_helium_netid, HeliumNetId},
+    lora_field::{
+        DevAddrConstraint, DevAddrField, DevAddrRange, EuiField, EuiPair, NetIdField, Skf,
+    },
+    org,
+    route::{self, proto, PendingUpdates, Route, RouteStorageError, StreamFrom, StreamUpdate},
+};
+use base64::Engine;
+use chrono::Utc;
+use futures::stream::{Stream, TryStreamExt};
+use helium_crypto::{Keypair, PublicKeyBinary};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{types::Uuid, PgConnection, Pool, Postgres, Transaction};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+use tokio::sync::broadcast::Sender;
+
+/// Version of the snapshot format written by [`export`]
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// Attempts to export a consistent snapshot while the configuration changes
+const EXPORT_ATTEMPTS: usize = 3;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("audit log error: {0}")]
+    Audit(#[from] AuditError),
+    #[error("route storage error: {0}")]
+    Storage(#[from] RouteStorageError),
+    #[error("routing configuration changed during export")]
+    ExportConflict,
+    #[error("routing configuration changed since change seq {0}, retry the import")]
+    ImportConflict(i64),
+    #[error("unsupported snapshot version {0}")]
+    Version(u32),
+    #[error("invalid snapshot: {0}")]
+    Invalid(String),
+    #[error("update failed: {0}")]
+    Update(#[from] anyhow::Error),
+}
+
+/// The routing configuration of an org
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OrgSnapshot {
+    pub oui: u64,
+    pub owner: PublicKeyBinary,
+    pub payer: PublicKeyBinary,
+    pub locked: bool,
+    pub net_id: NetIdField,
+    pub delegate_keys: Vec<PublicKeyBinary>,
+    pub constraints: Vec<DevAddrConstraint>,
+}
+
+/// All orgs, routes, eui pairs, devaddr ranges and session key filters
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Unix timestamp (seconds) of the export
+    pub timestamp: u64,
+    /// Change sequence of the routing configuration at export
+    pub change_seq: i64,
+    pub orgs: Vec<OrgSnapshot>,
+    pub routes: Vec<Route>,
+    pub euis: Vec<EuiPair>,
+    pub devaddr_ranges: Vec<DevAddrRange>,
+    pub skfs: Vec<Skf>,
+}
+
+/// Changes to one kind of record
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Changes<T> {
+    pub added: Vec<T>,
+    pub updated: Vec<T>,
+    pub removed: Vec<T>,
+}
+
+/// The changes an import made, or would make if not committed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SnapshotDiff {
+    pub committed: bool,
+    /// Change sequence of the routing configuration the diff was computed
+    /// against
+    pub change_seq: i64,
+    /// Digest of the orgs the diff was computed against. Org changes are not
+    /// sequenced, so the orgs are compared separately.
+    pub orgs_digest: String,
+    /// Orgs are never removed; orgs not in the snapshot are left as they are,
+    /// without routes
+    pub orgs: Changes<OrgSnapshot>,
+    pub unlisted_orgs: Vec<u64>,
+    pub routes: Changes<Route>,
+    pub euis: Changes<EuiPair>,
+    pub devaddr_ranges: Changes<DevAddrRange>,
+    pub skfs: Changes<Skf>,
+}
+
+/// Export the routing configuration from the route, eui, devaddr range and
+/// session key filter streams, retrying if it changes while exporting. Org
+/// changes don't advance the change sequence, so the orgs are read again to
+/// check they are unchanged too.
+pub async fn export(db: &Pool<Postgres>) -> Result<Snapshot, SnapshotError> {
+    for _ in 0..EXPORT_ATTEMPTS {
+        let change_seq = route::committed_change_seq(db).await?;
+        let snapshot = read_snapshot(change_seq, db).await?;
+        if route::committed_change_seq(db).await? == change_seq
+            && read_orgs(&mut *db.acquire().await?).await? == snapshot.orgs
+        {
+            return Ok(snapshot);
+        }
+    }
+    Err(SnapshotError::ExportConflict)
+}
+
+async fn read_snapshot(change_seq: i64, db: &Pool<Postgres>) -> Result<Snapshot, SnapshotError> {
+    let orgs = read_orgs(&mut *db.acquire().await?).await?;
+    let from = StreamFrom::Sequence(0);
+    Ok(Snapshot {
+        version: SNAPSHOT_VERSION,
+        timestamp: Utc::now().timestamp() as u64,
+        change_seq,
+        orgs,
+        routes: current(route::route_stream(db, from)).await?,
+        euis: current(route::eui_stream(db, from)).await?,
+        devaddr_ranges: current(route::devaddr_range_stream(db, from)).await?,
+        skfs: current(route::skf_stream(db, from)).await?,
+    })
+}
+
+/// The orgs, ordered by oui with their delegate keys and constraints sorted,
+/// so reads of unchanged orgs compare equal
+async fn read_orgs(db: &mut PgConnection) -> Result<Vec<OrgSnapshot>, SnapshotError> {
+    let mut orgs = vec![];
+    for org in org::list(&mut *db).await? {
+        let mut delegate_keys = org.delegate_keys.unwrap_or_default();
+        delegate_keys.sort_by_key(|key| key.to_string());
+        let mut constraints = org.constraints.unwrap_or_default();
+        constraints.sort_by_key(|constraint| constraint.start_addr);
+        orgs.push(OrgSnapshot {
+            oui: org.oui,
+            net_id: org::get_org_netid(org.oui, &mut *db).await?,
+            owner: org.owner,
+            payer: org.payer,
+            locked: org.locked,
+            delegate_keys,
+            constraints,
+        });
+    }
+    orgs.sort_by_key(|org| org.oui);
+    Ok(orgs)
+}
+
+fn orgs_digest(orgs: &[OrgSnapshot]) -> Result<String, SnapshotError> {
+    let encoded = serde_json::to_vec(orgs)
+        .map_err(|err| SnapshotError::Invalid(format!("unable to encode orgs: {err}")))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(Sha256::digest(encoded)))
+}
+
+/// The entries of a stream that are not deleted, failing on the first entry
+/// that can't be read
+async fn current<T>(
+    stream: impl Stream<Item = Result<(T, bool), RouteStorageError>>,
+) -> Result<Vec<T>, SnapshotError> {
+    Ok(stream
+        .try_filter_map(|(entry, deleted)| async move { Ok((!deleted).then_some(entry)) })
+        .try_collect()
+        .await?)
+}
+
+/// Check a snapshot is internally consistent: org constraints are valid for
+/// their net id and don't overlap, and every route, eui pair, devaddr range
+/// and session key filter belongs to a route or org of the snapshot, within
+/// its constraints or devaddr ranges.
+pub fn validate(snapshot: &Snapshot) -> Result<(), SnapshotError> {
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::Version(snapshot.version));
+    }
+
+    unique(&snapshot.orgs, |org| org.oui, "org")?;
+    unique(
+        snapshot.orgs.iter().flat_map(|org| &org.delegate_keys),
+        |key| key.to_string(),
+        "delegate key",
+    )?;
+    let mut constraints = vec![];
+    for org in &snapshot.orgs {
+        validate_constraints(org)?;
+        constraints.extend(
+            org.constraints
+                .iter()
+                .map(|constraint| (constraint, org.oui)),
+        );
+    }
+    constraints.sort_by_key(|(constraint, _)| constraint.start_addr);
+    for pair in constraints.windows(2) {
+        let ((prev, prev_oui), (next, next_oui)) = (pair[0], pair[1]);
+        if next.start_addr <= prev.end_addr {
+            return invalid(format!(
+                "constraint {prev:?} of org {prev_oui} overlaps {next:?} of org {next_oui}"
+            ));
+        }
+    }
+
+    let orgs: HashMap<u64, &OrgSnapshot> = snapshot.orgs.iter().map(|org| (org.oui, org)).collect();
+    unique(&snapshot.routes, |route| route.id.clone(), "route")?;
+    let mut routes = HashMap::new();
+    for route in &snapshot.routes {
+        Uuid::try_parse(&route.id)
+            .map_err(|_| SnapshotError::Invalid(format!("invalid route id {}", route.id)))?;
+        if route.server.protocol.is_none() {
+            return invalid(format!("route {} has no protocol", route.id));
+        }
+        let org = orgs
+            .get(&route.oui)
+            .ok_or_else(|| SnapshotError::Invalid(format!("no org for route {}", route.id)))?;
+        routes.insert(route.id.as_str(), *org);
+    }
+
+    unique(&snapshot.euis, eui_key, "eui pair")?;
+    for eui in &snapshot.euis {
+        if !routes.contains_key(eui.route_id.as_str()) {
+            return invalid(format!("no route for eui pair {eui:?}"));
+        }
+    }
+
+    unique(&snapshot.devaddr_ranges, devaddr_range_key, "devaddr range")?;
+    let mut route_ranges: HashMap<&str, Vec<&DevAddrRange>> = HashMap::new();
+    for range in &snapshot.devaddr_ranges {
+        let org = routes
+            .get(range.route_id.as_str())
+            .ok_or_else(|| SnapshotError::Invalid(format!("no route for {range:?}")))?;
+        if range.end_addr < range.start_addr
+            || !org
+                .constraints
+                .iter()
+                .any(|constraint| constraint.contains_range(range))
+        {
+            return invalid(format!(
+                "devaddr range outside of org {} constraints {range:?}",
+                org.oui
+            ));
+        }
+        route_ranges
+            .entry(range.route_id.as_str())
+            .or_default()
+            .push(range);
+    }
+
+    unique(&snapshot.skfs, skf_key, "session key filter")?;
+    for skf in &snapshot.skfs {
+        let in_range = route_ranges
+            .get(skf.route_id.as_str())
+            .map_or(false, |ranges| {
+                ranges.iter().any(|range| range.contains_addr(skf.devaddr))
+            });
+        if !in_range {
+            return invalid(format!(
+                "session key filter outside of route devaddr ranges {skf:?}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_constraints(org: &OrgSnapshot) -> Result<(), SnapshotError> {
+    if org.constraints.is_empty() {
+        return invalid(format!("org {} has no devaddr constraints", org.oui));
+    }
+    if !is_helium_netid(&org.net_id) {
+        if org.constraints.len() != 1 {
+            return invalid(format!(
+                "roaming org {} must have exactly one devaddr constraint",
+                org.oui
+            ));
+        }
+        let constraint = &org.constraints[0];
+        if constraint.end_addr < constraint.start_addr {
+            return invalid(format!(
+                "invalid constraint {constraint:?} of org {}",
+                org.oui
+            ));
+        }
+        return Ok(());
+    }
+
+    let addr_range = HeliumNetId::try_from(org.net_id)
+        .map_err(|err| SnapshotError::Invalid(err.to_string()))?
+        .addr_range();
+    for constraint in &org.constraints {
+        let valid = DevAddrConstraint::new(constraint.start_addr, constraint.end_addr).is_ok()
+            && addr_range.contains(&u32::from(constraint.start_addr))
+            && addr_range.contains(&u32::from(constraint.end_addr));
+        if !valid {
+            return invalid(format!(
+                "constraint {constraint:?} of org {} invalid for net id {}",
+                org.oui, org.net_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn unique<'a, T, K>(
+    entries: impl IntoIterator<Item = &'a T>,
+    key: impl Fn(&T) -> K,
+    name: &str,
+) -> Result<(), SnapshotError>
+where
+    T: std::fmt::Debug + 'a,
+    K: Eq + Hash,
+{
+    let mut keys = HashSet::new();
+    for entry in entries {
+        if !keys.insert(key(entry)) {
+            return invalid(format!("duplicate {name} {entry:?}"));
+        }
+    }
+    Ok(())
+}
+
+fn invalid<T>(reason: String) -> Result<T, SnapshotError> {
+    Err(SnapshotError::Invalid(reason))
+}
+
+fn eui_key(eui: &EuiPair) -> (String, EuiField, EuiField) {
+    (eui.route_id.clone(), eui.app_eui, eui.dev_eui)
+}
+
+fn devaddr_range_key(range: &DevAddrRange) -> (String, DevAddrField, DevAddrField) {
+    (range.route_id.clone(), range.start_addr, range.end_addr)
+}
+
+fn skf_key(skf: &Skf) -> (String, DevAddrField, String) {
+    (skf.route_id.clone(), skf.devaddr, skf.session_key.clone())
+}
+
+/// Validate a snapshot and compute the changes restoring it would make to
+/// the current routing configuration
+pub async fn diff(snapshot: &Snapshot, db: &Pool<Postgres>) -> Result<SnapshotDiff, SnapshotError> {
+    validate(snapshot)?;
+    let current = export(db).await?;
+
+    let listed: HashSet<u64> = snapshot.orgs.iter().map(|org| org.oui).collect();
+    let mut orgs = changes(&current.orgs, &snapshot.orgs, |org| org.oui);
+    orgs.removed.clear();
+
+    Ok(SnapshotDiff {
+        committed: false,
+        change_seq: current.change_seq,
+        orgs_digest: orgs_digest(&current.orgs)?,
+        orgs,
+        unlisted_orgs: current
+            .orgs
+            .iter()
+            .map(|org| org.oui)
+            .filter(|oui| !listed.contains(oui))
+            .collect(),
+        routes: changes(&current.routes, &snapshot.routes, |route| route.id.clone()),
+        euis: changes(&current.euis, &snapshot.euis, eui_key),
+        devaddr_ranges: changes(
+            &current.devaddr_ranges,
+            &snapshot.devaddr_ranges,
+            devaddr_range_key,
+        ),
+        skfs: changes(&current.skfs, &snapshot.skfs, skf_key),
+    })
+}
+
+fn changes<T, K>(current: &[T], target: &[T], key: impl Fn(&T) -> K) -> Changes<T>
+where
+    T: Clone + PartialEq,
+    K: Eq + Hash,
+{
+    let current_by_key: HashMap<K, &T> = current.iter().map(|entry| (key(entry), entry)).collect();
+    let target_keys: HashSet<K> = target.iter().map(&key).collect();
+
+    let mut changes = Changes {
+        added: vec![],
+        updated: vec![],
+        removed: vec![],
+    };
+    for entry in target {
+        match current_by_key.get(&key(entry)) {
+            None => changes.added.push(entry.clone()),
+            Some(existing) if *existing != entry => changes.updated.push(entry.clone()),
+            Some(_) => (),
+        }
+    }
+    changes.removed = current
+        .iter()
+        .filter(|entry| !target_keys.contains(&key(entry)))
+        .cloned()
+        .collect();
+    changes
+}
+
+/// Apply the changes of a diff in a single transaction. The transaction holds
+/// the change sequence lock and locks the org tables against writes, so the
+/// diff is only applied if the routing configuration and orgs are unchanged
+/// since it was computed, and is recorded in the audit log in the same
+/// transaction. Route stream subscribers are sent the changes once committed.
+pub async fn apply(
+    diff: &mut SnapshotDiff,
+    signer: &Signer,
+    db: &Pool<Postgres>,
+    signing_key: &Keypair,
+    update_tx: Sender<StreamUpdate>,
+) -> Result<(), SnapshotError> {
+    let mut transaction = db.begin().await?;
+    if route::lock_change_seq(&mut transaction).await? != diff.change_seq {
+        return Err(SnapshotError::ImportConflict(diff.change_seq));
+    }
+    sqlx::query(
+        r#"
+        lock table organizations, organization_delegate_keys, organization_devaddr_constraints
+            in share row exclusive mode
+        "#,
+    )
+    .execute(&mut transaction)
+    .await?;
+    if orgs_digest(&read_orgs(&mut transaction).await?)? != diff.orgs_digest {
+        return Err(SnapshotError::ImportConflict(diff.change_seq));
+    }
+
+    let changed_orgs: Vec<&OrgSnapshot> =
+        diff.orgs.added.iter().chain(&diff.orgs.updated).collect();
+    if !changed_orgs.is_empty() {
+        upsert_orgs(&changed_orgs, &mut transaction).await?;
+    }
+
+    let removed_routes = diff.routes.removed.iter().map(|route| (route, true));
+    for (route, deleted) in diff
+        .routes
+        .added
+        .iter()
+        .chain(&diff.routes.updated)
+        .map(|route| (route, false))
+        .chain(removed_routes)
+    {
+        upsert_route(route, deleted, &mut transaction).await?;
+    }
+
+    for (eui, deleted) in flag_removed(&diff.euis) {
+        sqlx::query(
+            r#"
+            insert into route_eui_pairs (route_id, app_eui, dev_eui, deleted)
+            values ($1, $2, $3, $4)
+            on conflict (route_id, app_eui, dev_eui) do update set deleted = excluded.deleted
+            "#,
+        )
+        .bind(route_uuid(&eui.route_id)?)
+        .bind(i64::from(eui.app_eui))
+        .bind(i64::from(eui.dev_eui))
+        .bind(deleted)
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    for (range, deleted) in flag_removed(&diff.devaddr_ranges) {
+        sqlx::query(
+            r#"
+            insert into route_devaddr_ranges (route_id, start_addr, end_addr, deleted)
+            values ($1, $2, $3, $4)
+            on conflict (route_id, start_addr, end_addr) do update set deleted = excluded.deleted
+            "#,
+        )
+        .bind(route_uuid(&range.route_id)?)
+        .bind(i32::from(range.start_addr))
+        .bind(i32::from(range.end_addr))
+        .bind(deleted)
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    for (skf, deleted) in flag_removed(&diff.skfs) {
+        sqlx::query(
+            r#"
+            insert into route_session_key_filters (route_id, devaddr, session_key, max_copies, deleted)
+            values ($1, $2, $3, $4, $5)
+            on conflict (route_id, devaddr, session_key)
+                do update set max_copies = excluded.max_copies, deleted = excluded.deleted
+            "#,
+        )
+        .bind(route_uuid(&skf.route_id)?)
+        .bind(i32::from(skf.devaddr))
+        .bind(&skf.session_key)
+        .bind(skf.max_copies as i32)
+        .bind(deleted)
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    // The diff is recorded as committed, which it is only if the transaction
+    // recording it commits
+    diff.committed = true;
+    let updates = diff_updates(diff);
+    let committed = async {
+        audit::record(
+            audit::Entry::new(signer, "snapshot_import").after(&*diff),
+            &mut transaction,
+        )
+        .await?;
+        updates.commit(transaction, signing_key, &update_tx).await?;
+        Ok::<_, SnapshotError>(())
+    }
+    .await;
+    if let Err(err) = committed {
+        diff.committed = false;
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Added and updated entries, then removed entries flagged as deleted
+fn flag_removed<T>(changes: &Changes<T>) -> impl Iterator<Item = (&T, bool)> {
+    changes
+        .added
+        .iter()
+        .chain(&changes.updated)
+        .map(|entry| (entry, false))
+        .chain(changes.removed.iter().map(|entry| (entry, true)))
+}
+
+fn route_uuid(route_id: &str) -> Result<Uuid, SnapshotError> {
+    Uuid::try_parse(route_id)
+        .map_err(|_| SnapshotError::Invalid(format!("invalid route id {route_id}")))
+}
+
+async fn upsert_orgs(
+    orgs: &[&OrgSnapshot],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), SnapshotError> {
+    // Delegate keys and constraints may move between orgs, so all of the
+    // changed orgs' are cleared before any are inserted
+    let ouis: Vec<i64> = orgs.iter().map(|org| org.oui as i64).collect();
+    sqlx::query("delete from organization_delegate_keys where oui = any($1)")
+        .bind(&ouis)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query("delete from organization_devaddr_constraints where oui = any($1)")
+        .bind(&ouis)
+        .execute(&mut *transaction)
+        .await?;
+
+    for org in orgs {
+        sqlx::query(
+            r#"
+            insert into organizations (oui, owner_pubkey, payer_pubkey, locked)
+            values ($1, $2, $3, $4)
+            on conflict (oui) do update
+                set owner_pubkey = excluded.owner_pubkey,
+                    payer_pubkey = excluded.payer_pubkey,
+                    locked = excluded.locked
+            "#,
+        )
+        .bind(org.oui as i64)
+        .bind(&org.owner)
+        .bind(&org.payer)
+        .bind(org.locked)
+        .execute(&mut *transaction)
+        .await?;
+
+        for key in &org.delegate_keys {
+            sqlx::query(
+                "insert into organization_delegate_keys (delegate_pubkey, oui) values ($1, $2)",
+            )
+            .bind(key)
+            .bind(org.oui as i64)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        for constraint in &org.constraints {
+            sqlx::query(
+                r#"
+                insert into organization_devaddr_constraints (oui, net_id, start_addr, end_addr)
+                values ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(org.oui as i64)
+            .bind(i32::from(org.net_id))
+            .bind(i32::from(constraint.start_addr))
+            .bind(i32::from(constraint.end_addr))
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
+
+    // The helium devaddrs in use are those of the helium net id constraints
+    let helium_net_ids: Vec<i32> = [
+        HeliumNetId::Type0_0x00003c,
+        HeliumNetId::Type3_0x60002d,
+        HeliumNetId::Type6_0xc00053,
+    ]
+    .iter()
+    .map(|net_id| i32::from(net_id.id()))
+    .collect();
+    sqlx::query("delete from helium_used_devaddrs")
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query(
+        r#"
+        insert into helium_used_devaddrs (devaddr, net_id)
+        select generate_series(start_addr, end_addr), net_id
+        from organization_devaddr_constraints
+        where net_id = any($1)
+        "#,
+    )
+    .bind(&helium_net_ids)
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query(
+        r#"
+        select setval(pg_get_serial_sequence('organizations', 'oui'), max(oui))
+        from organizations
+        "#,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
+async fn upsert_route(
+    route: &Route,
+    deleted: bool,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), SnapshotError> {
+    sqlx::query(
+        r#"
+        insert into routes (id, oui, net_id, max_copies, server_host, server_port, server_protocol_opts, active, ignore_empty_skf, deleted)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        on conflict (id) do update
+            set oui = excluded.oui,
+                net_id = excluded.net_id,
+                max_copies = excluded.max_copies,
+                server_host = excluded.server_host,
+                server_port = excluded.server_port,
+                server_protocol_opts = excluded.server_protocol_opts,
+                active = excluded.active,
+                ignore_empty_skf = excluded.ignore_empty_skf,
+                deleted = excluded.deleted
+        "#,
+    )
+    .bind(route_uuid(&route.id)?)
+    .bind(route.oui as i64)
+    .bind(i32::from(route.net_id))
+    .bind(route.max_copies as i32)
+    .bind(&route.server.host)
+    .bind(route.server.port as i32)
+    .bind(json!(&route.server.protocol))
+    .bind(route.active)
+    .bind(route.ignore_empty_skf)
+    .bind(deleted)
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+fn diff_updates(diff: &SnapshotDiff) -> PendingUpdates {
+    use proto::route_stream_res_v1::Data;
+
+    let routes = flag_removed(&diff.routes)
+        .map(|(route, removed)| (Data::Route(route.clone().into()), removed));
+    let euis = flag_removed(&diff.euis).map(|(eui, removed)| (Data::EuiPair(eui.into()), removed));
+    let devaddr_ranges = flag_removed(&diff.devaddr_ranges)
+        .map(|(range, removed)| (Data::DevaddrRange(range.into()), removed));
+    let skfs = flag_removed(&diff.skfs).map(|(skf, removed)| (Data::Skf(skf.into()), removed));
+
+    let mut updates = PendingUpdates::default();
+    for (data, removed) in routes.chain(euis).chain(devaddr_ranges).chain(skfs) {
+        let action = if removed {
+            proto::ActionV1::Remove
+        } else {
+            proto::ActionV1::Add
+        };
+        updates.push(action, data);
+    }
+    updates
+}
@@ -1,5 +1,9 @@
 const RPC_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "grpc-request");
 const STREAM_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "grpc-stream");
+const STREAM_LAGGED_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "grpc-stream-lagged");
+const QUOTA_EXCEEDED_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "quota-exceeded");
+const WEBHOOK_DELIVERY_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "webhook-delivery");
+const AUDIT_FAILURE_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "audit-log-failure");
 const REGION_HEX_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "region-hexes");
 const REGION_LOOKUP_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "region-lookup");
 const SKF_ADD_COUNT_METRIC: &str = concat!(env!("CARGO_PKG_NAME"), "-", "skfs-added");
@@ -69,3 +73,19 @@ pub fn route_stream_subscribe() {
 pub fn route_stream_unsubscribe() {
     metrics::decrement_gauge!(STREAM_METRIC, 1.0);
 }
+
+pub fn route_stream_lagged() {
+    metrics::increment_counter!(STREAM_LAGGED_METRIC);
+}
+
+pub fn count_quota_exceeded(quota: &'static str) {
+    metrics::increment_counter!(QUOTA_EXCEEDED_METRIC, "quota" => quota);
+}
+
+pub fn count_webhook_delivery(result: &'static str) {
+    metrics::increment_counter!(WEBHOOK_DELIVERY_METRIC, "result" => result);
+}
+
+pub fn count_audit_failure(rpc: &'static str) {
+    metrics::increment_counter!(AUDIT_FAILURE_METRIC, "rpc" => rpc);
+}
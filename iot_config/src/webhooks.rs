@@ -0,0 +1,751 @@
+use crate::{
+    audit::{self, AuditError, AuditRecord},
+    devaddr_slabs::Reclaim,
+    route::{self, Route, RouteStorageError, StreamFrom},
+    settings::WebhookSettings,
+    snapshot::SnapshotDiff,
+    telemetry,
+};
+use base64::Engine;
+use chrono::Utc;
+use futures::{future, TryFutureExt, TryStreamExt};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{Pool, Postgres};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+use task_manager::ManagedTask;
+use tokio::{sync::mpsc, time::Instant};
+
+/// Header holding the unix timestamp (seconds) an event was signed at
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// Header holding the base64 encoded hmac-sha256, keyed by the webhook's
+/// secret, of the timestamp header, a `.` and the request body
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// The audit log is read for new changes this often. Route entry changes of
+/// a route read together are notified as one event
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const POLL_LIMIT: i64 = 1000;
+/// Audit log ids skipped by a read are looked for again for this long, in
+/// case their transaction is yet to commit, before they're taken to be
+/// rolled back
+const SKIPPED_ID_TIMEOUT: Duration = Duration::from_secs(60);
+/// Most ids skipped by one entry that are looked for again
+const MAX_SKIPPED_IDS: i64 = 1000;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+/// Events waiting for delivery to a webhook before new events are dropped
+const DELIVERY_QUEUE: usize = 1024;
+
+/// A change to an org or its routes, as posted to webhooks
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookEvent {
+    pub oui: u64,
+    /// Unix timestamp (seconds) the change was committed at
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    OrgDisabled,
+    OrgEnabled,
+    RouteCreated {
+        route: Route,
+    },
+    RouteUpdated {
+        route: Route,
+    },
+    RouteDeleted {
+        route_id: String,
+    },
+    /// Eui pairs, devaddr ranges and session key filters of a route added
+    /// and removed by the changes read together
+    RouteEntriesChanged {
+        route_id: String,
+        #[serde(flatten)]
+        counts: EntryCounts,
+    },
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EntryCounts {
+    pub euis_added: u64,
+    pub euis_removed: u64,
+    pub devaddr_ranges_added: u64,
+    pub devaddr_ranges_removed: u64,
+    pub skfs_added: u64,
+    pub skfs_removed: u64,
+}
+
+/// Posts the org and route changes recorded in the audit log to the
+/// configured webhooks.
+///
+/// The log is followed from its latest entry at start up; changes committed
+/// while the notifier isn't running, and events not yet delivered on
+/// shutdown, are not notified.
+pub struct WebhookNotifier {
+    pool: Pool<Postgres>,
+    webhooks: Vec<WebhookSettings>,
+}
+
+impl ManagedTask for WebhookNotifier {
+    fn start_task(
+        self: Box<Self>,
+        shutdown: triggered::Listener,
+    ) -> futures::future::LocalBoxFuture<'static, anyhow::Result<()>> {
+        Box::pin(
+            tokio::spawn(self.run(shutdown))
+                .map_err(anyhow::Error::from)
+                .and_then(|result| async move { result }),
+        )
+    }
+}
+
+/// Position in the audit log: the latest entry read and the earlier ids not
+/// yet seen, with when they were first skipped
+struct AuditCursor {
+    last_id: i64,
+    skipped: BTreeMap<i64, Instant>,
+}
+
+/// Ouis of the known routes, for changes recorded without one, and the
+/// delivery queues of the webhooks, with the orgs each is notified of
+struct Notifications {
+    route_ouis: HashMap<String, u64>,
+    deliveries: Vec<(Vec<u64>, mpsc::Sender<Arc<WebhookEvent>>)>,
+}
+
+impl WebhookNotifier {
+    pub fn new(pool: Pool<Postgres>, webhooks: Vec<WebhookSettings>) -> Self {
+        Self { pool, webhooks }
+    }
+
+    async fn run(self, mut shutdown: triggered::Listener) -> anyhow::Result<()> {
+        tracing::info!(webhooks = self.webhooks.len(), "starting webhook notifier");
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()?;
+        let deliveries = self
+            .webhooks
+            .iter()
+            .map(|webhook| {
+                let (tx, rx) = mpsc::channel(DELIVERY_QUEUE);
+                tokio::spawn(deliver(
+                    client.clone(),
+                    webhook.clone(),
+                    rx,
+                    shutdown.clone(),
+                ));
+                (webhook.ouis.clone(), tx)
+            })
+            .collect();
+        let mut cursor = AuditCursor::new(audit::latest_id(&self.pool).await?);
+        let mut notifications = Notifications {
+            route_ouis: known_routes(&self.pool).await?,
+            deliveries,
+        };
+
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => {
+                    tracing::info!("stopping webhook notifier");
+                    return Ok(());
+                }
+                _ = poll.tick() => {
+                    if let Err(err) = notifications.poll(&mut cursor, &self.pool).await {
+                        tracing::error!(?err, "failed reading audit log for webhooks");
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AuditCursor {
+    fn new(last_id: i64) -> Self {
+        Self {
+            last_id,
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    fn skipped_ids(&self) -> Vec<i64> {
+        self.skipped.keys().copied().collect()
+    }
+
+    /// Move past the ids read, remembering the ids skipped and forgetting
+    /// those skipped too long ago
+    fn advance(&mut self, ids: impl IntoIterator<Item = i64>, now: Instant) {
+        for id in ids {
+            if self.skipped.remove(&id).is_some() || id <= self.last_id {
+                continue;
+            }
+            let first_skipped = (self.last_id + 1).max(id - MAX_SKIPPED_IDS);
+            self.skipped
+                .extend((first_skipped..id).map(|skipped| (skipped, now)));
+            self.last_id = id;
+        }
+        self.skipped
+            .retain(|_, skipped_at| now.duration_since(*skipped_at) < SKIPPED_ID_TIMEOUT);
+    }
+}
+
+impl Notifications {
+    /// Notify the changes recorded in the audit log since the last poll
+    async fn poll(
+        &mut self,
+        cursor: &mut AuditCursor,
+        db: &Pool<Postgres>,
+    ) -> Result<(), AuditError> {
+        loop {
+            let records =
+                audit::entries_after(cursor.last_id, &cursor.skipped_ids(), POLL_LIMIT, db).await?;
+            cursor.advance(records.iter().map(|record| record.id), Instant::now());
+            for event in batch_events(&records, &mut self.route_ouis) {
+                self.notify(event);
+            }
+            if (records.len() as i64) < POLL_LIMIT {
+                return Ok(());
+            }
+        }
+    }
+
+    fn notify(&self, event: WebhookEvent) {
+        let event = Arc::new(event);
+        for (ouis, tx) in &self.deliveries {
+            if !ouis.is_empty() && !ouis.contains(&event.oui) {
+                continue;
+            }
+            if tx.try_send(event.clone()).is_err() {
+                tracing::warn!(
+                    oui = event.oui,
+                    "webhook delivery queue full; event dropped"
+                );
+                telemetry::count_webhook_delivery("dropped");
+            }
+        }
+    }
+}
+
+impl EventKind {
+    fn route_id(&self) -> Option<&str> {
+        match self {
+            Self::OrgDisabled | Self::OrgEnabled => None,
+            Self::RouteCreated { route } | Self::RouteUpdated { route } => Some(&route.id),
+            Self::RouteDeleted { route_id } | Self::RouteEntriesChanged { route_id, .. } => {
+                Some(route_id)
+            }
+        }
+    }
+}
+
+impl EntryCounts {
+    fn add(&mut self, other: &EntryCounts) {
+        self.euis_added += other.euis_added;
+        self.euis_removed += other.euis_removed;
+        self.devaddr_ranges_added += other.devaddr_ranges_added;
+        self.devaddr_ranges_removed += other.devaddr_ranges_removed;
+        self.skfs_added += other.skfs_added;
+        self.skfs_removed += other.skfs_removed;
+    }
+
+    /// Counts of a route entry update recorded under `rpc`, with the removed
+    /// entries before and the added entries after
+    fn of_update(rpc: &str, removed: u64, added: u64) -> Option<Self> {
+        let counts = match rpc {
+            "route_update_euis" | "route_sync_euis" => Self {
+                euis_added: added,
+                euis_removed: removed,
+                ..Default::default()
+            },
+            "route_update_devaddr_ranges" | "route_sync_devaddr_ranges" => Self {
+                devaddr_ranges_added: added,
+                devaddr_ranges_removed: removed,
+                ..Default::default()
+            },
+            "route_update_skfs" | "route_sync_skfs" => Self {
+                skfs_added: added,
+                skfs_removed: removed,
+                ..Default::default()
+            },
+            _ => return None,
+        };
+        Some(counts)
+    }
+}
+
+/// Events of audit log records read together, in order. The entry changes of
+/// a route are merged into its last event if that's an entry change too
+fn batch_events(
+    records: &[AuditRecord],
+    route_ouis: &mut HashMap<String, u64>,
+) -> Vec<WebhookEvent> {
+    let mut events: Vec<WebhookEvent> = vec![];
+    for event in records
+        .iter()
+        .flat_map(|record| record_events(record, route_ouis))
+    {
+        if let EventKind::RouteEntriesChanged { route_id, counts } = &event.kind {
+            let last = events
+                .iter_mut()
+                .rev()
+                .find(|known| known.kind.route_id() == Some(route_id.as_str()));
+            if let Some(WebhookEvent {
+                timestamp,
+                kind: EventKind::RouteEntriesChanged { counts: merged, .. },
+                ..
+            }) = last
+            {
+                merged.add(counts);
+                *timestamp = event.timestamp;
+                continue;
+            }
+        }
+        events.push(event);
+    }
+    events
+}
+
+/// Events of an audit log record, tracking the ouis of the known routes.
+/// Records of changes that aren't notified have none
+fn record_events(record: &AuditRecord, route_ouis: &mut HashMap<String, u64>) -> Vec<WebhookEvent> {
+    let timestamp = record.inserted_at.timestamp();
+    let event = |oui: u64, kind: EventKind| WebhookEvent {
+        oui,
+        timestamp,
+        kind,
+    };
+    let oui = record.oui.map(|oui| oui as u64).or_else(|| {
+        record
+            .route_id
+            .as_ref()
+            .and_then(|route_id| route_ouis.get(route_id).copied())
+    });
+
+    match record.rpc.as_str() {
+        "org_disable" => oui
+            .map(|oui| event(oui, EventKind::OrgDisabled))
+            .into_iter()
+            .collect(),
+        "org_enable" => oui
+            .map(|oui| event(oui, EventKind::OrgEnabled))
+            .into_iter()
+            .collect(),
+        "route_create" | "route_update" => {
+            let Some(route) = parse::<Route>(record, &record.after) else {
+                return vec![];
+            };
+            route_ouis.insert(route.id.clone(), route.oui);
+            let oui = route.oui;
+            let kind = if record.rpc == "route_create" {
+                EventKind::RouteCreated { route }
+            } else {
+                EventKind::RouteUpdated { route }
+            };
+            vec![event(oui, kind)]
+        }
+        "route_delete" => {
+            let Some(route) = parse::<Route>(record, &record.before) else {
+                return vec![];
+            };
+            route_ouis.remove(&route.id);
+            vec![event(
+                route.oui,
+                EventKind::RouteDeleted { route_id: route.id },
+            )]
+        }
+        "snapshot_import" => parse::<SnapshotDiff>(record, &record.after)
+            .map(|diff| snapshot_events(diff, route_ouis, timestamp))
+            .unwrap_or_default(),
+        "devaddr_reclaim" => {
+            let Some(reclaim) = parse::<Reclaim>(record, &record.before) else {
+                return vec![];
+            };
+            let mut counts: BTreeMap<(u64, String), EntryCounts> = BTreeMap::new();
+            for org in reclaim.orgs {
+                for range in org.devaddr_ranges {
+                    counts
+                        .entry((org.oui, range.route_id))
+                        .or_default()
+                        .devaddr_ranges_removed += 1;
+                }
+            }
+            counts
+                .into_iter()
+                .map(|((oui, route_id), counts)| {
+                    event(oui, EventKind::RouteEntriesChanged { route_id, counts })
+                })
+                .collect()
+        }
+        rpc => {
+            let removed = entry_count(&record.before);
+            let added = entry_count(&record.after);
+            match (
+                oui,
+                &record.route_id,
+                EntryCounts::of_update(rpc, removed, added),
+            ) {
+                (Some(oui), Some(route_id), Some(counts)) => vec![event(
+                    oui,
+                    EventKind::RouteEntriesChanged {
+                        route_id: route_id.clone(),
+                        counts,
+                    },
+                )],
+                _ => vec![],
+            }
+        }
+    }
+}
+
+/// Events of an imported snapshot: its created and updated routes, the entry
+/// changes of each route, with updated session key filters counted as added,
+/// and then its deleted routes
+fn snapshot_events(
+    diff: SnapshotDiff,
+    route_ouis: &mut HashMap<String, u64>,
+    timestamp: i64,
+) -> Vec<WebhookEvent> {
+    let event = |oui: u64, kind: EventKind| WebhookEvent {
+        oui,
+        timestamp,
+        kind,
+    };
+    let mut events = vec![];
+    for route in diff.routes.added {
+        route_ouis.insert(route.id.clone(), route.oui);
+        events.push(event(route.oui, EventKind::RouteCreated { route }));
+    }
+    for route in diff.routes.updated {
+        route_ouis.insert(route.id.clone(), route.oui);
+        events.push(event(route.oui, EventKind::RouteUpdated { route }));
+    }
+
+    let mut counts: BTreeMap<String, EntryCounts> = BTreeMap::new();
+    let mut count = |route_id: String, field: fn(&mut EntryCounts) -> &mut u64| {
+        *field(counts.entry(route_id).or_default()) += 1;
+    };
+    for eui in diff.euis.added.into_iter().chain(diff.euis.updated) {
+        count(eui.route_id, |counts| &mut counts.euis_added);
+    }
+    for eui in diff.euis.removed {
+        count(eui.route_id, |counts| &mut counts.euis_removed);
+    }
+    for range in diff
+        .devaddr_ranges
+        .added
+        .into_iter()
+        .chain(diff.devaddr_ranges.updated)
+    {
+        count(range.route_id, |counts| &mut counts.devaddr_ranges_added);
+    }
+    for range in diff.devaddr_ranges.removed {
+        count(range.route_id, |counts| &mut counts.devaddr_ranges_removed);
+    }
+    for skf in diff.skfs.added.into_iter().chain(diff.skfs.updated) {
+        count(skf.route_id, |counts| &mut counts.skfs_added);
+    }
+    for skf in diff.skfs.removed {
+        count(skf.route_id, |counts| &mut counts.skfs_removed);
+    }
+    for (route_id, counts) in counts {
+        if let Some(oui) = route_ouis.get(&route_id).copied() {
+            events.push(event(
+                oui,
+                EventKind::RouteEntriesChanged { route_id, counts },
+            ));
+        }
+    }
+
+    for route in diff.routes.removed {
+        route_ouis.remove(&route.id);
+        events.push(event(
+            route.oui,
+            EventKind::RouteDeleted { route_id: route.id },
+        ));
+    }
+    events
+}
+
+fn entry_count(entries: &Option<serde_json::Value>) -> u64 {
+    entries
+        .as_ref()
+        .and_then(|entries| entries.as_array())
+        .map_or(0, |entries| entries.len() as u64)
+}
+
+fn parse<T: DeserializeOwned>(
+    record: &AuditRecord,
+    value: &Option<serde_json::Value>,
+) -> Option<T> {
+    let value = value.clone()?;
+    serde_json::from_value(value)
+        .map_err(|err| {
+            tracing::warn!(
+                id = record.id,
+                rpc = %record.rpc,
+                reason = ?err,
+                "unreadable audit log record not notified"
+            );
+        })
+        .ok()
+}
+
+async fn known_routes(db: &Pool<Postgres>) -> Result<HashMap<String, u64>, RouteStorageError> {
+    route::route_stream(db, StreamFrom::Sequence(0))
+        .try_filter(|(_, deleted)| future::ready(!deleted))
+        .map_ok(|(route, _)| (route.id, route.oui))
+        .try_collect()
+        .await
+}
+
+/// Post the events of a webhook in order, retrying each with backoff until
+/// it's delivered or out of attempts
+async fn deliver(
+    client: reqwest::Client,
+    webhook: WebhookSettings,
+    mut events: mpsc::Receiver<Arc<WebhookEvent>>,
+    mut shutdown: triggered::Listener,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = &mut shutdown => return,
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => return,
+            },
+        };
+        let body = match serde_json::to_vec(event.as_ref()) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!(reason = ?err, "failed to serialize webhook event");
+                continue;
+            }
+        };
+
+        let mut backoff = Duration::from_secs(1);
+        let max_attempts = webhook.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            match post(&client, &webhook, &body).await {
+                Ok(()) => {
+                    telemetry::count_webhook_delivery("delivered");
+                    break;
+                }
+                Err(err) if attempt == max_attempts => {
+                    tracing::warn!(
+                        url = %webhook.url,
+                        oui = event.oui,
+                        reason = ?err,
+                        "webhook delivery failed; event dropped"
+                    );
+                    telemetry::count_webhook_delivery("failed");
+                }
+                Err(err) => {
+                    tracing::debug!(
+                        url = %webhook.url,
+                        attempt,
+                        reason = ?err,
+                        "webhook delivery failed; retrying"
+                    );
+                    tokio::select! {
+                        _ = &mut shutdown => return,
+                        _ = tokio::time::sleep(backoff) => (),
+                    }
+                    backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                }
+            }
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    webhook: &WebhookSettings,
+    body: &[u8],
+) -> anyhow::Result<()> {
+    let timestamp = Utc::now().timestamp().to_string();
+    let signature = sign(&webhook.secret, &timestamp, body)?;
+    client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(SIGNATURE_HEADER, signature)
+        .body(body.to_vec())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Base64 encoded hmac-sha256 of the timestamp, a `.` and the body
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> anyhow::Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|err| anyhow::anyhow!("invalid webhook secret: {err}"))?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{audit::SignerType, lora_field::net_id};
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn route(id: &str, oui: u64) -> Route {
+        let mut route = Route::new(net_id(0xc00053), oui, 1);
+        route.id = id.to_string();
+        route
+    }
+
+    fn record(id: i64, rpc: &str) -> AuditRecord {
+        AuditRecord {
+            id,
+            signer: "signer".to_string(),
+            signer_type: SignerType::Administrator,
+            rpc: rpc.to_string(),
+            oui: None,
+            route_id: None,
+            before: None,
+            after: None,
+            inserted_at: Utc.timestamp_opt(1_700_000_000 + id, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn audit_records_are_notified_in_order() {
+        let mut route_ouis = HashMap::new();
+        let first = route("first", 1);
+        let euis = |count: usize| json!(vec![json!({}); count]);
+        let records = vec![
+            AuditRecord {
+                oui: Some(2),
+                ..record(1, "org_disable")
+            },
+            AuditRecord {
+                oui: Some(1),
+                route_id: Some(first.id.clone()),
+                after: Some(json!(first)),
+                ..record(2, "route_create")
+            },
+            AuditRecord {
+                route_id: Some(first.id.clone()),
+                after: Some(euis(3)),
+                ..record(3, "route_update_euis")
+            },
+            AuditRecord {
+                route_id: Some(first.id.clone()),
+                before: Some(euis(1)),
+                after: Some(euis(1)),
+                ..record(4, "route_sync_euis")
+            },
+            AuditRecord {
+                oui: Some(1),
+                route_id: Some(first.id.clone()),
+                before: Some(json!(first)),
+                ..record(5, "route_delete")
+            },
+            AuditRecord {
+                route_id: Some(first.id.clone()),
+                after: Some(euis(1)),
+                ..record(6, "route_update_euis")
+            },
+            AuditRecord {
+                oui: Some(2),
+                ..record(7, "admin_add_key")
+            },
+        ];
+
+        let events = batch_events(&records, &mut route_ouis);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            &events[0],
+            WebhookEvent {
+                oui: 2,
+                kind: EventKind::OrgDisabled,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &events[1],
+            WebhookEvent {
+                oui: 1,
+                kind: EventKind::RouteCreated { .. },
+                ..
+            }
+        ));
+        match &events[2] {
+            WebhookEvent {
+                oui: 1,
+                timestamp,
+                kind: EventKind::RouteEntriesChanged { route_id, counts },
+            } => {
+                assert_eq!(route_id, "first");
+                assert_eq!(*timestamp, 1_700_000_004);
+                assert_eq!(
+                    *counts,
+                    EntryCounts {
+                        euis_added: 4,
+                        euis_removed: 1,
+                        ..Default::default()
+                    }
+                );
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        assert!(matches!(
+            &events[3],
+            WebhookEvent {
+                oui: 1,
+                kind: EventKind::RouteDeleted { .. },
+                ..
+            }
+        ));
+        assert!(route_ouis.is_empty());
+    }
+
+    #[test]
+    fn skipped_audit_ids_are_read_again_until_they_time_out() {
+        let now = Instant::now();
+        let mut cursor = AuditCursor::new(1);
+        cursor.advance([2, 5], now);
+        assert_eq!(cursor.last_id, 5);
+        assert_eq!(cursor.skipped_ids(), vec![3, 4]);
+
+        cursor.advance([4], now);
+        assert_eq!(cursor.skipped_ids(), vec![3]);
+
+        cursor.advance([6], now + SKIPPED_ID_TIMEOUT);
+        assert_eq!(cursor.last_id, 6);
+        assert!(cursor.skipped_ids().is_empty());
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_body() {
+        let signature = sign("secret", "1700000000", b"{}").expect("signed");
+        assert_eq!(
+            signature,
+            sign("secret", "1700000000", b"{}").expect("signed")
+        );
+        assert_ne!(
+            signature,
+            sign("secret", "1700000001", b"{}").expect("signed")
+        );
+        assert_ne!(
+            signature,
+            sign("other", "1700000000", b"{}").expect("signed")
+        );
+    }
+}
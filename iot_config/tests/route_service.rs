@@ -9,7 +9,19 @@ use helium_proto::services::iot_config::{
 };
 use iot_config::{
     admin::{AuthCache, KeyType},
+    audit::{self, AuditQuery, Signer, SignerType},
+    devaddr_slabs::{self, ReclaimRequest, SlabRequest},
+    lora_field::{EuiPair, LoraField, Skf},
     org::{self},
+    quota::{QuotaError, Quotas},
+    route,
+    route_diff::{self, RouteDiffError, RouteUpdate, UpdateMode},
+    route_service::{
+        ValidatedDiff, CHANGE_SEQ_METADATA_KEY, VALIDATE_ONLY_DIFF_METADATA_KEY,
+        VALIDATE_ONLY_METADATA_KEY,
+    },
+    settings::QuotaSettings,
+    snapshot::{self, SnapshotError},
     OrgService, RouteService,
 };
 use prost::Message;
@@ -276,6 +288,697 @@ async fn stream_updates_with_deactivate_reactivate(pool: Pool<Postgres>) {
     assert_route_result(&responses, proto::ActionV1::Add, &route.id);
 }
 
+#[sqlx::test]
+async fn stream_resumes_after_change_seq(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair, auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+
+    create_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let response = client
+        .stream(route_stream_req_v1(&client_keypair, 0))
+        .await
+        .expect("stream request");
+    let synced_seq = response
+        .metadata()
+        .get(CHANGE_SEQ_METADATA_KEY)
+        .expect("change seq metadata")
+        .to_str()
+        .expect("ascii change seq")
+        .to_string();
+    drop(response);
+
+    create_euis(&mut client, &route, vec![(202, 203)], &admin_keypair).await;
+    delete_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let mut request = tonic::Request::new(route_stream_req_v1(&client_keypair, 0));
+    request.metadata_mut().insert(
+        CHANGE_SEQ_METADATA_KEY,
+        synced_seq.parse().expect("change seq metadata value"),
+    );
+    let response = client.stream(request).await.expect("stream request");
+    let responses: Vec<proto::RouteStreamResV1> = drain_stream(response.into_inner())
+        .await
+        .expect("drain stream contents");
+
+    assert_eq!(responses.len(), 2);
+    assert_eui_pair_result(&responses, proto::ActionV1::Add, &route.id, 202, 203);
+    assert_eui_pair_result(&responses, proto::ActionV1::Remove, &route.id, 200, 201);
+}
+
+#[sqlx::test]
+async fn route_changes_are_audited(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair, auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+    create_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+    delete_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let page = audit::query(
+        &AuditQuery {
+            oui: Some(org.oui),
+            ..Default::default()
+        },
+        &pool,
+    )
+    .await
+    .expect("query audit log");
+
+    let rpcs: Vec<&str> = page
+        .entries
+        .iter()
+        .map(|entry| entry.rpc.as_str())
+        .collect();
+    assert_eq!(
+        rpcs,
+        vec![
+            "route_update_euis",
+            "route_update_euis",
+            "route_create",
+            "org_create_helium"
+        ]
+    );
+    for entry in &page.entries {
+        assert_eq!(entry.signer, admin_keypair.public_key().to_string());
+        assert_eq!(entry.signer_type, SignerType::Administrator);
+        assert_eq!(entry.oui, Some(org.oui as i64));
+    }
+
+    let removed = &page.entries[0];
+    assert_eq!(removed.route_id.as_deref(), Some(route.id.as_str()));
+    assert_eq!(
+        removed
+            .before
+            .as_ref()
+            .and_then(|euis| euis.as_array())
+            .map(Vec::len),
+        Some(1)
+    );
+    assert_eq!(removed.after, Some(serde_json::json!([])));
+
+    let by_route = audit::query(
+        &AuditQuery {
+            route_id: Some(route.id.clone()),
+            ..Default::default()
+        },
+        &pool,
+    )
+    .await
+    .expect("query audit log by route");
+    assert_eq!(by_route.entries.len(), 3);
+}
+
+#[sqlx::test]
+async fn route_changes_fail_without_audit_entry(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair, auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+
+    sqlx::query("alter table audit_log add constraint reject_entries check (false) not valid")
+        .execute(&pool)
+        .await
+        .expect("reject audit log entries");
+
+    let mut request = proto::RouteDeleteReqV1 {
+        id: route.id.clone(),
+        timestamp: Utc::now().timestamp() as u64,
+        signature: vec![],
+        signer: admin_keypair.public_key().into(),
+    };
+    request.signature = admin_keypair
+        .sign(&request.encode_to_vec())
+        .expect("sign delete route");
+    assert!(client.delete(request).await.is_err());
+
+    let routes = route::list_routes(org.oui, &pool)
+        .await
+        .expect("list routes");
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].id, route.id);
+}
+
+#[sqlx::test]
+async fn validate_only_eui_updates_are_not_committed(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair, auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+
+    let mut request = proto::RouteUpdateEuisReqV1 {
+        action: proto::ActionV1::Add as i32,
+        eui_pair: Some(proto::EuiPairV1 {
+            route_id: route.id.clone(),
+            app_eui: 1,
+            dev_eui: 1,
+        }),
+        timestamp: Utc::now().timestamp() as u64,
+        signature: vec![],
+        signer: admin_keypair.public_key().into(),
+    };
+    request.signature = admin_keypair.sign(&request.encode_to_vec()).expect("sign");
+
+    let mut request = tonic::Request::new(futures::stream::iter(vec![request]));
+    request
+        .metadata_mut()
+        .insert(VALIDATE_ONLY_METADATA_KEY, "true".parse().unwrap());
+    let response = client
+        .update_euis(request)
+        .await
+        .expect("validate eui pairs");
+    assert_eq!(
+        response
+            .metadata()
+            .get(VALIDATE_ONLY_METADATA_KEY)
+            .and_then(|flag| flag.to_str().ok()),
+        Some("true")
+    );
+    let diff = response
+        .metadata()
+        .get_bin(VALIDATE_ONLY_DIFF_METADATA_KEY)
+        .and_then(|diff| diff.to_bytes().ok())
+        .expect("validated diff");
+    let diff: ValidatedDiff<EuiPair> = serde_json::from_slice(&diff).expect("decode diff");
+    assert_eq!(
+        diff,
+        ValidatedDiff {
+            adds: vec![EuiPair::new(route.id.clone(), LoraField(1), LoraField(1))],
+            removes: vec![],
+        }
+    );
+
+    assert!(stored_euis(&route.id, &pool).await.is_empty());
+    let entries = audit::query(
+        &AuditQuery {
+            route_id: Some(route.id.clone()),
+            ..Default::default()
+        },
+        &pool,
+    )
+    .await
+    .expect("query audit log");
+    assert!(entries
+        .entries
+        .iter()
+        .all(|entry| entry.rpc != "route_update_euis"));
+}
+
+#[sqlx::test]
+async fn route_diff_previews_then_sets_euis(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair.clone(), auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+    create_euis(
+        &mut client,
+        &route,
+        vec![(200, 201), (202, 203)],
+        &admin_keypair,
+    )
+    .await;
+
+    let eui_pair =
+        |app_eui, dev_eui| EuiPair::new(route.id.clone(), LoraField(app_eui), LoraField(dev_eui));
+    let mut update = RouteUpdate {
+        route_id: route.id.clone(),
+        mode: UpdateMode::Set,
+        commit: false,
+        entries: vec![eui_pair(202, 203), eui_pair(202, 203)],
+    };
+    let (update_tx, mut update_rx) = tokio::sync::broadcast::channel(16);
+    let signer = Signer::new(admin_keypair.public_key().clone(), KeyType::Administrator);
+    let apply = |update| apply_euis(update, &signer, &pool, &signing_keypair, &update_tx);
+
+    let duplicate = apply(update.clone()).await;
+    assert!(matches!(duplicate, Err(RouteDiffError::Invalid(_))));
+
+    update.entries = vec![eui_pair(202, 203), eui_pair(204, 205)];
+    let preview = apply(update.clone()).await.expect("route diff preview");
+    assert!(!preview.committed);
+    assert_eq!(preview.adds, vec![eui_pair(204, 205)]);
+    assert_eq!(preview.removes, vec![eui_pair(200, 201)]);
+    assert_eq!(stored_euis(&route.id, &pool).await.len(), 2);
+    assert!(update_rx.try_recv().is_err());
+
+    update.commit = true;
+    let diff = apply(update.clone()).await.expect("apply route diff");
+    assert!(diff.committed);
+    assert_eq!(diff.adds, preview.adds);
+    assert_eq!(diff.removes, preview.removes);
+
+    let mut euis = stored_euis(&route.id, &pool).await;
+    euis.sort_by_key(|pair| pair.app_eui.0);
+    assert_eq!(euis, vec![eui_pair(202, 203), eui_pair(204, 205)]);
+
+    // Both updates are broadcast with the sequence the diff committed at
+    let committed_seq = route::committed_change_seq(&pool)
+        .await
+        .expect("committed change seq");
+    for _ in 0..2 {
+        let update = update_rx.try_recv().expect("broadcast update");
+        assert_eq!(update.change_seq, committed_seq);
+    }
+    assert!(update_rx.try_recv().is_err());
+}
+
+#[sqlx::test]
+async fn snapshot_import_restores_exported_routes(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair.clone(), auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+    create_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let exported = snapshot::export(&pool).await.expect("export snapshot");
+    assert_eq!(exported.orgs.len(), 1);
+    assert_eq!(exported.routes.len(), 1);
+    assert_eq!(exported.euis.len(), 1);
+
+    create_euis(&mut client, &route, vec![(202, 203)], &admin_keypair).await;
+    assert_eq!(stored_euis(&route.id, &pool).await.len(), 2);
+
+    let mut invalid = exported.clone();
+    invalid.euis[0].route_id = "00000000-0000-0000-0000-000000000001".to_string();
+    let invalid = snapshot::diff(&invalid, &pool).await;
+    assert!(matches!(invalid, Err(SnapshotError::Invalid(_))));
+
+    let mut diff = snapshot::diff(&exported, &pool)
+        .await
+        .expect("snapshot diff");
+    assert!(diff.orgs.added.is_empty() && diff.orgs.updated.is_empty());
+    assert!(diff.routes.updated.is_empty());
+    assert_eq!(
+        diff.euis.removed,
+        vec![EuiPair::new(
+            route.id.clone(),
+            LoraField(202),
+            LoraField(203)
+        )]
+    );
+
+    let (update_tx, _update_rx) = tokio::sync::broadcast::channel(16);
+    let signer = Signer::new(admin_keypair.public_key().clone(), KeyType::Administrator);
+    snapshot::apply(&mut diff, &signer, &pool, &signing_keypair, update_tx)
+        .await
+        .expect("apply snapshot");
+    assert!(diff.committed);
+    assert_eq!(stored_euis(&route.id, &pool).await, exported.euis);
+}
+
+#[sqlx::test]
+async fn snapshot_import_conflicts_with_org_changes(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair.clone(), auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+    create_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let exported = snapshot::export(&pool).await.expect("export snapshot");
+    create_euis(&mut client, &route, vec![(202, 203)], &admin_keypair).await;
+    let mut diff = snapshot::diff(&exported, &pool)
+        .await
+        .expect("snapshot diff");
+
+    // Org changes don't advance the route change sequence
+    sqlx::query("update organizations set locked = true where oui = $1")
+        .bind(org.oui as i64)
+        .execute(&pool)
+        .await
+        .expect("lock org");
+
+    let (update_tx, _update_rx) = tokio::sync::broadcast::channel(16);
+    let signer = Signer::new(admin_keypair.public_key().clone(), KeyType::Administrator);
+    let applied = snapshot::apply(&mut diff, &signer, &pool, &signing_keypair, update_tx).await;
+    assert!(matches!(applied, Err(SnapshotError::ImportConflict(_))));
+    assert!(!diff.committed);
+    assert_eq!(stored_euis(&route.id, &pool).await.len(), 2);
+}
+
+#[sqlx::test]
+async fn reclaimed_devaddrs_are_allocated_again(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair.clone(), auth_cache, pool.clone()).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 {
+        org: Some(org),
+        net_id,
+        devaddr_constraints,
+        ..
+    } = org_res_v1
+    else {
+        panic!("invalid OrgResV1")
+    };
+
+    org::toggle_locked(org.oui, &pool)
+        .await
+        .expect("disable org");
+    sqlx::query("update organizations set locked_at = now() - interval '2 days' where oui = $1")
+        .bind(org.oui as i64)
+        .execute(&pool)
+        .await
+        .expect("backdate disabled org");
+
+    let (update_tx, _update_rx) = tokio::sync::broadcast::channel(16);
+    let mut request = ReclaimRequest {
+        grace_period_secs: 3 * 86_400,
+        commit: true,
+    };
+    let signer = Signer::new(admin_keypair.public_key().clone(), KeyType::Administrator);
+    let reclaim = devaddr_slabs::reclaim(
+        &request,
+        &signer,
+        &pool,
+        &signing_keypair,
+        update_tx.clone(),
+    )
+    .await
+    .expect("reclaim within grace period");
+    assert!(reclaim.is_empty());
+
+    request.grace_period_secs = 86_400;
+    let reclaim = devaddr_slabs::reclaim(&request, &signer, &pool, &signing_keypair, update_tx)
+        .await
+        .expect("reclaim");
+    assert_eq!(reclaim.orgs.len(), 1);
+    assert_eq!(reclaim.orgs[0].oui, org.oui);
+    let usage = devaddr_slabs::usage(&pool).await.expect("devaddr usage");
+    assert!(usage.iter().all(|net_id| net_id.used_addrs == 0));
+
+    let allocation = devaddr_slabs::allocate_slab(
+        &SlabRequest {
+            oui: org.oui,
+            count: 8,
+            net_id: Some(LoraField(net_id as u64)),
+            commit: true,
+        },
+        &signer,
+        &pool,
+    )
+    .await
+    .expect("allocate slab");
+    assert_eq!(
+        proto::DevaddrConstraintV1::from(allocation.constraint),
+        devaddr_constraints[0]
+    );
+}
+
+#[sqlx::test]
+async fn orphaned_devaddrs_are_reclaimed_after_grace_period(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair.clone(), auth_cache, pool.clone()).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    // Devaddrs claimed long ago are only orphaned once their constraints are
+    // removed
+    sqlx::query("update helium_used_devaddrs set updated_at = now() - interval '2 days'")
+        .execute(&pool)
+        .await
+        .expect("backdate claimed devaddrs");
+    sqlx::query("delete from organization_devaddr_constraints where oui = $1")
+        .bind(org.oui as i64)
+        .execute(&pool)
+        .await
+        .expect("remove org constraints");
+
+    let (update_tx, _update_rx) = tokio::sync::broadcast::channel(16);
+    let request = ReclaimRequest {
+        grace_period_secs: 86_400,
+        commit: true,
+    };
+    let signer = Signer::new(admin_keypair.public_key().clone(), KeyType::Administrator);
+    let reclaim = devaddr_slabs::reclaim(
+        &request,
+        &signer,
+        &pool,
+        &signing_keypair,
+        update_tx.clone(),
+    )
+    .await
+    .expect("reclaim within grace period");
+    assert!(reclaim.orphaned.is_empty());
+
+    sqlx::query("update helium_used_devaddrs set orphaned_at = now() - interval '2 days'")
+        .execute(&pool)
+        .await
+        .expect("backdate orphaned devaddrs");
+    let reclaim = devaddr_slabs::reclaim(&request, &signer, &pool, &signing_keypair, update_tx)
+        .await
+        .expect("reclaim");
+    assert_eq!(reclaim.orphaned.len(), 1);
+    let usage = devaddr_slabs::usage(&pool).await.expect("devaddr usage");
+    assert!(usage.iter().all(|net_id| net_id.used_addrs == 0));
+}
+
+#[sqlx::test]
+async fn quotas_limit_routes_euis_and_skfs(pool: Pool<Postgres>) {
+    let signing_keypair = Arc::new(generate_keypair());
+    let admin_keypair = generate_keypair();
+    let client_keypair = generate_keypair();
+
+    let port = get_port();
+
+    let auth_cache = create_auth_cache(
+        admin_keypair.public_key().clone(),
+        client_keypair.public_key().clone(),
+        &pool,
+    )
+    .await;
+
+    let _handle = start_server(port, signing_keypair, auth_cache, pool.clone()).await;
+    let mut client = connect_client(port).await;
+
+    let org_res_v1 = create_org(port, &admin_keypair).await;
+
+    let proto::OrgResV1 { org: Some(org), .. } = org_res_v1 else {
+        panic!("invalid OrgResV1")
+    };
+
+    let route = create_route(&mut client, &org, &admin_keypair).await;
+    create_euis(&mut client, &route, vec![(200, 201)], &admin_keypair).await;
+
+    let quotas = Quotas::new(QuotaSettings {
+        max_routes_per_org: 1,
+        max_euis_per_route: 2,
+        max_skfs_per_route: 1,
+        ..Default::default()
+    });
+    let mut conn = pool.acquire().await.expect("acquire connection");
+    assert!(matches!(
+        quotas.check_routes(org.oui, &mut conn).await,
+        Err(QuotaError::Routes { limit: 1, .. })
+    ));
+
+    let eui_pair =
+        |app_eui, dev_eui| EuiPair::new(route.id.clone(), LoraField(app_eui), LoraField(dev_eui));
+    quotas
+        .check_euis(&[eui_pair(202, 203)], &mut conn)
+        .await
+        .expect("room for one more eui pair");
+    assert!(matches!(
+        quotas
+            .check_euis(&[eui_pair(202, 203), eui_pair(204, 205)], &mut conn)
+            .await,
+        Err(QuotaError::Entries { limit: 2, .. })
+    ));
+
+    // re-adding a stored session key filter upserts it, so it is only counted
+    // once against the quota
+    let skf = |session_key: &str| {
+        Skf::new(
+            route.id.clone(),
+            0x48000001_u32.into(),
+            session_key.to_string(),
+            1,
+        )
+    };
+    let mut transaction = pool.begin().await.expect("begin transaction");
+    for _ in 0..2 {
+        route::update_skfs(&[skf("0123456789abcdef")], &[], &mut transaction)
+            .await
+            .expect("upsert skf");
+        quotas
+            .check_stored_skfs(
+                [(route.id.as_str(), 0)].into_iter().collect(),
+                &mut transaction,
+            )
+            .await
+            .expect("stored skf within quota");
+    }
+    assert!(matches!(
+        quotas
+            .check_skfs(&[skf("fedcba9876543210")], &mut transaction)
+            .await,
+        Err(QuotaError::Entries { limit: 1, .. })
+    ));
+}
+
 async fn drain_stream(
     stream: Streaming<proto::RouteStreamResV1>,
 ) -> Result<Vec<proto::RouteStreamResV1>, tonic::Status> {
@@ -444,6 +1147,32 @@ fn route_stream_req_v1(signer: &Keypair, since: u64) -> RouteStreamReqV1 {
     request
 }
 
+async fn stored_euis(route_id: &str, pool: &Pool<Postgres>) -> Vec<EuiPair> {
+    route::list_euis_for_route(route_id, pool)
+        .expect("list euis")
+        .map(|pair| pair.expect("eui pair"))
+        .collect()
+        .await
+}
+
+async fn apply_euis(
+    update: RouteUpdate<EuiPair>,
+    signer: &Signer,
+    pool: &Pool<Postgres>,
+    signing_keypair: &Arc<Keypair>,
+    update_tx: &tokio::sync::broadcast::Sender<route::StreamUpdate>,
+) -> Result<route_diff::RouteDiff<EuiPair>, RouteDiffError> {
+    route_diff::apply(
+        &update,
+        signer,
+        &Quotas::default(),
+        pool,
+        signing_keypair.clone(),
+        update_tx.clone(),
+    )
+    .await
+}
+
 async fn connect_client(port: u64) -> RouteClient<Channel> {
     (|| RouteClient::connect(format!("http://127.0.0.1:{port}")))
         .retry(&ExponentialBuilder::default())
@@ -461,8 +1190,12 @@ async fn start_server(
         .await
         .expect("delete keys cache");
 
-    let route_service =
-        RouteService::new(signing_keypair.clone(), auth_cache.clone(), pool.clone());
+    let route_service = RouteService::new(
+        signing_keypair.clone(),
+        auth_cache.clone(),
+        pool.clone(),
+        Quotas::default(),
+    );
 
     let org_service = OrgService::new(
         signing_keypair.clone(),
@@ -0,0 +1,279 @@
+use crate::{current_timestamp, KeyType, Result};
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use helium_crypto::{Keypair, PublicKey, Sign, Verify};
+use helium_proto::{
+    services::iot_config::{
+        admin_client, AdminAddKeyReqV1, AdminKeyResV1, AdminLoadRegionReqV1, AdminLoadRegionResV1,
+        AdminRemoveKeyReqV1,
+    },
+    BlockchainRegionParamsV1, Message, Region,
+};
+use iot_config::{
+    api_server::{
+        RegionParamsAt, RegionParamsQuery, Signed, SnapshotExport, SnapshotImport, AUDIT_PATH,
+        DEVADDR_ALLOCATE_PATH, DEVADDR_RECLAIM_PATH, DEVADDR_USAGE_PATH, REGION_LOOKUP_PATH,
+        REGION_PARAMS_PATH, ROUTE_DEVADDR_RANGES_PATH, ROUTE_EUIS_PATH, ROUTE_SKFS_PATH,
+        SIGNATURE_HEADER, SNAPSHOT_EXPORT_PATH, SNAPSHOT_IMPORT_PATH,
+    },
+    audit::{AuditPage, AuditQuery},
+    devaddr_slabs::{NetIdUsage, Reclaim, ReclaimRequest, SlabAllocation, SlabRequest, UsageQuery},
+    lora_field::{DevAddrRange, EuiPair, Skf},
+    region_lookup::{RegionLookup, RegionLookupQuery},
+    route_diff::{RouteDiff, RouteUpdate},
+    snapshot::{Snapshot, SnapshotDiff},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::str::FromStr;
+
+pub struct AdminClient {
+    client: admin_client::AdminClient<helium_proto::services::Channel>,
+    server_pubkey: PublicKey,
+}
+
+impl AdminClient {
+    pub async fn new(host: &str, server_pubkey: &str) -> Result<Self> {
+        Ok(Self {
+            client: admin_client::AdminClient::connect(host.to_owned()).await?,
+            server_pubkey: PublicKey::from_str(server_pubkey)?,
+        })
+    }
+
+    pub async fn add_key(
+        &mut self,
+        pubkey: &PublicKey,
+        key_type: KeyType,
+        keypair: &Keypair,
+    ) -> Result {
+        let mut request = AdminAddKeyReqV1 {
+            pubkey: pubkey.into(),
+            key_type: key_type.into(),
+            timestamp: current_timestamp()?,
+            signer: keypair.public_key().into(),
+            signature: vec![],
+        };
+        request.signature = request.sign(keypair)?;
+        self.client
+            .add_key(request)
+            .await?
+            .into_inner()
+            .verify(&self.server_pubkey)
+    }
+
+    pub async fn remove_key(&mut self, pubkey: &PublicKey, keypair: &Keypair) -> Result {
+        let mut request = AdminRemoveKeyReqV1 {
+            pubkey: pubkey.into(),
+            timestamp: current_timestamp()?,
+            signer: keypair.public_key().into(),
+            signature: vec![],
+        };
+        request.signature = request.sign(keypair)?;
+        self.client
+            .remove_key(request)
+            .await?
+            .into_inner()
+            .verify(&self.server_pubkey)
+    }
+
+    pub async fn load_region(
+        &mut self,
+        region: Region,
+        params: BlockchainRegionParamsV1,
+        hex_indexes: Vec<u8>,
+        keypair: &Keypair,
+    ) -> Result {
+        let mut request = AdminLoadRegionReqV1 {
+            region: region.into(),
+            params: Some(params),
+            hex_indexes,
+            timestamp: current_timestamp()?,
+            signer: keypair.public_key().into(),
+            signature: vec![],
+        };
+        request.signature = request.sign(keypair)?;
+        self.client
+            .load_region(request)
+            .await?
+            .into_inner()
+            .verify(&self.server_pubkey)
+    }
+}
+
+/// Client of the config service http api
+pub struct ApiClient {
+    client: reqwest::Client,
+    url: String,
+    server_pubkey: PublicKey,
+}
+
+impl ApiClient {
+    pub fn new(url: &str, server_pubkey: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            server_pubkey: PublicKey::from_str(server_pubkey)?,
+        })
+    }
+
+    pub async fn audit(&self, query: AuditQuery, keypair: &Keypair) -> Result<AuditPage> {
+        self.post(AUDIT_PATH, query, keypair).await
+    }
+
+    pub async fn sync_euis(
+        &self,
+        update: RouteUpdate<EuiPair>,
+        keypair: &Keypair,
+    ) -> Result<RouteDiff<EuiPair>> {
+        self.post(ROUTE_EUIS_PATH, update, keypair).await
+    }
+
+    pub async fn sync_devaddr_ranges(
+        &self,
+        update: RouteUpdate<DevAddrRange>,
+        keypair: &Keypair,
+    ) -> Result<RouteDiff<DevAddrRange>> {
+        self.post(ROUTE_DEVADDR_RANGES_PATH, update, keypair).await
+    }
+
+    pub async fn sync_skfs(
+        &self,
+        update: RouteUpdate<Skf>,
+        keypair: &Keypair,
+    ) -> Result<RouteDiff<Skf>> {
+        self.post(ROUTE_SKFS_PATH, update, keypair).await
+    }
+
+    pub async fn export_snapshot(&self, keypair: &Keypair) -> Result<Signed<Snapshot>> {
+        let request = SnapshotExport {};
+        self.post(SNAPSHOT_EXPORT_PATH, request, keypair).await
+    }
+
+    pub async fn import_snapshot(
+        &self,
+        import: SnapshotImport,
+        keypair: &Keypair,
+    ) -> Result<SnapshotDiff> {
+        self.post(SNAPSHOT_IMPORT_PATH, import, keypair).await
+    }
+
+    pub async fn devaddr_usage(&self, keypair: &Keypair) -> Result<Vec<NetIdUsage>> {
+        let query = UsageQuery {};
+        self.post(DEVADDR_USAGE_PATH, query, keypair).await
+    }
+
+    pub async fn reclaim_devaddrs(
+        &self,
+        request: ReclaimRequest,
+        keypair: &Keypair,
+    ) -> Result<Reclaim> {
+        self.post(DEVADDR_RECLAIM_PATH, request, keypair).await
+    }
+
+    pub async fn allocate_devaddr_slab(
+        &self,
+        request: SlabRequest,
+        keypair: &Keypair,
+    ) -> Result<SlabAllocation> {
+        self.post(DEVADDR_ALLOCATE_PATH, request, keypair).await
+    }
+
+    pub async fn region_params(
+        &self,
+        query: RegionParamsQuery,
+        keypair: &Keypair,
+    ) -> Result<RegionParamsAt> {
+        self.post(REGION_PARAMS_PATH, query, keypair).await
+    }
+
+    pub async fn region_lookup(
+        &self,
+        query: RegionLookupQuery,
+        keypair: &Keypair,
+    ) -> Result<RegionLookup> {
+        self.post(REGION_LOOKUP_PATH, query, keypair).await
+    }
+
+    async fn post<Req, Res>(&self, path: &str, request: Req, keypair: &Keypair) -> Result<Res>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let request = Signed::sign(request, keypair)?;
+        let response = self
+            .client
+            .post(format!("{}{path}", self.url))
+            .json(&request)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            bail!(
+                "api request failed: {} {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            );
+        }
+        let signature = response
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|signature| signature.to_str().ok())
+            .and_then(|signature| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(signature)
+                    .ok()
+            })
+            .ok_or_else(|| anyhow!("missing or invalid api response signature"))?;
+        let body = response.bytes().await?;
+        self.server_pubkey
+            .verify(&body, &signature)
+            .context("verifying api response")?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+pub trait MsgSign: Message + std::clone::Clone {
+    fn sign(&self, keypair: &Keypair) -> Result<Vec<u8>>
+    where
+        Self: std::marker::Sized;
+}
+
+macro_rules! impl_sign {
+    ($msg_type:ty, $( $sig: ident ),+ ) => {
+        impl MsgSign for $msg_type {
+            fn sign(&self, keypair: &Keypair) -> Result<Vec<u8>> {
+                let mut msg = self.clone();
+                $(msg.$sig = vec![];)+
+                Ok(keypair.sign(&msg.encode_to_vec())?)
+            }
+        }
+    }
+}
+
+impl_sign!(AdminAddKeyReqV1, signature);
+impl_sign!(AdminRemoveKeyReqV1, signature);
+impl_sign!(AdminLoadRegionReqV1, signature);
+
+pub trait MsgVerify: Message + std::clone::Clone {
+    fn verify(&self, verifier: &PublicKey) -> Result
+    where
+        Self: std::marker::Sized;
+}
+
+macro_rules! impl_verify {
+    ($msg_type:ty, $sig: ident) => {
+        impl MsgVerify for $msg_type {
+            fn verify(&self, verifier: &PublicKey) -> Result {
+                let mut buf = vec![];
+                let mut msg = self.clone();
+                msg.$sig = vec![];
+                msg.encode(&mut buf)?;
+                verifier
+                    .verify(&buf, &self.$sig)
+                    .map_err(anyhow::Error::from)
+            }
+        }
+    };
+}
+
+impl_verify!(AdminKeyResV1, signature);
+impl_verify!(AdminLoadRegionResV1, signature);
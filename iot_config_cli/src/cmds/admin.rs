@@ -0,0 +1,125 @@
+use crate::{client, cmds::PathBufKeypair, Msg, PrettyJson, Result};
+use anyhow::{anyhow, Context};
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+use helium_proto::{BlockchainRegionParamsV1, Message, Region};
+use iot_config::{api_server::RegionParamsQuery, region_lookup::RegionLookupQuery};
+use std::{fs, str::FromStr};
+
+use super::{
+    require_config_pubkey, AdminAddKey, AdminLoadRegion, AdminRegionLookup, AdminRegionParams,
+    AdminRemoveKey,
+};
+
+pub async fn add_key(args: AdminAddKey) -> Result<Msg> {
+    let output = format!("Added {} as {} key", args.pubkey, args.key_type);
+
+    if args.commit {
+        let mut client = client::AdminClient::new(
+            &args.config_host,
+            require_config_pubkey(&args.config_pubkey)?,
+        )
+        .await?;
+        client
+            .add_key(&args.pubkey, args.key_type, &args.keypair.to_keypair()?)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn remove_key(args: AdminRemoveKey) -> Result<Msg> {
+    let output = format!("Removed {} key", args.pubkey);
+
+    if args.commit {
+        let mut client = client::AdminClient::new(
+            &args.config_host,
+            require_config_pubkey(&args.config_pubkey)?,
+        )
+        .await?;
+        client
+            .remove_key(&args.pubkey, &args.keypair.to_keypair()?)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn load_region(args: AdminLoadRegion) -> Result<Msg> {
+    let region = Region::from_str(&args.region)
+        .map_err(|_| anyhow!("unknown lora region {}", args.region))?;
+    let params = fs::read(&args.params_file).context("reading region params file")?;
+    let params = BlockchainRegionParamsV1::decode(params.as_slice())
+        .context("decoding region params file")?;
+    let hex_indexes = match &args.indexes_file {
+        Some(indexes_file) => fs::read(indexes_file).context("reading region indexes file")?,
+        None => vec![],
+    };
+
+    let output = format!(
+        "Loaded {} region params{}",
+        args.region,
+        if hex_indexes.is_empty() {
+            String::new()
+        } else {
+            format!(" and {} bytes of hex indexes", hex_indexes.len())
+        }
+    );
+
+    if args.commit {
+        let mut client = client::AdminClient::new(
+            &args.config_host,
+            require_config_pubkey(&args.config_pubkey)?,
+        )
+        .await?;
+        client
+            .load_region(region, params, hex_indexes, &args.keypair.to_keypair()?)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn region_params(args: AdminRegionParams) -> Result<Msg> {
+    let client =
+        client::ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let query = RegionParamsQuery {
+        region: args.region,
+        at: args.at.unwrap_or_else(Utc::now).timestamp(),
+    };
+    let params = client
+        .region_params(query, &args.keypair.to_keypair()?)
+        .await?;
+    let encoded = base64::engine::general_purpose::STANDARD
+        .decode(&params.params)
+        .context("decoding region params")?;
+    BlockchainRegionParamsV1::decode(encoded.as_slice()).context("decoding region params")?;
+    fs::write(&args.out_file, encoded).context("writing region params file")?;
+
+    let active_from = Utc
+        .timestamp_opt(params.active_from, 0)
+        .single()
+        .map_or_else(|| params.active_from.to_string(), |time| time.to_rfc3339());
+    Msg::ok(format!(
+        "Wrote {} region params active from {} to {}",
+        params.region,
+        active_from,
+        args.out_file.display()
+    ))
+}
+
+pub async fn region_lookup(args: AdminRegionLookup) -> Result<Msg> {
+    let client =
+        client::ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let query = RegionLookupQuery {
+        location: args.location,
+        lat: args.lat,
+        lng: args.lng,
+        resolution: Some(args.resolution),
+        radius_m: Some(args.radius_m),
+    };
+    let lookup = client
+        .region_lookup(query, &args.keypair.to_keypair()?)
+        .await?;
+    Msg::ok(lookup.pretty_json()?)
+}
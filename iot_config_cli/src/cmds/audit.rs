@@ -0,0 +1,18 @@
+use crate::{client::ApiClient, Msg, PrettyJson, Result};
+use iot_config::audit::AuditQuery;
+
+use super::{require_config_pubkey, AuditLog, PathBufKeypair};
+
+pub async fn query(args: AuditLog) -> Result<Msg> {
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let query = AuditQuery {
+        oui: args.oui,
+        route_id: args.route_id,
+        start: args.start.map(|start| start.timestamp()),
+        end: args.end.map(|end| end.timestamp()),
+        before_id: args.before_id,
+        limit: Some(args.limit),
+    };
+    let page = client.audit(query, &args.keypair.to_keypair()?).await?;
+    Msg::ok(page.pretty_json()?)
+}
@@ -0,0 +1,57 @@
+use crate::{client::ApiClient, Msg, PrettyJson, Result};
+use iot_config::devaddr_slabs::{ReclaimRequest, SlabRequest};
+
+use super::{require_config_pubkey, AllocateSlab, DevaddrUsage, PathBufKeypair, ReclaimDevaddrs};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+pub async fn usage(args: DevaddrUsage) -> Result<Msg> {
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let usage = client.devaddr_usage(&args.keypair.to_keypair()?).await?;
+    Msg::ok(usage.pretty_json()?)
+}
+
+pub async fn reclaim(args: ReclaimDevaddrs) -> Result<Msg> {
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let request = ReclaimRequest {
+        grace_period_secs: args.grace_days.saturating_mul(SECS_PER_DAY),
+        commit: args.commit,
+    };
+    let reclaim = client
+        .reclaim_devaddrs(request, &args.keypair.to_keypair()?)
+        .await?;
+    let output = format!(
+        "Reclaimed {} orphaned devaddr slabs and the devaddrs of {} disabled orgs\n{}",
+        reclaim.orphaned.len(),
+        reclaim.orgs.len(),
+        reclaim.pretty_json()?
+    );
+    if reclaim.committed {
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn allocate(args: AllocateSlab) -> Result<Msg> {
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let request = SlabRequest {
+        oui: args.oui,
+        count: args.devaddr_count,
+        net_id: args.net_id.map(Into::into),
+        commit: args.commit,
+    };
+    let allocation = client
+        .allocate_devaddr_slab(request, &args.keypair.to_keypair()?)
+        .await?;
+    let output = format!(
+        "Allocated devaddrs {} to {} to org {}\n{}",
+        allocation.constraint.start_addr,
+        allocation.constraint.end_addr,
+        allocation.oui,
+        allocation.pretty_json()?
+    );
+    if allocation.committed {
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
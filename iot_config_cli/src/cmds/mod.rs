@@ -0,0 +1,614 @@
+use crate::{Action, HeliumNetId, KeyType, Result, SyncMode};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use helium_crypto::PublicKey;
+use iot_config::{region_lookup, ClientSettings};
+use std::path::{Path, PathBuf};
+
+pub mod admin;
+pub mod audit;
+pub mod devaddr;
+pub mod env;
+pub mod org;
+pub mod route;
+pub mod snapshot;
+
+pub const ENV_CONFIG_HOST: &str = "HELIUM_CONFIG_HOST";
+pub const ENV_CONFIG_PUBKEY: &str = "HELIUM_CONFIG_PUBKEY";
+pub const ENV_CONFIG_API_URL: &str = "HELIUM_CONFIG_API_URL";
+pub const ENV_KEYPAIR_BIN: &str = "HELIUM_KEYPAIR_BIN";
+
+/// Connect and rpc timeout in seconds of the route and org clients
+const CLIENT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Parser)]
+#[command(name = "iot-config")]
+#[command(author, version, about, long_about=None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+
+    #[arg(
+        global = true,
+        long,
+        env = ENV_CONFIG_HOST,
+        default_value = "https://config.iot.mainnet.helium.io:6080"
+    )]
+    pub config_host: String,
+
+    #[arg(global = true, long, env = ENV_CONFIG_PUBKEY)]
+    pub config_pubkey: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        env = ENV_KEYPAIR_BIN,
+        default_value = "./keypair.bin"
+    )]
+    pub keypair: PathBuf,
+
+    #[arg(global = true, long)]
+    pub print_command: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Environment
+    Env {
+        #[command(subcommand)]
+        command: EnvCommands,
+    },
+    /// Admin
+    Admin {
+        #[command(subcommand)]
+        command: AdminCommands,
+    },
+    /// Org
+    Org {
+        #[command(subcommand)]
+        command: OrgCommands,
+    },
+    /// Route
+    Route {
+        #[command(subcommand)]
+        command: RouteCommands,
+    },
+    /// Query the audit log of config changes, most recent first. Requires
+    /// an administrator keypair
+    Audit(AuditLog),
+    /// Helium net id devaddr usage, reclamation and slab allocation.
+    /// Requires an administrator keypair
+    Devaddr {
+        #[command(subcommand)]
+        command: DevaddrCommands,
+    },
+    /// Export or import the complete routing configuration. Requires an
+    /// administrator keypair
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DevaddrCommands {
+    /// Show the used and free devaddrs of each helium net id, with how
+    /// fragmented the free devaddrs are
+    Usage(DevaddrUsage),
+    /// Release the devaddrs of orgs disabled for longer than the grace
+    /// period, along with their routes' devaddr ranges, and claimed devaddrs
+    /// left behind by deleted orgs
+    Reclaim(ReclaimDevaddrs),
+    /// Allocate an org a contiguous slab of devaddrs, as close to its
+    /// existing constraints as possible
+    Allocate(AllocateSlab),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommands {
+    /// Write the orgs, routes, eui pairs, devaddr ranges and session key
+    /// filters to a signed snapshot file
+    Export(ExportSnapshot),
+    /// Validate a snapshot file and show the changes restoring it makes.
+    /// The changes are applied in a single transaction with `--commit`
+    Import(ImportSnapshot),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EnvCommands {
+    /// Make Environment variable to ease use
+    Init,
+    /// View information about your environment
+    Info(EnvInfo),
+    /// Make a new keypair
+    GenerateKeypair(GenerateKeypair),
+}
+
+#[derive(Debug, Args)]
+pub struct EnvInfo {
+    #[arg(long, env = ENV_CONFIG_HOST, default_value="unset")]
+    pub config_host: Option<String>,
+    #[arg(long, env = ENV_KEYPAIR_BIN, default_value="unset")]
+    pub keypair: Option<PathBuf>,
+    #[arg(long, env = ENV_CONFIG_PUBKEY, default_value="unset")]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateKeypair {
+    #[arg(default_value = "./keypair.bin")]
+    pub out_file: PathBuf,
+    /// The Helium network for which to issue keys
+    #[arg(long, short, value_enum, default_value = "mainnet")]
+    pub network: env::NetworkArg,
+    /// overwrite <out_file> if it already exists
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminCommands {
+    /// Add a pubkey/key type
+    AddKey(AdminAddKey),
+    /// Remove a pubkey
+    RemoveKey(AdminRemoveKey),
+    /// Load the params and, optionally, the hexes of a region
+    LoadRegion(AdminLoadRegion),
+    /// Fetch the params a region had at a point in time
+    RegionParams(AdminRegionParams),
+    /// Resolve the region, params and neighbouring regions of a location
+    RegionLookup(AdminRegionLookup),
+}
+
+#[derive(Debug, Args)]
+pub struct AdminAddKey {
+    #[arg(long, value_enum)]
+    pub key_type: KeyType,
+    #[arg(long)]
+    pub pubkey: PublicKey,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AdminRemoveKey {
+    #[arg(long)]
+    pub pubkey: PublicKey,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AdminLoadRegion {
+    /// Region to load, eg: US915
+    #[arg(long)]
+    pub region: String,
+    /// Protobuf encoded `BlockchainRegionParamsV1` file
+    #[arg(long)]
+    pub params_file: PathBuf,
+    /// Compressed h3 index file of the hexes in the region
+    #[arg(long)]
+    pub indexes_file: Option<PathBuf>,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AdminRegionParams {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    /// Region to fetch, eg: US915
+    #[arg(long)]
+    pub region: String,
+    /// Time the params were active at, eg: 2023-06-01T00:00:00Z. Defaults to now
+    #[arg(long)]
+    pub at: Option<DateTime<Utc>>,
+    /// File to write the protobuf encoded `BlockchainRegionParamsV1` to
+    #[arg(long)]
+    pub out_file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AdminRegionLookup {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    /// Hex encoded h3 cell, eg: 8c2ab38f1ee67ff
+    #[arg(
+        long,
+        conflicts_with_all = ["lat", "lng"],
+        required_unless_present_all = ["lat", "lng"]
+    )]
+    pub location: Option<String>,
+    #[arg(long, requires = "lng", allow_hyphen_values = true)]
+    pub lat: Option<f64>,
+    #[arg(long, requires = "lat", allow_hyphen_values = true)]
+    pub lng: Option<f64>,
+    /// Resolution of the cell a lat/lng is looked up at
+    #[arg(long, default_value_t = region_lookup::DEFAULT_RESOLUTION)]
+    pub resolution: u8,
+    /// Distance around the location to search for other regions, in meters
+    #[arg(long, default_value_t = region_lookup::DEFAULT_RADIUS_M)]
+    pub radius_m: u32,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum OrgCommands {
+    /// Create an org with devaddrs from a Helium net id
+    CreateHelium(CreateHelium),
+    /// Create an org for a roaming partner's net id
+    CreateRoamer(CreateRoamer),
+    /// Enable an org, routing its traffic
+    Enable(OrgOui),
+    /// Disable an org, no longer routing its traffic
+    Disable(OrgOui),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateHelium {
+    #[arg(long)]
+    pub owner: PublicKey,
+    #[arg(long)]
+    pub payer: PublicKey,
+    /// Number of devaddrs to check out, minimum 8, even number required
+    #[arg(long)]
+    pub devaddr_count: u64,
+    #[arg(long, value_enum)]
+    pub net_id: HeliumNetId,
+    /// Keys allowed to manage the org's routes, may be repeated
+    #[arg(long)]
+    pub delegate: Vec<PublicKey>,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CreateRoamer {
+    #[arg(long)]
+    pub owner: PublicKey,
+    #[arg(long)]
+    pub payer: PublicKey,
+    /// Hex encoded net id, eg: 00003c
+    #[arg(long)]
+    pub net_id: String,
+    /// Keys allowed to manage the org's routes, may be repeated
+    #[arg(long)]
+    pub delegate: Vec<PublicKey>,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct OrgOui {
+    #[arg(long)]
+    pub oui: u64,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RouteCommands {
+    /// List the routes of an org
+    List(ListRoutes),
+    /// Retrieve a route
+    Get(GetRoute),
+    /// Create a route for an org from a json route file
+    Create(CreateRoute),
+    /// Update a route from a json route file
+    Update(UpdateRoute),
+    /// Delete a route
+    Delete(DeleteRoute),
+    /// Add or remove a route's eui pairs from a csv or json file with
+    /// `app_eui` and `dev_eui` hex columns
+    UpdateEuis(UpdateRouteEntries),
+    /// Add or remove a route's devaddr ranges from a csv or json file with
+    /// `start_addr` and `end_addr` hex columns
+    UpdateDevaddrRanges(UpdateRouteEntries),
+    /// Add or remove a route's session key filters from a csv or json file
+    /// with `devaddr`, `session_key` and `max_copies` columns
+    UpdateSkfs(UpdateRouteEntries),
+    /// Sync a route's eui pairs with a csv or json file, as in `update-euis`,
+    /// showing the changes the config service computes
+    SyncEuis(SyncRouteEntries),
+    /// Sync a route's devaddr ranges with a csv or json file, as in
+    /// `update-devaddr-ranges`, showing the changes the config service
+    /// computes
+    SyncDevaddrRanges(SyncRouteEntries),
+    /// Sync a route's session key filters with a csv or json file, as in
+    /// `update-skfs`, showing the changes the config service computes
+    SyncSkfs(SyncRouteEntries),
+}
+
+#[derive(Debug, Args)]
+pub struct ListRoutes {
+    #[arg(long)]
+    pub oui: u64,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetRoute {
+    #[arg(long)]
+    pub route_id: String,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CreateRoute {
+    #[arg(long)]
+    pub oui: u64,
+    /// Json route file, any id in it is ignored
+    #[arg(long)]
+    pub route_file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateRoute {
+    /// Json route file, including the id of the route to update
+    #[arg(long)]
+    pub route_file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteRoute {
+    #[arg(long)]
+    pub route_id: String,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateRouteEntries {
+    #[arg(long)]
+    pub route_id: String,
+    #[arg(long, value_enum)]
+    pub action: Action,
+    /// Csv or json file, by extension
+    #[arg(long)]
+    pub file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_host: String,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncRouteEntries {
+    #[arg(long)]
+    pub route_id: String,
+    /// `set` makes the file the route's complete list, removing any others
+    #[arg(long, value_enum)]
+    pub mode: SyncMode,
+    /// Csv or json file, by extension
+    #[arg(long)]
+    pub file: PathBuf,
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AuditLog {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    #[arg(long)]
+    pub oui: Option<u64>,
+    #[arg(long)]
+    pub route_id: Option<String>,
+    /// Earliest change to list, eg: 2023-06-01T00:00:00Z
+    #[arg(long)]
+    pub start: Option<DateTime<Utc>>,
+    /// List changes before this time, eg: 2023-07-01T00:00:00Z
+    #[arg(long)]
+    pub end: Option<DateTime<Utc>>,
+    /// List changes older than this entry id, to page through results
+    #[arg(long)]
+    pub before_id: Option<i64>,
+    #[arg(long, default_value = "100")]
+    pub limit: i64,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DevaddrUsage {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReclaimDevaddrs {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    /// Days an org must have been disabled before its devaddrs are reclaimed
+    #[arg(long, default_value = "30")]
+    pub grace_days: u64,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AllocateSlab {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    #[arg(long)]
+    pub oui: u64,
+    /// Number of devaddrs to allocate, even number required
+    #[arg(long)]
+    pub devaddr_count: u64,
+    /// Net id of the slab, only needed if the org has no devaddrs, eg: after
+    /// they were reclaimed
+    #[arg(long, value_enum)]
+    pub net_id: Option<HeliumNetId>,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportSnapshot {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    /// Path of the snapshot file to write
+    #[arg(long)]
+    pub out_file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportSnapshot {
+    /// Url of the config service http api
+    #[arg(long, env = ENV_CONFIG_API_URL)]
+    pub api_url: String,
+    /// Path of a snapshot file written by `snapshot export`
+    #[arg(long)]
+    pub file: PathBuf,
+    #[arg(from_global)]
+    pub keypair: PathBuf,
+    #[arg(from_global)]
+    pub config_pubkey: Option<String>,
+    #[arg(long)]
+    pub commit: bool,
+}
+
+pub trait PathBufKeypair {
+    fn to_keypair(&self) -> Result<helium_crypto::Keypair>;
+}
+
+impl PathBufKeypair for PathBuf {
+    fn to_keypair(&self) -> Result<helium_crypto::Keypair> {
+        let data = std::fs::read(self).context("reading keypair file")?;
+        Ok(helium_crypto::Keypair::try_from(&data[..])?)
+    }
+}
+
+pub fn require_config_pubkey(config_pubkey: &Option<String>) -> Result<&str> {
+    config_pubkey.as_deref().ok_or_else(|| {
+        anyhow!("config pubkey required, set --config-pubkey or {ENV_CONFIG_PUBKEY}")
+    })
+}
+
+/// Settings of the signing iot config clients
+pub fn client_settings(
+    config_host: &str,
+    config_pubkey: &Option<String>,
+    keypair: &Path,
+) -> Result<ClientSettings> {
+    Ok(ClientSettings {
+        url: config_host.parse().context("parsing config host")?,
+        signing_keypair: keypair.display().to_string(),
+        config_pubkey: require_config_pubkey(config_pubkey)?.to_string(),
+        connect_timeout: CLIENT_TIMEOUT_SECS,
+        rpc_timeout: CLIENT_TIMEOUT_SECS,
+        batch_size: 1000,
+    })
+}
@@ -0,0 +1,98 @@
+use crate::{Msg, PrettyJson, Result};
+use anyhow::anyhow;
+use helium_crypto::PublicKeyBinary;
+use helium_proto::services::iot_config::OrgResV1;
+use iot_config::{
+    client::{org_client::Orgs, OrgClient},
+    lora_field::{DevAddrConstraint, NetIdField},
+};
+use serde_json::json;
+use std::str::FromStr;
+
+use super::{client_settings, CreateHelium, CreateRoamer, OrgOui};
+
+pub async fn create_helium(args: CreateHelium) -> Result<Msg> {
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        let mut client = OrgClient::from_settings(&settings)?;
+        let org = client
+            .create_helium(
+                &args.owner,
+                &args.payer,
+                args.devaddr_count,
+                args.net_id.into(),
+                &args.delegate,
+            )
+            .await?;
+        return Msg::ok(org_json(org).pretty_json()?);
+    }
+    Msg::dry_run(format!(
+        "Created helium org owned by {} with {} devaddrs from net id {}",
+        args.owner, args.devaddr_count, args.net_id
+    ))
+}
+
+pub async fn create_roamer(args: CreateRoamer) -> Result<Msg> {
+    let net_id = NetIdField::from_str(&args.net_id)
+        .map_err(|err| anyhow!("invalid net id {}: {err}", args.net_id))?;
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        let mut client = OrgClient::from_settings(&settings)?;
+        let org = client
+            .create_roamer(&args.owner, &args.payer, net_id.into(), &args.delegate)
+            .await?;
+        return Msg::ok(org_json(org).pretty_json()?);
+    }
+    Msg::dry_run(format!(
+        "Created roamer org owned by {} with net id {net_id}",
+        args.owner
+    ))
+}
+
+pub async fn enable(args: OrgOui) -> Result<Msg> {
+    let output = format!("Enabled org {}", args.oui);
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        OrgClient::from_settings(&settings)?
+            .enable(args.oui)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn disable(args: OrgOui) -> Result<Msg> {
+    let output = format!("Disabled org {}", args.oui);
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        OrgClient::from_settings(&settings)?
+            .disable(args.oui)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+fn org_json(res: OrgResV1) -> serde_json::Value {
+    let org = res.org.unwrap_or_default();
+    json!({
+        "oui": org.oui,
+        "owner": PublicKeyBinary::from(org.owner).to_string(),
+        "payer": PublicKeyBinary::from(org.payer).to_string(),
+        "delegate_keys": org
+            .delegate_keys
+            .into_iter()
+            .map(|key| PublicKeyBinary::from(key).to_string())
+            .collect::<Vec<_>>(),
+        "locked": org.locked,
+        "net_id": NetIdField::from(res.net_id).to_string(),
+        "devaddr_constraints": res
+            .devaddr_constraints
+            .iter()
+            .map(DevAddrConstraint::from)
+            .collect::<Vec<_>>(),
+    })
+}
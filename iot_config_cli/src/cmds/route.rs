@@ -0,0 +1,306 @@
+use crate::{client::ApiClient, proto::ActionV1, Msg, PrettyJson, Result};
+use anyhow::{anyhow, bail, Context};
+use iot_config::{
+    client::RouteClient,
+    lora_field::{DevAddrField, DevAddrRange, EuiField, EuiPair, Skf},
+    route::Route,
+    route_diff::{RouteDiff, RouteUpdate},
+    route_service::ValidatedDiff,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, path::Path, str::FromStr};
+
+use super::{
+    client_settings, require_config_pubkey, CreateRoute, DeleteRoute, GetRoute, ListRoutes,
+    PathBufKeypair, SyncRouteEntries, UpdateRoute, UpdateRouteEntries,
+};
+
+#[derive(Debug, serde::Deserialize)]
+struct EuiRow {
+    app_eui: String,
+    dev_eui: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DevAddrRangeRow {
+    start_addr: String,
+    end_addr: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SkfRow {
+    devaddr: String,
+    session_key: String,
+    max_copies: u32,
+}
+
+pub async fn list_routes(args: ListRoutes) -> Result<Msg> {
+    let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+    let routes = RouteClient::from_settings(&settings)?
+        .list(args.oui)
+        .await?;
+    Msg::ok(routes.pretty_json()?)
+}
+
+pub async fn get_route(args: GetRoute) -> Result<Msg> {
+    let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+    let route = RouteClient::from_settings(&settings)?
+        .get(&args.route_id)
+        .await?;
+    Msg::ok(route.pretty_json()?)
+}
+
+pub async fn create_route(args: CreateRoute) -> Result<Msg> {
+    let mut route = read_route(&args.route_file)?;
+    route.id = String::new();
+    route.oui = args.oui;
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        let route = RouteClient::from_settings(&settings)?
+            .create(args.oui, route)
+            .await?;
+        return Msg::ok(route.pretty_json()?);
+    }
+    Msg::dry_run(format!(
+        "Created route for org {}\n{}",
+        args.oui,
+        route.pretty_json()?
+    ))
+}
+
+pub async fn update_route(args: UpdateRoute) -> Result<Msg> {
+    let route = read_route(&args.route_file)?;
+    if route.id.is_empty() {
+        bail!("route file is missing the id of the route to update");
+    }
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        let route = RouteClient::from_settings(&settings)?.update(route).await?;
+        return Msg::ok(route.pretty_json()?);
+    }
+    Msg::dry_run(format!(
+        "Updated route {}\n{}",
+        route.id,
+        route.pretty_json()?
+    ))
+}
+
+pub async fn delete_route(args: DeleteRoute) -> Result<Msg> {
+    let output = format!("Deleted route {}", args.route_id);
+
+    if args.commit {
+        let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+        RouteClient::from_settings(&settings)?
+            .delete(&args.route_id)
+            .await?;
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+pub async fn update_euis(args: UpdateRouteEntries) -> Result<Msg> {
+    let euis = read_euis(&args.route_id, &args.file)?;
+    let output = format!(
+        "{} {} eui pairs of route {}",
+        action_verb(&args),
+        euis.len(),
+        args.route_id
+    );
+
+    // Dry runs are validated by the service without being committed
+    let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+    let validated = RouteClient::from_settings(&settings)?
+        .update_euis(args.action.into(), &euis, !args.commit)
+        .await?;
+    validated_msg(output, validated)
+}
+
+pub async fn update_devaddr_ranges(args: UpdateRouteEntries) -> Result<Msg> {
+    let ranges = read_devaddr_ranges(&args.route_id, &args.file)?;
+    let output = format!(
+        "{} {} devaddr ranges of route {}",
+        action_verb(&args),
+        ranges.len(),
+        args.route_id
+    );
+
+    // Dry runs are validated by the service without being committed
+    let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+    let validated = RouteClient::from_settings(&settings)?
+        .update_devaddr_ranges(args.action.into(), &ranges, !args.commit)
+        .await?;
+    validated_msg(output, validated)
+}
+
+pub async fn update_skfs(args: UpdateRouteEntries) -> Result<Msg> {
+    let action: ActionV1 = args.action.into();
+    let updates: Vec<(ActionV1, Skf)> = read_skfs(&args.route_id, &args.file)?
+        .into_iter()
+        .map(|skf| (action, skf))
+        .collect();
+    let output = format!(
+        "{} {} session key filters of route {}",
+        action_verb(&args),
+        updates.len(),
+        args.route_id
+    );
+
+    // Dry runs are validated by the service without being committed
+    let settings = client_settings(&args.config_host, &args.config_pubkey, &args.keypair)?;
+    let validated = RouteClient::from_settings(&settings)?
+        .update_skfs(&args.route_id, &updates, !args.commit)
+        .await?;
+    validated_msg(output, validated)
+}
+
+pub async fn sync_euis(args: SyncRouteEntries) -> Result<Msg> {
+    let update = route_update(&args, read_euis(&args.route_id, &args.file)?);
+    let diff = api_client(&args)?
+        .sync_euis(update, &args.keypair.to_keypair()?)
+        .await?;
+    diff_msg("eui pairs", diff)
+}
+
+pub async fn sync_devaddr_ranges(args: SyncRouteEntries) -> Result<Msg> {
+    let update = route_update(&args, read_devaddr_ranges(&args.route_id, &args.file)?);
+    let diff = api_client(&args)?
+        .sync_devaddr_ranges(update, &args.keypair.to_keypair()?)
+        .await?;
+    diff_msg("devaddr ranges", diff)
+}
+
+pub async fn sync_skfs(args: SyncRouteEntries) -> Result<Msg> {
+    let update = route_update(&args, read_skfs(&args.route_id, &args.file)?);
+    let diff = api_client(&args)?
+        .sync_skfs(update, &args.keypair.to_keypair()?)
+        .await?;
+    diff_msg("session key filters", diff)
+}
+
+fn api_client(args: &SyncRouteEntries) -> Result<ApiClient> {
+    ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)
+}
+
+fn route_update<T>(args: &SyncRouteEntries, entries: Vec<T>) -> RouteUpdate<T> {
+    RouteUpdate {
+        route_id: args.route_id.clone(),
+        mode: args.mode.into(),
+        commit: args.commit,
+        entries,
+    }
+}
+
+/// The changes computed by the config service, applied if committed
+fn diff_msg<T: Serialize>(entries: &str, diff: RouteDiff<T>) -> Result<Msg> {
+    let output = format!(
+        "Added {} and removed {} {entries} of route {}\n{}",
+        diff.adds.len(),
+        diff.removes.len(),
+        diff.route_id,
+        diff.pretty_json()?
+    );
+    if diff.committed {
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+fn validated_msg<T: Serialize>(output: String, validated: Option<ValidatedDiff<T>>) -> Result<Msg> {
+    let Some(diff) = validated else {
+        return Msg::ok(output);
+    };
+    Msg::dry_run(format!(
+        "{output}, adding {} and removing {}\n{}",
+        diff.adds.len(),
+        diff.removes.len(),
+        diff.pretty_json()?
+    ))
+}
+
+fn action_verb(args: &UpdateRouteEntries) -> &'static str {
+    match args.action {
+        crate::Action::Add => "Added",
+        crate::Action::Remove => "Removed",
+    }
+}
+
+fn read_route(path: &Path) -> Result<Route> {
+    let data = fs::read(path).context("reading route file")?;
+    serde_json::from_slice(&data).context("parsing route file")
+}
+
+fn read_euis(route_id: &str, path: &Path) -> Result<Vec<EuiPair>> {
+    read_rows::<EuiRow>(path)?
+        .into_iter()
+        .map(|row| {
+            Ok(EuiPair::new(
+                route_id.to_string(),
+                parse_field::<EuiField>("app_eui", &row.app_eui)?,
+                parse_field::<EuiField>("dev_eui", &row.dev_eui)?,
+            ))
+        })
+        .collect()
+}
+
+fn read_devaddr_ranges(route_id: &str, path: &Path) -> Result<Vec<DevAddrRange>> {
+    read_rows::<DevAddrRangeRow>(path)?
+        .into_iter()
+        .map(|row| {
+            let start_addr = parse_field::<DevAddrField>("start_addr", &row.start_addr)?;
+            let end_addr = parse_field::<DevAddrField>("end_addr", &row.end_addr)?;
+            if end_addr < start_addr {
+                bail!("devaddr range {start_addr} - {end_addr} ends before it starts");
+            }
+            Ok(DevAddrRange::new(
+                route_id.to_string(),
+                start_addr,
+                end_addr,
+            ))
+        })
+        .collect()
+}
+
+fn read_skfs(route_id: &str, path: &Path) -> Result<Vec<Skf>> {
+    read_rows::<SkfRow>(path)?
+        .into_iter()
+        .map(|row| {
+            Ok(Skf::new(
+                route_id.to_string(),
+                parse_field::<DevAddrField>("devaddr", &row.devaddr)?,
+                row.session_key,
+                row.max_copies,
+            ))
+        })
+        .collect()
+}
+
+/// Read the rows of a csv file with a header row, or of a json array of
+/// objects, chosen by the file extension. Fields are read as strings so hex
+/// values are never mistaken for decimal numbers.
+fn read_rows<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => csv::Reader::from_path(path)
+            .context("reading csv file")?
+            .deserialize()
+            .collect::<std::result::Result<Vec<T>, _>>()
+            .context("parsing csv file"),
+        Some("json") => {
+            let data = fs::read(path).context("reading json file")?;
+            serde_json::from_slice(&data).context("parsing json file")
+        }
+        _ => bail!(
+            "unsupported file {}, expected a .csv or .json file",
+            path.display()
+        ),
+    }
+}
+
+fn parse_field<T>(name: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    T::from_str(value).map_err(|err| anyhow!("invalid {name} {value}: {err}"))
+}
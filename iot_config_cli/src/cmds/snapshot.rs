@@ -0,0 +1,61 @@
+use crate::{client::ApiClient, Msg, PrettyJson, Result};
+use anyhow::Context;
+use iot_config::{
+    api_server::{Signed, SnapshotImport},
+    snapshot::{Changes, Snapshot},
+};
+use std::fs;
+
+use super::{require_config_pubkey, ExportSnapshot, ImportSnapshot, PathBufKeypair};
+
+pub async fn export(args: ExportSnapshot) -> Result<Msg> {
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let snapshot = client.export_snapshot(&args.keypair.to_keypair()?).await?;
+    fs::write(&args.out_file, snapshot.pretty_json()?).context("writing snapshot file")?;
+    let snapshot = snapshot.request;
+    Msg::ok(format!(
+        "Exported {} orgs, {} routes, {} eui pairs, {} devaddr ranges and {} session key filters at change {} to {}",
+        snapshot.orgs.len(),
+        snapshot.routes.len(),
+        snapshot.euis.len(),
+        snapshot.devaddr_ranges.len(),
+        snapshot.skfs.len(),
+        snapshot.change_seq,
+        args.out_file.display()
+    ))
+}
+
+pub async fn import(args: ImportSnapshot) -> Result<Msg> {
+    let data = fs::read(&args.file).context("reading snapshot file")?;
+    let snapshot: Signed<Snapshot> = serde_json::from_slice(&data).context("parsing snapshot")?;
+    let client = ApiClient::new(&args.api_url, require_config_pubkey(&args.config_pubkey)?)?;
+    let import = SnapshotImport {
+        snapshot,
+        commit: args.commit,
+    };
+    let diff = client
+        .import_snapshot(import, &args.keypair.to_keypair()?)
+        .await?;
+    let output = format!(
+        "orgs: {}\nroutes: {}\neui pairs: {}\ndevaddr ranges: {}\nsession key filters: {}\n{}",
+        summary(&diff.orgs),
+        summary(&diff.routes),
+        summary(&diff.euis),
+        summary(&diff.devaddr_ranges),
+        summary(&diff.skfs),
+        diff.pretty_json()?
+    );
+    if diff.committed {
+        return Msg::ok(output);
+    }
+    Msg::dry_run(output)
+}
+
+fn summary<T>(changes: &Changes<T>) -> String {
+    format!(
+        "{} added, {} updated, {} removed",
+        changes.added.len(),
+        changes.updated.len(),
+        changes.removed.len()
+    )
+}
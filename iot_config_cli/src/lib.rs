@@ -0,0 +1,196 @@
+pub mod client;
+pub mod cmds;
+
+use anyhow::Error;
+use iot_config::lora_field::{LoraField, NetIdField};
+use serde::Serialize;
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub mod proto {
+    pub use helium_proto::services::iot_config::{
+        admin_add_key_req_v1::KeyTypeV1, org_create_helium_req_v1::HeliumNetId, ActionV1,
+    };
+}
+
+pub type Result<T = (), E = Error> = anyhow::Result<T, E>;
+
+#[derive(Debug, Serialize)]
+pub enum Msg {
+    DryRun(String),
+    Success(String),
+    Error(String),
+}
+
+impl Msg {
+    pub fn ok(msg: String) -> Result<Self> {
+        Ok(Self::Success(msg))
+    }
+
+    pub fn err(msg: String) -> Result<Self> {
+        Ok(Self::Error(msg))
+    }
+
+    pub fn dry_run(msg: String) -> Result<Self> {
+        Ok(Self::DryRun(msg))
+    }
+
+    pub fn into_inner(self) -> String {
+        match self {
+            Msg::DryRun(s) => s,
+            Msg::Success(s) => s,
+            Msg::Error(s) => s,
+        }
+    }
+}
+
+impl Display for Msg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Msg::DryRun(msg) => write!(f, "== DRY RUN == (pass `--commit`)\n{msg}"),
+            Msg::Success(msg) => write!(f, "{msg}"),
+            Msg::Error(msg) => write!(f, "\u{2717} {msg}"),
+        }
+    }
+}
+
+pub fn current_timestamp() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+pub trait PrettyJson {
+    fn print_pretty_json(&self) -> Result;
+    fn pretty_json(&self) -> Result<String>;
+}
+
+impl<S: ?Sized + serde::Serialize> PrettyJson for S {
+    fn print_pretty_json(&self) -> Result {
+        println!("{}", self.pretty_json()?);
+        Ok(())
+    }
+
+    fn pretty_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self).map_err(|e| e.into())
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Serialize)]
+pub enum KeyType {
+    #[value(alias("admin"))]
+    Administrator,
+    #[value(alias("pr"))]
+    PacketRouter,
+    Oracle,
+}
+
+impl From<KeyType> for proto::KeyTypeV1 {
+    fn from(value: KeyType) -> Self {
+        match value {
+            KeyType::Administrator => Self::Administrator,
+            KeyType::PacketRouter => Self::PacketRouter,
+            KeyType::Oracle => Self::Oracle,
+        }
+    }
+}
+
+impl From<KeyType> for i32 {
+    fn from(value: KeyType) -> Self {
+        proto::KeyTypeV1::from(value) as i32
+    }
+}
+
+impl Display for KeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyType::Administrator => write!(f, "administrator"),
+            KeyType::PacketRouter => write!(f, "packet router"),
+            KeyType::Oracle => write!(f, "oracle"),
+        }
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Serialize)]
+pub enum HeliumNetId {
+    #[value(alias("0x00003c"))]
+    Type0,
+    #[value(alias("0x60002d"))]
+    Type3,
+    #[value(alias("0xc00053"))]
+    Type6,
+}
+
+impl From<HeliumNetId> for proto::HeliumNetId {
+    fn from(value: HeliumNetId) -> Self {
+        match value {
+            HeliumNetId::Type0 => Self::Type00x00003c,
+            HeliumNetId::Type3 => Self::Type30x60002d,
+            HeliumNetId::Type6 => Self::Type60xc00053,
+        }
+    }
+}
+
+impl From<HeliumNetId> for NetIdField {
+    fn from(value: HeliumNetId) -> Self {
+        match value {
+            HeliumNetId::Type0 => LoraField(0x00003c),
+            HeliumNetId::Type3 => LoraField(0x60002d),
+            HeliumNetId::Type6 => LoraField(0xc00053),
+        }
+    }
+}
+
+impl Display for HeliumNetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeliumNetId::Type0 => write!(f, "type 0 (0x00003c)"),
+            HeliumNetId::Type3 => write!(f, "type 3 (0x60002d)"),
+            HeliumNetId::Type6 => write!(f, "type 6 (0xc00053)"),
+        }
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Serialize)]
+pub enum Action {
+    Add,
+    Remove,
+}
+
+impl From<Action> for proto::ActionV1 {
+    fn from(value: Action) -> Self {
+        match value {
+            Action::Add => Self::Add,
+            Action::Remove => Self::Remove,
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Add => write!(f, "add"),
+            Action::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Serialize)]
+pub enum SyncMode {
+    /// Add the entries to the route
+    Add,
+    /// Remove the entries from the route
+    Remove,
+    /// Make the entries the route's complete list, removing any others
+    Set,
+}
+
+impl From<SyncMode> for iot_config::route_diff::UpdateMode {
+    fn from(value: SyncMode) -> Self {
+        match value {
+            SyncMode::Add => Self::Add,
+            SyncMode::Remove => Self::Remove,
+            SyncMode::Set => Self::Set,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use clap::Parser;
+use iot_config_cli::{
+    cmds::{self, admin, audit, devaddr, env, org, route, snapshot, Cli, Commands},
+    Msg, Result,
+};
+
+#[tokio::main]
+async fn main() -> Result {
+    let cli = Cli::parse();
+
+    if cli.print_command {
+        println!("{cli:#?}");
+    }
+
+    let msg = handle_cli(cli).await?;
+    println!("{msg}");
+
+    Ok(())
+}
+
+pub async fn handle_cli(cli: Cli) -> Result<Msg> {
+    match cli.command {
+        Commands::Env { command } => match command {
+            cmds::EnvCommands::Init => env::env_init().await,
+            cmds::EnvCommands::Info(args) => env::env_info(args),
+            cmds::EnvCommands::GenerateKeypair(args) => env::generate_keypair(args),
+        },
+        Commands::Admin { command } => match command {
+            cmds::AdminCommands::AddKey(args) => admin::add_key(args).await,
+            cmds::AdminCommands::RemoveKey(args) => admin::remove_key(args).await,
+            cmds::AdminCommands::LoadRegion(args) => admin::load_region(args).await,
+            cmds::AdminCommands::RegionParams(args) => admin::region_params(args).await,
+            cmds::AdminCommands::RegionLookup(args) => admin::region_lookup(args).await,
+        },
+        Commands::Org { command } => match command {
+            cmds::OrgCommands::CreateHelium(args) => org::create_helium(args).await,
+            cmds::OrgCommands::CreateRoamer(args) => org::create_roamer(args).await,
+            cmds::OrgCommands::Enable(args) => org::enable(args).await,
+            cmds::OrgCommands::Disable(args) => org::disable(args).await,
+        },
+        Commands::Route { command } => match command {
+            cmds::RouteCommands::List(args) => route::list_routes(args).await,
+            cmds::RouteCommands::Get(args) => route::get_route(args).await,
+            cmds::RouteCommands::Create(args) => route::create_route(args).await,
+            cmds::RouteCommands::Update(args) => route::update_route(args).await,
+            cmds::RouteCommands::Delete(args) => route::delete_route(args).await,
+            cmds::RouteCommands::UpdateEuis(args) => route::update_euis(args).await,
+            cmds::RouteCommands::UpdateDevaddrRanges(args) => {
+                route::update_devaddr_ranges(args).await
+            }
+            cmds::RouteCommands::UpdateSkfs(args) => route::update_skfs(args).await,
+            cmds::RouteCommands::SyncEuis(args) => route::sync_euis(args).await,
+            cmds::RouteCommands::SyncDevaddrRanges(args) => route::sync_devaddr_ranges(args).await,
+            cmds::RouteCommands::SyncSkfs(args) => route::sync_skfs(args).await,
+        },
+        Commands::Audit(args) => audit::query(args).await,
+        Commands::Devaddr { command } => match command {
+            cmds::DevaddrCommands::Usage(args) => devaddr::usage(args).await,
+            cmds::DevaddrCommands::Reclaim(args) => devaddr::reclaim(args).await,
+            cmds::DevaddrCommands::Allocate(args) => devaddr::allocate(args).await,
+        },
+        Commands::Snapshot { command } => match command {
+            cmds::SnapshotCommands::Export(args) => snapshot::export(args).await,
+            cmds::SnapshotCommands::Import(args) => snapshot::import(args).await,
+        },
+    }
+}
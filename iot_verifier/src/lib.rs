@@ -11,6 +11,7 @@ pub mod poc;
 pub mod poc_report;
 pub mod purger;
 pub mod region_cache;
+pub mod reverify;
 pub mod reward_share;
 pub mod rewarder;
 pub mod runner;
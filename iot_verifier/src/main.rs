@@ -9,7 +9,7 @@ use file_store::{
 use iot_config::client::Client as IotConfigClient;
 use iot_verifier::{
     entropy_loader, gateway_cache::GatewayCache, gateway_updater::GatewayUpdater, loader,
-    packet_loader, purger, rewarder::Rewarder, runner, telemetry,
+    packet_loader, purger, reverify, rewarder::Rewarder, runner, telemetry,
     tx_scaler::Server as DensityScaler, Settings,
 };
 use price::PriceTracker;
@@ -42,12 +42,14 @@ impl Cli {
 #[derive(Debug, clap::Subcommand)]
 pub enum Cmd {
     Server(Server),
+    Reverify(reverify::Cmd),
 }
 
 impl Cmd {
     pub async fn run(&self, settings: Settings) -> Result<()> {
         match self {
             Self::Server(cmd) => cmd.run(&settings).await,
+            Self::Reverify(cmd) => cmd.run(&settings).await,
         }
     }
 }
@@ -279,6 +281,22 @@ impl Server {
         .create()
         .await?;
 
+        // poc diagnostics are only written when explaining verifications
+        let (runner_diagnostics_sink, runner_diagnostics_sink_server) = if settings.explain {
+            let (sink, server) = file_sink::FileSinkBuilder::new(
+                FileType::IotPocDiagnostics,
+                store_base_path,
+                concat!(env!("CARGO_PKG_NAME"), "_poc_diagnostics"),
+            )
+            .file_upload(Some(file_upload.clone()))
+            .roll_time(ChronoDuration::minutes(5))
+            .create()
+            .await?;
+            (Some(sink), Some(server))
+        } else {
+            (None, None)
+        };
+
         let runner = runner::Runner::from_settings(
             settings,
             iot_config_client.clone(),
@@ -288,10 +306,11 @@ impl Server {
             runner_invalid_witness_sink,
             runner_poc_sink,
             density_scaler.hex_density_map.clone(),
+            runner_diagnostics_sink,
         )
         .await?;
 
-        TaskManager::builder()
+        let mut task_manager = TaskManager::builder()
             .add_task(file_upload_server)
             .add_task(gateway_rewards_sink_server)
             .add_task(reward_manifests_sink_server)
@@ -311,9 +330,11 @@ impl Server {
             .add_task(loader)
             .add_task(pk_loader_server)
             .add_task(entropy_loader_server)
-            .add_task(rewarder)
-            .start()
-            .await
+            .add_task(rewarder);
+        if let Some(runner_diagnostics_sink_server) = runner_diagnostics_sink_server {
+            task_manager = task_manager.add_task(runner_diagnostics_sink_server);
+        }
+        task_manager.start().await
     }
 }
 
@@ -6,11 +6,13 @@ use crate::{
     last_beacon::{LastBeacon, LastBeaconError},
     region_cache::{RegionCache, RegionCacheError},
 };
+use base64::Engine;
 use beacon;
 use chrono::{DateTime, Duration, DurationRound, Utc};
 use denylist::denylist::DenyList;
 use file_store::{
     iot_beacon_report::{IotBeaconIngestReport, IotBeaconReport},
+    iot_poc_diagnostics::VerificationCheck,
     iot_valid_poc::IotVerifiedWitnessReport,
     iot_witness_report::IotWitnessIngestReport,
 };
@@ -28,8 +30,8 @@ use iot_config::{
 };
 use lazy_static::lazy_static;
 use rust_decimal::Decimal;
-use sqlx::PgPool;
-use std::{convert::Infallible, f64::consts::PI};
+use serde::Serialize;
+use std::{collections::BTreeMap, convert::Infallible, f64::consts::PI, fmt::Display};
 
 pub type GenericVerifyResult<T = ()> = std::result::Result<T, InvalidResponse>;
 
@@ -64,6 +66,26 @@ pub struct InvalidResponse {
     details: Option<InvalidDetails>,
 }
 
+/// The outcome of a single verification check and the values it was
+/// evaluated with
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CheckOutcome {
+    pub check: &'static str,
+    pub passed: bool,
+    /// the invalid reason a failed check renders the report with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub values: BTreeMap<&'static str, String>,
+}
+
+/// The checks run by a verification
+pub struct Trace {
+    /// the outcome of each check, recorded when explaining
+    outcomes: Option<Vec<CheckOutcome>>,
+    /// the first failed check when explaining, as every check is run
+    failure: Option<InvalidResponse>,
+}
+
 pub struct Poc {
     beacon_report: IotBeaconIngestReport,
     witness_reports: Vec<IotWitnessIngestReport>,
@@ -78,6 +100,8 @@ pub struct VerifyBeaconResult {
     pub invalid_details: Option<InvalidDetails>,
     pub gateway_info: Option<GatewayInfo>,
     pub hex_scale: Option<Decimal>,
+    /// the outcome of each check when explained
+    pub checks: Vec<CheckOutcome>,
 }
 
 pub struct VerifyWitnessesResult {
@@ -122,13 +146,15 @@ impl Poc {
         hex_density_map: &HexDensityMap,
         gateway_cache: &GatewayCache,
         region_cache: &RegionCache<G>,
-        pool: &PgPool,
+        last_beacon: Option<LastBeacon>,
         beacon_interval: Duration,
         deny_list: &DenyList,
+        explain: bool,
     ) -> Result<VerifyBeaconResult, VerificationError<G::Error>>
     where
         G: Gateways,
     {
+        let mut trace = Trace::new(explain);
         let beacon = &self.beacon_report.report;
         let beaconer_pub_key = beacon.pub_key.clone();
         // get the beaconer info from our follower
@@ -136,29 +162,27 @@ impl Poc {
         let beaconer_info = match gateway_cache.resolve_gateway_info(&beaconer_pub_key).await {
             Ok(res) => res,
             Err(GatewayCacheError::GatewayNotFound(_)) => {
-                return Ok(VerifyBeaconResult::gateway_not_found())
-            }
-        };
-        let beaconer_metadata = match beaconer_info.metadata {
-            Some(ref metadata) => metadata,
-            None => {
-                return Ok(VerifyBeaconResult::invalid(
-                    InvalidReason::NotAsserted,
-                    None,
-                    beaconer_info,
-                ))
+                trace.record("gateway_info", &gateway_not_found(), || {
+                    [("pub_key", beaconer_pub_key.to_string())]
+                });
+                return Ok(VerifyBeaconResult::gateway_not_found().with_checks(trace));
             }
         };
-        let beaconer_region_info = match region_cache
-            .resolve_region_info(beaconer_metadata.region)
-            .await
-        {
-            Ok(res) => res,
-            Err(err) => return Err(VerificationError::RegionCache(err)),
+        // region params are only resolved for an asserted beaconer,
+        // the verifications render a beaconer without a location invalid
+        let beaconer_location = beaconer_info
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.location);
+        let beaconer_region_params = match beaconer_info.metadata {
+            Some(ref metadata) => match region_cache.resolve_region_info(metadata.region).await {
+                Ok(res) => res.region_params,
+                Err(err) => return Err(VerificationError::RegionCache(err)),
+            },
+            None => vec![],
         };
         // we have beaconer info, proceed to verifications
-        let last_beacon = LastBeacon::get(pool, beaconer_pub_key.as_ref()).await?;
-        match do_beacon_verifications(
+        let result = match do_beacon_verifications(
             deny_list,
             self.entropy_start,
             self.entropy_end,
@@ -166,30 +190,36 @@ impl Poc {
             last_beacon,
             &self.beacon_report,
             &beaconer_info,
-            &beaconer_region_info.region_params,
+            &beaconer_region_params,
             beacon_interval,
+            &mut trace,
         ) {
             Ok(()) => {
-                let tx_scale = hex_density_map
-                    .get(beaconer_metadata.location)
-                    .await
-                    .unwrap_or(*DEFAULT_TX_SCALE);
-                Ok(VerifyBeaconResult::valid(beaconer_info, tx_scale))
+                let tx_scale = match beaconer_location {
+                    Some(location) => hex_density_map.get(location).await,
+                    None => None,
+                }
+                .unwrap_or(*DEFAULT_TX_SCALE);
+                VerifyBeaconResult::valid(beaconer_info, tx_scale)
             }
-            Err(invalid_response) => Ok(VerifyBeaconResult::invalid(
+            Err(invalid_response) => VerifyBeaconResult::invalid(
                 invalid_response.reason,
                 invalid_response.details,
                 beaconer_info,
-            )),
-        }
+            ),
+        };
+        Ok(result.with_checks(trace))
     }
 
+    /// Verify the witnesses of a valid beacon. When explaining, every check of
+    /// each witness is run and its outcome recorded in the witness's checks
     pub async fn verify_witnesses(
         &mut self,
         beacon_info: &GatewayInfo,
         hex_density_map: &HexDensityMap,
         gateway_cache: &GatewayCache,
         deny_list: &DenyList,
+        explain: bool,
     ) -> Result<VerifyWitnessesResult, VerificationError<Infallible>> {
         let mut verified_witnesses: Vec<IotVerifiedWitnessReport> = Vec::new();
         let mut failed_witnesses: Vec<IotWitnessIngestReport> = Vec::new();
@@ -211,6 +241,7 @@ impl Poc {
                             gateway_cache,
                             hex_density_map,
                             witness_earliest_received_ts,
+                            explain,
                         )
                         .await
                     {
@@ -245,6 +276,7 @@ impl Poc {
         Ok(resp)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn verify_witness(
         &mut self,
         deny_list: &DenyList,
@@ -253,14 +285,19 @@ impl Poc {
         gateway_cache: &GatewayCache,
         hex_density_map: &HexDensityMap,
         witness_first_ts: DateTime<Utc>,
+        explain: bool,
     ) -> Result<IotVerifiedWitnessReport, VerificationError<Infallible>> {
+        let mut trace = Trace::new(explain);
         let witness = &witness_report.report;
         let witness_pub_key = witness.pub_key.clone();
         // pull the witness info from our follower
         let witness_info = match gateway_cache.resolve_gateway_info(&witness_pub_key).await {
             Ok(res) => res,
             Err(GatewayCacheError::GatewayNotFound(_)) => {
-                return Ok(IotVerifiedWitnessReport::invalid(
+                trace.record("gateway_info", &gateway_not_found(), || {
+                    [("pub_key", witness_pub_key.to_string())]
+                });
+                let mut invalid_witness = IotVerifiedWitnessReport::invalid(
                     InvalidReason::GatewayNotFound,
                     None,
                     &witness_report.report,
@@ -270,26 +307,12 @@ impl Poc {
                     0,
                     0,
                     InvalidParticipantSide::Witness,
-                ));
+                );
+                invalid_witness.checks = trace.into_checks();
+                return Ok(invalid_witness);
             }
         };
 
-        let witness_metadata = match witness_info.metadata {
-            Some(ref metadata) => metadata,
-            None => {
-                return Ok(IotVerifiedWitnessReport::invalid(
-                    InvalidReason::NotAsserted,
-                    None,
-                    &witness_report.report,
-                    witness_report.received_timestamp,
-                    None,
-                    // if location is None, default gain and elevation to zero
-                    0,
-                    0,
-                    InvalidParticipantSide::Witness,
-                ));
-            }
-        };
         // to avoid assuming beaconer location is set and to avoid unwrap
         // we explicitly match location here again
         let Some(ref beaconer_metadata) = beaconer_info.metadata else {
@@ -305,8 +328,14 @@ impl Poc {
                 InvalidParticipantSide::Beaconer,
             ));
         };
+        // the verifications render a witness without a location invalid,
+        // if location is None, default gain and elevation to zero
+        let (witness_location, witness_gain, witness_elevation) = match witness_info.metadata {
+            Some(ref metadata) => (Some(metadata.location), metadata.gain, metadata.elevation),
+            None => (None, 0, 0),
+        };
         // run the witness verifications
-        match do_witness_verifications(
+        let mut verified_witness = match do_witness_verifications(
             deny_list,
             self.entropy_start,
             self.entropy_end,
@@ -315,35 +344,40 @@ impl Poc {
             &self.beacon_report,
             beaconer_metadata,
             witness_first_ts,
+            &mut trace,
         ) {
             Ok(()) => {
                 let tx_scale = hex_density_map
                     .get(beaconer_metadata.location)
                     .await
                     .unwrap_or(*DEFAULT_TX_SCALE);
-                Ok(IotVerifiedWitnessReport::valid(
+                IotVerifiedWitnessReport::valid(
                     &witness_report.report,
                     witness_report.received_timestamp,
-                    Some(witness_metadata.location),
-                    witness_metadata.gain,
-                    witness_metadata.elevation,
+                    witness_location,
+                    witness_gain,
+                    witness_elevation,
                     tx_scale,
-                ))
+                )
             }
-            Err(invalid_response) => Ok(IotVerifiedWitnessReport::invalid(
+            Err(invalid_response) => IotVerifiedWitnessReport::invalid(
                 invalid_response.reason,
                 invalid_response.details,
                 &witness_report.report,
                 witness_report.received_timestamp,
-                Some(witness_metadata.location),
-                witness_metadata.gain,
-                witness_metadata.elevation,
+                witness_location,
+                witness_gain,
+                witness_elevation,
                 InvalidParticipantSide::Witness,
-            )),
-        }
+            ),
+        };
+        verified_witness.checks = trace.into_checks();
+        Ok(verified_witness)
     }
 }
 
+/// Run the beacon verifications, stopping at the first failure unless the
+/// trace is explaining them
 #[allow(clippy::too_many_arguments)]
 pub fn do_beacon_verifications(
     deny_list: &DenyList,
@@ -355,33 +389,82 @@ pub fn do_beacon_verifications(
     beaconer_info: &GatewayInfo,
     beaconer_region_params: &[BlockchainRegionParamV1],
     beacon_interval: Duration,
+    trace: &mut Trace,
 ) -> GenericVerifyResult {
     tracing::debug!(
         "verifying beacon from beaconer: {:?}",
         beaconer_info.address.clone()
     );
+    let beacon = &beacon_report.report;
     let beacon_received_ts = beacon_report.received_timestamp;
-    let beaconer_metadata = match beaconer_info.metadata {
-        Some(ref metadata) => metadata,
-        None => {
-            return Err(InvalidResponse {
-                reason: InvalidReason::NotAsserted,
-                details: None,
-            })
-        }
-    };
-    verify_denylist(&beacon_report.report.pub_key, deny_list)?;
-    verify_entropy(entropy_start, entropy_end, beacon_received_ts)?;
-    verify_gw_capability(beaconer_info.is_full_hotspot)?;
-    verify_beacon_schedule(&last_beacon, beacon_received_ts, beacon_interval)?;
-    verify_beacon_payload(
-        &beacon_report.report,
-        beaconer_metadata.region,
-        beaconer_region_params,
-        beaconer_metadata.gain,
-        entropy_start,
-        entropy_version as u32,
+    let beaconer_metadata = asserted(trace, beaconer_info)?;
+    trace.check(
+        "verify_denylist",
+        verify_denylist(&beacon.pub_key, deny_list),
+        || {
+            [
+                ("pub_key", beacon.pub_key.to_string()),
+                ("denylist_tag", deny_list.tag_name.to_string()),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_entropy",
+        verify_entropy(entropy_start, entropy_end, beacon_received_ts),
+        || {
+            [
+                ("received_ts", beacon_received_ts.to_string()),
+                ("entropy_start", entropy_start.to_string()),
+                ("entropy_end", entropy_end.to_string()),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_gw_capability",
+        verify_gw_capability(beaconer_info.is_full_hotspot),
+        || [("is_full_hotspot", beaconer_info.is_full_hotspot.to_string())],
+    )?;
+    trace.check(
+        "verify_beacon_schedule",
+        verify_beacon_schedule(&last_beacon, beacon_received_ts, beacon_interval),
+        || {
+            [
+                (
+                    "last_beacon_ts",
+                    last_beacon
+                        .as_ref()
+                        .map_or_else(|| "none".to_string(), |last| last.timestamp.to_string()),
+                ),
+                ("received_ts", beacon_received_ts.to_string()),
+                (
+                    "beacon_interval_secs",
+                    beacon_interval.num_seconds().to_string(),
+                ),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_beacon_payload",
+        verify_beacon_payload(
+            beacon,
+            beaconer_metadata.region,
+            beaconer_region_params,
+            beaconer_metadata.gain,
+            entropy_start,
+            entropy_version as u32,
+        ),
+        || {
+            [
+                ("region", beaconer_metadata.region.as_str_name().to_string()),
+                ("region_params", beaconer_region_params.len().to_string()),
+                ("gain", beaconer_metadata.gain.to_string()),
+                ("entropy_start", entropy_start.to_string()),
+                ("entropy_version", entropy_version.to_string()),
+                ("data", encode_data(&beacon.data)),
+            ]
+        },
     )?;
+    trace.finish()?;
     tracing::debug!(
         "valid beacon from beaconer: {:?}",
         beaconer_info.address.clone()
@@ -389,6 +472,9 @@ pub fn do_beacon_verifications(
     Ok(())
 }
 
+/// Run the witness verifications, stopping at the first failure unless the
+/// trace is explaining them. Checks can't run past a witness without an
+/// asserted location
 #[allow(clippy::too_many_arguments)]
 pub fn do_witness_verifications(
     deny_list: &DenyList,
@@ -399,59 +485,207 @@ pub fn do_witness_verifications(
     beacon_report: &IotBeaconIngestReport,
     beaconer_metadata: &GatewayMetadata,
     witness_first_ts: DateTime<Utc>,
+    trace: &mut Trace,
 ) -> GenericVerifyResult {
     tracing::debug!(
         "verifying witness from gateway: {:?}",
         witness_info.address.clone()
     );
-    let beacon_report = &beacon_report;
-    let witness_metadata = match witness_info.metadata {
-        Some(ref metadata) => metadata,
-        None => {
-            return Err(InvalidResponse {
-                reason: InvalidReason::NotAsserted,
-                details: None,
-            })
-        }
-    };
-    verify_denylist(&witness_report.report.pub_key, deny_list)?;
-    verify_edge_denylist(
-        &beacon_report.report.pub_key,
-        &witness_report.report.pub_key,
-        deny_list,
+    let beacon = &beacon_report.report;
+    let witness = &witness_report.report;
+    let witness_metadata = asserted(trace, witness_info)?;
+    trace.check(
+        "verify_denylist",
+        verify_denylist(&witness.pub_key, deny_list),
+        || {
+            [
+                ("pub_key", witness.pub_key.to_string()),
+                ("denylist_tag", deny_list.tag_name.to_string()),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_edge_denylist",
+        verify_edge_denylist(&beacon.pub_key, &witness.pub_key, deny_list),
+        || {
+            [
+                ("beaconer", beacon.pub_key.to_string()),
+                ("witness", witness.pub_key.to_string()),
+            ]
+        },
     )?;
-    verify_self_witness(
-        &beacon_report.report.pub_key,
-        &witness_report.report.pub_key,
+    trace.check(
+        "verify_self_witness",
+        verify_self_witness(&beacon.pub_key, &witness.pub_key),
+        || {
+            [
+                ("beaconer", beacon.pub_key.to_string()),
+                ("witness", witness.pub_key.to_string()),
+            ]
+        },
     )?;
-    verify_entropy(
-        entropy_start,
-        entropy_end,
-        witness_report.received_timestamp,
+    trace.check(
+        "verify_entropy",
+        verify_entropy(
+            entropy_start,
+            entropy_end,
+            witness_report.received_timestamp,
+        ),
+        || {
+            [
+                ("received_ts", witness_report.received_timestamp.to_string()),
+                ("entropy_start", entropy_start.to_string()),
+                ("entropy_end", entropy_end.to_string()),
+            ]
+        },
     )?;
-    verify_witness_lag(
-        beacon_report.received_timestamp,
-        witness_first_ts,
-        witness_report.received_timestamp,
+    trace.check(
+        "verify_witness_lag",
+        verify_witness_lag(
+            beacon_report.received_timestamp,
+            witness_first_ts,
+            witness_report.received_timestamp,
+        ),
+        || {
+            let (lag, max_permitted_lag) = witness_lag(
+                beacon_report.received_timestamp,
+                witness_first_ts,
+                witness_report.received_timestamp,
+            );
+            [
+                ("lag_ms", lag.num_milliseconds().to_string()),
+                (
+                    "max_permitted_lag_ms",
+                    max_permitted_lag.num_milliseconds().to_string(),
+                ),
+                (
+                    "beacon_received_ts",
+                    beacon_report.received_timestamp.to_string(),
+                ),
+                ("first_witness_ts", witness_first_ts.to_string()),
+                ("received_ts", witness_report.received_timestamp.to_string()),
+            ]
+        },
     )?;
-    verify_witness_data(&beacon_report.report.data, &witness_report.report.data)?;
-    verify_gw_capability(witness_info.is_full_hotspot)?;
-    verify_witness_freq(
-        beacon_report.report.frequency,
-        witness_report.report.frequency,
+    trace.check(
+        "verify_witness_data",
+        verify_witness_data(&beacon.data, &witness.data),
+        || {
+            [
+                ("beacon_data", encode_data(&beacon.data)),
+                ("witness_data", encode_data(&witness.data)),
+            ]
+        },
     )?;
-    verify_witness_region(beaconer_metadata.region, witness_metadata.region)?;
-    verify_witness_cell_distance(beaconer_metadata.location, witness_metadata.location)?;
-    verify_witness_distance(beaconer_metadata.location, witness_metadata.location)?;
-    verify_witness_rssi(
-        witness_report.report.signal,
-        witness_report.report.frequency,
-        beacon_report.report.tx_power,
-        beaconer_metadata.gain,
-        witness_metadata.gain,
-        beaconer_metadata.location,
-        witness_metadata.location,
+    trace.check(
+        "verify_gw_capability",
+        verify_gw_capability(witness_info.is_full_hotspot),
+        || [("is_full_hotspot", witness_info.is_full_hotspot.to_string())],
     )?;
+    trace.check(
+        "verify_witness_freq",
+        verify_witness_freq(beacon.frequency, witness.frequency),
+        || {
+            [
+                ("beacon_freq", beacon.frequency.to_string()),
+                ("witness_freq", witness.frequency.to_string()),
+                (
+                    "freq_delta",
+                    beacon.frequency.abs_diff(witness.frequency).to_string(),
+                ),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_witness_region",
+        verify_witness_region(beaconer_metadata.region, witness_metadata.region),
+        || {
+            [
+                (
+                    "beacon_region",
+                    beaconer_metadata.region.as_str_name().to_string(),
+                ),
+                (
+                    "witness_region",
+                    witness_metadata.region.as_str_name().to_string(),
+                ),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_witness_cell_distance",
+        verify_witness_cell_distance(beaconer_metadata.location, witness_metadata.location),
+        || {
+            [
+                (
+                    "cell_distance",
+                    display_result(calc_cell_distance(
+                        beaconer_metadata.location,
+                        witness_metadata.location,
+                    )),
+                ),
+                ("min_cell_distance", POC_CELL_DISTANCE_MINIMUM.to_string()),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_witness_distance",
+        verify_witness_distance(beaconer_metadata.location, witness_metadata.location),
+        || {
+            [
+                (
+                    "distance_m",
+                    display_result(calc_distance(
+                        beaconer_metadata.location,
+                        witness_metadata.location,
+                    )),
+                ),
+                ("max_distance_km", POC_DISTANCE_LIMIT.to_string()),
+            ]
+        },
+    )?;
+    trace.check(
+        "verify_witness_rssi",
+        verify_witness_rssi(
+            witness.signal,
+            witness.frequency,
+            beacon.tx_power,
+            beaconer_metadata.gain,
+            witness_metadata.gain,
+            beaconer_metadata.location,
+            witness_metadata.location,
+        ),
+        || {
+            let (fpsl, expected_rssi) =
+                match calc_distance(beaconer_metadata.location, witness_metadata.location) {
+                    Ok(distance) => (
+                        calc_fpsl(witness.frequency, distance).to_string(),
+                        calc_expected_rssi(
+                            beacon.tx_power,
+                            witness.frequency,
+                            distance,
+                            beaconer_metadata.gain,
+                            witness_metadata.gain,
+                        )
+                        .to_string(),
+                    ),
+                    Err(err) => (err.to_string(), err.to_string()),
+                };
+            [
+                (
+                    "observed_rssi_dbm",
+                    (witness.signal as f64 / 10.0).to_string(),
+                ),
+                ("expected_rssi_dbm", expected_rssi),
+                ("fpsl_db", fpsl),
+                ("beacon_tx_power", beacon.tx_power.to_string()),
+                ("beacon_gain", beaconer_metadata.gain.to_string()),
+                ("witness_gain", witness_metadata.gain.to_string()),
+                ("witness_freq", witness.frequency.to_string()),
+            ]
+        },
+    )?;
+    trace.finish()?;
     tracing::debug!(
         "valid witness from gateway: {:?}",
         witness_info.address.clone()
@@ -459,6 +693,146 @@ pub fn do_witness_verifications(
     Ok(())
 }
 
+impl Trace {
+    /// A trace of a verification. Explaining records the outcome of every
+    /// check and the values it was evaluated with, and runs every check
+    /// rather than stopping at the first failure
+    pub fn new(explain: bool) -> Self {
+        Self {
+            outcomes: explain.then(Vec::new),
+            failure: None,
+        }
+    }
+
+    pub fn into_outcomes(self) -> Vec<CheckOutcome> {
+        self.outcomes.unwrap_or_default()
+    }
+
+    fn into_checks(self) -> Vec<VerificationCheck> {
+        self.into_outcomes()
+            .into_iter()
+            .map(VerificationCheck::from)
+            .collect()
+    }
+
+    /// check the result of a verification. Unless explaining, a failure is
+    /// returned so checks stop at it. The values are only evaluated when
+    /// explaining
+    fn check<const N: usize>(
+        &mut self,
+        check: &'static str,
+        result: GenericVerifyResult,
+        values: impl FnOnce() -> [(&'static str, String); N],
+    ) -> GenericVerifyResult {
+        if self.outcomes.is_none() {
+            return result;
+        }
+        self.record(check, &result, values);
+        if let Err(invalid_response) = result {
+            self.failure.get_or_insert(invalid_response);
+        }
+        Ok(())
+    }
+
+    /// record the outcome of a check when explaining
+    fn record<const N: usize>(
+        &mut self,
+        check: &'static str,
+        result: &GenericVerifyResult,
+        values: impl FnOnce() -> [(&'static str, String); N],
+    ) {
+        let Some(outcomes) = &mut self.outcomes else {
+            return;
+        };
+        let mut values = BTreeMap::from(values());
+        let (passed, reason) = match result {
+            Ok(()) => (true, None),
+            Err(invalid_response) => {
+                if let Some(InvalidDetails {
+                    data: Some(invalid_details::Data::DenylistTag(tag)),
+                }) = &invalid_response.details
+                {
+                    values.insert("denied_by", tag.clone());
+                }
+                (false, Some(invalid_response.reason.as_str_name()))
+            }
+        };
+        outcomes.push(CheckOutcome {
+            check,
+            passed,
+            reason,
+            values,
+        });
+    }
+
+    /// the first failure of the checks run when explaining
+    fn finish(&mut self) -> GenericVerifyResult {
+        self.failure.take().map_or(Ok(()), Err)
+    }
+}
+
+impl From<CheckOutcome> for VerificationCheck {
+    fn from(outcome: CheckOutcome) -> Self {
+        Self {
+            check: outcome.check.to_string(),
+            passed: outcome.passed,
+            reason: outcome
+                .reason
+                .and_then(InvalidReason::from_str_name)
+                .unwrap_or(InvalidReason::ReasonNone),
+            values: outcome
+                .values
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+}
+
+/// check the gateway has an asserted location, returning its metadata if so
+fn asserted<'a>(
+    trace: &mut Trace,
+    gateway_info: &'a GatewayInfo,
+) -> GenericVerifyResult<&'a GatewayMetadata> {
+    let not_asserted = || InvalidResponse {
+        reason: InvalidReason::NotAsserted,
+        details: None,
+    };
+    let result = match gateway_info.metadata {
+        Some(_) => Ok(()),
+        None => Err(not_asserted()),
+    };
+    trace.check("asserted", result, || {
+        let location = gateway_info.metadata.as_ref().map_or_else(
+            || "none".to_string(),
+            |metadata| format!("{:x}", metadata.location),
+        );
+        [
+            ("pub_key", gateway_info.address.to_string()),
+            ("location", location),
+        ]
+    })?;
+    gateway_info.metadata.as_ref().ok_or_else(not_asserted)
+}
+
+fn gateway_not_found() -> GenericVerifyResult {
+    Err(InvalidResponse {
+        reason: InvalidReason::GatewayNotFound,
+        details: None,
+    })
+}
+
+fn display_result<T: Display, E: Display>(result: Result<T, E>) -> String {
+    match result {
+        Ok(value) => value.to_string(),
+        Err(err) => err.to_string(),
+    }
+}
+
+fn encode_data(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
 /// verify beaconer is permitted to beacon at this time
 fn verify_beacon_schedule(
     last_beacon: &Option<LastBeacon>,
@@ -510,20 +884,20 @@ fn verify_beacon_schedule(
 
 /// verify if gateway is on the deny list
 fn verify_denylist(pub_key: &PublicKeyBinary, deny_list: &DenyList) -> GenericVerifyResult {
-    if deny_list.contains_key(pub_key) {
+    if let Some(denied_by) = deny_list.key_denied_by(pub_key) {
         tracing::debug!(
             "report verification failed, reason: {:?}.
-            pubkey: {}, tagname: {}",
+            pubkey: {}, source: {}, tagname: {}, activated_at: {:?}",
             InvalidReason::Denied,
             pub_key,
-            deny_list.tag_name
+            denied_by.source,
+            denied_by.tag_name,
+            denied_by.activated_at
         );
         return Err(InvalidResponse {
             reason: InvalidReason::Denied,
             details: Some(InvalidDetails {
-                data: Some(invalid_details::Data::DenylistTag(
-                    deny_list.tag_name.to_string(),
-                )),
+                data: Some(invalid_details::Data::DenylistTag(denied_by.to_string())),
             }),
         });
     }
@@ -538,21 +912,21 @@ fn verify_edge_denylist(
     witness: &PublicKeyBinary,
     deny_list: &DenyList,
 ) -> GenericVerifyResult {
-    if deny_list.contains_edge(beaconer, witness) {
+    if let Some(denied_by) = deny_list.edge_denied_by(beaconer, witness) {
         tracing::debug!(
             "report verification failed, reason: {:?}.
-            beacon: {}, witness {}, tagname: {}",
+            beacon: {}, witness {}, source: {}, tagname: {}, activated_at: {:?}",
             InvalidReason::DeniedEdge,
             beaconer,
             witness,
-            deny_list.tag_name
+            denied_by.source,
+            denied_by.tag_name,
+            denied_by.activated_at
         );
         return Err(InvalidResponse {
             reason: InvalidReason::DeniedEdge,
             details: Some(InvalidDetails {
-                data: Some(invalid_details::Data::DenylistTag(
-                    deny_list.tag_name.to_string(),
-                )),
+                data: Some(invalid_details::Data::DenylistTag(denied_by.to_string())),
             }),
         });
     }
@@ -686,12 +1060,8 @@ fn verify_witness_lag(
     first_witness_ts: DateTime<Utc>,
     received_ts: DateTime<Utc>,
 ) -> GenericVerifyResult {
-    let (first_event_ts, max_permitted_lag) = if beacon_received_ts <= first_witness_ts {
-        (beacon_received_ts, *MAX_BEACON_TO_WITNESS_LAG)
-    } else {
-        (first_witness_ts, *MAX_WITNESS_LAG)
-    };
-    let this_witness_lag = received_ts - first_event_ts;
+    let (this_witness_lag, max_permitted_lag) =
+        witness_lag(beacon_received_ts, first_witness_ts, received_ts);
     if this_witness_lag > max_permitted_lag {
         tracing::debug!(
             reason = ?InvalidReason::TooLate,
@@ -708,6 +1078,21 @@ fn verify_witness_lag(
     Ok(())
 }
 
+/// the lag of a witness from the first received event of its poc and the
+/// max lag permitted from that event
+fn witness_lag(
+    beacon_received_ts: DateTime<Utc>,
+    first_witness_ts: DateTime<Utc>,
+    received_ts: DateTime<Utc>,
+) -> (Duration, Duration) {
+    let (first_event_ts, max_permitted_lag) = if beacon_received_ts <= first_witness_ts {
+        (beacon_received_ts, *MAX_BEACON_TO_WITNESS_LAG)
+    } else {
+        (first_witness_ts, *MAX_WITNESS_LAG)
+    };
+    (received_ts - first_event_ts, max_permitted_lag)
+}
+
 /// verify witness report is not in response to its own beacon
 fn verify_self_witness(
     beacon_pub_key: &PublicKeyBinary,
@@ -954,6 +1339,14 @@ impl VerifyBeaconResult {
             invalid_details,
             gateway_info,
             hex_scale,
+            checks: vec![],
+        }
+    }
+
+    fn with_checks(self, trace: Trace) -> Self {
+        Self {
+            checks: trace.into_outcomes(),
+            ..self
         }
     }
 
@@ -1000,7 +1393,10 @@ mod tests {
     use denylist::DenyList;
     use file_store::iot_beacon_report::IotBeaconReport;
     use file_store::iot_witness_report::IotWitnessReport;
+    use futures::StreamExt;
     use helium_proto::DataRate;
+    use iot_config::{client::RegionParamsInfo, gateway_info::GatewayInfoStream};
+    use std::collections::HashMap;
     use std::str::FromStr;
 
     const EU868_PARAMS: &[u8] = &[
@@ -1232,7 +1628,7 @@ mod tests {
             Err(InvalidResponse {
                 reason: InvalidReason::Denied,
                 details: Some(InvalidDetails {
-                    data: Some(invalid_details::Data::DenylistTag("0".to_string()))
+                    data: Some(invalid_details::Data::DenylistTag("0@static".to_string()))
                 }),
             }),
             verify_denylist(
@@ -1272,7 +1668,7 @@ mod tests {
             Err(InvalidResponse {
                 reason: InvalidReason::DeniedEdge,
                 details: Some(InvalidDetails {
-                    data: Some(invalid_details::Data::DenylistTag("0".to_string()))
+                    data: Some(invalid_details::Data::DenylistTag("0@static".to_string()))
                 }),
             }),
             verify_edge_denylist(
@@ -1286,7 +1682,7 @@ mod tests {
             Err(InvalidResponse {
                 reason: InvalidReason::DeniedEdge,
                 details: Some(InvalidDetails {
-                    data: Some(invalid_details::Data::DenylistTag("0".to_string()))
+                    data: Some(invalid_details::Data::DenylistTag("0@static".to_string()))
                 }),
             }),
             verify_edge_denylist(
@@ -1534,12 +1930,13 @@ mod tests {
             &beaconer_info,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
                 reason: InvalidReason::Denied,
                 details: Some(InvalidDetails {
-                    data: Some(invalid_details::Data::DenylistTag("0".to_string()))
+                    data: Some(invalid_details::Data::DenylistTag("0@static".to_string()))
                 }),
             }),
             resp1
@@ -1557,6 +1954,7 @@ mod tests {
             &beaconer_info,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1579,6 +1977,7 @@ mod tests {
             &beacon_info2,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1604,6 +2003,7 @@ mod tests {
             &beaconer_info,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1626,6 +2026,7 @@ mod tests {
             &beacon_info4,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1647,6 +2048,7 @@ mod tests {
             &beaconer_info,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1668,6 +2070,7 @@ mod tests {
             &beaconer_info,
             &default_region_params(),
             beacon_interval,
+            &mut Trace::new(false),
         );
         assert_eq!(Ok(()), resp6);
     }
@@ -1706,6 +2109,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report1.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1726,6 +2130,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report2.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1746,6 +2151,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report3.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1767,6 +2173,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report4.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1787,6 +2194,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report5.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1808,6 +2216,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report6.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1829,6 +2238,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report7.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1850,6 +2260,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report8.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1870,6 +2281,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report9.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1891,6 +2303,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report10.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1913,6 +2326,7 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report11.received_timestamp - Duration::milliseconds(6000),
+            &mut Trace::new(false),
         );
         assert_eq!(
             Err(InvalidResponse {
@@ -1934,10 +2348,226 @@ mod tests {
             &beacon_report,
             &beaconer_metadata,
             witness_report12.received_timestamp,
+            &mut Trace::new(false),
         );
         assert_eq!(Ok(()), resp12);
     }
 
+    #[test]
+    fn test_explain_witness_verifications() {
+        // explaining a witness verification must reach the same verdict,
+        // with the first failed check being the one that failed it
+        let beacon_report = valid_beacon_report(PUBKEY1, Utc::now() - Duration::minutes(2));
+        let beaconer_info = beaconer_gateway_info(Some(LOC0), ProtoRegion::Eu868, true);
+        let beaconer_metadata = beaconer_info
+            .metadata
+            .expect("beaconer should have metadata");
+        let witness_info = witness_gateway_info(Some(LOC4), ProtoRegion::Eu868, true);
+        let entropy_start = Utc.timestamp_millis_opt(1676381847900).unwrap();
+        let entropy_end = entropy_start + Duration::minutes(3);
+        let received_ts = entropy_start + Duration::minutes(2);
+        let deny_list: DenyList = vec![PublicKeyBinary::from_str(DENIED_PUBKEY1).unwrap()]
+            .try_into()
+            .unwrap();
+        let verify = |witness_report: &IotWitnessIngestReport, explain: bool| {
+            let mut trace = Trace::new(explain);
+            let verdict = do_witness_verifications(
+                &deny_list,
+                entropy_start,
+                entropy_end,
+                witness_report,
+                &witness_info,
+                &beacon_report,
+                &beaconer_metadata,
+                received_ts,
+                &mut trace,
+            );
+            (verdict, trace.into_outcomes())
+        };
+
+        let witness_reports = [
+            valid_witness_report(PUBKEY2, received_ts),
+            invalid_witness_self_witness(received_ts),
+            invalid_witness_bad_data(received_ts),
+            invalid_witness_bad_freq(received_ts),
+            invalid_witness_bad_rssi(received_ts),
+        ];
+        for witness_report in witness_reports.iter() {
+            let (verdict, unexplained) = verify(witness_report, false);
+            let (explained_verdict, checks) = verify(witness_report, true);
+            assert!(unexplained.is_empty());
+            assert_eq!(verdict, explained_verdict);
+            let first_failed = checks.iter().find(|check| !check.passed);
+            match verdict {
+                Ok(()) => assert!(first_failed.is_none()),
+                Err(invalid_response) => assert_eq!(
+                    Some(invalid_response.reason.as_str_name()),
+                    first_failed.and_then(|check| check.reason)
+                ),
+            }
+            assert_eq!("verify_witness_rssi", checks.last().unwrap().check);
+        }
+
+        let (_, checks) = verify(&invalid_witness_bad_rssi(received_ts), true);
+        let rssi = checks.last().unwrap();
+        assert!(!rssi.passed);
+        assert_eq!("30", rssi.values["observed_rssi_dbm"]);
+        assert!(rssi.values.contains_key("expected_rssi_dbm"));
+
+        // explaining runs every check past the first failure
+        let mut witness_report = invalid_witness_bad_freq(received_ts);
+        witness_report.report.signal = 300;
+        let (verdict, checks) = verify(&witness_report, true);
+        assert_eq!(
+            Err(InvalidResponse {
+                reason: InvalidReason::InvalidFrequency,
+                details: None,
+            }),
+            verdict
+        );
+        let failed: Vec<&str> = checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.check)
+            .collect();
+        assert_eq!(vec!["verify_witness_freq", "verify_witness_rssi"], failed);
+        let freq = checks
+            .iter()
+            .find(|check| check.check == "verify_witness_freq")
+            .unwrap();
+        assert_eq!("800000", freq.values["freq_delta"]);
+    }
+
+    #[derive(Clone)]
+    struct RegionParamsGateways;
+
+    #[async_trait::async_trait]
+    impl Gateways for RegionParamsGateways {
+        type Error = Infallible;
+
+        async fn resolve_gateway_info(
+            &mut self,
+            _address: &PublicKeyBinary,
+        ) -> Result<Option<GatewayInfo>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn stream_gateways_info(&mut self) -> Result<GatewayInfoStream, Self::Error> {
+            Ok(futures::stream::empty().boxed())
+        }
+
+        async fn resolve_region_params(
+            &mut self,
+            region: ProtoRegion,
+        ) -> Result<RegionParamsInfo, Self::Error> {
+            Ok(RegionParamsInfo {
+                region,
+                region_params: default_region_params(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_explain_beacon_verifications() {
+        // explaining a beacon verification, as reverify does, must reach the
+        // same verdict as the runner's verification, with the first failed
+        // check being the one that failed it
+        let entropy_start = Utc.timestamp_millis_opt(ENTROPY_TIMESTAMP).unwrap();
+        let received_ts = entropy_start + Duration::minutes(2);
+        let beacon_interval = Duration::seconds(21600);
+        let deny_list: DenyList = vec![PublicKeyBinary::from_str(DENIED_PUBKEY1).unwrap()]
+            .try_into()
+            .unwrap();
+        let region_cache =
+            RegionCache::new(std::time::Duration::from_secs(60), RegionParamsGateways).unwrap();
+        let beaconer_info = beaconer_gateway_info(Some(LOC0), ProtoRegion::Eu868, true);
+        let cases = [
+            (
+                valid_beacon_report(PUBKEY1, received_ts),
+                Some(beaconer_info.clone()),
+                None,
+            ),
+            (
+                valid_beacon_report(DENIED_PUBKEY1, received_ts),
+                Some(beaconer_info.clone()),
+                None,
+            ),
+            (
+                valid_beacon_report(PUBKEY1, entropy_start + Duration::minutes(4)),
+                Some(beaconer_info.clone()),
+                None,
+            ),
+            (
+                valid_beacon_report(PUBKEY1, received_ts),
+                Some(beaconer_gateway_info(None, ProtoRegion::Eu868, true)),
+                None,
+            ),
+            (
+                valid_beacon_report(PUBKEY1, received_ts),
+                Some(beaconer_info.clone()),
+                Some(received_ts - Duration::minutes(1)),
+            ),
+            (
+                valid_beacon_report(PUBKEY1, received_ts),
+                Some(beaconer_gateway_info(Some(LOC0), ProtoRegion::Eu868, false)),
+                None,
+            ),
+            (
+                invalid_beacon_bad_payload(received_ts),
+                Some(beaconer_info.clone()),
+                None,
+            ),
+            (valid_beacon_report(PUBKEY1, received_ts), None, None),
+        ];
+        for (beacon_report, beaconer_info, last_beacon) in cases {
+            let gateways: HashMap<PublicKeyBinary, GatewayInfo> = beaconer_info
+                .into_iter()
+                .map(|info| (beacon_report.report.pub_key.clone(), info))
+                .collect();
+            let gateway_cache = GatewayCache::new(tokio::sync::watch::channel(gateways).1);
+            let mut results = vec![];
+            for explain in [false, true] {
+                let mut poc = Poc::new(
+                    beacon_report.clone(),
+                    vec![],
+                    entropy_start,
+                    ENTROPY_VERSION,
+                )
+                .await;
+                let last_beacon = last_beacon.map(|timestamp| LastBeacon {
+                    id: vec![],
+                    timestamp,
+                });
+                results.push(
+                    poc.verify_beacon(
+                        &HexDensityMap::new(),
+                        &gateway_cache,
+                        &region_cache,
+                        last_beacon,
+                        beacon_interval,
+                        &deny_list,
+                        explain,
+                    )
+                    .await
+                    .unwrap(),
+                );
+            }
+            let (verified, explained) = (&results[0], &results[1]);
+            assert!(verified.checks.is_empty());
+            assert_eq!(verified.result, explained.result);
+            assert_eq!(verified.invalid_reason, explained.invalid_reason);
+            assert_eq!(verified.invalid_details, explained.invalid_details);
+            let first_failed = explained.checks.iter().find(|check| !check.passed);
+            match verified.result {
+                VerificationStatus::Valid => assert!(first_failed.is_none()),
+                VerificationStatus::Invalid => assert_eq!(
+                    Some(verified.invalid_reason.as_str_name()),
+                    first_failed.and_then(|check| check.reason)
+                ),
+            }
+        }
+    }
+
     fn beaconer_gateway_info(
         location: Option<u64>,
         region: ProtoRegion,
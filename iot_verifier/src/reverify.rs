@@ -0,0 +1,613 @@
+//! Offline re-verification of PoCs from the ingest reports, gateway info,
+//! region params and entropy they were originally verified against
+
+use crate::{
+    gateway_cache::GatewayCache,
+    hex_density::HexDensityMap,
+    last_beacon::LastBeacon,
+    poc::{CheckOutcome, Poc},
+    region_cache::RegionCache,
+    runner::{filter_witnesses, sort_and_split_witnesses},
+    Settings,
+};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use denylist::DenyList;
+use file_store::{
+    entropy_report::EntropyReport,
+    file_source,
+    iot_beacon_report::IotBeaconIngestReport,
+    iot_poc_diagnostics::VerificationCheck,
+    iot_witness_report::IotWitnessIngestReport,
+    traits::{MsgDecode, ReportId},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use helium_crypto::PublicKeyBinary;
+use helium_proto::{
+    services::poc_lora::{InvalidParticipantSide, VerificationStatus},
+    BlockchainRegionParamV1, BlockchainRegionParamsV1, Message, Region as ProtoRegion,
+};
+use iot_config::{
+    client::{Gateways, RegionParamsInfo},
+    gateway_info::{GatewayInfo, GatewayInfoStream, GatewayMetadata},
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf, str::FromStr, sync::Arc};
+use tokio::sync::watch;
+
+/// Re-run the verifications of one or more beacons and their witnesses from
+/// local ingest files and snapshots, printing the outcome of every check
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Beacon ingest report files
+    #[clap(long, required = true, num_args = 1..)]
+    beacons: Vec<PathBuf>,
+    /// Witness ingest report files
+    #[clap(long, num_args = 1..)]
+    witnesses: Vec<PathBuf>,
+    /// Entropy report files
+    #[clap(long, required = true, num_args = 1..)]
+    entropy: Vec<PathBuf>,
+    /// JSON snapshot of the info of the beaconers and witnesses
+    #[clap(long)]
+    gateways: PathBuf,
+    /// Region params snapshot, as written by `helium-config-service-cli admin
+    /// region-params`, given as `<region>=<path>`
+    #[clap(
+        long = "region-params",
+        required = true,
+        num_args = 1..,
+        value_parser = parse_region_params
+    )]
+    region_params: Vec<(ProtoRegion, PathBuf)>,
+    /// Base64 encoded poc id or packet data of the beacon to re-verify
+    #[clap(long)]
+    beacon_id: Option<String>,
+    /// Only re-verify beacons received at or after this time
+    #[clap(long)]
+    after: Option<NaiveDateTime>,
+    /// Only re-verify beacons received before this time
+    #[clap(long)]
+    before: Option<NaiveDateTime>,
+    /// Time of the beaconer's last beacon before the beacons re-verified,
+    /// beacon schedules are not checked if absent
+    #[clap(long)]
+    last_beacon: Option<NaiveDateTime>,
+    /// JSON map of hex encoded h3 cells to the density scale the verifier
+    /// applied to them at the time of the beacons, hex scales are not
+    /// reproduced if absent
+    #[clap(long)]
+    hex_density: Option<PathBuf>,
+}
+
+/// A gateway's info as of the time of the beacons re-verified
+#[derive(Debug, Deserialize)]
+pub struct GatewaySnapshot {
+    pub address: String,
+    /// Hex encoded h3 cell of the asserted location, if any
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub elevation: i32,
+    #[serde(default)]
+    pub gain: i32,
+    /// Region of the asserted location, eg: EU868
+    #[serde(default)]
+    pub region: Option<String>,
+    pub is_full_hotspot: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BeaconVerdict {
+    pub poc_id: String,
+    pub data: String,
+    pub pub_key: String,
+    pub received_timestamp: DateTime<Utc>,
+    pub status: Option<&'static str>,
+    pub invalid_reason: Option<&'static str>,
+    /// Why the beacon couldn't be verified, the verifier leaves such beacons
+    /// pending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unverifiable: Option<String>,
+    /// What of the original verification the inputs can't reproduce, the
+    /// verdict may differ from the original in those respects
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_reproduced: Vec<NotReproduced>,
+    /// Density scale of the beaconer's hex, when valid and reproduced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hex_scale: Option<String>,
+    pub checks: Vec<CheckOutcome>,
+    pub witnesses: Vec<WitnessVerdict>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WitnessVerdict {
+    pub pub_key: String,
+    pub received_timestamp: DateTime<Utc>,
+    pub status: &'static str,
+    pub invalid_reason: &'static str,
+    pub participant_side: &'static str,
+    /// Selected witnesses are the valid witnesses rewarded for the poc,
+    /// self witnesses are neither selected nor unselected
+    pub selection: &'static str,
+    /// Density scale of the beaconer's hex, when valid and reproduced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hex_scale: Option<String>,
+    pub checks: Vec<VerificationCheck>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct NotReproduced {
+    /// The checks or values affected
+    pub affects: &'static [&'static str],
+    pub reason: String,
+}
+
+const DENYLIST_CHECKS: &[&str] = &["verify_denylist", "verify_edge_denylist"];
+const HEX_SCALE: &[&str] = &["hex_scale"];
+
+struct Entropy {
+    start: DateTime<Utc>,
+    version: i32,
+}
+
+/// The gateway info and region params snapshots, resolved in place of the
+/// config service
+#[derive(Clone)]
+struct Snapshots {
+    gateways: Arc<HashMap<PublicKeyBinary, GatewayInfo>>,
+    region_params: Arc<HashMap<ProtoRegion, Vec<BlockchainRegionParamV1>>>,
+}
+
+/// The caches a poc is verified with, filled from the snapshots
+struct Verifier<'a> {
+    gateway_cache: GatewayCache,
+    region_cache: RegionCache<Snapshots>,
+    /// Only given hex densities are reproduced, the map is otherwise empty
+    hex_density_map: Option<HexDensityMap>,
+    deny_list: &'a DenyList,
+    beacon_interval: Duration,
+    max_witnesses_per_poc: usize,
+}
+
+impl Cmd {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        let deny_list = DenyList::new(&settings.denylist)?;
+        let snapshots = Snapshots {
+            gateways: Arc::new(self.gateways()?),
+            region_params: Arc::new(self.region_params()?),
+        };
+        let verifier = Verifier {
+            gateway_cache: GatewayCache::new(watch::channel(snapshots.gateways.as_ref().clone()).1),
+            region_cache: RegionCache::new(settings.region_params_refresh_interval(), snapshots)?,
+            hex_density_map: self.hex_density_map().await?,
+            deny_list: &deny_list,
+            beacon_interval: settings.beacon_interval()?,
+            max_witnesses_per_poc: settings.max_witnesses_per_poc as usize,
+        };
+        let entropy = self.entropy().await?;
+        let last_beacon = self
+            .last_beacon
+            .map(|timestamp| Utc.from_utc_datetime(&timestamp));
+
+        let mut beacons: Vec<IotBeaconIngestReport> = file_source::source(&self.beacons)
+            .map_err(anyhow::Error::from)
+            .and_then(|buf| async move { Ok(IotBeaconIngestReport::decode(buf)?) })
+            .try_filter(|beacon| futures::future::ready(self.selects(beacon)))
+            .try_collect()
+            .await?;
+        beacons.sort_by(|a, b| {
+            (a.received_timestamp, &a.report.data).cmp(&(b.received_timestamp, &b.report.data))
+        });
+        let mut witnesses: HashMap<Vec<u8>, Vec<IotWitnessIngestReport>> = HashMap::new();
+        let mut witness_stream = file_source::source(&self.witnesses);
+        while let Some(buf) = witness_stream.try_next().await? {
+            let witness = IotWitnessIngestReport::decode(buf)?;
+            witnesses
+                .entry(witness.report.data.clone())
+                .or_default()
+                .push(witness);
+        }
+
+        let mut verdicts: Vec<BeaconVerdict> = Vec::new();
+        for beacon in &beacons {
+            let mut beacon_witnesses = witnesses
+                .get(&beacon.report.data)
+                .cloned()
+                .unwrap_or_default();
+            beacon_witnesses.sort_by_key(|witness| {
+                (
+                    witness.received_timestamp,
+                    witness.report.pub_key.to_string(),
+                )
+            });
+            verdicts.push(
+                verifier
+                    .reverify(beacon, beacon_witnesses, &entropy, last_beacon)
+                    .await,
+            );
+        }
+        println!("{}", serde_json::to_string_pretty(&verdicts)?);
+        Ok(())
+    }
+
+    fn selects(&self, beacon: &IotBeaconIngestReport) -> bool {
+        let received = beacon.received_timestamp;
+        if let Some(beacon_id) = &self.beacon_id {
+            let poc_id = encode(&beacon.report.report_id(received));
+            if *beacon_id != poc_id && *beacon_id != encode(&beacon.report.data) {
+                return false;
+            }
+        }
+        self.after
+            .map_or(true, |after| received >= Utc.from_utc_datetime(&after))
+            && self
+                .before
+                .map_or(true, |before| received < Utc.from_utc_datetime(&before))
+    }
+
+    fn gateways(&self) -> Result<HashMap<PublicKeyBinary, GatewayInfo>> {
+        let snapshot = fs::read(&self.gateways).context("reading gateways snapshot")?;
+        let snapshot: Vec<GatewaySnapshot> =
+            serde_json::from_slice(&snapshot).context("decoding gateways snapshot")?;
+        snapshot
+            .into_iter()
+            .map(|gateway| {
+                let info = gateway.into_info()?;
+                Ok((info.address.clone(), info))
+            })
+            .collect()
+    }
+
+    fn region_params(&self) -> Result<HashMap<ProtoRegion, Vec<BlockchainRegionParamV1>>> {
+        self.region_params
+            .iter()
+            .map(|(region, path)| {
+                let params = fs::read(path)
+                    .with_context(|| format!("reading {} region params", region.as_str_name()))?;
+                let params = BlockchainRegionParamsV1::decode(params.as_slice())
+                    .with_context(|| format!("decoding {} region params", region.as_str_name()))?;
+                Ok((*region, params.region_params))
+            })
+            .collect()
+    }
+
+    async fn hex_density_map(&self) -> Result<Option<HexDensityMap>> {
+        let Some(path) = &self.hex_density else {
+            return Ok(None);
+        };
+        let densities = fs::read(path).context("reading hex density")?;
+        let densities: HashMap<String, String> =
+            serde_json::from_slice(&densities).context("decoding hex density")?;
+        let densities = densities
+            .into_iter()
+            .map(|(hex, scale)| {
+                let hex =
+                    u64::from_str_radix(&hex, 16).with_context(|| format!("invalid hex {hex}"))?;
+                let scale = Decimal::from_str(&scale)
+                    .with_context(|| format!("invalid scale {scale} of {hex:x}"))?;
+                Ok((hex, scale))
+            })
+            .collect::<Result<HashMap<u64, Decimal>>>()?;
+        let hex_density_map = HexDensityMap::new();
+        hex_density_map.swap(densities).await;
+        Ok(Some(hex_density_map))
+    }
+
+    async fn entropy(&self) -> Result<HashMap<Vec<u8>, Entropy>> {
+        let mut entropy = HashMap::new();
+        let mut entropy_stream = file_source::source(&self.entropy);
+        while let Some(buf) = entropy_stream.try_next().await? {
+            let report = EntropyReport::decode(buf)?;
+            entropy.insert(
+                report.data,
+                Entropy {
+                    start: report.timestamp,
+                    version: report.version as i32,
+                },
+            );
+        }
+        Ok(entropy)
+    }
+}
+
+impl GatewaySnapshot {
+    fn into_info(self) -> Result<GatewayInfo> {
+        let address = PublicKeyBinary::from_str(&self.address)
+            .with_context(|| format!("invalid gateway address {}", self.address))?;
+        let metadata = match (self.location, self.region) {
+            (Some(location), Some(region)) => Some(GatewayMetadata {
+                location: u64::from_str_radix(&location, 16)
+                    .with_context(|| format!("invalid location {location} of {address}"))?,
+                elevation: self.elevation,
+                gain: self.gain,
+                region: ProtoRegion::from_str_name(&region)
+                    .ok_or_else(|| anyhow!("invalid region {region} of {address}"))?,
+            }),
+            _ => None,
+        };
+        Ok(GatewayInfo {
+            address,
+            metadata,
+            is_full_hotspot: self.is_full_hotspot,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Gateways for Snapshots {
+    type Error = anyhow::Error;
+
+    async fn resolve_gateway_info(
+        &mut self,
+        address: &PublicKeyBinary,
+    ) -> Result<Option<GatewayInfo>, Self::Error> {
+        Ok(self.gateways.get(address).cloned())
+    }
+
+    async fn stream_gateways_info(&mut self) -> Result<GatewayInfoStream, Self::Error> {
+        let gateways: Vec<GatewayInfo> = self.gateways.values().cloned().collect();
+        Ok(stream::iter(gateways).boxed())
+    }
+
+    async fn resolve_region_params(
+        &mut self,
+        region: ProtoRegion,
+    ) -> Result<RegionParamsInfo, Self::Error> {
+        let region_params = self
+            .region_params
+            .get(&region)
+            .ok_or_else(|| anyhow!("no region params for {}", region.as_str_name()))?;
+        Ok(RegionParamsInfo {
+            region,
+            region_params: region_params.clone(),
+        })
+    }
+}
+
+impl Verifier<'_> {
+    /// Verify a beacon and its witnesses by the runner's verifications,
+    /// explained to record the outcome of every check
+    async fn reverify(
+        &self,
+        beacon: &IotBeaconIngestReport,
+        witnesses: Vec<IotWitnessIngestReport>,
+        entropy: &HashMap<Vec<u8>, Entropy>,
+        last_beacon: Option<DateTime<Utc>>,
+    ) -> BeaconVerdict {
+        let received = beacon.received_timestamp;
+        let mut verdict = BeaconVerdict {
+            poc_id: encode(&beacon.report.report_id(received)),
+            data: encode(&beacon.report.data),
+            pub_key: beacon.report.pub_key.to_string(),
+            received_timestamp: received,
+            status: None,
+            invalid_reason: None,
+            unverifiable: None,
+            not_reproduced: self.not_reproduced(received),
+            hex_scale: None,
+            checks: vec![],
+            witnesses: vec![],
+        };
+        let Some(entropy) = entropy.get(&beacon.report.remote_entropy) else {
+            verdict.unverifiable = Some("no entropy report matches the remote entropy".to_string());
+            return verdict;
+        };
+        let hex_density_map = self.hex_density_map.clone().unwrap_or_default();
+        let mut poc = Poc::new(
+            beacon.clone(),
+            witnesses.clone(),
+            entropy.start,
+            entropy.version,
+        )
+        .await;
+
+        let beacon_result = match poc
+            .verify_beacon(
+                &hex_density_map,
+                &self.gateway_cache,
+                &self.region_cache,
+                last_beacon.map(|timestamp| LastBeacon {
+                    id: vec![],
+                    timestamp,
+                }),
+                self.beacon_interval,
+                self.deny_list,
+                true,
+            )
+            .await
+        {
+            Ok(beacon_result) => beacon_result,
+            Err(err) => {
+                verdict.unverifiable = Some(format!("{err:?}"));
+                return verdict;
+            }
+        };
+        verdict.checks = beacon_result.checks;
+        verdict.hex_scale = self.hex_scale(beacon_result.hex_scale);
+        verdict.status = Some(beacon_result.result.as_str_name());
+        verdict.invalid_reason = Some(beacon_result.invalid_reason.as_str_name());
+        let (VerificationStatus::Valid, Some(beacon_info)) =
+            (beacon_result.result, beacon_result.gateway_info)
+        else {
+            // an invalid beacon renders every witness invalid
+            verdict.witnesses = witnesses
+                .iter()
+                .map(|witness| WitnessVerdict {
+                    pub_key: witness.report.pub_key.to_string(),
+                    received_timestamp: witness.received_timestamp,
+                    status: VerificationStatus::Invalid.as_str_name(),
+                    invalid_reason: beacon_result.invalid_reason.as_str_name(),
+                    participant_side: InvalidParticipantSide::Beaconer.as_str_name(),
+                    selection: "unselected",
+                    hex_scale: None,
+                    checks: vec![],
+                })
+                .collect();
+            return verdict;
+        };
+
+        let witnesses_result = match poc
+            .verify_witnesses(
+                &beacon_info,
+                &hex_density_map,
+                &self.gateway_cache,
+                self.deny_list,
+                true,
+            )
+            .await
+        {
+            Ok(witnesses_result) if witnesses_result.failed_witnesses.is_empty() => {
+                witnesses_result
+            }
+            Ok(witnesses_result) => {
+                verdict.unverifiable = Some(format!(
+                    "{} witnesses failed to verify",
+                    witnesses_result.failed_witnesses.len()
+                ));
+                return verdict;
+            }
+            Err(err) => {
+                verdict.unverifiable = Some(format!("{err:?}"));
+                return verdict;
+            }
+        };
+        let (mut selected, invalid) = filter_witnesses(witnesses_result.verified_witnesses);
+        let unselected = match sort_and_split_witnesses(&mut selected, self.max_witnesses_per_poc) {
+            Ok(unselected) => unselected,
+            Err(err) => {
+                verdict.unverifiable = Some(err.to_string());
+                return verdict;
+            }
+        };
+        let selections = [
+            (selected, "selected"),
+            (unselected, "unselected"),
+            (invalid, "unselected"),
+        ];
+        for (witnesses, selection) in selections {
+            for witness in witnesses {
+                verdict.witnesses.push(WitnessVerdict {
+                    pub_key: witness.report.pub_key.to_string(),
+                    received_timestamp: witness.received_timestamp,
+                    status: witness.status.as_str_name(),
+                    invalid_reason: witness.invalid_reason.as_str_name(),
+                    participant_side: witness.participant_side.as_str_name(),
+                    selection,
+                    hex_scale: match witness.status {
+                        VerificationStatus::Valid => self.hex_scale(Some(witness.hex_scale)),
+                        _ => None,
+                    },
+                    checks: witness.checks,
+                });
+            }
+        }
+        verdict
+    }
+
+    fn hex_scale(&self, hex_scale: Option<Decimal>) -> Option<String> {
+        self.hex_density_map
+            .as_ref()
+            .and(hex_scale)
+            .map(|hex_scale| hex_scale.to_string())
+    }
+
+    /// The checks a beacon received at `at` is not verified as it originally
+    /// was. Denylist checks are reproduced only if each source's loaded
+    /// filter is of the tag the verifier recorded applying at the time.
+    fn not_reproduced(&self, at: DateTime<Utc>) -> Vec<NotReproduced> {
+        let mut not_reproduced: Vec<NotReproduced> = self
+            .deny_list
+            .filters
+            .iter()
+            .filter_map(|source| {
+                let reason = match source.tag_at(at) {
+                    _ if source.filter.is_none() => {
+                        format!("no filter of denylist source {} is loaded", source.name)
+                    }
+                    Some(tag_name) if tag_name == source.tag_name => return None,
+                    Some(tag_name) => format!(
+                        "denylist source {} applied tag {tag_name} at the time, tag {} is loaded",
+                        source.name, source.tag_name
+                    ),
+                    None => format!(
+                        "no tag of denylist source {} is recorded at the time",
+                        source.name
+                    ),
+                };
+                Some(NotReproduced {
+                    affects: DENYLIST_CHECKS,
+                    reason,
+                })
+            })
+            .collect();
+        if self.hex_density_map.is_none() {
+            not_reproduced.push(NotReproduced {
+                affects: HEX_SCALE,
+                reason: "no hex density given".to_string(),
+            });
+        }
+        not_reproduced
+    }
+}
+
+fn parse_region_params(arg: &str) -> Result<(ProtoRegion, PathBuf)> {
+    let (region, path) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected <region>=<path>"))?;
+    let region =
+        ProtoRegion::from_str_name(region).ok_or_else(|| anyhow!("invalid region {region}"))?;
+    Ok((region, PathBuf::from(path)))
+}
+
+fn encode(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gateway_snapshot_into_info() {
+        let snapshot: Vec<GatewaySnapshot> = serde_json::from_str(
+            r#"[
+                {
+                    "address": "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf",
+                    "location": "8c3f5a6c1c6a5ff",
+                    "gain": 12,
+                    "elevation": 100,
+                    "region": "EU868",
+                    "is_full_hotspot": true
+                },
+                {
+                    "address": "11z69eJ3czc92k6snrfR9ek7g2uRWXosFbnG9v4bXgwhfUCivUo",
+                    "is_full_hotspot": false
+                }
+            ]"#,
+        )
+        .unwrap();
+        let infos: Vec<GatewayInfo> = snapshot
+            .into_iter()
+            .map(|gateway| gateway.into_info().unwrap())
+            .collect();
+
+        let metadata = infos[0].metadata.as_ref().expect("asserted gateway");
+        assert_eq!(0x8c3f5a6c1c6a5ff, metadata.location);
+        assert_eq!(ProtoRegion::Eu868, metadata.region);
+        assert_eq!(12, metadata.gain);
+        assert!(infos[1].metadata.is_none());
+        assert!(!infos[1].is_full_hotspot);
+    }
+
+    #[test]
+    fn region_params_arg() {
+        let (region, path) = parse_region_params("EU868=params/eu868").unwrap();
+        assert_eq!(ProtoRegion::Eu868, region);
+        assert_eq!(PathBuf::from("params/eu868"), path);
+        assert!(parse_region_params("EU868").is_err());
+        assert!(parse_region_params("XX999=params").is_err());
+    }
+}
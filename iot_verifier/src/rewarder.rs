@@ -76,14 +76,20 @@ impl Rewarder {
             let sleep_duration = if scheduler.should_reward(now) {
                 let iot_price = self
                     .price_tracker
-                    .price(&helium_proto::BlockchainTokenTypeV1::Iot)
+                    .epoch_price(
+                        &helium_proto::BlockchainTokenTypeV1::Iot,
+                        &scheduler.reward_period,
+                    )
                     .await?;
                 tracing::info!(
-                    "Rewarding for period: {:?} with iot_price: {iot_price}",
-                    scheduler.reward_period
+                    "Rewarding for period: {:?} with iot_price: {}, stale: {}",
+                    scheduler.reward_period,
+                    iot_price.price,
+                    iot_price.stale
                 );
                 if self.data_current_check(&scheduler.reward_period).await? {
-                    self.reward(&scheduler, Decimal::from(iot_price)).await?;
+                    self.reward(&scheduler, Decimal::from(iot_price.price))
+                        .await?;
                     scheduler.sleep_duration(Utc::now())?
                 } else {
                     tracing::info!(
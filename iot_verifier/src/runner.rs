@@ -14,6 +14,9 @@ use file_store::{
     file_sink::FileSinkClient,
     iot_beacon_report::IotBeaconIngestReport,
     iot_invalid_poc::{IotInvalidBeaconReport, IotInvalidWitnessReport},
+    iot_poc_diagnostics::{
+        IotPocDiagnostics, IotPocDiagnosticsV1, VerificationCheck, WitnessDiagnostics,
+    },
     iot_valid_poc::{IotPoc, IotValidBeaconReport, IotVerifiedWitnessReport},
     iot_witness_report::IotWitnessIngestReport,
     traits::{IngestId, MsgDecode, ReportId},
@@ -46,7 +49,6 @@ pub struct Runner<G> {
     pub max_witnesses_per_poc: u64,
     pub beacon_max_retries: u64,
     pub witness_max_retries: u64,
-    pub deny_list_latest_url: String,
     pub deny_list_trigger_interval: Duration,
     pub deny_list: DenyList,
     pub gateway_cache: GatewayCache,
@@ -55,6 +57,10 @@ pub struct Runner<G> {
     pub invalid_witness_sink: FileSinkClient,
     pub poc_sink: FileSinkClient,
     pub hex_density_map: HexDensityMap,
+    /// a sample of the pocs are explained when set, with the diagnostics of
+    /// their verifications written here
+    pub diagnostics_sink: Option<FileSinkClient>,
+    pub explain_sample_rate: f64,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -99,21 +105,22 @@ where
         invalid_witness_sink: FileSinkClient,
         poc_sink: FileSinkClient,
         hex_density_map: HexDensityMap,
+        diagnostics_sink: Option<FileSinkClient>,
     ) -> anyhow::Result<Self> {
         let beacon_interval = settings.beacon_interval()?;
         let max_witnesses_per_poc = settings.max_witnesses_per_poc;
         let beacon_max_retries = settings.beacon_max_retries;
         let witness_max_retries = settings.witness_max_retries;
-        let deny_list_latest_url = settings.denylist.denylist_url.clone();
         let mut deny_list = DenyList::new(&settings.denylist)?;
         let region_cache = RegionCache::new(settings.region_params_refresh_interval(), gateways)?;
         // force update to latest in order to update the tag name
         // when first run, the denylist will load the local filter
-        // but we dont save the tag name so it defaults to 0
+        // and restore the tag name from the saved tag history, if there
+        // is no history the tag name defaults to 0
         // updating it here forces the tag name to be refreshed
         // which will see it carry through to invalid poc reports
         // if we cant update such as github being down then ignore
-        match deny_list.update_to_latest(&deny_list_latest_url).await {
+        match deny_list.update_to_latest().await {
             Ok(()) => (),
             Err(err) => {
                 tracing::error!("error whilst updating denylist to latest: {err:?}");
@@ -128,13 +135,14 @@ where
             region_cache,
             beacon_max_retries,
             witness_max_retries,
-            deny_list_latest_url,
             deny_list_trigger_interval: settings.denylist.trigger_interval(),
             deny_list,
             invalid_beacon_sink,
             invalid_witness_sink,
             poc_sink,
             hex_density_map,
+            diagnostics_sink,
+            explain_sample_rate: settings.explain_sample_rate,
         })
     }
 
@@ -176,11 +184,7 @@ where
         // sink any errors whilst updating the denylist
         // the verifier should not stop just because github
         // could not be reached for example
-        match self
-            .deny_list
-            .update_to_latest(&self.deny_list_latest_url)
-            .await
-        {
+        match self.deny_list.update_to_latest().await {
             Ok(()) => (),
             Err(e) => tracing::warn!("failed to update denylist: {e}"),
         }
@@ -259,15 +263,22 @@ where
         )
         .await;
 
+        // decide up front whether to explain this poc, so only the sampled
+        // pocs pay for explaining their verifications
+        let explain =
+            self.diagnostics_sink.is_some() && rand::random::<f64>() < self.explain_sample_rate;
+
         // verify POC beacon
+        let last_beacon = LastBeacon::get(&self.pool, beacon.pub_key.as_ref()).await?;
         let beacon_verify_result = poc
             .verify_beacon(
                 &self.hex_density_map,
                 &self.gateway_cache,
                 &self.region_cache,
-                &self.pool,
+                last_beacon,
                 self.beacon_interval,
                 &self.deny_list,
+                explain,
             )
             .await?;
         match beacon_verify_result.result {
@@ -280,6 +291,7 @@ where
                             &self.hex_density_map,
                             &self.gateway_cache,
                             &self.deny_list,
+                            explain,
                         )
                         .await?;
                     // check if there are any failed witnesses
@@ -357,17 +369,25 @@ where
                         report: beacon.clone(),
                         reward_unit: beaconer_reward_units,
                     };
+                    let beacon_checks = explain.then(|| {
+                        beacon_verify_result
+                            .checks
+                            .into_iter()
+                            .map(VerificationCheck::from)
+                            .collect()
+                    });
                     self.handle_valid_poc(
                         valid_beacon_report,
                         selected_witnesses,
                         unselected_witnesses,
+                        beacon_checks,
                     )
                     .await?;
                 }
             }
             VerificationStatus::Invalid => {
                 // the beacon is invalid, which in turn renders all witnesses invalid
-                self.handle_invalid_poc(beacon_verify_result, &beacon_report, witnesses)
+                self.handle_invalid_poc(beacon_verify_result, &beacon_report, witnesses, explain)
                     .await?;
             }
         }
@@ -379,6 +399,7 @@ where
         beacon_verify_result: VerifyBeaconResult,
         beacon_report: &IotBeaconIngestReport,
         witness_reports: Vec<IotWitnessIngestReport>,
+        explain: bool,
     ) -> anyhow::Result<()> {
         // the beacon is invalid, which in turn renders all witnesses invalid
         let beacon = &beacon_report.report;
@@ -386,6 +407,7 @@ where
         let beacon_report_id = beacon_report.ingest_id();
         let beacon_invalid_reason = beacon_verify_result.invalid_reason;
         let beacon_invalid_details = beacon_verify_result.invalid_details;
+        let beacon_checks = beacon_verify_result.checks;
 
         let (location, elevation, gain) = match beacon_verify_result.gateway_info {
             Some(gateway_info) => match gateway_info.metadata {
@@ -422,6 +444,20 @@ where
                 return Ok(());
             }
         }
+        // the witnesses of an invalid beacon are never verified, so only
+        // the beacon checks are explained
+        if explain {
+            self.write_poc_diagnostics(IotPocDiagnostics {
+                poc_id: beacon.report_id(beacon_report.received_timestamp),
+                received_timestamp: beacon_report.received_timestamp,
+                beacon: beacon.clone(),
+                status: VerificationStatus::Invalid,
+                invalid_reason: beacon_invalid_reason,
+                beacon_checks: beacon_checks.into_iter().map(From::from).collect(),
+                witnesses: vec![],
+            })
+            .await;
+        }
         // save invalid witnesses to s3, ignore any failed witness writes
         // taking the lossly approach here as if we re attempt the POC later
         // we will have to clean out any successful writes of other witnesses
@@ -462,14 +498,24 @@ where
         valid_beacon_report: IotValidBeaconReport,
         selected_witnesses: Vec<IotVerifiedWitnessReport>,
         unselected_witnesses: Vec<IotVerifiedWitnessReport>,
+        beacon_checks: Option<Vec<VerificationCheck>>,
     ) -> anyhow::Result<()> {
         let received_timestamp = valid_beacon_report.received_timestamp;
         let pub_key = valid_beacon_report.report.pub_key.clone();
         let beacon_id = valid_beacon_report.report.report_id(received_timestamp);
         let packet_data = valid_beacon_report.report.data.clone();
         let beacon_report_id = valid_beacon_report.report.report_id(received_timestamp);
+        let diagnostics = beacon_checks.map(|beacon_checks| IotPocDiagnostics {
+            poc_id: beacon_id.clone(),
+            received_timestamp,
+            beacon: valid_beacon_report.report.clone(),
+            status: VerificationStatus::Valid,
+            invalid_reason: InvalidReason::ReasonNone,
+            beacon_checks,
+            witnesses: vec![],
+        });
         let iot_poc: IotPoc = IotPoc {
-            poc_id: beacon_id,
+            poc_id: beacon_id.clone(),
             beacon_report: valid_beacon_report,
             selected_witnesses: selected_witnesses.clone(),
             unselected_witnesses: unselected_witnesses.clone(),
@@ -500,12 +546,42 @@ where
         // but could nae get it to get a way past the lack of COPY
         fire_invalid_witness_metric(&selected_witnesses);
         fire_invalid_witness_metric(&unselected_witnesses);
+        if let Some(mut diagnostics) = diagnostics {
+            diagnostics.witnesses = selected_witnesses
+                .into_iter()
+                .map(|witness| WitnessDiagnostics {
+                    witness,
+                    selected: true,
+                })
+                .chain(
+                    unselected_witnesses
+                        .into_iter()
+                        .map(|witness| WitnessDiagnostics {
+                            witness,
+                            selected: false,
+                        }),
+                )
+                .collect();
+            self.write_poc_diagnostics(diagnostics).await;
+        }
         // update timestamp of last beacon for the beaconer
         LastBeacon::update_last_timestamp(&self.pool, pub_key.as_ref(), received_timestamp).await?;
         Report::delete_poc(&self.pool, &packet_data).await?;
         telemetry::decrement_num_beacons();
         Ok(())
     }
+
+    /// write the diagnostics of an explained poc, failed writes are logged
+    /// and otherwise ignored
+    async fn write_poc_diagnostics(&self, diagnostics: IotPocDiagnostics) {
+        let Some(diagnostics_sink) = &self.diagnostics_sink else {
+            return;
+        };
+        let diagnostics: IotPocDiagnosticsV1 = diagnostics.into();
+        if let Err(err) = diagnostics_sink.write(diagnostics, []).await {
+            tracing::warn!("ignoring failed write of poc diagnostics: {err}");
+        }
+    }
 }
 
 fn poc_beaconer_reward_unit(num_witnesses: u32) -> anyhow::Result<Decimal> {
@@ -554,7 +630,7 @@ fn poc_per_witness_reward_unit(num_witnesses: u32) -> anyhow::Result<Decimal> {
 // split the list into two
 // one representing selected witnesses
 // the other representing unselected witnesses
-fn sort_and_split_witnesses(
+pub fn sort_and_split_witnesses(
     witnesses: &mut Vec<IotVerifiedWitnessReport>,
     max_count: usize,
 ) -> anyhow::Result<Vec<IotVerifiedWitnessReport>> {
@@ -566,7 +642,7 @@ fn sort_and_split_witnesses(
     Ok(unselected_witnesses)
 }
 
-fn filter_witnesses(
+pub fn filter_witnesses(
     witnesses: Vec<IotVerifiedWitnessReport>,
 ) -> (Vec<IotVerifiedWitnessReport>, Vec<IotVerifiedWitnessReport>) {
     let (valid_witnesses, invalid_witnesses) = witnesses
@@ -640,6 +716,7 @@ mod tests {
             invalid_reason: InvalidReason::ReasonNone,
             invalid_details: None,
             participant_side: InvalidParticipantSide::SideNone,
+            checks: vec![],
         };
 
         let witness2 = IotVerifiedWitnessReport {
@@ -654,6 +731,7 @@ mod tests {
             invalid_reason: InvalidReason::SelfWitness,
             invalid_details: None,
             participant_side: InvalidParticipantSide::Witness,
+            checks: vec![],
         };
 
         let witness3 = IotVerifiedWitnessReport {
@@ -668,6 +746,7 @@ mod tests {
             invalid_reason: InvalidReason::Stale,
             invalid_details: None,
             participant_side: InvalidParticipantSide::Witness,
+            checks: vec![],
         };
 
         let witness4 = IotVerifiedWitnessReport {
@@ -682,6 +761,7 @@ mod tests {
             invalid_reason: InvalidReason::Duplicate,
             invalid_details: None,
             participant_side: InvalidParticipantSide::Witness,
+            checks: vec![],
         };
 
         let witnesses = vec![witness1, witness2, witness3, witness4];
@@ -737,6 +817,7 @@ mod tests {
                 invalid_reason: InvalidReason::ReasonNone,
                 invalid_details: None,
                 participant_side: InvalidParticipantSide::SideNone,
+                checks: vec![],
             })
             .collect::<Vec<IotVerifiedWitnessReport>>();
         selected_witnesses.reverse();
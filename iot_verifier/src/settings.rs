@@ -97,6 +97,17 @@ pub struct Settings {
     /// interval at which region params in the cache are refreshed
     #[serde(default = "default_region_params_refresh_interval")]
     pub region_params_refresh_interval: u64,
+    /// explain poc verifications, running every beacon and witness check
+    /// rather than stopping at the first failure and recording the outcome
+    /// of each
+    #[serde(default)]
+    pub explain: bool,
+    /// the fraction of pocs, between 0 and 1, that are explained and have
+    /// their diagnostics written to the poc diagnostics sink. An explained poc
+    /// runs every check of its beacon and witnesses and writes a diagnostics
+    /// report per beacon and witness, so raise it with care
+    #[serde(default = "default_explain_sample_rate")]
+    pub explain_sample_rate: f64,
 }
 
 // Default: 30 minutes
@@ -109,6 +120,11 @@ fn default_region_params_refresh_interval() -> u64 {
     30 * 60
 }
 
+// Default: 1 in 100 pocs
+fn default_explain_sample_rate() -> f64 {
+    0.01
+}
+
 // Default: 60 minutes
 // this should be at least poc_loader_window_width * 2
 pub fn default_loader_window_max_lookback_age() -> i64 {
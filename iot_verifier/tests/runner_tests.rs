@@ -102,6 +102,8 @@ impl TestContext {
             invalid_witness_sink: invalid_witness_client,
             poc_sink: valid_poc_client,
             hex_density_map: density_scaler.hex_density_map.clone(),
+            diagnostics_sink: None,
+            explain_sample_rate: 1.0,
         };
 
         // generate a datetime based on a hardcoded timestamp
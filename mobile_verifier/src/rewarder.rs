@@ -170,11 +170,16 @@ where
 
         let mobile_price = self
             .price_tracker
-            .price(&helium_proto::BlockchainTokenTypeV1::Mobile)
+            .epoch_price(&helium_proto::BlockchainTokenTypeV1::Mobile, reward_period)
             .await?;
+        tracing::info!(
+            "Rewarding with mobile_price: {}, stale: {}",
+            mobile_price.price,
+            mobile_price.stale
+        );
 
         // Mobile prices are supplied in 10^6, so we must convert them to Decimal
-        let mobile_bone_price = Decimal::from(mobile_price)
+        let mobile_bone_price = Decimal::from(mobile_price.price)
                 / dec!(1_000_000)  // Per Mobile token
                 / dec!(1_000_000); // Per Bone
 
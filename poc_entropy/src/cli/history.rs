@@ -0,0 +1,44 @@
+use crate::history_server::HISTORY_PATH;
+use base64::Engine;
+
+/// Look up historic entropy from a running entropy server, either by the
+/// time it was valid at or by its data.
+#[derive(Debug, clap::Args)]
+pub struct Cmd {
+    /// Base url of the entropy history server, eg "http://127.0.0.1:8081"
+    #[clap(short, long)]
+    url: String,
+    /// Unix timestamp (secs) to get the valid entropy at
+    #[clap(short, long, conflicts_with = "data", required_unless_present = "data")]
+    timestamp: Option<i64>,
+    /// Base64 encoded entropy data to look up
+    #[clap(short, long)]
+    data: Option<String>,
+}
+
+impl Cmd {
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let query = match (&self.timestamp, &self.data) {
+            (Some(timestamp), _) => format!("timestamp={timestamp}"),
+            (None, Some(data)) => {
+                let data = base64::engine::general_purpose::STANDARD.decode(data)?;
+                format!(
+                    "data={}",
+                    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data)
+                )
+            }
+            (None, None) => anyhow::bail!("either timestamp or data is required"),
+        };
+        let url = format!("{}{HISTORY_PATH}?{query}", self.url.trim_end_matches('/'));
+        let response = reqwest::get(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let entropy: serde_json::Value = response.json().await?;
+                println!("{}", serde_json::to_string_pretty(&entropy)?);
+                Ok(())
+            }
+            reqwest::StatusCode::NOT_FOUND => anyhow::bail!("no entropy found"),
+            other => anyhow::bail!("unexpected status {other}"),
+        }
+    }
+}
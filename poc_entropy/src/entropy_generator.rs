@@ -1,24 +1,18 @@
+use crate::entropy_source::{EntropySource, GetEntropyError};
 use base64::Engine;
 use chrono::Utc;
 use file_store::file_sink;
-use futures::TryFutureExt;
 use helium_proto::EntropyReportV1;
-use jsonrpsee::{
-    core::client::ClientT,
-    http_client::{HttpClient, HttpClientBuilder},
-    rpc_params,
-};
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::Serialize;
 use tokio::{sync::watch, time};
 
 pub const ENTROPY_TICK_TIME: time::Duration = time::Duration::from_secs(60);
-const ENTROPY_TIMEOUT: time::Duration = time::Duration::from_secs(5);
 
 pub type MessageSender = watch::Sender<Entropy>;
 pub type MessageReceiver = watch::Receiver<Entropy>;
 
-pub const ENTROPY_VERSION: u32 = 0;
+/// Version 1 mixes the named, length prefixed data of every source used
+pub const ENTROPY_VERSION: u32 = 1;
 
 pub fn message_channel(init: Entropy) -> (MessageSender, MessageReceiver) {
     watch::channel(init)
@@ -30,6 +24,8 @@ pub struct Entropy {
     pub timestamp: i64,
     #[serde(serialize_with = "ser_base64")]
     pub data: Vec<u8>,
+    /// names of the sources the data was mixed from
+    pub sources: Vec<String>,
 }
 
 impl From<Entropy> for EntropyReportV1 {
@@ -42,6 +38,18 @@ impl From<Entropy> for EntropyReportV1 {
     }
 }
 
+impl From<EntropyReportV1> for Entropy {
+    fn from(value: EntropyReportV1) -> Self {
+        Self {
+            version: value.version,
+            timestamp: value.timestamp as i64,
+            data: value.data,
+            // sources are not recorded in entropy reports
+            sources: vec![],
+        }
+    }
+}
+
 fn ser_base64<T, S>(key: &T, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
     T: AsRef<[u8]>,
@@ -65,51 +73,34 @@ impl From<&Entropy> for EntropyReportV1 {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct JsonRpcResult {
-    context: serde_json::Map<String, serde_json::Value>,
-    value: serde_json::Map<String, serde_json::Value>,
-}
-
 pub struct EntropyGenerator {
     pub receiver: MessageReceiver,
 
-    client: HttpClient,
+    sources: Vec<Box<dyn EntropySource>>,
     sender: MessageSender,
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum GetEntropyError {
-    #[error("no blockhash found")]
-    NoBlockHashFound,
-    #[error("failed to decode hash: {0}")]
-    DecodeError(#[from] bs58::decode::Error),
-    #[error("json rpc error: {0}")]
-    JsonRpcError(#[from] jsonrpsee::core::Error),
-}
-
 impl EntropyGenerator {
-    pub async fn new(url: impl AsRef<str>) -> Result<Self, GetEntropyError> {
-        let client = HttpClientBuilder::default()
-            .request_timeout(ENTROPY_TIMEOUT)
-            .build(url)?;
-        let entropy = Self::get_entropy(&client)
-            .map_ok(|data| Entropy {
+    pub async fn new(sources: Vec<Box<dyn EntropySource>>) -> Result<Self, GetEntropyError> {
+        let timestamp = Utc::now().timestamp();
+        let entropy = mix_sources(&sources, timestamp)
+            .await
+            .map(|(data, sources)| Entropy {
                 data,
-                timestamp: Utc::now().timestamp(),
+                timestamp,
                 version: ENTROPY_VERSION,
+                sources,
             })
-            .inspect_ok(|entropy| {
-                tracing::info!(
-                    "initialized entropy: {} at: {}",
-                    entropy.to_string(),
-                    entropy.timestamp
-                )
-            })
-            .await?;
+            .ok_or(GetEntropyError::NoSourceAvailable)?;
+        tracing::info!(
+            "initialized entropy: {} at: {} from: {:?}",
+            entropy.to_string(),
+            entropy.timestamp,
+            entropy.sources
+        );
         let (sender, receiver) = watch::channel(entropy);
         Ok(Self {
-            client,
+            sources,
             receiver,
             sender,
         })
@@ -149,50 +140,145 @@ impl EntropyGenerator {
         &mut self,
         file_sink: &file_sink::FileSinkClient,
     ) -> anyhow::Result<()> {
-        let source_data = match Self::get_entropy(&self.client).await {
-            Ok(data) => data,
-            Err(err) => {
-                tracing::warn!("failed to get entropy: {err:?}");
-                (*self.receiver.borrow().data).to_vec()
+        let timestamp = Utc::now().timestamp();
+        let (data, sources) = match mix_sources(&self.sources, timestamp).await {
+            Some(mixed) => mixed,
+            None => {
+                tracing::warn!("failed to get entropy from every source, reusing last entropy");
+                metrics::increment_counter!("entropy_generator_stale_count");
+                let entropy = self.receiver.borrow();
+                (
+                    mix(timestamp, [("stale", entropy.data.as_slice())]),
+                    vec!["stale".to_string()],
+                )
             }
         };
-        let timestamp = Utc::now().timestamp();
-
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(&timestamp.to_le_bytes());
-        hasher.update(&source_data);
-        let data = hasher.finalize().as_bytes().to_vec();
 
         self.sender.send_modify(|entry| {
+            entry.version = ENTROPY_VERSION;
             entry.timestamp = timestamp;
             entry.data = data;
+            entry.sources = sources;
         });
 
         let entropy = &*self.receiver.borrow();
         tracing::info!(
-            "using entropy: {} at: {}",
+            "using entropy: {} at: {} from: {:?}",
             entropy.to_string(),
-            entropy.timestamp
+            entropy.timestamp,
+            entropy.sources
         );
 
         file_sink.write(EntropyReportV1::from(entropy), []).await?;
 
         Ok(())
     }
+}
 
-    async fn get_entropy(client: &HttpClient) -> Result<Vec<u8>, GetEntropyError> {
-        let params = rpc_params!(json!({"commitment": "processed"}));
-        client
-            .request("getLatestBlockhash", params)
-            .map_err(GetEntropyError::from)
-            .and_then(|result: JsonRpcResult| async move {
-                result
-                    .value
-                    .get("blockhash")
-                    .and_then(|v| v.as_str())
-                    .ok_or(GetEntropyError::NoBlockHashFound)
-                    .and_then(|hash| bs58::decode(hash).into_vec().map_err(GetEntropyError::from))
-            })
-            .await
+/// Collect entropy from every primary source and mix it with the timestamp.
+/// Fallback sources are only used when every primary source fails. Returns
+/// the mixed entropy and the names of the sources it was mixed from, or
+/// `None` when no source produced entropy
+async fn mix_sources(
+    sources: &[Box<dyn EntropySource>],
+    timestamp: i64,
+) -> Option<(Vec<u8>, Vec<String>)> {
+    let (fallbacks, primaries): (Vec<&dyn EntropySource>, Vec<&dyn EntropySource>) = sources
+        .iter()
+        .map(AsRef::as_ref)
+        .partition(|source| source.is_fallback());
+    let mut collected = collect_sources(&primaries).await;
+    if collected.is_empty() && !fallbacks.is_empty() {
+        tracing::warn!("failed to get entropy from every primary source, using fallback");
+        metrics::increment_counter!("entropy_generator_fallback_count");
+        collected = collect_sources(&fallbacks).await;
+    }
+    if collected.is_empty() {
+        return None;
+    }
+    let data = mix(
+        timestamp,
+        collected
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice())),
+    );
+    let names = collected.into_iter().map(|(name, _)| name).collect();
+    Some((data, names))
+}
+
+async fn collect_sources(sources: &[&dyn EntropySource]) -> Vec<(String, Vec<u8>)> {
+    let mut collected = vec![];
+    for source in sources {
+        match source.get_entropy().await {
+            Ok(data) => collected.push((source.name().to_string(), data)),
+            Err(err) => {
+                tracing::warn!(source = source.name(), "failed to get entropy: {err:?}");
+                metrics::increment_counter!(
+                    "entropy_source_failure_count",
+                    "source" => source.name().to_string()
+                );
+            }
+        }
+    }
+    collected
+}
+
+/// Hash the timestamp and every named source into a single piece of entropy.
+/// Sources are length prefixed so that moving bytes between adjacent sources
+/// yields different entropy
+fn mix<'a>(timestamp: i64, sources: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Vec<u8> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&timestamp.to_le_bytes());
+    for (name, data) in sources {
+        hasher.update(&(name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update(&(data.len() as u64).to_le_bytes());
+        hasher.update(data);
+    }
+    hasher.finalize().as_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mix_is_deterministic() {
+        let sources = [("solana", &[1u8, 2, 3][..]), ("beacon_file", &[4u8][..])];
+        assert_eq!(mix(1, sources), mix(1, sources));
+        assert_eq!(mix(1, sources).len(), blake3::OUT_LEN);
+    }
+
+    #[test]
+    fn mix_depends_on_timestamp_and_sources() {
+        let sources = [("solana", &[1u8, 2, 3][..]), ("beacon_file", &[4u8][..])];
+        let mixed = mix(1, sources);
+        assert_ne!(mixed, mix(2, sources));
+        assert_ne!(mixed, mix(1, [("solana", &[1u8, 2, 3][..])]));
+        assert_ne!(
+            mixed,
+            mix(
+                1,
+                [("beacon_file", &[4u8][..]), ("solana", &[1u8, 2, 3][..])]
+            )
+        );
+        assert_ne!(
+            mixed,
+            mix(
+                1,
+                [("solana", &[1u8, 2, 4][..]), ("beacon_file", &[4u8][..])]
+            )
+        );
+    }
+
+    #[test]
+    fn mix_length_prefixes_sources() {
+        // moving bytes between adjacent sources, or between a name and its
+        // data, must not produce the same entropy
+        assert_ne!(
+            mix(1, [("a", &[1u8, 2][..]), ("b", &[3u8][..])]),
+            mix(1, [("a", &[1u8][..]), ("b", &[2u8, 3][..])])
+        );
+        assert_ne!(mix(1, [("ab", &[][..])]), mix(1, [("a", &[b'b'][..])]));
     }
 }
@@ -0,0 +1,207 @@
+use crate::entropy_generator::{Entropy, MessageReceiver};
+use chrono::{DateTime, Duration, Utc};
+use file_store::{FileInfo, FileStore, FileType};
+use futures::{StreamExt, TryStreamExt};
+use helium_proto::{EntropyReportV1, Message};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum EntropyHistoryError {
+    #[error("file store error: {0}")]
+    FileStore(#[from] file_store::Error),
+}
+
+/// Bounded history of generated entropy, oldest first
+#[derive(Clone)]
+pub struct EntropyHistory {
+    entries: Arc<RwLock<VecDeque<Entropy>>>,
+    max_age: Duration,
+}
+
+impl EntropyHistory {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            max_age,
+        }
+    }
+
+    /// Restore the history from the entropy reports uploaded by the entropy
+    /// file sink, so that history survives a restart of the server
+    pub async fn load(
+        &self,
+        file_store: &FileStore,
+        now: DateTime<Utc>,
+    ) -> Result<(), EntropyHistoryError> {
+        let after = now - self.max_age;
+        let mut files = file_store.list(FileType::EntropyReport.to_str(), after, None);
+        while let Some(file) = files.try_next().await? {
+            self.load_file(file_store, file).await?;
+        }
+        self.prune(now);
+        tracing::info!("loaded {} entropy history entries", self.len());
+        Ok(())
+    }
+
+    async fn load_file(
+        &self,
+        file_store: &FileStore,
+        file: FileInfo,
+    ) -> Result<(), EntropyHistoryError> {
+        tracing::debug!("loading entropy history file {}", file.key);
+        let mut reports = file_store.stream_file(file).await?;
+        while let Some(buf) = reports.next().await {
+            match EntropyReportV1::decode(buf?) {
+                Ok(report) => self.push(Entropy::from(report)),
+                Err(err) => tracing::warn!("skipping entropy report due to error {err:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Record every entropy published on the generator's watch channel
+    pub async fn run(
+        self,
+        mut receiver: MessageReceiver,
+        shutdown: triggered::Listener,
+    ) -> anyhow::Result<()> {
+        tracing::info!("started entropy history");
+        let entropy = receiver.borrow().clone();
+        self.push(entropy);
+        loop {
+            tokio::select! {
+                biased;
+                _ = shutdown.clone() => break,
+                changed = receiver.changed() => {
+                    changed?;
+                    let entropy = receiver.borrow().clone();
+                    self.push(entropy);
+                    self.prune(Utc::now());
+                }
+            }
+        }
+        tracing::info!("stopping entropy history");
+        Ok(())
+    }
+
+    /// The entropy valid at the given timestamp, ie the last entropy
+    /// generated at or before it
+    pub fn at(&self, timestamp: i64) -> Option<Entropy> {
+        self.read()
+            .iter()
+            .rev()
+            .find(|entropy| entropy.timestamp <= timestamp)
+            .cloned()
+    }
+
+    /// The entropy with the given data
+    pub fn by_data(&self, data: &[u8]) -> Option<Entropy> {
+        self.read()
+            .iter()
+            .find(|entropy| entropy.data == data)
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub(crate) fn push(&self, entropy: Entropy) {
+        let mut entries = self.write();
+        // entries loaded from the store and published on the channel may
+        // overlap on startup
+        if entries
+            .back()
+            .map_or(true, |last| last.timestamp < entropy.timestamp)
+        {
+            entries.push_back(entropy);
+        }
+    }
+
+    fn prune(&self, now: DateTime<Utc>) {
+        let oldest = (now - self.max_age).timestamp();
+        let mut entries = self.write();
+        while entries
+            .front()
+            .map_or(false, |entropy| entropy.timestamp < oldest)
+        {
+            entries.pop_front();
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, VecDeque<Entropy>> {
+        self.entries.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, VecDeque<Entropy>> {
+        self.entries.write().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entropy(timestamp: i64) -> Entropy {
+        Entropy {
+            version: 1,
+            timestamp,
+            data: timestamp.to_le_bytes().to_vec(),
+            sources: vec![],
+        }
+    }
+
+    fn history(timestamps: &[i64]) -> EntropyHistory {
+        let history = EntropyHistory::new(Duration::seconds(100));
+        for timestamp in timestamps {
+            history.push(entropy(*timestamp));
+        }
+        history
+    }
+
+    #[test]
+    fn at_returns_entropy_valid_at_timestamp() {
+        let history = history(&[100, 160, 220]);
+        assert!(history.at(99).is_none());
+        assert_eq!(history.at(100).map(|e| e.timestamp), Some(100));
+        assert_eq!(history.at(219).map(|e| e.timestamp), Some(160));
+        assert_eq!(history.at(1000).map(|e| e.timestamp), Some(220));
+    }
+
+    #[test]
+    fn by_data_returns_matching_entropy() {
+        let history = history(&[100, 160]);
+        assert_eq!(
+            history.by_data(&160i64.to_le_bytes()).map(|e| e.timestamp),
+            Some(160)
+        );
+        assert!(history.by_data(&[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn push_ignores_entropy_older_than_the_latest() {
+        let history = history(&[100, 160, 160, 120]);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.at(150).map(|e| e.timestamp), Some(100));
+    }
+
+    #[test]
+    fn prune_drops_entropy_older_than_max_age() {
+        let history = history(&[100, 160, 220]);
+        history.prune(Utc.timestamp_opt(260, 0).unwrap());
+        assert_eq!(history.len(), 2);
+        assert!(history.at(159).is_none());
+        assert_eq!(history.at(160).map(|e| e.timestamp), Some(160));
+
+        history.prune(Utc.timestamp_opt(1000, 0).unwrap());
+        assert!(history.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::settings::SourceSettings;
+use futures::TryFutureExt;
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+    rpc_params,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::time;
+
+const ENTROPY_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+const OS_RNG_ENTROPY_SIZE: usize = 32;
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetEntropyError {
+    #[error("no entropy source available")]
+    NoSourceAvailable,
+    #[error("no blockhash found")]
+    NoBlockHashFound,
+    #[error("failed to decode hash: {0}")]
+    DecodeError(#[from] bs58::decode::Error),
+    #[error("json rpc error: {0}")]
+    JsonRpcError(#[from] jsonrpsee::core::Error),
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid beacon: {0}")]
+    InvalidBeacon(String),
+    #[error("stale beacon round: {0}")]
+    StaleBeacon(u64),
+    #[error("os rng error: {0}")]
+    RngError(#[from] rand::Error),
+}
+
+/// A source of entropy mixed into the generated entropy
+#[async_trait::async_trait]
+pub trait EntropySource: Send + Sync {
+    /// Name recorded against generated entropy and source metrics
+    fn name(&self) -> &str;
+
+    /// Whether the source is only consulted when every other source fails
+    fn is_fallback(&self) -> bool {
+        false
+    }
+
+    async fn get_entropy(&self) -> Result<Vec<u8>, GetEntropyError>;
+}
+
+pub fn from_settings(settings: &SourceSettings) -> Result<Box<dyn EntropySource>, GetEntropyError> {
+    let source: Box<dyn EntropySource> = match settings {
+        SourceSettings::Solana { url } => Box::new(SolanaBlockhash::new(url)?),
+        SourceSettings::BeaconFile { path } => Box::new(BeaconFile::new(path.clone())),
+        SourceSettings::OsRng => Box::new(OsRngFallback),
+    };
+    Ok(source)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonRpcResult {
+    context: serde_json::Map<String, serde_json::Value>,
+    value: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Latest blockhash of a solana cluster over JSON-RPC
+pub struct SolanaBlockhash {
+    client: HttpClient,
+}
+
+impl SolanaBlockhash {
+    pub fn new(url: impl AsRef<str>) -> Result<Self, GetEntropyError> {
+        let client = HttpClientBuilder::default()
+            .request_timeout(ENTROPY_TIMEOUT)
+            .build(url)?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait::async_trait]
+impl EntropySource for SolanaBlockhash {
+    fn name(&self) -> &str {
+        "solana"
+    }
+
+    async fn get_entropy(&self) -> Result<Vec<u8>, GetEntropyError> {
+        let params = rpc_params!(json!({"commitment": "processed"}));
+        self.client
+            .request("getLatestBlockhash", params)
+            .map_err(GetEntropyError::from)
+            .and_then(|result: JsonRpcResult| async move {
+                result
+                    .value
+                    .get("blockhash")
+                    .and_then(|v| v.as_str())
+                    .ok_or(GetEntropyError::NoBlockHashFound)
+                    .and_then(|hash| bs58::decode(hash).into_vec().map_err(GetEntropyError::from))
+            })
+            .await
+    }
+}
+
+/// A drand style randomness beacon written to a local file, for example by a
+/// drand client relay or a stand-in for one. The file holds the latest round
+/// as json: `{"round": 1234, "randomness": "<hex>"}`
+pub struct BeaconFile {
+    path: PathBuf,
+    last_round: AtomicU64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeaconRound {
+    round: u64,
+    randomness: String,
+}
+
+impl BeaconFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            last_round: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EntropySource for BeaconFile {
+    fn name(&self) -> &str {
+        "beacon_file"
+    }
+
+    async fn get_entropy(&self) -> Result<Vec<u8>, GetEntropyError> {
+        let bytes = tokio::fs::read(&self.path).await?;
+        let beacon: BeaconRound = serde_json::from_slice(&bytes)
+            .map_err(|err| GetEntropyError::InvalidBeacon(err.to_string()))?;
+        let randomness = hex::decode(&beacon.randomness)
+            .map_err(|err| GetEntropyError::InvalidBeacon(err.to_string()))?;
+        // a beacon which has not advanced since the last tick is not fresh
+        // entropy and must not be mixed in again, the round is only consumed
+        // once it has been successfully decoded
+        let last_round = self.last_round.fetch_max(beacon.round, Ordering::SeqCst);
+        if beacon.round <= last_round {
+            return Err(GetEntropyError::StaleBeacon(beacon.round));
+        }
+        Ok(randomness)
+    }
+}
+
+/// Random bytes from the operating system, used only when every other
+/// source fails
+pub struct OsRngFallback;
+
+#[async_trait::async_trait]
+impl EntropySource for OsRngFallback {
+    fn name(&self) -> &str {
+        "os_rng"
+    }
+
+    fn is_fallback(&self) -> bool {
+        true
+    }
+
+    async fn get_entropy(&self) -> Result<Vec<u8>, GetEntropyError> {
+        let mut data = vec![0u8; OS_RNG_ENTROPY_SIZE];
+        OsRng.try_fill_bytes(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn write_round(path: &std::path::Path, round: u64, randomness: &str) {
+        let beacon = json!({"round": round, "randomness": randomness});
+        tokio::fs::write(path, beacon.to_string())
+            .await
+            .expect("write beacon");
+    }
+
+    #[tokio::test]
+    async fn beacon_file_consumes_each_round_once() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("beacon.json");
+        let source = BeaconFile::new(path.clone());
+
+        write_round(&path, 1, "0a0b").await;
+        assert_eq!(source.get_entropy().await.expect("entropy"), vec![10, 11]);
+        assert!(matches!(
+            source.get_entropy().await,
+            Err(GetEntropyError::StaleBeacon(1))
+        ));
+
+        write_round(&path, 2, "0c0d").await;
+        assert_eq!(source.get_entropy().await.expect("entropy"), vec![12, 13]);
+    }
+
+    #[tokio::test]
+    async fn malformed_beacon_round_is_not_consumed() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("beacon.json");
+        let source = BeaconFile::new(path.clone());
+
+        write_round(&path, 5, "not hex").await;
+        assert!(matches!(
+            source.get_entropy().await,
+            Err(GetEntropyError::InvalidBeacon(_))
+        ));
+
+        // the relay rewriting the same round with valid randomness is used
+        write_round(&path, 5, "ff").await;
+        assert_eq!(source.get_entropy().await.expect("entropy"), vec![255]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::entropy_history::EntropyHistory;
+use base64::Engine;
+use http_api::{query_param, status};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::net::SocketAddr;
+
+pub const HISTORY_PATH: &str = "/entropy";
+
+/// Serves lookups into the entropy history over http.
+///
+/// `GET /entropy?timestamp=<unix secs>` returns the entropy valid at the given
+/// time, `GET /entropy?data=<url safe base64>` returns the entropy with the
+/// given data. Entropy is returned as json.
+pub struct HistoryServer {
+    pub socket_addr: SocketAddr,
+    history: EntropyHistory,
+}
+
+impl HistoryServer {
+    pub fn new(socket_addr: SocketAddr, history: EntropyHistory) -> Self {
+        Self {
+            socket_addr,
+            history,
+        }
+    }
+
+    pub async fn run(self, shutdown: &triggered::Listener) -> anyhow::Result<()> {
+        tracing::info!(listen = self.socket_addr.to_string(), "starting history");
+        let history = self.history;
+        http_api::serve(
+            &self.socket_addr,
+            move |req| {
+                let history = history.clone();
+                async move { handle(&history, req) }
+            },
+            shutdown.clone(),
+        )
+        .await?;
+        tracing::info!("stopping history server");
+        Ok(())
+    }
+}
+
+fn handle(history: &EntropyHistory, req: Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET || req.uri().path() != HISTORY_PATH {
+        return status(StatusCode::NOT_FOUND);
+    }
+    metrics::increment_counter!("entropy_server_history_count");
+    let query = req.uri().query().unwrap_or_default();
+    let entropy = match query_param(query, "timestamp") {
+        Some(timestamp) => match timestamp.parse::<i64>() {
+            Ok(timestamp) => history.at(timestamp),
+            Err(_) => return status(StatusCode::BAD_REQUEST),
+        },
+        None => match query_param(query, "data") {
+            Some(data) => match base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(data) {
+                Ok(data) => history.by_data(&data),
+                Err(_) => return status(StatusCode::BAD_REQUEST),
+            },
+            None => return status(StatusCode::BAD_REQUEST),
+        },
+    };
+    match entropy.map(|entropy| serde_json::to_vec(&entropy)) {
+        Some(Ok(json)) => http_api::json(json).unwrap_or_else(status),
+        Some(Err(_)) => status(StatusCode::INTERNAL_SERVER_ERROR),
+        None => status(StatusCode::NOT_FOUND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy_generator::Entropy;
+    use chrono::Duration;
+
+    fn history() -> EntropyHistory {
+        let history = EntropyHistory::new(Duration::hours(1));
+        for timestamp in [100, 160] {
+            history.push(Entropy {
+                version: 1,
+                timestamp,
+                data: vec![timestamp as u8; 4],
+                sources: vec![],
+            });
+        }
+        history
+    }
+
+    async fn get(history: &EntropyHistory, uri: &str) -> (StatusCode, Option<serde_json::Value>) {
+        let req = Request::get(uri).body(Body::empty()).expect("request");
+        let response = handle(history, req);
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn looks_up_entropy_by_timestamp() {
+        let history = history();
+        let (status, json) = get(&history, "/entropy?timestamp=159").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.expect("json")["timestamp"], 100);
+
+        let (status, _) = get(&history, "/entropy?timestamp=99").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&history, "/entropy?timestamp=soon").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn looks_up_entropy_by_data() {
+        let history = history();
+        let data = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode([160u8; 4]);
+        let (status, json) = get(&history, &format!("/entropy?data={data}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.expect("json")["timestamp"], 160);
+
+        let (status, _) = get(&history, "/entropy?data=!!").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_other_requests() {
+        let history = history();
+        let (status, _) = get(&history, "/entropy").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&history, "/other?timestamp=100").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
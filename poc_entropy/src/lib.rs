@@ -1,4 +1,8 @@
+pub mod cli;
 pub mod entropy_generator;
+pub mod entropy_history;
+pub mod entropy_source;
+pub mod history_server;
 pub mod server;
 pub mod settings;
 
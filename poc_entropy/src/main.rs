@@ -1,9 +1,13 @@
 use anyhow::{Error, Result};
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use clap::Parser;
+use file_store::FileStore;
 use file_store::{file_sink, file_upload, FileType};
 use futures_util::TryFutureExt;
-use poc_entropy::{entropy_generator::EntropyGenerator, server::ApiServer, Settings};
+use poc_entropy::{
+    cli::history, entropy_generator::EntropyGenerator, entropy_history::EntropyHistory,
+    entropy_source, history_server::HistoryServer, server::ApiServer, Settings,
+};
 use std::{net::SocketAddr, path};
 use tokio::{self, signal};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -26,20 +30,24 @@ pub struct Cli {
 
 impl Cli {
     pub async fn run(self) -> Result<()> {
-        let settings = Settings::new(self.config)?;
-        self.cmd.run(settings).await
+        self.cmd.run(self.config).await
     }
 }
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Cmd {
     Server(Server),
+    History(history::Cmd),
 }
 
 impl Cmd {
-    pub async fn run(&self, settings: Settings) -> Result<()> {
+    pub async fn run(&self, config: Option<path::PathBuf>) -> Result<()> {
         match self {
-            Self::Server(cmd) => cmd.run(&settings).await,
+            Self::Server(cmd) => {
+                let settings = Settings::new(config)?;
+                cmd.run(&settings).await
+            }
+            Self::History(cmd) => cmd.run().await,
         }
     }
 }
@@ -75,9 +83,21 @@ impl Server {
         let store_base_path = path::Path::new(&settings.cache);
 
         // entropy
-        let mut entropy_generator = EntropyGenerator::new(&settings.source).await?;
+        let entropy_sources = settings
+            .sources()
+            .iter()
+            .map(entropy_source::from_settings)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut entropy_generator = EntropyGenerator::new(entropy_sources).await?;
         let entropy_watch = entropy_generator.receiver();
 
+        // history, restored from previously uploaded entropy reports
+        let entropy_history = EntropyHistory::new(settings.history_max_age());
+        let file_store = FileStore::from_settings(&settings.output).await?;
+        if let Err(err) = entropy_history.load(&file_store, Utc::now()).await {
+            tracing::warn!("failed to load entropy history: {err:?}");
+        }
+
         let (entropy_sink, entropy_sink_server) = file_sink::FileSinkBuilder::new(
             FileType::EntropyReport,
             store_base_path,
@@ -90,12 +110,17 @@ impl Server {
 
         // server
         let socket_addr: SocketAddr = settings.listen.parse()?;
-        let api_server = ApiServer::new(socket_addr, entropy_watch).await?;
+        let api_server = ApiServer::new(socket_addr, entropy_watch.clone()).await?;
+        let history_addr: SocketAddr = settings.history_listen.parse()?;
+        let history_server = HistoryServer::new(history_addr, entropy_history.clone());
 
         tracing::info!("api listening on {}", api_server.socket_addr);
+        tracing::info!("history listening on {}", history_server.socket_addr);
 
         tokio::try_join!(
             api_server.run(&shutdown),
+            history_server.run(&shutdown),
+            entropy_history.run(entropy_watch, shutdown.clone()),
             entropy_generator
                 .run(entropy_sink, &shutdown)
                 .map_err(Error::from),
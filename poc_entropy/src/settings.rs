@@ -1,6 +1,7 @@
+use chrono::Duration;
 use config::{Config, Environment, File};
 use serde::Deserialize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize)]
 pub struct Settings {
@@ -11,10 +12,22 @@ pub struct Settings {
     /// Listen address for http requests for entropy. Default "0.0.0.0:8080"
     #[serde(default = "default_listen_addr")]
     pub listen: String,
-    /// Source URL for entropy data. Required
-    pub source: String,
+    /// Solana JSON-RPC URL used as the single entropy source when no
+    /// `sources` are configured
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Entropy sources mixed into the generated entropy, in mixing order
+    #[serde(default)]
+    pub sources: Vec<SourceSettings>,
     /// Target output bucket details
     pub output: file_store::Settings,
+    /// Listen address for http requests for entropy history. Default
+    /// "0.0.0.0:8081"
+    #[serde(default = "default_history_listen_addr")]
+    pub history_listen: String,
+    /// Max age of entropy kept in the history (secs). Default 7 days
+    #[serde(default = "default_history_max_age")]
+    pub history_max_age: i64,
     /// Folder for locacl cache of ingest data
     #[serde(default = "default_cache")]
     pub cache: String,
@@ -22,6 +35,17 @@ pub struct Settings {
     pub metrics: poc_metrics::Settings,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceSettings {
+    /// Latest blockhash from a solana JSON-RPC url
+    Solana { url: String },
+    /// drand style randomness beacon read from a local file
+    BeaconFile { path: PathBuf },
+    /// Operating system randomness, only used when every other source fails
+    OsRng,
+}
+
 pub fn default_log() -> String {
     "poc_entropy=debug,poc_store=info".to_string()
 }
@@ -34,6 +58,14 @@ pub fn default_listen_addr() -> String {
     "0.0.0.0:8080".to_string()
 }
 
+pub fn default_history_listen_addr() -> String {
+    "0.0.0.0:8081".to_string()
+}
+
+fn default_history_max_age() -> i64 {
+    7 * 24 * 60 * 60
+}
+
 impl Settings {
     /// Load Settings from a given path. Settings are loaded from a given
     /// optional path and can be overriden with environment variables.
@@ -56,4 +88,17 @@ impl Settings {
             .build()
             .and_then(|config| config.try_deserialize())
     }
+
+    /// The configured entropy sources, falling back to a single solana
+    /// source from `source`
+    pub fn sources(&self) -> Vec<SourceSettings> {
+        match (&self.source, self.sources.is_empty()) {
+            (Some(url), true) => vec![SourceSettings::Solana { url: url.clone() }],
+            _ => self.sources.clone(),
+        }
+    }
+
+    pub fn history_max_age(&self) -> Duration {
+        Duration::seconds(self.history_max_age)
+    }
 }
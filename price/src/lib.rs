@@ -1,6 +1,7 @@
 pub mod cli;
 pub mod metrics;
 pub mod price_generator;
+pub mod price_source;
 pub mod price_tracker;
 pub mod settings;
 
@@ -109,16 +109,6 @@ impl Server {
 
         let store_base_path = path::Path::new(&settings.cache);
 
-        // price generators
-        let mut hnt_price_generator =
-            PriceGenerator::new(settings, BlockchainTokenTypeV1::Hnt).await?;
-        let mut mobile_price_generator =
-            PriceGenerator::new(settings, BlockchainTokenTypeV1::Mobile).await?;
-        let mut iot_price_generator =
-            PriceGenerator::new(settings, BlockchainTokenTypeV1::Iot).await?;
-        let mut hst_price_generator =
-            PriceGenerator::new(settings, BlockchainTokenTypeV1::Hst).await?;
-
         let (price_sink, price_sink_server) = file_sink::FileSinkBuilder::new(
             FileType::PriceReport,
             store_base_path,
@@ -129,6 +119,30 @@ impl Server {
         .create()
         .await?;
 
+        let (sources_sink, sources_sink_server) = file_sink::FileSinkBuilder::new(
+            FileType::PriceSourcesReport,
+            store_base_path,
+            concat!(env!("CARGO_PKG_NAME"), "_sources_report_submission"),
+        )
+        .deposits(Some(file_upload_tx.clone()))
+        .roll_time(Duration::minutes(PRICE_SINK_ROLL_MINS))
+        .create()
+        .await?;
+
+        // price generators
+        let mut hnt_price_generator =
+            PriceGenerator::new(settings, BlockchainTokenTypeV1::Hnt, sources_sink.clone()).await?;
+        let mut mobile_price_generator = PriceGenerator::new(
+            settings,
+            BlockchainTokenTypeV1::Mobile,
+            sources_sink.clone(),
+        )
+        .await?;
+        let mut iot_price_generator =
+            PriceGenerator::new(settings, BlockchainTokenTypeV1::Iot, sources_sink.clone()).await?;
+        let mut hst_price_generator =
+            PriceGenerator::new(settings, BlockchainTokenTypeV1::Hst, sources_sink).await?;
+
         tokio::try_join!(
             hnt_price_generator
                 .run(price_sink.clone(), &shutdown)
@@ -143,6 +157,9 @@ impl Server {
                 .run(price_sink, &shutdown)
                 .map_err(Error::from),
             price_sink_server.run(shutdown.clone()).map_err(Error::from),
+            sources_sink_server
+                .run(shutdown.clone())
+                .map_err(Error::from),
             file_upload.run(shutdown.clone()).map_err(Error::from),
         )
         .map(|_| ())
@@ -173,22 +190,50 @@ impl Server {
         .create()
         .await?;
 
+        let (sources_sink, sources_sink_server) = file_sink::FileSinkBuilder::new(
+            FileType::PriceSourcesReport,
+            store_base_path,
+            concat!(env!("CARGO_PKG_NAME"), "_sources_report_submission"),
+        )
+        .file_upload(Some(file_upload.clone()))
+        .roll_time(Duration::minutes(PRICE_SINK_ROLL_MINS))
+        .create()
+        .await?;
+
         // price generators
-        let hnt_price_generator =
-            PriceGenerator::new_tm(settings, BlockchainTokenTypeV1::Hnt, price_sink.clone())
-                .await?;
-        let mobile_price_generator =
-            PriceGenerator::new_tm(settings, BlockchainTokenTypeV1::Mobile, price_sink.clone())
-                .await?;
-        let iot_price_generator =
-            PriceGenerator::new_tm(settings, BlockchainTokenTypeV1::Iot, price_sink.clone())
-                .await?;
-        let hst_price_generator =
-            PriceGenerator::new_tm(settings, BlockchainTokenTypeV1::Hst, price_sink).await?;
+        let hnt_price_generator = PriceGenerator::new_tm(
+            settings,
+            BlockchainTokenTypeV1::Hnt,
+            price_sink.clone(),
+            sources_sink.clone(),
+        )
+        .await?;
+        let mobile_price_generator = PriceGenerator::new_tm(
+            settings,
+            BlockchainTokenTypeV1::Mobile,
+            price_sink.clone(),
+            sources_sink.clone(),
+        )
+        .await?;
+        let iot_price_generator = PriceGenerator::new_tm(
+            settings,
+            BlockchainTokenTypeV1::Iot,
+            price_sink.clone(),
+            sources_sink.clone(),
+        )
+        .await?;
+        let hst_price_generator = PriceGenerator::new_tm(
+            settings,
+            BlockchainTokenTypeV1::Hst,
+            price_sink,
+            sources_sink,
+        )
+        .await?;
 
         TaskManager::builder()
             .add_task(file_upload_server)
             .add_task(price_sink_server)
+            .add_task(sources_sink_server)
             .add_task(hnt_price_generator)
             .add_task(mobile_price_generator)
             .add_task(iot_price_generator)
@@ -4,11 +4,64 @@ const PRICE_GAUGE: &str = concat!(env!("CARGO_PKG_NAME"), "_", "price_gauge");
 
 pub struct Metrics;
 
+const SOURCE_ERROR_COUNTER: &str = concat!(env!("CARGO_PKG_NAME"), "_", "source_error_counter");
+const SOURCE_USED_COUNTER: &str = concat!(env!("CARGO_PKG_NAME"), "_", "source_used_counter");
+const TRACKER_PRICE_AGE_GAUGE: &str =
+    concat!(env!("CARGO_PKG_NAME"), "_", "tracker_price_age_seconds");
+const TRACKER_PRICE_READY_GAUGE: &str = concat!(env!("CARGO_PKG_NAME"), "_", "tracker_price_ready");
+const TRACKER_STALE_PRICE_COUNTER: &str =
+    concat!(env!("CARGO_PKG_NAME"), "_", "tracker_stale_price_count");
+const TRACKER_PRICE_ERROR_COUNTER: &str =
+    concat!(env!("CARGO_PKG_NAME"), "_", "tracker_price_error_count");
+
 impl Metrics {
     pub fn update(counter: String, token_type: BlockchainTokenTypeV1, price: f64) {
         increment_counter(counter, token_type);
         set_gauge(token_type, price)
     }
+
+    pub fn source_error(source: &str, token_type: BlockchainTokenTypeV1) {
+        metrics::increment_counter!(
+            SOURCE_ERROR_COUNTER,
+            "token_type" => token_type.as_str_name(),
+            "source" => source.to_string()
+        );
+    }
+
+    pub fn source_used(source: &str, token_type: BlockchainTokenTypeV1) {
+        metrics::increment_counter!(
+            SOURCE_USED_COUNTER,
+            "token_type" => token_type.as_str_name(),
+            "source" => source.to_string()
+        );
+    }
+
+    pub fn tracked_price_age(token_type: BlockchainTokenTypeV1, age_seconds: f64, ready: bool) {
+        metrics::gauge!(
+            TRACKER_PRICE_AGE_GAUGE,
+            age_seconds,
+            "token_type" => token_type.as_str_name()
+        );
+        metrics::gauge!(
+            TRACKER_PRICE_READY_GAUGE,
+            if ready { 1.0 } else { 0.0 },
+            "token_type" => token_type.as_str_name()
+        );
+    }
+
+    pub fn stale_price(token_type: BlockchainTokenTypeV1) {
+        metrics::increment_counter!(
+            TRACKER_STALE_PRICE_COUNTER,
+            "token_type" => token_type.as_str_name()
+        );
+    }
+
+    pub fn price_error(token_type: BlockchainTokenTypeV1) {
+        metrics::increment_counter!(
+            TRACKER_PRICE_ERROR_COUNTER,
+            "token_type" => token_type.as_str_name()
+        );
+    }
 }
 
 fn increment_counter(counter: String, token_type: BlockchainTokenTypeV1) {
@@ -1,14 +1,18 @@
-use crate::{metrics::Metrics, Settings};
-use anchor_lang::AccountDeserialize;
+use crate::{
+    metrics::Metrics,
+    price_source::{PriceAggregator, PriceSource, SourcePrice},
+    Settings,
+};
 use anyhow::{anyhow, Error, Result};
 use chrono::{DateTime, Duration, TimeZone, Utc};
-use file_store::file_sink;
+use file_store::{
+    file_sink,
+    price_sources_report::{PriceSourcesReportV1, SourcePriceV1},
+};
 use futures::{future::LocalBoxFuture, TryFutureExt};
-use helium_anchor_gen::price_oracle::{calculate_current_price, PriceOracleV0};
 use helium_proto::{BlockchainTokenTypeV1, PriceReportV1};
 use serde::{Deserialize, Serialize};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::pubkey::Pubkey as SolPubkey;
 use std::{path::PathBuf, str::FromStr};
 use task_manager::ManagedTask;
 use tokio::{fs, time};
@@ -18,6 +22,9 @@ pub struct Price {
     timestamp: DateTime<Utc>,
     price: u64,
     token_type: BlockchainTokenTypeV1,
+    /// the source prices which contributed to the price
+    #[serde(default)]
+    sources: Vec<SourcePrice>,
 }
 
 impl Price {
@@ -26,6 +33,7 @@ impl Price {
             timestamp,
             price,
             token_type,
+            sources: vec![],
         }
     }
 }
@@ -35,11 +43,13 @@ pub struct PriceGenerator {
     client: RpcClient,
     interval_duration: std::time::Duration,
     last_price_opt: Option<Price>,
-    key: Option<SolPubkey>,
+    sources: Vec<PriceSource>,
+    aggregator: PriceAggregator,
     default_price: Option<u64>,
     stale_price_duration: Duration,
     latest_price_file: PathBuf,
     file_sink: Option<file_sink::FileSinkClient>,
+    sources_sink: Option<file_sink::FileSinkClient>,
 }
 
 impl ManagedTask for PriceGenerator {
@@ -61,6 +71,25 @@ impl From<Price> for PriceReportV1 {
     }
 }
 
+impl From<&Price> for PriceSourcesReportV1 {
+    fn from(value: &Price) -> Self {
+        Self {
+            timestamp: value.timestamp.timestamp() as u64,
+            token_type: value.token_type.into(),
+            price: value.price,
+            sources: value
+                .sources
+                .iter()
+                .map(|source| SourcePriceV1 {
+                    source: source.source.clone(),
+                    price: source.price,
+                    timestamp: source.timestamp.timestamp() as u64,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl TryFrom<PriceReportV1> for Price {
     type Error = Error;
 
@@ -74,24 +103,31 @@ impl TryFrom<PriceReportV1> for Price {
                 .ok_or_else(|| anyhow!("invalid timestamp"))?,
             price: value.price,
             token_type: tt,
+            sources: vec![],
         })
     }
 }
 
 impl PriceGenerator {
-    pub async fn new(settings: &Settings, token_type: BlockchainTokenTypeV1) -> Result<Self> {
+    pub async fn new(
+        settings: &Settings,
+        token_type: BlockchainTokenTypeV1,
+        sources_sink: file_sink::FileSinkClient,
+    ) -> Result<Self> {
         let client = RpcClient::new(settings.source.clone());
         Ok(Self {
             last_price_opt: None,
             token_type,
             client,
-            key: settings.price_key(token_type)?,
+            sources: settings.price_sources(token_type)?,
+            aggregator: PriceAggregator::new(settings.aggregation.clone()),
             default_price: settings.default_price(token_type),
             interval_duration: settings.interval().to_std()?,
             stale_price_duration: settings.stale_price_duration(),
             latest_price_file: PathBuf::from_str(&settings.cache)?
                 .join(format!("{token_type:?}.latest")),
             file_sink: None,
+            sources_sink: Some(sources_sink),
         })
     }
 
@@ -99,19 +135,22 @@ impl PriceGenerator {
         settings: &Settings,
         token_type: BlockchainTokenTypeV1,
         file_sink: file_sink::FileSinkClient,
+        sources_sink: file_sink::FileSinkClient,
     ) -> Result<Self> {
         let client = RpcClient::new(settings.source.clone());
         Ok(Self {
             last_price_opt: None,
             token_type,
             client,
-            key: settings.price_key(token_type)?,
+            sources: settings.price_sources(token_type)?,
+            aggregator: PriceAggregator::new(settings.aggregation.clone()),
             default_price: settings.default_price(token_type),
             interval_duration: settings.interval().to_std()?,
             stale_price_duration: settings.stale_price_duration(),
             latest_price_file: PathBuf::from_str(&settings.cache)?
                 .join(format!("{token_type:?}.latest")),
             file_sink: Some(file_sink),
+            sources_sink: Some(sources_sink),
         })
     }
 
@@ -120,9 +159,9 @@ impl PriceGenerator {
         file_sink: file_sink::FileSinkClient,
         shutdown: &triggered::Listener,
     ) -> Result<()> {
-        match (self.key, self.default_price) {
-            (Some(key), _) => self.run_with_key(key, file_sink, shutdown).await,
-            (None, Some(defaut_price)) => {
+        match (self.sources.is_empty(), self.default_price) {
+            (false, _) => self.run_with_sources(file_sink, shutdown).await,
+            (true, Some(defaut_price)) => {
                 self.run_with_default(defaut_price, file_sink, shutdown)
                     .await
             }
@@ -137,9 +176,13 @@ impl PriceGenerator {
     }
 
     pub async fn run_tm(mut self, shutdown: triggered::Listener) -> Result<()> {
-        match (self.key, self.default_price, self.file_sink.clone()) {
-            (Some(key), _, Some(file_sink)) => self.run_with_key(key, file_sink, &shutdown).await,
-            (None, Some(defaut_price), Some(file_sink)) => {
+        match (
+            self.sources.is_empty(),
+            self.default_price,
+            self.file_sink.clone(),
+        ) {
+            (false, _, Some(file_sink)) => self.run_with_sources(file_sink, &shutdown).await,
+            (true, Some(defaut_price), Some(file_sink)) => {
                 self.run_with_default(defaut_price, file_sink, &shutdown)
                     .await
             }
@@ -182,9 +225,8 @@ impl PriceGenerator {
         Ok(())
     }
 
-    async fn run_with_key(
+    async fn run_with_sources(
         &mut self,
-        key: SolPubkey,
         file_sink: file_sink::FileSinkClient,
         shutdown: &triggered::Listener,
     ) -> Result<()> {
@@ -196,7 +238,7 @@ impl PriceGenerator {
             tokio::select! {
                 biased;
                 _ = shutdown.clone() => break,
-                _ = trigger.tick() => self.handle(&key, &file_sink).await?,
+                _ = trigger.tick() => self.handle(&file_sink).await?,
             }
         }
 
@@ -204,17 +246,18 @@ impl PriceGenerator {
         Ok(())
     }
 
-    async fn handle(
-        &mut self,
-        key: &SolPubkey,
-        file_sink: &file_sink::FileSinkClient,
-    ) -> Result<()> {
-        let price_opt = match get_price(&self.client, key, self.token_type).await {
+    async fn handle(&mut self, file_sink: &file_sink::FileSinkClient) -> Result<()> {
+        let price_opt = match self.get_price().await {
             Ok(new_price) => {
                 tracing::info!(
-                    "updating price for {:?} to {}",
+                    "updating price for {:?} to {} from {:?}",
                     self.token_type,
-                    new_price.price
+                    new_price.price,
+                    new_price
+                        .sources
+                        .iter()
+                        .map(|source| source.source.as_str())
+                        .collect::<Vec<_>>()
                 );
                 self.last_price_opt = Some(new_price.clone());
                 self.write_price_file(&new_price).await;
@@ -224,6 +267,14 @@ impl PriceGenerator {
                     self.token_type,
                     new_price.price as f64,
                 );
+                for source in &new_price.sources {
+                    Metrics::source_used(&source.source, self.token_type);
+                }
+                if let Some(sources_sink) = &self.sources_sink {
+                    sources_sink
+                        .write(PriceSourcesReportV1::from(&new_price), [])
+                        .await?;
+                }
 
                 Some(new_price)
             }
@@ -241,11 +292,10 @@ impl PriceGenerator {
                             old_price.price as f64,
                         );
 
-                        Some(Price::new(
-                            Utc::now(),
-                            old_price.price,
-                            old_price.token_type,
-                        ))
+                        Some(Price {
+                            timestamp: Utc::now(),
+                            ..old_price.clone()
+                        })
                     }
                     Some(_old_price) => {
                         tracing::warn!(
@@ -269,6 +319,34 @@ impl PriceGenerator {
         Ok(())
     }
 
+    /// Read every source and aggregate their prices
+    async fn get_price(&mut self) -> Result<Price> {
+        let mut prices = vec![];
+        for source in &self.sources {
+            match source.get_price(&self.client, self.token_type).await {
+                Ok(price) => prices.push(price),
+                Err(err) => {
+                    tracing::warn!(
+                        source = %source.name,
+                        "error in retrieving price for {:?}: {err:?}",
+                        self.token_type
+                    );
+                    Metrics::source_error(&source.name, self.token_type);
+                }
+            }
+        }
+        let now = Utc::now();
+        self.aggregator
+            .aggregate(prices, now)
+            .map(|aggregated| Price {
+                timestamp: now,
+                price: aggregated.price,
+                token_type: self.token_type,
+                sources: aggregated.sources,
+            })
+            .ok_or_else(|| anyhow!("unable to fetch price!"))
+    }
+
     fn is_valid(&self, price: &Price) -> bool {
         price.timestamp > Utc::now() - self.stale_price_duration
     }
@@ -304,22 +382,54 @@ impl PriceGenerator {
     }
 }
 
-pub async fn get_price(
-    client: &RpcClient,
-    price_key: &SolPubkey,
-    token_type: BlockchainTokenTypeV1,
-) -> Result<Price> {
-    let price_oracle_v0_data = client.get_account_data(price_key).await?;
-    let mut price_oracle_v0_data = price_oracle_v0_data.as_ref();
-    let price_oracle_v0 = PriceOracleV0::try_deserialize(&mut price_oracle_v0_data)?;
-
-    let current_time = Utc::now();
-    let current_timestamp = current_time.timestamp();
-
-    calculate_current_price(&price_oracle_v0.oracles, current_timestamp)
-        .map(|price| {
-            tracing::debug!("got price: {:?} for token_type: {:?}", price, token_type);
-            Price::new(current_time, price, token_type)
-        })
-        .ok_or_else(|| anyhow!("unable to fetch price!"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_sources_report_carries_every_source() {
+        let timestamp = Utc.timestamp_opt(1_680_000_060, 0).unwrap();
+        let price = Price {
+            timestamp,
+            price: 101,
+            token_type: BlockchainTokenTypeV1::Hnt,
+            sources: vec![
+                SourcePrice {
+                    source: "oracle".to_string(),
+                    timestamp,
+                    price: 100,
+                },
+                SourcePrice {
+                    source: "pyth".to_string(),
+                    timestamp: Utc.timestamp_opt(1_680_000_000, 0).unwrap(),
+                    price: 102,
+                },
+            ],
+        };
+
+        let report = PriceSourcesReportV1::from(&price);
+        assert_eq!(report.timestamp, 1_680_000_060);
+        assert_eq!(report.token_type(), BlockchainTokenTypeV1::Hnt);
+        assert_eq!(report.price, 101);
+        assert_eq!(
+            report.sources,
+            vec![
+                SourcePriceV1 {
+                    source: "oracle".to_string(),
+                    price: 100,
+                    timestamp: 1_680_000_060,
+                },
+                SourcePriceV1 {
+                    source: "pyth".to_string(),
+                    price: 102,
+                    timestamp: 1_680_000_000,
+                },
+            ]
+        );
+
+        let price_report = PriceReportV1::from(price);
+        assert_eq!(price_report.timestamp, report.timestamp);
+        assert_eq!(price_report.price, report.price);
+        assert_eq!(price_report.token_type, report.token_type);
+    }
 }
@@ -0,0 +1,514 @@
+use crate::settings::{AggregationMethod, AggregationSettings, SourceSettings, SourceType};
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use helium_anchor_gen::price_oracle::{calculate_current_price, PriceOracleV0};
+use helium_proto::BlockchainTokenTypeV1;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey as SolPubkey;
+use std::{collections::VecDeque, path::PathBuf, str::FromStr};
+use tokio::fs;
+
+/// magic number at the start of every pyth account
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+/// aggregate price status of a pyth price which is currently trading
+const PYTH_STATUS_TRADING: u32 = 1;
+// offsets into a pyth v2 price account
+const PYTH_EXPO_OFFSET: usize = 20;
+const PYTH_TIMESTAMP_OFFSET: usize = 96;
+const PYTH_AGG_PRICE_OFFSET: usize = 208;
+const PYTH_AGG_STATUS_OFFSET: usize = 224;
+
+/// A price as reported by a single source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcePrice {
+    pub source: String,
+    pub timestamp: DateTime<Utc>,
+    pub price: u64,
+}
+
+/// A location from which a price for a token is read
+pub struct PriceSource {
+    pub name: String,
+    kind: PriceSourceKind,
+}
+
+enum PriceSourceKind {
+    /// helium price oracle account, priced using the on chain median of
+    /// submitted oracle prices
+    PriceOracle(SolPubkey),
+    /// pyth style aggregate price account
+    Pyth(SolPubkey),
+    /// local json file of the form `{"price": 100000000, "timestamp": 1680000000}`
+    /// used as a stand-in for an off chain feed
+    JsonFeed(PathBuf),
+}
+
+impl PriceSource {
+    pub fn price_oracle(name: impl Into<String>, key: SolPubkey) -> Self {
+        Self {
+            name: name.into(),
+            kind: PriceSourceKind::PriceOracle(key),
+        }
+    }
+
+    pub fn from_settings(settings: &SourceSettings) -> Result<Self> {
+        let key = || {
+            settings
+                .key
+                .as_ref()
+                .ok_or_else(|| anyhow!("missing key for price source {}", settings.name))
+                .and_then(|key| {
+                    SolPubkey::from_str(key).map_err(|_| anyhow!("unable to parse {}", key))
+                })
+        };
+        let kind = match settings.r#type {
+            SourceType::PriceOracle => PriceSourceKind::PriceOracle(key()?),
+            SourceType::Pyth => PriceSourceKind::Pyth(key()?),
+            SourceType::JsonFeed => PriceSourceKind::JsonFeed(
+                settings
+                    .path
+                    .clone()
+                    .ok_or_else(|| anyhow!("missing path for price source {}", settings.name))?,
+            ),
+        };
+        Ok(Self {
+            name: settings.name.clone(),
+            kind,
+        })
+    }
+
+    pub async fn get_price(
+        &self,
+        client: &RpcClient,
+        token_type: BlockchainTokenTypeV1,
+    ) -> Result<SourcePrice> {
+        let (timestamp, price) = match &self.kind {
+            PriceSourceKind::PriceOracle(key) => {
+                let data = client.get_account_data(key).await?;
+                let price_oracle_v0 = PriceOracleV0::try_deserialize(&mut data.as_ref())?;
+                let current_time = Utc::now();
+                let price =
+                    calculate_current_price(&price_oracle_v0.oracles, current_time.timestamp())
+                        .ok_or_else(|| anyhow!("unable to fetch price!"))?;
+                (current_time, price)
+            }
+            PriceSourceKind::Pyth(key) => {
+                let data = client.get_account_data(key).await?;
+                pyth_price(&data, token_decimals(token_type))?
+            }
+            PriceSourceKind::JsonFeed(path) => {
+                #[derive(Deserialize)]
+                struct JsonFeedPrice {
+                    price: u64,
+                    timestamp: i64,
+                }
+                let feed: JsonFeedPrice = serde_json::from_slice(&fs::read(path).await?)?;
+                let timestamp = Utc
+                    .timestamp_opt(feed.timestamp, 0)
+                    .single()
+                    .ok_or_else(|| anyhow!("invalid timestamp"))?;
+                (timestamp, feed.price)
+            }
+        };
+        tracing::debug!(
+            "got price: {:?} for token_type: {:?} from {}",
+            price,
+            token_type,
+            self.name
+        );
+        Ok(SourcePrice {
+            source: self.name.clone(),
+            timestamp,
+            price,
+        })
+    }
+}
+
+/// The number of decimals prices of the given token are reported in
+pub fn token_decimals(token_type: BlockchainTokenTypeV1) -> i32 {
+    match token_type {
+        BlockchainTokenTypeV1::Hnt => 8,
+        BlockchainTokenTypeV1::Mobile | BlockchainTokenTypeV1::Iot | BlockchainTokenTypeV1::Hst => {
+            6
+        }
+    }
+}
+
+/// Decode the aggregate price of a pyth price account, scaled to the given
+/// number of decimals
+fn pyth_price(data: &[u8], decimals: i32) -> Result<(DateTime<Utc>, u64)> {
+    let u32_at = |offset: usize| -> Result<u32> {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap_or_default()))
+            .ok_or_else(|| anyhow!("pyth account too short"))
+    };
+    let i64_at = |offset: usize| -> Result<i64> {
+        data.get(offset..offset + 8)
+            .map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+            .ok_or_else(|| anyhow!("pyth account too short"))
+    };
+
+    if u32_at(0)? != PYTH_MAGIC {
+        return Err(anyhow!("not a pyth account"));
+    }
+    if u32_at(PYTH_AGG_STATUS_OFFSET)? != PYTH_STATUS_TRADING {
+        return Err(anyhow!("pyth price is not trading"));
+    }
+    let expo = u32_at(PYTH_EXPO_OFFSET)? as i32;
+    let timestamp = Utc
+        .timestamp_opt(i64_at(PYTH_TIMESTAMP_OFFSET)?, 0)
+        .single()
+        .ok_or_else(|| anyhow!("invalid timestamp"))?;
+    let price = u64::try_from(i64_at(PYTH_AGG_PRICE_OFFSET)?)
+        .map_err(|_| anyhow!("negative pyth price"))?;
+
+    let scale = expo + decimals;
+    let scaled = if scale >= 0 {
+        10u64
+            .checked_pow(scale as u32)
+            .and_then(|factor| price.checked_mul(factor))
+    } else {
+        10u64
+            .checked_pow(scale.unsigned_abs())
+            .map(|factor| price / factor)
+    }
+    .ok_or_else(|| anyhow!("pyth price out of range"))?;
+    Ok((timestamp, scaled))
+}
+
+/// Aggregated price along with the source prices which contributed to it
+#[derive(Debug, Clone)]
+pub struct AggregatedPrice {
+    pub price: u64,
+    pub sources: Vec<SourcePrice>,
+}
+
+/// Combines prices of several sources, rejecting stale and outlying prices
+pub struct PriceAggregator {
+    settings: AggregationSettings,
+    /// prior aggregated medians used for twap, oldest first
+    samples: VecDeque<(DateTime<Utc>, u64)>,
+    /// the last aggregated price, used to choose between two diverging
+    /// sources
+    last_price: Option<u64>,
+}
+
+impl PriceAggregator {
+    pub fn new(settings: AggregationSettings) -> Self {
+        Self {
+            settings,
+            samples: VecDeque::new(),
+            last_price: None,
+        }
+    }
+
+    /// Aggregate the prices of every source, given in configured order
+    pub fn aggregate(
+        &mut self,
+        prices: Vec<SourcePrice>,
+        now: DateTime<Utc>,
+    ) -> Option<AggregatedPrice> {
+        let fresh: Vec<SourcePrice> = prices
+            .into_iter()
+            .filter(|price| {
+                let fresh = now - price.timestamp <= self.settings.max_source_age();
+                if !fresh {
+                    tracing::warn!(source = %price.source, "rejecting stale price");
+                }
+                fresh
+            })
+            .collect();
+        let median = median_price(fresh.iter().map(|price| price.price))?;
+
+        let max_deviation = self.settings.max_deviation_percent;
+        let (accepted, rejected): (Vec<SourcePrice>, Vec<SourcePrice>) =
+            fresh.into_iter().partition(|price| {
+                let deviation = price.price.abs_diff(median) as f64 * 100.0 / median.max(1) as f64;
+                deviation <= max_deviation
+            });
+        let accepted = match (accepted.is_empty(), rejected.as_slice()) {
+            // the median of two diverging prices is their midpoint, which
+            // rejects both, so one of them is chosen instead
+            (true, [first, second]) => vec![self.tie_break(first, second).clone()],
+            _ => {
+                for price in &rejected {
+                    tracing::warn!(
+                        source = %price.source,
+                        price = price.price,
+                        median,
+                        "rejecting outlying price"
+                    );
+                }
+                accepted
+            }
+        };
+        if accepted.len() < self.settings.min_sources {
+            tracing::warn!(
+                "only {} of required {} price sources available",
+                accepted.len(),
+                self.settings.min_sources
+            );
+            return None;
+        }
+
+        let median = median_price(accepted.iter().map(|price| price.price))?;
+        let price = match self.settings.method {
+            AggregationMethod::Median => median,
+            AggregationMethod::Twap => self.twap(now, median),
+        };
+        self.last_price = Some(price);
+        Some(AggregatedPrice {
+            price,
+            sources: accepted,
+        })
+    }
+
+    /// Choose between two diverging source prices: the one closest to the
+    /// last aggregated price, or the first configured source if there is no
+    /// last price or both are equally close
+    fn tie_break<'a>(&self, first: &'a SourcePrice, second: &'a SourcePrice) -> &'a SourcePrice {
+        let chosen = match self.last_price {
+            Some(last) if second.price.abs_diff(last) < first.price.abs_diff(last) => second,
+            _ => first,
+        };
+        tracing::warn!(
+            source = %chosen.source,
+            first = first.price,
+            second = second.price,
+            "two diverging prices, using the price of {}",
+            chosen.source
+        );
+        chosen
+    }
+
+    /// Record the latest median and return the time weighted average of the
+    /// medians within the twap window, each weighted by how long it was the
+    /// latest
+    fn twap(&mut self, now: DateTime<Utc>, median: u64) -> u64 {
+        self.samples.push_back((now, median));
+        let window_start = now - self.settings.twap_window();
+        while self.samples.len() > 1
+            && self
+                .samples
+                .get(1)
+                .map_or(false, |(timestamp, _)| *timestamp <= window_start)
+        {
+            self.samples.pop_front();
+        }
+
+        let mut weighted_sum: u128 = 0;
+        let mut total_weight: u128 = 0;
+        let ends = self
+            .samples
+            .iter()
+            .skip(1)
+            .map(|(timestamp, _)| *timestamp)
+            .chain(std::iter::once(now));
+        for ((start, price), end) in self.samples.iter().zip(ends) {
+            let start = (*start).max(window_start);
+            let weight = (end - start).num_seconds().max(0) as u128;
+            weighted_sum += weight * *price as u128;
+            total_weight += weight;
+        }
+        if total_weight == 0 {
+            median
+        } else {
+            (weighted_sum / total_weight) as u64
+        }
+    }
+}
+
+fn median_price(prices: impl Iterator<Item = u64>) -> Option<u64> {
+    let mut prices: Vec<u64> = prices.collect();
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort_unstable();
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        Some(((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64)
+    } else {
+        Some(prices[mid])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::AggregationMethod;
+    use chrono::Duration;
+
+    /// A pyth v2 price account with the given aggregate price, laid out as
+    /// magic (0), expo (20), timestamp (96), agg.price (208), agg.status (224)
+    fn pyth_account(expo: i32, price: i64, status: u32, timestamp: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 240];
+        data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        data[4..8].copy_from_slice(&2u32.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[96..104].copy_from_slice(&timestamp.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&1000u64.to_le_bytes());
+        data[224..228].copy_from_slice(&status.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn parses_pyth_price_account() {
+        let data = pyth_account(-8, 350_000_000, 1, 1_680_000_000);
+        let (timestamp, price) = pyth_price(&data, 8).expect("price");
+        assert_eq!(timestamp.timestamp(), 1_680_000_000);
+        assert_eq!(price, 350_000_000);
+    }
+
+    #[test]
+    fn scales_pyth_price_to_token_decimals() {
+        let data = pyth_account(-8, 123_456_789, 1, 1_680_000_000);
+        assert_eq!(pyth_price(&data, 6).expect("price").1, 1_234_567);
+        let data = pyth_account(-4, 12_345, 1, 1_680_000_000);
+        assert_eq!(pyth_price(&data, 6).expect("price").1, 1_234_500);
+        let data = pyth_account(-4, i64::MAX, 1, 1_680_000_000);
+        assert!(pyth_price(&data, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_pyth_accounts() {
+        let mut data = pyth_account(-8, 350_000_000, 1, 1_680_000_000);
+        data[0] = 0;
+        assert!(pyth_price(&data, 8).is_err());
+        // not trading
+        let data = pyth_account(-8, 350_000_000, 0, 1_680_000_000);
+        assert!(pyth_price(&data, 8).is_err());
+        let data = pyth_account(-8, -1, 1, 1_680_000_000);
+        assert!(pyth_price(&data, 8).is_err());
+        let data = pyth_account(-8, 350_000_000, 1, 1_680_000_000);
+        assert!(pyth_price(&data[..224], 8).is_err());
+    }
+
+    fn source_price(source: &str, price: u64, timestamp: DateTime<Utc>) -> SourcePrice {
+        SourcePrice {
+            source: source.to_string(),
+            timestamp,
+            price,
+        }
+    }
+
+    fn sources(aggregated: &AggregatedPrice) -> Vec<&str> {
+        aggregated
+            .sources
+            .iter()
+            .map(|price| price.source.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn rejects_stale_and_outlying_prices() {
+        let now = Utc::now();
+        let mut aggregator = PriceAggregator::new(AggregationSettings::default());
+        let aggregated = aggregator
+            .aggregate(
+                vec![
+                    source_price("oracle", 100, now),
+                    source_price("pyth", 102, now),
+                    source_price("feed", 150, now),
+                    source_price("stale", 101, now - Duration::hours(2)),
+                ],
+                now,
+            )
+            .expect("aggregated");
+        assert_eq!(aggregated.price, 101);
+        assert_eq!(sources(&aggregated), vec!["oracle", "pyth"]);
+    }
+
+    #[test]
+    fn requires_min_sources() {
+        let now = Utc::now();
+        let mut aggregator = PriceAggregator::new(AggregationSettings {
+            min_sources: 2,
+            ..Default::default()
+        });
+        assert!(aggregator
+            .aggregate(
+                vec![
+                    source_price("oracle", 100, now),
+                    source_price("stale", 100, now - Duration::hours(2)),
+                ],
+                now,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn two_diverging_sources_use_the_first_without_a_last_price() {
+        let now = Utc::now();
+        let mut aggregator = PriceAggregator::new(AggregationSettings::default());
+        let aggregated = aggregator
+            .aggregate(
+                vec![
+                    source_price("oracle", 100, now),
+                    source_price("pyth", 200, now),
+                ],
+                now,
+            )
+            .expect("aggregated");
+        assert_eq!(aggregated.price, 100);
+        assert_eq!(sources(&aggregated), vec!["oracle"]);
+    }
+
+    #[test]
+    fn two_diverging_sources_use_the_closest_to_the_last_price() {
+        let now = Utc::now();
+        let mut aggregator = PriceAggregator::new(AggregationSettings::default());
+        aggregator
+            .aggregate(vec![source_price("pyth", 190, now)], now)
+            .expect("aggregated");
+        let aggregated = aggregator
+            .aggregate(
+                vec![
+                    source_price("oracle", 100, now),
+                    source_price("pyth", 200, now),
+                ],
+                now,
+            )
+            .expect("aggregated");
+        assert_eq!(aggregated.price, 200);
+        assert_eq!(sources(&aggregated), vec!["pyth"]);
+
+        // two agreeing sources are both used
+        let aggregated = aggregator
+            .aggregate(
+                vec![
+                    source_price("oracle", 200, now),
+                    source_price("pyth", 204, now),
+                ],
+                now,
+            )
+            .expect("aggregated");
+        assert_eq!(aggregated.price, 202);
+        assert_eq!(sources(&aggregated), vec!["oracle", "pyth"]);
+    }
+
+    #[test]
+    fn twap_weights_medians_by_duration() {
+        let start = Utc::now();
+        let mut aggregator = PriceAggregator::new(AggregationSettings {
+            method: AggregationMethod::Twap,
+            twap_window_secs: 300,
+            ..Default::default()
+        });
+        let mut aggregate = |price: u64, at: DateTime<Utc>| {
+            aggregator
+                .aggregate(vec![source_price("oracle", price, at)], at)
+                .expect("aggregated")
+                .price
+        };
+        assert_eq!(aggregate(100, start), 100);
+        // 100 for 60s then 200 at the end of the window
+        assert_eq!(aggregate(200, start + Duration::seconds(60)), 100);
+        // 100 for 60s and 200 for 180s
+        assert_eq!(aggregate(200, start + Duration::seconds(240)), 175);
+        // only 200 within the window
+        assert_eq!(aggregate(200, start + Duration::seconds(600)), 200);
+    }
+}
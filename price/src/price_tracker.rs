@@ -1,3 +1,4 @@
+use crate::metrics::Metrics;
 use anyhow::anyhow;
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use file_store::{FileInfo, FileStore, FileType};
@@ -7,7 +8,10 @@ use futures::{
 };
 use helium_proto::{BlockchainTokenTypeV1, Message, PriceReportV1};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+};
 use task_manager::ManagedTask;
 use tokio;
 use tokio::sync::{mpsc, watch};
@@ -52,23 +56,171 @@ impl TryFrom<&PriceReportV1> for Price {
     }
 }
 
-type Prices = HashMap<BlockchainTokenTypeV1, Price>;
+/// Latest and historic prices of every token. History is ordered oldest
+/// first and bounded by the retention duration
+struct Prices {
+    latest: HashMap<BlockchainTokenTypeV1, Price>,
+    history: HashMap<BlockchainTokenTypeV1, VecDeque<Price>>,
+    retention: Duration,
+}
+
+impl Prices {
+    fn new(retention: Duration) -> Self {
+        Self {
+            latest: HashMap::new(),
+            history: HashMap::new(),
+            retention,
+        }
+    }
+
+    fn insert(&mut self, token_type: BlockchainTokenTypeV1, price: Price) {
+        let history = self.history.entry(token_type).or_default();
+        if history
+            .back()
+            .map_or(true, |last| last.timestamp < price.timestamp)
+        {
+            history.push_back(price.clone());
+        }
+        let oldest = price.timestamp - self.retention;
+        while history
+            .front()
+            .map_or(false, |first| first.timestamp < oldest)
+        {
+            history.pop_front();
+        }
+        self.latest.insert(token_type, price);
+    }
+
+    /// The last price at or before the given time
+    fn price_at(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+        timestamp: DateTime<Utc>,
+    ) -> Option<&Price> {
+        self.history
+            .get(token_type)?
+            .iter()
+            .rev()
+            .find(|price| price.timestamp <= timestamp)
+    }
+
+    /// Time weighted average of the prices in effect over the range. Each
+    /// price is weighted by how long it was the latest price within the range
+    fn twap(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+        range: &Range<DateTime<Utc>>,
+    ) -> Option<u64> {
+        let history = self.history.get(token_type)?;
+        // the price in effect at the start of the range and every price
+        // reported within it
+        let first = history
+            .iter()
+            .rposition(|price| price.timestamp <= range.start)
+            .unwrap_or(0);
+        let prices: Vec<&Price> = history
+            .iter()
+            .skip(first)
+            .take_while(|price| price.timestamp < range.end)
+            .collect();
+        let ends = prices
+            .iter()
+            .skip(1)
+            .map(|price| price.timestamp)
+            .chain(std::iter::once(range.end));
+
+        let mut weighted_sum: u128 = 0;
+        let mut total_weight: u128 = 0;
+        for (price, end) in prices.iter().zip(ends) {
+            let start = price.timestamp.max(range.start);
+            let weight = (end - start).num_seconds().max(0) as u128;
+            weighted_sum += weight * price.price as u128;
+            total_weight += weight;
+        }
+        if total_weight == 0 {
+            None
+        } else {
+            Some((weighted_sum / total_weight) as u64)
+        }
+    }
+
+    /// The time weighted average price over the range, if the retained
+    /// history covers it. The range is covered when the prices in effect at
+    /// its start and end were both reported within the price duration
+    fn epoch_price(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+        range: &Range<DateTime<Utc>>,
+        price_duration: Duration,
+    ) -> Option<TrackedPrice> {
+        let covers = |timestamp: DateTime<Utc>| {
+            self.price_at(token_type, timestamp)
+                .filter(|price| price.timestamp > timestamp - price_duration)
+        };
+        covers(range.start)?;
+        let last = covers(range.end)?;
+        self.twap(token_type, range).map(|price| TrackedPrice {
+            price,
+            timestamp: last.timestamp,
+            stale: false,
+        })
+    }
+}
+
+/// What to do when the latest price of a token is older than the price
+/// duration, or when a reward epoch is not covered by the price history
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StalePriceBehavior {
+    /// Return an error to the caller only
+    FailCaller,
+    /// Return the last known price, flagged as stale
+    LastKnown,
+    /// Return an error to the caller and stop the price tracker, bringing
+    /// down the process
+    #[default]
+    Kill,
+}
+
+/// A price along with whether it is older than the price duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedPrice {
+    pub price: u64,
+    pub timestamp: DateTime<Utc>,
+    pub stale: bool,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     price_duration_minutes: u64,
+    /// Behavior when a price is older than the price duration. Default kill
+    #[serde(default)]
+    stale_price_behavior: StalePriceBehavior,
+    /// How long prices are kept for historic lookups (hours). Epochs older
+    /// than this fall back to the stale price behavior. Default 48
+    #[serde(default = "default_history_hours")]
+    history_hours: u64,
     file_store: file_store::Settings,
 }
 
+fn default_history_hours() -> u64 {
+    48
+}
+
 impl Settings {
     fn price_duration(&self) -> Duration {
         Duration::minutes(self.price_duration_minutes as i64)
     }
+
+    fn history_duration(&self) -> Duration {
+        Duration::hours(self.history_hours as i64).max(self.price_duration())
+    }
 }
 
 #[derive(Clone)]
 pub struct PriceTracker {
     price_duration: Duration,
+    stale_price_behavior: StalePriceBehavior,
     task_killer: mpsc::Sender<String>,
     price_receiver: watch::Receiver<Prices>,
 }
@@ -85,11 +237,14 @@ impl PriceTracker {
         PriceTrackerError,
     > {
         let file_store = FileStore::from_settings(&settings.file_store).await?;
-        let (price_sender, price_receiver) = watch::channel(Prices::new());
+        let (price_sender, price_receiver) =
+            watch::channel(Prices::new(settings.history_duration()));
         let (task_kill_sender, task_kill_receiver) = mpsc::channel(1);
         let initial_timestamp =
-            calculate_initial_prices(&file_store, settings.price_duration(), &price_sender).await?;
+            calculate_initial_prices(&file_store, settings.history_duration(), &price_sender)
+                .await?;
 
+        let price_duration = settings.price_duration();
         let shutdown_clone = shutdown.clone();
         let handle = tokio::spawn(async move {
             run(
@@ -97,13 +252,15 @@ impl PriceTracker {
                 task_kill_receiver,
                 price_sender,
                 initial_timestamp,
+                price_duration,
                 shutdown_clone,
             )
             .await
         });
 
         let tracker = Self {
-            price_duration: settings.price_duration(),
+            price_duration,
+            stale_price_behavior: settings.stale_price_behavior,
             task_killer: task_kill_sender,
             price_receiver,
         };
@@ -119,15 +276,16 @@ impl PriceTracker {
 
     pub async fn new_tm(settings: &Settings) -> anyhow::Result<(Self, PriceTrackerDaemon)> {
         let file_store = FileStore::from_settings(&settings.file_store).await?;
-        let price_duration = settings.price_duration();
-        let (price_sender, price_receiver) = watch::channel(Prices::new());
+        let history_duration = settings.history_duration();
+        let (price_sender, price_receiver) = watch::channel(Prices::new(history_duration));
         let (task_kill_sender, task_kill_receiver) = mpsc::channel(1);
         let initial_timestamp =
-            calculate_initial_prices(&file_store, price_duration, &price_sender).await?;
+            calculate_initial_prices(&file_store, history_duration, &price_sender).await?;
 
         Ok((
             Self {
                 price_duration: settings.price_duration(),
+                stale_price_behavior: settings.stale_price_behavior,
                 price_receiver,
                 task_killer: task_kill_sender,
             },
@@ -136,6 +294,7 @@ impl PriceTracker {
                 price_sender,
                 task_killer: task_kill_receiver,
                 after: initial_timestamp,
+                price_duration: settings.price_duration(),
             },
         ))
     }
@@ -144,32 +303,112 @@ impl PriceTracker {
         &self,
         token_type: &BlockchainTokenTypeV1,
     ) -> Result<u64, PriceTrackerError> {
-        let result = self
+        self.tracked_price(token_type)
+            .await
+            .map(|tracked| tracked.price)
+    }
+
+    /// The latest price of the token. When the price is older than the
+    /// price duration the configured stale price behavior applies
+    pub async fn tracked_price(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+    ) -> Result<TrackedPrice, PriceTrackerError> {
+        let stale_before = Utc::now() - self.price_duration;
+        let price = self
             .price_receiver
             .borrow()
+            .latest
             .get(token_type)
-            .ok_or(PriceTrackerError::PriceNotAvailable)
-            .and_then(|price| {
-                if price.timestamp > Utc::now() - self.price_duration {
-                    Ok(price.price)
-                } else {
-                    Err(PriceTrackerError::PriceTooOld(price.timestamp))
-                }
+            .map(|price| TrackedPrice {
+                price: price.price,
+                timestamp: price.timestamp,
+                stale: price.timestamp <= stale_before,
             });
+        self.apply_stale_price_behavior(token_type, price).await
+    }
 
-        if let Err(error) = &result {
-            self.task_killer.send(error.to_string()).await?;
+    /// The price of the token over a reward epoch. This is the time weighted
+    /// average over the range when the retained price history covers it, so
+    /// the result does not depend on when it is calculated. Otherwise the
+    /// latest price is used, flagged as stale, and the configured stale price
+    /// behavior applies
+    pub async fn epoch_price(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+        range: &Range<DateTime<Utc>>,
+    ) -> Result<TrackedPrice, PriceTrackerError> {
+        let price = {
+            let prices = self.price_receiver.borrow();
+            prices
+                .epoch_price(token_type, range, self.price_duration)
+                .or_else(|| {
+                    prices.latest.get(token_type).map(|price| TrackedPrice {
+                        price: price.price,
+                        timestamp: price.timestamp,
+                        stale: true,
+                    })
+                })
+        };
+        if matches!(price, Some(TrackedPrice { stale: true, .. })) {
+            tracing::warn!(
+                token = ?token_type,
+                "price history does not cover epoch {} to {}",
+                range.start,
+                range.end
+            );
+        }
+        self.apply_stale_price_behavior(token_type, price).await
+    }
+
+    async fn apply_stale_price_behavior(
+        &self,
+        token_type: &BlockchainTokenTypeV1,
+        price: Option<TrackedPrice>,
+    ) -> Result<TrackedPrice, PriceTrackerError> {
+        let result = stale_price_result(self.stale_price_behavior, price);
+
+        match &result {
+            Ok(tracked) if tracked.stale => {
+                tracing::warn!(
+                    token = ?token_type,
+                    "using stale price from {}",
+                    tracked.timestamp
+                );
+                Metrics::stale_price(*token_type);
+            }
+            Ok(_) => (),
+            Err(error) => {
+                Metrics::price_error(*token_type);
+                if self.stale_price_behavior == StalePriceBehavior::Kill {
+                    self.task_killer.send(error.to_string()).await?;
+                }
+            }
         }
 
         result
     }
 }
 
+/// Stale prices are only returned when the behavior allows the last known
+/// price, otherwise they are an error
+fn stale_price_result(
+    behavior: StalePriceBehavior,
+    price: Option<TrackedPrice>,
+) -> Result<TrackedPrice, PriceTrackerError> {
+    match price {
+        None => Err(PriceTrackerError::PriceNotAvailable),
+        Some(price) if !price.stale || behavior == StalePriceBehavior::LastKnown => Ok(price),
+        Some(price) => Err(PriceTrackerError::PriceTooOld(price.timestamp)),
+    }
+}
+
 async fn run(
     file_store: FileStore,
     mut task_killer: mpsc::Receiver<String>,
     price_sender: watch::Sender<Prices>,
     mut after: DateTime<Utc>,
+    price_duration: Duration,
     shutdown: triggered::Listener,
 ) -> Result<(), PriceTrackerError> {
     let mut trigger = tokio::time::interval(std::time::Duration::from_secs(30));
@@ -185,6 +424,7 @@ async fn run(
             _ = trigger.tick() => {
                 let timestamp = process_files(&file_store, &price_sender, after).await?;
                 after = timestamp.unwrap_or(after);
+                record_price_ages(&price_sender, price_duration);
             }
             msg = task_killer.recv() => if let Some(error) = msg {
                     return Err(PriceTrackerError::KilledError(error));
@@ -200,6 +440,7 @@ pub struct PriceTrackerDaemon {
     price_sender: watch::Sender<Prices>,
     task_killer: mpsc::Receiver<String>,
     after: DateTime<Utc>,
+    price_duration: Duration,
 }
 
 impl ManagedTask for PriceTrackerDaemon {
@@ -222,6 +463,7 @@ impl PriceTrackerDaemon {
                 _ = trigger.tick() => {
                     let timestamp = process_files(&self.file_store, &self.price_sender, self.after).await?;
                     self.after = timestamp.unwrap_or(self.after);
+                    record_price_ages(&self.price_sender, self.price_duration);
                 }
                 msg = self.task_killer.recv() => if let Some(error) = msg {
                     return Err(anyhow!(error));
@@ -233,13 +475,23 @@ impl PriceTrackerDaemon {
     }
 }
 
+/// Report the age of the latest price of every tracked token and whether it
+/// is young enough to be used
+fn record_price_ages(sender: &watch::Sender<Prices>, price_duration: Duration) {
+    let now = Utc::now();
+    for (token_type, price) in sender.borrow().latest.iter() {
+        let age = now - price.timestamp;
+        Metrics::tracked_price_age(*token_type, age.num_seconds() as f64, age < price_duration);
+    }
+}
+
 async fn calculate_initial_prices(
     file_store: &FileStore,
-    price_duration: Duration,
+    history_duration: Duration,
     sender: &watch::Sender<Prices>,
 ) -> Result<DateTime<Utc>, PriceTrackerError> {
     tracing::debug!("PriceTracker: Updating initial prices");
-    process_files(file_store, sender, Utc::now() - price_duration)
+    process_files(file_store, sender, Utc::now() - history_duration)
         .await?
         .ok_or(PriceTrackerError::PriceNotAvailable)
 }
@@ -287,3 +539,168 @@ async fn process_file(
 
     Ok(timestamp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: BlockchainTokenTypeV1 = BlockchainTokenTypeV1::Iot;
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000, 0).unwrap() + Duration::hours(hour)
+    }
+
+    fn prices(reports: &[(i64, u64)]) -> Prices {
+        let mut prices = Prices::new(Duration::hours(48));
+        for (hour, price) in reports {
+            prices.insert(
+                TOKEN,
+                Price {
+                    price: *price,
+                    timestamp: at(*hour),
+                },
+            );
+        }
+        prices
+    }
+
+    fn tracked(price: u64, hour: i64, stale: bool) -> TrackedPrice {
+        TrackedPrice {
+            price,
+            timestamp: at(hour),
+            stale,
+        }
+    }
+
+    #[test]
+    fn twap_weights_prices_by_duration_in_range() {
+        let prices = prices(&[(0, 100), (12, 200), (18, 400)]);
+        // 100 for 6h, 200 for 6h, 400 for 12h
+        assert_eq!(Some(275), prices.twap(&TOKEN, &(at(6)..at(30))));
+    }
+
+    #[test]
+    fn history_is_bounded_by_retention() {
+        let prices = prices(&[(0, 100), (24, 200), (50, 300)]);
+        assert!(prices.price_at(&TOKEN, at(1)).is_none());
+        assert_eq!(200, prices.price_at(&TOKEN, at(25)).unwrap().price);
+    }
+
+    #[test]
+    fn epoch_price_is_twap_when_history_covers_range() {
+        let prices = prices(&[(0, 100), (1, 100), (2, 300), (4, 300)]);
+        assert_eq!(
+            Some(tracked(200, 2, false)),
+            prices.epoch_price(&TOKEN, &(at(1)..at(3)), Duration::hours(2))
+        );
+    }
+
+    #[test]
+    fn epoch_price_requires_history_at_start_and_end() {
+        let prices = prices(&[(10, 100), (11, 200)]);
+        let duration = Duration::hours(2);
+        // epoch before the retained history
+        assert_eq!(None, prices.epoch_price(&TOKEN, &(at(8)..at(11)), duration));
+        // prices stopped during the epoch
+        assert_eq!(
+            None,
+            prices.epoch_price(&TOKEN, &(at(11)..at(14)), duration)
+        );
+    }
+
+    fn tracker(
+        prices: Prices,
+        behavior: StalePriceBehavior,
+    ) -> (PriceTracker, mpsc::Receiver<String>) {
+        let (task_killer, killed) = mpsc::channel(1);
+        let (_, price_receiver) = watch::channel(prices);
+        let tracker = PriceTracker {
+            price_duration: Duration::hours(1),
+            stale_price_behavior: behavior,
+            task_killer,
+            price_receiver,
+        };
+        (tracker, killed)
+    }
+
+    #[tokio::test]
+    async fn tracked_price_flags_stale_latest_price() {
+        let mut prices = Prices::new(Duration::hours(48));
+        let timestamp = Utc::now() - Duration::hours(2);
+        prices.insert(
+            TOKEN,
+            Price {
+                price: 100,
+                timestamp,
+            },
+        );
+        let (tracker, mut killed) = tracker(prices, StalePriceBehavior::LastKnown);
+
+        let price = tracker.tracked_price(&TOKEN).await.unwrap();
+        assert_eq!(
+            TrackedPrice {
+                price: 100,
+                timestamp,
+                stale: true
+            },
+            price
+        );
+        assert!(killed.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn epoch_outside_history_kills_tracker() {
+        let (tracker, mut killed) = tracker(prices(&[(10, 100)]), StalePriceBehavior::Kill);
+
+        let result = tracker.epoch_price(&TOKEN, &(at(0)..at(5))).await;
+        assert!(matches!(result, Err(PriceTrackerError::PriceTooOld(_))));
+        assert!(killed.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn epoch_outside_history_fails_caller_only() {
+        let (tracker, mut killed) = tracker(prices(&[(10, 100)]), StalePriceBehavior::FailCaller);
+
+        let result = tracker.epoch_price(&TOKEN, &(at(0)..at(5))).await;
+        assert!(matches!(result, Err(PriceTrackerError::PriceTooOld(_))));
+        assert!(killed.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn epoch_outside_history_uses_last_known_price() {
+        let (tracker, _killed) = tracker(
+            prices(&[(10, 100), (11, 200)]),
+            StalePriceBehavior::LastKnown,
+        );
+
+        let price = tracker.epoch_price(&TOKEN, &(at(0)..at(5))).await.unwrap();
+        assert_eq!(tracked(200, 11, true), price);
+    }
+
+    #[test]
+    fn stale_prices_follow_behavior() {
+        let fresh = tracked(100, 0, false);
+        let stale = tracked(100, 0, true);
+        for behavior in [
+            StalePriceBehavior::FailCaller,
+            StalePriceBehavior::LastKnown,
+            StalePriceBehavior::Kill,
+        ] {
+            assert_eq!(fresh, stale_price_result(behavior, Some(fresh)).unwrap());
+            assert!(matches!(
+                stale_price_result(behavior, None),
+                Err(PriceTrackerError::PriceNotAvailable)
+            ));
+        }
+        assert_eq!(
+            stale,
+            stale_price_result(StalePriceBehavior::LastKnown, Some(stale)).unwrap()
+        );
+        for behavior in [StalePriceBehavior::FailCaller, StalePriceBehavior::Kill] {
+            assert!(matches!(
+                stale_price_result(behavior, Some(stale)),
+                Err(PriceTrackerError::PriceTooOld(timestamp)) if timestamp == at(0)
+            ));
+        }
+    }
+}
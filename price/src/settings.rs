@@ -1,10 +1,14 @@
+use crate::price_source::PriceSource;
 use anyhow::{anyhow, Result};
 use chrono::Duration;
 use config::{Config, Environment, File};
 use helium_proto::BlockchainTokenTypeV1;
 use serde::Deserialize;
 use solana_sdk::pubkey::Pubkey as SolPubkey;
-use std::{path::Path, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ClusterConfig {
@@ -17,6 +21,115 @@ pub struct ClusterConfig {
     pub iot_price: Option<u64>,
     pub hst_price_key: Option<String>,
     pub hst_price: Option<u64>,
+    /// Price sources aggregated per token. A token without sources uses its
+    /// price key as its only source
+    #[serde(default)]
+    pub sources: Vec<SourceSettings>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SourceSettings {
+    /// Name recorded against prices from this source
+    pub name: String,
+    /// Token priced by this source, eg "hnt". Unknown tokens are rejected
+    /// when the settings are loaded
+    #[serde(deserialize_with = "deserialize_token")]
+    pub token: BlockchainTokenTypeV1,
+    pub r#type: SourceType,
+    /// Account key of price_oracle and pyth sources
+    pub key: Option<String>,
+    /// File path of json_feed sources
+    pub path: Option<PathBuf>,
+}
+
+fn deserialize_token<'de, D>(deserializer: D) -> Result<BlockchainTokenTypeV1, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let token = String::deserialize(deserializer)?;
+    BlockchainTokenTypeV1::from_str_name(&token.to_lowercase())
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown price source token: {token}")))
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    PriceOracle,
+    Pyth,
+    JsonFeed,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationMethod {
+    Median,
+    Twap,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AggregationSettings {
+    /// How source prices are combined. Default median
+    #[serde(default = "default_aggregation_method")]
+    pub method: AggregationMethod,
+    /// Max deviation from the median of all sources before a source price is
+    /// rejected as an outlier (percent). With exactly two sources which
+    /// deviate by more than this the source closest to the last aggregated
+    /// price is used, or the first configured source if there is none.
+    /// Default 5
+    #[serde(default = "default_max_deviation_percent")]
+    pub max_deviation_percent: f64,
+    /// Max age of a source price before it is rejected as stale (secs).
+    /// Default 1 hour
+    #[serde(default = "default_max_source_age_secs")]
+    pub max_source_age_secs: i64,
+    /// Min number of accepted source prices required. Default 1
+    #[serde(default = "default_min_sources")]
+    pub min_sources: usize,
+    /// Window of the time weighted average price (secs). Default 1 hour
+    #[serde(default = "default_twap_window_secs")]
+    pub twap_window_secs: i64,
+}
+
+impl Default for AggregationSettings {
+    fn default() -> Self {
+        Self {
+            method: default_aggregation_method(),
+            max_deviation_percent: default_max_deviation_percent(),
+            max_source_age_secs: default_max_source_age_secs(),
+            min_sources: default_min_sources(),
+            twap_window_secs: default_twap_window_secs(),
+        }
+    }
+}
+
+fn default_aggregation_method() -> AggregationMethod {
+    AggregationMethod::Median
+}
+
+fn default_max_deviation_percent() -> f64 {
+    5.0
+}
+
+fn default_max_source_age_secs() -> i64 {
+    60 * 60
+}
+
+fn default_min_sources() -> usize {
+    1
+}
+
+fn default_twap_window_secs() -> i64 {
+    60 * 60
+}
+
+impl AggregationSettings {
+    pub fn max_source_age(&self) -> Duration {
+        Duration::seconds(self.max_source_age_secs)
+    }
+
+    pub fn twap_window(&self) -> Duration {
+        Duration::seconds(self.twap_window_secs)
+    }
 }
 
 impl Default for ClusterConfig {
@@ -31,6 +144,7 @@ impl Default for ClusterConfig {
             iot_price: None,
             hst_price_key: None,
             hst_price: None,
+            sources: vec![],
         }
     }
 }
@@ -57,6 +171,9 @@ pub struct Settings {
     /// Cluster Configuration
     #[serde(default = "default_cluster")]
     pub cluster: ClusterConfig,
+    /// Aggregation of prices from multiple sources
+    #[serde(default)]
+    pub aggregation: AggregationSettings,
     /// How long to use a stale price in minutes
     #[serde(default = "default_stale_price_minutes")]
     pub stale_price_minutes: u64,
@@ -124,6 +241,26 @@ impl Settings {
             .transpose()
     }
 
+    /// Sources configured for the given token, falling back to the token's
+    /// price key
+    pub fn price_sources(&self, token_type: BlockchainTokenTypeV1) -> Result<Vec<PriceSource>> {
+        let sources = self
+            .cluster
+            .sources
+            .iter()
+            .filter(|source| source.token == token_type)
+            .map(PriceSource::from_settings)
+            .collect::<Result<Vec<_>>>()?;
+        if !sources.is_empty() {
+            return Ok(sources);
+        }
+        Ok(self
+            .price_key(token_type)?
+            .map(|key| PriceSource::price_oracle("price_oracle", key))
+            .into_iter()
+            .collect())
+    }
+
     pub fn default_price(&self, token_type: BlockchainTokenTypeV1) -> Option<u64> {
         match token_type {
             BlockchainTokenTypeV1::Hnt => self.cluster.hnt_price,
@@ -142,3 +279,31 @@ impl Settings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rejects_unknown_source_tokens() {
+        let source = |token: &str| {
+            serde_json::from_value::<SourceSettings>(json!({
+                "name": "pyth",
+                "token": token,
+                "type": "pyth",
+                "key": "6Eg8YdfFJQF2HHonzPUBSCCmyUEhrStg9VBLK957sBe6",
+            }))
+        };
+        assert_eq!(
+            source("hnt").expect("hnt source").token,
+            BlockchainTokenTypeV1::Hnt
+        );
+        assert_eq!(
+            source("IOT").expect("iot source").token,
+            BlockchainTokenTypeV1::Iot
+        );
+        assert!(source("hnt ").is_err());
+        assert!(source("hntt").is_err());
+    }
+}
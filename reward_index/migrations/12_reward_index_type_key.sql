@@ -0,0 +1,5 @@
+-- rows indexed before reward types were recorded have no type. The type can
+-- not be derived from the address, so these are kept untyped and reported
+-- as such, and typed rewards of the same address are totalled separately
+alter table reward_index drop constraint reward_index_pkey;
+create unique index reward_index_address_type_idx on reward_index (address, reward_type);
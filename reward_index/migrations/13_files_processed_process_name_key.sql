@@ -0,0 +1,3 @@
+-- sources indexed into the same database may write files with the same name
+alter table files_processed drop constraint files_processed_pkey;
+alter table files_processed add primary key (process_name, file_name);
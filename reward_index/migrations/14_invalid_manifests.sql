@@ -0,0 +1,9 @@
+-- manifests which failed validation, recorded as processed but not indexed
+create table invalid_manifests (
+    process_name text not null,
+    manifest text not null,
+    reason text not null,
+    error text not null,
+    inserted_at timestamptz not null default now(),
+    primary key (process_name, manifest)
+);
@@ -0,0 +1,9 @@
+-- manifests whose rewards were rolled back. They stay recorded as processed,
+-- so the file poller does not index them again, and are only indexed again
+-- on request
+create table rolled_back_manifests (
+    process_name text not null,
+    manifest text not null,
+    rolled_back_at timestamptz not null default now(),
+    primary key (process_name, manifest)
+);
@@ -0,0 +1,200 @@
+use crate::reward_index::{self, ListCursor, RewardIndex};
+use base64::Engine;
+use helium_crypto::Keypair;
+use http_api::{
+    hyper::{self, Body, Method, Request, Response, StatusCode},
+    query_param, signed, status,
+};
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+pub use http_api::{SIGNATURE_HEADER, SIGNER_HEADER};
+
+pub const REWARDS_PATH: &str = "/rewards";
+pub const BATCH_PATH: &str = "/rewards/batch";
+
+const MAX_BATCH_SIZE: usize = 100;
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Serves reward totals over http.
+///
+/// - `GET /rewards/<address>` returns the reward totals of an address
+/// - `POST /rewards/batch` with a json array of addresses returns the reward
+///   totals of each address
+/// - `GET /rewards?limit=<n>&cursor=<cursor>` returns a page of reward totals,
+///   most recently rewarded first, along with the cursor of the next page
+///
+/// Responses are json, signed by the configured keypair. The signature of the
+/// exact response body is in the `x-signature` header, the signer in
+/// `x-signer`.
+pub struct ApiServer {
+    pub socket_addr: SocketAddr,
+    pool: Pool<Postgres>,
+    signing_key: Arc<Keypair>,
+}
+
+#[derive(Serialize)]
+struct AddressRewards {
+    address: String,
+    total: i64,
+    rewards: Vec<RewardIndex>,
+}
+
+impl AddressRewards {
+    fn new(address: String, rewards: Vec<RewardIndex>) -> Self {
+        Self {
+            total: rewards.iter().map(|reward| reward.rewards).sum(),
+            address,
+            rewards,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RewardsPage {
+    rewards: Vec<RewardIndex>,
+    cursor: Option<String>,
+}
+
+impl ApiServer {
+    pub fn new(socket_addr: SocketAddr, pool: Pool<Postgres>, signing_key: Keypair) -> Self {
+        Self {
+            socket_addr,
+            pool,
+            signing_key: Arc::new(signing_key),
+        }
+    }
+
+    pub async fn run(self, shutdown: triggered::Listener) -> anyhow::Result<()> {
+        tracing::info!(listen = self.socket_addr.to_string(), "starting api");
+        let pool = self.pool;
+        let signing_key = self.signing_key;
+        http_api::serve(
+            &self.socket_addr,
+            move |req| {
+                let pool = pool.clone();
+                let signing_key = signing_key.clone();
+                async move { handle(&pool, &signing_key, req).await }
+            },
+            shutdown,
+        )
+        .await?;
+        tracing::info!("stopping api server");
+        Ok(())
+    }
+}
+
+/// Route a single request of the api, responding with a signed json body or
+/// an empty error status
+pub async fn handle(
+    pool: &Pool<Postgres>,
+    signing_key: &Keypair,
+    req: Request<Body>,
+) -> Response<Body> {
+    metrics::increment_counter!("reward_index_api_request_count");
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let result = match (&method, path.as_str()) {
+        (&Method::POST, BATCH_PATH) => batch(pool, req).await,
+        (&Method::GET, REWARDS_PATH) => list(pool, req.uri().query().unwrap_or_default()).await,
+        (&Method::GET, path) => match path
+            .strip_prefix(REWARDS_PATH)
+            .and_then(|p| p.strip_prefix('/'))
+        {
+            Some(address) if !address.is_empty() => lookup(pool, address).await,
+            _ => Err(StatusCode::NOT_FOUND),
+        },
+        _ => Err(StatusCode::NOT_FOUND),
+    };
+    match result.and_then(|json| signed(signing_key, json)) {
+        Ok(response) => response,
+        Err(code) => status(code),
+    }
+}
+
+async fn lookup(pool: &Pool<Postgres>, address: &str) -> Result<Vec<u8>, StatusCode> {
+    let rewards = reward_index::fetch(pool, address)
+        .await
+        .map_err(internal_error)?;
+    to_json(&AddressRewards::new(address.to_string(), rewards))
+}
+
+async fn batch(pool: &Pool<Postgres>, req: Request<Body>) -> Result<Vec<u8>, StatusCode> {
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let addresses: Vec<String> =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if addresses.len() > MAX_BATCH_SIZE {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let mut by_address: HashMap<String, Vec<RewardIndex>> = HashMap::new();
+    for reward in reward_index::fetch_batch(pool, &addresses)
+        .await
+        .map_err(internal_error)?
+    {
+        by_address
+            .entry(reward.address.clone())
+            .or_default()
+            .push(reward);
+    }
+    let response: Vec<AddressRewards> = addresses
+        .into_iter()
+        .map(|address| {
+            let rewards = by_address.get(&address).cloned().unwrap_or_default();
+            AddressRewards::new(address, rewards)
+        })
+        .collect();
+    to_json(&response)
+}
+
+async fn list(pool: &Pool<Postgres>, query: &str) -> Result<Vec<u8>, StatusCode> {
+    let limit = match query_param(query, "limit") {
+        Some(limit) => limit
+            .parse::<i64>()
+            .map_err(|_| StatusCode::BAD_REQUEST)?
+            .clamp(1, MAX_PAGE_SIZE),
+        None => DEFAULT_PAGE_SIZE,
+    };
+    let cursor = query_param(query, "cursor")
+        .map(decode_cursor)
+        .transpose()?;
+    let rewards = reward_index::list(pool, cursor.as_ref(), limit)
+        .await
+        .map_err(internal_error)?;
+    let cursor = if rewards.len() as i64 == limit {
+        rewards
+            .last()
+            .and_then(ListCursor::after)
+            .map(|cursor| encode_cursor(&cursor))
+            .transpose()?
+    } else {
+        None
+    };
+    to_json(&RewardsPage { rewards, cursor })
+}
+
+fn encode_cursor(cursor: &ListCursor) -> Result<String, StatusCode> {
+    serde_json::to_vec(cursor)
+        .map(|json| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn decode_cursor(cursor: &str) -> Result<ListCursor, StatusCode> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(StatusCode::BAD_REQUEST)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, StatusCode> {
+    serde_json::to_vec(value).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn internal_error(err: sqlx::Error) -> StatusCode {
+    tracing::error!("reward index api query failed: {err:?}");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
@@ -1,8 +1,14 @@
-use crate::{reward_index, settings, telemetry, Settings};
+use crate::{
+    manifest_validator::ManifestValidator,
+    reward_index,
+    settings::{self, SourceSettings},
+    telemetry, Settings,
+};
 use anyhow::{anyhow, bail, Result};
 use chrono::Utc;
 use file_store::{
-    file_info_poller::FileInfoStream, reward_manifest::RewardManifest, FileInfo, FileStore,
+    file_info_poller::FileInfoStream, reward_manifest::RewardManifest, traits::MsgDecode, FileInfo,
+    FileStore,
 };
 use futures::{stream, StreamExt, TryStreamExt};
 use helium_crypto::PublicKeyBinary;
@@ -11,7 +17,7 @@ use helium_proto::{
     services::poc_mobile::{mobile_reward_share::Reward as MobileReward, MobileRewardShare},
     Message, ServiceProvider,
 };
-use poc_metrics::record_duration;
+use serde::Serialize;
 use sqlx::{Pool, Postgres, Transaction};
 use std::{collections::HashMap, str::FromStr};
 use tokio::sync::mpsc::Receiver;
@@ -20,12 +26,15 @@ pub struct Indexer {
     pool: Pool<Postgres>,
     verifier_store: FileStore,
     mode: settings::Mode,
+    process_name: String,
     op_fund_key: String,
     unallocated_reward_key: String,
+    validate_manifests: bool,
 }
 
-#[derive(sqlx::Type, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(sqlx::Type, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "reward_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum RewardType {
     MobileGateway,
     IotGateway,
@@ -36,6 +45,20 @@ pub enum RewardType {
     IotUnallocated,
 }
 
+impl std::fmt::Display for RewardType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MobileGateway => "mobile_gateway",
+            Self::IotGateway => "iot_gateway",
+            Self::IotOperational => "iot_operational",
+            Self::MobileSubscriber => "mobile_subscriber",
+            Self::MobileServiceProvider => "mobile_service_provider",
+            Self::MobileUnallocated => "mobile_unallocated",
+            Self::IotUnallocated => "iot_unallocated",
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RewardKey {
     key: String,
@@ -43,20 +66,28 @@ pub struct RewardKey {
 }
 
 impl Indexer {
-    pub async fn new(settings: &Settings, pool: Pool<Postgres>) -> Result<Self> {
+    pub async fn new(
+        settings: &Settings,
+        source: &SourceSettings,
+        pool: Pool<Postgres>,
+    ) -> Result<Self> {
         Ok(Self {
-            mode: settings.mode,
-            verifier_store: FileStore::from_settings(&settings.verifier).await?,
+            mode: source.mode,
+            process_name: source.process_name(),
+            verifier_store: FileStore::from_settings(&source.verifier).await?,
             pool,
-            op_fund_key: match settings.mode {
-                settings::Mode::Iot => settings
-                    .operation_fund_key()
+            op_fund_key: match source.mode {
+                settings::Mode::Iot => source
+                    .operation_fund_key
+                    .clone()
                     .ok_or_else(|| anyhow!("operation fund key is required for IOT mode"))?,
                 settings::Mode::Mobile => String::new(),
             },
-            unallocated_reward_key: settings
-                .unallocated_reward_entity_key()
+            unallocated_reward_key: source
+                .unallocated_reward_entity_key
+                .clone()
                 .ok_or_else(|| anyhow!("missing unallocated reward key"))?,
+            validate_manifests: settings.validate_manifests,
         })
     }
 
@@ -65,41 +96,95 @@ impl Indexer {
         shutdown: triggered::Listener,
         mut receiver: Receiver<FileInfoStream<RewardManifest>>,
     ) -> Result<()> {
-        tracing::info!(mode = self.mode.to_string(), "starting index");
+        tracing::info!(
+            mode = self.mode.to_string(),
+            source = %self.process_name,
+            "starting index"
+        );
 
         loop {
             tokio::select! {
             biased;
             _ = shutdown.clone() => {
-                    tracing::info!("Indexer shutting down");
+                    tracing::info!(source = %self.process_name, "Indexer shutting down");
                     return Ok(());
             }
             msg = receiver.recv() => if let Some(file_info_stream) = msg {
                     let key = &file_info_stream.file_info.key.clone();
-                    tracing::info!(file = %key, "Processing reward file");
+                    tracing::info!(
+                        file = %key,
+                        source = %self.process_name,
+                        "Processing reward file"
+                    );
                     let mut txn = self.pool.begin().await?;
                     let mut stream = file_info_stream.into_stream(&mut txn).await?;
+                    // a rolled back manifest is only indexed again on request
+                    if reward_index::is_rolled_back(&mut txn, &self.process_name, key).await? {
+                        tracing::warn!(
+                            file = %key,
+                            source = %self.process_name,
+                            "skipping rolled back reward file"
+                        );
+                        txn.commit().await?;
+                        continue;
+                    }
 
                     while let Some(reward_manifest) = stream.next().await {
-                        record_duration!(
+                        let timer = std::time::Instant::now();
+                        self.handle_rewards(&mut txn, key, reward_manifest).await?;
+                        metrics::histogram!(
                             "reward_index_duration",
-                            self.handle_rewards(&mut txn, reward_manifest).await?
-                        )
+                            timer.elapsed(),
+                            "source" => self.process_name.clone()
+                        );
                     }
                     txn.commit().await?;
-                    tracing::info!(file = %key, "Completed processing reward file");
-                    telemetry::last_reward_processed_time(&self.pool, Utc::now()).await?;
+                    tracing::info!(
+                        file = %key,
+                        source = %self.process_name,
+                        "Completed processing reward file"
+                    );
+                    telemetry::last_reward_processed_time(
+                        &self.pool,
+                        &self.process_name,
+                        Utc::now(),
+                    )
+                    .await?;
                 }
             }
         }
     }
 
+    /// Index a rolled back manifest again, for example once its reward
+    /// files have been corrected
+    pub async fn reindex(&mut self, manifest_key: &str) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        if !reward_index::is_rolled_back(&mut txn, &self.process_name, manifest_key).await? {
+            bail!(
+                "manifest {manifest_key} of source {} has not been rolled back",
+                self.process_name
+            );
+        }
+        let file_info = FileInfo::from_str(manifest_key)?;
+        let mut msgs = self.verifier_store.stream_file(file_info).await?;
+        while let Some(msg) = msgs.try_next().await? {
+            let reward_manifest = RewardManifest::decode(msg)?;
+            self.handle_rewards(&mut txn, manifest_key, reward_manifest)
+                .await?;
+        }
+        reward_index::clear_rolled_back(&mut txn, &self.process_name, manifest_key).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
     async fn handle_rewards(
         &mut self,
         txn: &mut Transaction<'_, Postgres>,
+        manifest_key: &str,
         manifest: RewardManifest,
     ) -> Result<()> {
         let manifest_time = manifest.end_timestamp;
+        let manifest_start = manifest.start_timestamp;
 
         let reward_files = stream::iter(
             manifest
@@ -111,13 +196,52 @@ impl Indexer {
 
         let mut reward_shares = self.verifier_store.source_unordered(5, reward_files);
         let mut hotspot_rewards: HashMap<RewardKey, u64> = HashMap::new();
+        let mut validator = ManifestValidator::new(self.mode);
 
         while let Some(msg) = reward_shares.try_next().await? {
-            let (key, amount) = self.extract_reward_share(&msg)?;
+            let (key, amount, share_id) = self.extract_reward_share(&msg)?;
+            validator.add(share_id, &key.reward_type, amount);
             *hotspot_rewards.entry(key).or_default() += amount;
         }
 
+        if self.validate_manifests {
+            if let Err(err) = validator.validate(&(manifest_start..manifest_time)) {
+                // skip the manifest rather than failing, so the indexer does
+                // not restart on it forever. It stays flagged for an operator
+                tracing::error!(
+                    manifest = %manifest_key,
+                    source = %self.process_name,
+                    "skipping invalid manifest: {err}"
+                );
+                metrics::increment_counter!(
+                    "reward_index_invalid_manifest",
+                    "reason" => err.reason(),
+                    "source" => self.process_name.clone()
+                );
+                reward_index::insert_invalid_manifest(
+                    &mut *txn,
+                    &self.process_name,
+                    manifest_key,
+                    err.reason(),
+                    &err.to_string(),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+
         for (reward_key, amount) in hotspot_rewards {
+            reward_index::insert_history(
+                &mut *txn,
+                &self.process_name,
+                manifest_key,
+                &reward_key.key,
+                amount,
+                reward_key.reward_type.clone(),
+                &manifest_start,
+                &manifest_time,
+            )
+            .await?;
             reward_index::insert(
                 &mut *txn,
                 reward_key.key,
@@ -131,32 +255,46 @@ impl Indexer {
         Ok(())
     }
 
-    fn extract_reward_share(&self, msg: &[u8]) -> Result<(RewardKey, u64)> {
+    /// Decode a reward share into its reward key and amount, along with an
+    /// id identifying the share within the reward period
+    fn extract_reward_share(&self, msg: &[u8]) -> Result<(RewardKey, u64, String)> {
         match self.mode {
             settings::Mode::Mobile => {
                 let share = MobileRewardShare::decode(msg)?;
                 match share.reward {
-                    Some(MobileReward::RadioReward(r)) => Ok((
-                        RewardKey {
-                            key: PublicKeyBinary::from(r.hotspot_key).to_string(),
-                            reward_type: RewardType::MobileGateway,
-                        },
-                        r.poc_reward,
-                    )),
-                    Some(MobileReward::GatewayReward(r)) => Ok((
-                        RewardKey {
-                            key: PublicKeyBinary::from(r.hotspot_key).to_string(),
-                            reward_type: RewardType::MobileGateway,
-                        },
-                        r.dc_transfer_reward,
-                    )),
-                    Some(MobileReward::SubscriberReward(r)) => Ok((
-                        RewardKey {
-                            key: bs58::encode(&r.subscriber_id).into_string(),
-                            reward_type: RewardType::MobileSubscriber,
-                        },
-                        r.discovery_location_amount,
-                    )),
+                    Some(MobileReward::RadioReward(r)) => {
+                        let key = PublicKeyBinary::from(r.hotspot_key).to_string();
+                        Ok((
+                            RewardKey {
+                                key: key.clone(),
+                                reward_type: RewardType::MobileGateway,
+                            },
+                            r.poc_reward,
+                            format!("radio:{key}:{}", r.cbsd_id),
+                        ))
+                    }
+                    Some(MobileReward::GatewayReward(r)) => {
+                        let key = PublicKeyBinary::from(r.hotspot_key).to_string();
+                        Ok((
+                            RewardKey {
+                                key: key.clone(),
+                                reward_type: RewardType::MobileGateway,
+                            },
+                            r.dc_transfer_reward,
+                            format!("gateway:{key}"),
+                        ))
+                    }
+                    Some(MobileReward::SubscriberReward(r)) => {
+                        let key = bs58::encode(&r.subscriber_id).into_string();
+                        Ok((
+                            RewardKey {
+                                key: key.clone(),
+                                reward_type: RewardType::MobileSubscriber,
+                            },
+                            r.discovery_location_amount,
+                            format!("subscriber:{key}"),
+                        ))
+                    }
                     Some(MobileReward::ServiceProviderReward(r)) => {
                         if let Some(sp) = ServiceProvider::from_i32(r.service_provider_id) {
                             Ok((
@@ -165,6 +303,7 @@ impl Indexer {
                                     reward_type: RewardType::MobileServiceProvider,
                                 },
                                 r.amount,
+                                format!("service_provider:{sp}"),
                             ))
                         } else {
                             bail!("failed to decode service provider")
@@ -176,6 +315,7 @@ impl Indexer {
                             reward_type: RewardType::MobileUnallocated,
                         },
                         r.amount,
+                        format!("unallocated:{}", r.reward_type),
                     )),
                     _ => bail!("got an invalid reward share"),
                 }
@@ -183,19 +323,24 @@ impl Indexer {
             settings::Mode::Iot => {
                 let share = IotRewardShare::decode(msg)?;
                 match share.reward {
-                    Some(IotReward::GatewayReward(r)) => Ok((
-                        RewardKey {
-                            key: PublicKeyBinary::from(r.hotspot_key).to_string(),
-                            reward_type: RewardType::IotGateway,
-                        },
-                        r.witness_amount + r.beacon_amount + r.dc_transfer_amount,
-                    )),
+                    Some(IotReward::GatewayReward(r)) => {
+                        let key = PublicKeyBinary::from(r.hotspot_key).to_string();
+                        Ok((
+                            RewardKey {
+                                key: key.clone(),
+                                reward_type: RewardType::IotGateway,
+                            },
+                            r.witness_amount + r.beacon_amount + r.dc_transfer_amount,
+                            format!("gateway:{key}"),
+                        ))
+                    }
                     Some(IotReward::OperationalReward(r)) => Ok((
                         RewardKey {
                             key: self.op_fund_key.clone(),
                             reward_type: RewardType::IotOperational,
                         },
                         r.amount,
+                        "operational".to_string(),
                     )),
                     Some(IotReward::UnallocatedReward(r)) => Ok((
                         RewardKey {
@@ -203,6 +348,7 @@ impl Indexer {
                             reward_type: RewardType::IotUnallocated,
                         },
                         r.amount,
+                        format!("unallocated:{}", r.reward_type),
                     )),
                     _ => bail!("got an invalid iot reward share"),
                 }
@@ -1,7 +1,10 @@
+pub mod api_server;
 pub mod indexer;
-mod reward_index;
+pub mod manifest_validator;
+pub mod reward_index;
 pub mod settings;
 pub mod telemetry;
 
+pub use api_server::ApiServer;
 pub use indexer::Indexer;
 pub use settings::Settings;
@@ -1,12 +1,15 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use chrono::{TimeZone, Utc};
 use clap::Parser;
 use file_store::{
     file_info_poller::LookbackBehavior, file_source, reward_manifest::RewardManifest, FileStore,
     FileType,
 };
-use futures_util::TryFutureExt;
-use reward_index::{settings::Settings, telemetry, Indexer};
+use futures_util::{future, TryFutureExt};
+use reward_index::{
+    settings::{Settings, SourceSettings},
+    telemetry, ApiServer, Indexer,
+};
 use std::path::PathBuf;
 use tokio::signal;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -35,12 +38,169 @@ impl Cli {
 #[derive(Debug, clap::Subcommand)]
 pub enum Cmd {
     Server(Server),
+    Rewards(Rewards),
+    History(History),
+    Rollback(Rollback),
+    Reindex(Reindex),
 }
 
 impl Cmd {
     pub async fn run(&self, settings: Settings) -> Result<()> {
         match self {
             Self::Server(cmd) => cmd.run(&settings).await,
+            Self::Rewards(cmd) => cmd.run(&settings).await,
+            Self::History(cmd) => cmd.run(&settings).await,
+            Self::Rollback(cmd) => cmd.run(&settings).await,
+            Self::Reindex(cmd) => cmd.run(&settings).await,
+        }
+    }
+}
+
+/// Print the reward totals of an address, broken down by reward type
+#[derive(Debug, clap::Args)]
+pub struct Rewards {
+    /// Address to look up rewards for
+    address: String,
+}
+
+impl Rewards {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        let pool = settings
+            .database
+            .connect(&format!("{}_rewards", env!("CARGO_PKG_NAME")))
+            .await?;
+        let rewards = reward_index::reward_index::fetch(&pool, &self.address).await?;
+        let total: i64 = rewards.iter().map(|reward| reward.rewards).sum();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "address": self.address,
+                "total": total,
+                "rewards": rewards,
+            }))?
+        );
+        Ok(())
+    }
+}
+
+/// Print the per epoch rewards of an address
+#[derive(Debug, clap::Args)]
+pub struct History {
+    /// Address to look up rewards for
+    address: String,
+    /// Only print the rewards of the epoch covering this unix timestamp
+    #[clap(long)]
+    timestamp: Option<i64>,
+    /// Maximum number of rewards to print, most recent first
+    #[clap(long, default_value = "100")]
+    limit: i64,
+}
+
+impl History {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        let pool = settings
+            .database
+            .connect(&format!("{}_history", env!("CARGO_PKG_NAME")))
+            .await?;
+        let history = match self.timestamp {
+            Some(timestamp) => {
+                let timestamp = Utc
+                    .timestamp_opt(timestamp, 0)
+                    .single()
+                    .ok_or_else(|| anyhow!("invalid timestamp {timestamp}"))?;
+                reward_index::reward_index::history_at(&pool, &self.address, &timestamp).await?
+            }
+            None => reward_index::reward_index::history(&pool, &self.address, self.limit).await?,
+        };
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        Ok(())
+    }
+}
+
+/// Subtract the rewards of a reward manifest from the reward totals
+#[derive(Debug, clap::Args)]
+pub struct Rollback {
+    /// Key of the reward manifest file to roll back
+    manifest: String,
+    /// Process name of the source the manifest was indexed from. Required
+    /// when more than one source is configured
+    #[clap(long)]
+    source: Option<String>,
+}
+
+impl Rollback {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        let process_name = source_settings(settings, &self.source)?.process_name();
+        let pool = settings
+            .database
+            .connect(&format!("{}_rollback", env!("CARGO_PKG_NAME")))
+            .await?;
+        let mut txn = pool.begin().await?;
+        let rewards =
+            reward_index::reward_index::manifest_history(&mut txn, &process_name, &self.manifest)
+                .await?;
+        if rewards.is_empty() {
+            bail!(
+                "no rewards recorded for manifest {} of source {process_name}",
+                self.manifest
+            );
+        }
+        let rolled_back =
+            reward_index::reward_index::rollback_manifest(&mut txn, &process_name, &self.manifest)
+                .await?;
+        txn.commit().await?;
+        println!(
+            "rolled back {} rewards of manifest {} of source {process_name} across {rolled_back} reward totals",
+            rewards.len(),
+            self.manifest
+        );
+        Ok(())
+    }
+}
+
+/// Index a rolled back reward manifest again
+#[derive(Debug, clap::Args)]
+pub struct Reindex {
+    /// Key of the rolled back reward manifest file to index
+    manifest: String,
+    /// Process name of the source the manifest was indexed from. Required
+    /// when more than one source is configured
+    #[clap(long)]
+    source: Option<String>,
+}
+
+impl Reindex {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        let source = source_settings(settings, &self.source)?;
+        let pool = settings
+            .database
+            .connect(&format!("{}_reindex", env!("CARGO_PKG_NAME")))
+            .await?;
+        let mut indexer = Indexer::new(settings, &source, pool).await?;
+        indexer.reindex(&self.manifest).await?;
+        println!(
+            "indexed manifest {} of source {} again",
+            self.manifest,
+            source.process_name()
+        );
+        Ok(())
+    }
+}
+
+/// The settings of the source with the given process name, which may only
+/// be omitted when a single source is configured
+fn source_settings(settings: &Settings, source: &Option<String>) -> Result<SourceSettings> {
+    let mut sources = settings.sources()?;
+    match source {
+        Some(source) => match sources.iter().position(|s| &s.process_name() == source) {
+            Some(idx) => Ok(sources.swap_remove(idx)),
+            None => bail!("unknown source {source}"),
+        },
+        None if sources.len() == 1 => Ok(sources.remove(0)),
+        None => {
+            let process_names: Vec<String> =
+                sources.iter().map(|source| source.process_name()).collect();
+            bail!("--source is required, one of {}", process_names.join(", "))
         }
     }
 }
@@ -69,35 +229,62 @@ impl Server {
         });
 
         // Create database pool
-        let app_name = format!("{}_{}", settings.mode, env!("CARGO_PKG_NAME"));
-        let pool = settings.database.connect(&app_name).await?;
+        let pool = settings.database.connect(&settings.app_name()).await?;
         sqlx::migrate!().run(&pool).await?;
 
-        telemetry::initialize(&pool).await?;
+        // A file poller and indexer per verifier bucket, each recording
+        // processed files under its own process name
+        let mut source_join_handles = Vec::new();
+        let mut indexers = Vec::new();
+        for source in settings.sources()? {
+            telemetry::initialize(&pool, &source.process_name()).await?;
+            let file_store = FileStore::from_settings(&source.verifier).await?;
+            let start_after = source.start_after.unwrap_or(settings.start_after);
+            let (receiver, server) = file_source::continuous_source::<RewardManifest, _>()
+                .state(pool.clone())
+                .store(file_store)
+                .prefix(FileType::RewardManifest.to_string())
+                .process_name(source.process_name())
+                .lookback(LookbackBehavior::StartAfter(
+                    Utc.timestamp_opt(start_after as i64, 0).single().unwrap(),
+                ))
+                .poll_duration(settings.interval())
+                .offset(settings.interval() * 2)
+                .create()
+                .await?;
+            source_join_handles.push(server.start(shutdown_listener.clone()).await?);
 
-        let file_store = FileStore::from_settings(&settings.verifier).await?;
+            // Reward server
+            let indexer = Indexer::new(settings, &source, pool.clone()).await?;
+            indexers.push((indexer, receiver));
+        }
 
-        let (receiver, server) = file_source::continuous_source::<RewardManifest, _>()
-            .state(pool.clone())
-            .store(file_store)
-            .prefix(FileType::RewardManifest.to_string())
-            .lookback(LookbackBehavior::StartAfter(
-                Utc.timestamp_opt(settings.start_after as i64, 0)
-                    .single()
-                    .unwrap(),
-            ))
-            .poll_duration(settings.interval())
-            .offset(settings.interval() * 2)
-            .create()
-            .await?;
-        let source_join_handle = server.start(shutdown_listener.clone()).await?;
+        // Read api, served alongside the indexer if configured
+        let api_server = match &settings.api {
+            Some(api) => Some(ApiServer::new(
+                api.listen_addr()?,
+                pool.clone(),
+                api.signing_keypair()?,
+            )),
+            None => None,
+        };
+        let api_shutdown = shutdown_listener.clone();
+        let api = async move {
+            match api_server {
+                Some(api_server) => api_server.run(api_shutdown).await,
+                None => Ok(()),
+            }
+        };
 
-        // Reward server
-        let mut indexer = Indexer::new(settings, pool).await?;
+        let indexers = indexers.into_iter().map(|(mut indexer, receiver)| {
+            let shutdown = shutdown_listener.clone();
+            async move { indexer.run(shutdown, receiver).await }
+        });
 
         tokio::try_join!(
-            source_join_handle.map_err(anyhow::Error::from),
-            indexer.run(shutdown_listener, receiver),
+            future::try_join_all(source_join_handles).map_err(anyhow::Error::from),
+            future::try_join_all(indexers),
+            api,
         )?;
 
         Ok(())
@@ -0,0 +1,263 @@
+use crate::{indexer::RewardType, settings::Mode};
+use chrono::{DateTime, Duration, Utc};
+use iot_verifier::reward_share as iot_emissions;
+use mobile_verifier::reward_shares as mobile_emissions;
+use rust_decimal::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvalidManifest {
+    #[error("duplicate reward share {0}")]
+    DuplicateShare(String),
+    #[error("{pool} rewards of {total} exceed scheduled emissions of {scheduled}")]
+    ExceedsSchedule {
+        pool: &'static str,
+        total: u64,
+        scheduled: Decimal,
+    },
+}
+
+impl InvalidManifest {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::DuplicateShare(_) => "duplicate_share",
+            Self::ExceedsSchedule { .. } => "exceeds_schedule",
+        }
+    }
+}
+
+/// Sums the reward shares of a manifest by reward type and checks them
+/// against the emissions scheduled for the manifest's reward period
+pub struct ManifestValidator {
+    mode: Mode,
+    totals: HashMap<RewardType, u64>,
+    shares: HashSet<String>,
+    duplicate: Option<String>,
+}
+
+impl ManifestValidator {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            totals: HashMap::new(),
+            shares: HashSet::new(),
+            duplicate: None,
+        }
+    }
+
+    /// Record a reward share. `share_id` identifies the share within the
+    /// reward period, no two shares of a manifest may have the same id
+    pub fn add(&mut self, share_id: String, reward_type: &RewardType, amount: u64) {
+        *self.totals.entry(reward_type.clone()).or_default() += amount;
+        if !self.shares.insert(share_id.clone()) && self.duplicate.is_none() {
+            self.duplicate = Some(share_id);
+        }
+    }
+
+    pub fn validate(&self, period: &Range<DateTime<Utc>>) -> Result<(), InvalidManifest> {
+        if let Some(share_id) = &self.duplicate {
+            return Err(InvalidManifest::DuplicateShare(share_id.clone()));
+        }
+        for (pool, reward_types, scheduled) in self.pools(period) {
+            let total = match reward_types {
+                Some(reward_types) => reward_types
+                    .iter()
+                    .filter_map(|reward_type| self.totals.get(reward_type))
+                    .sum(),
+                None => self.totals.values().sum(),
+            };
+            // shares are rounded down to whole tokens so never exceed the
+            // schedule rounded up
+            if Decimal::from(total) > scheduled.ceil() {
+                return Err(InvalidManifest::ExceedsSchedule {
+                    pool,
+                    total,
+                    scheduled,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reward pools, the reward types paid from each and their scheduled
+    /// emissions. A pool without reward types covers every reward of the
+    /// manifest, including unallocated rewards
+    fn pools(
+        &self,
+        period: &Range<DateTime<Utc>>,
+    ) -> Vec<(&'static str, Option<&'static [RewardType]>, Decimal)> {
+        let duration = period.end - period.start;
+        match self.mode {
+            Mode::Iot => vec![
+                (
+                    "poc_and_dc",
+                    Some(&[RewardType::IotGateway]),
+                    iot_poc_and_dc_tokens(duration),
+                ),
+                (
+                    "operational",
+                    Some(&[RewardType::IotOperational]),
+                    iot_emissions::get_scheduled_ops_fund_tokens(duration),
+                ),
+                (
+                    "total",
+                    None,
+                    iot_poc_and_dc_tokens(duration)
+                        + iot_emissions::get_scheduled_ops_fund_tokens(duration)
+                        + iot_emissions::get_scheduled_oracle_tokens(duration),
+                ),
+            ],
+            Mode::Mobile => vec![
+                (
+                    "poc",
+                    Some(&[RewardType::MobileGateway]),
+                    mobile_emissions::get_scheduled_tokens_for_poc(duration),
+                ),
+                (
+                    "mappers",
+                    Some(&[RewardType::MobileSubscriber]),
+                    mobile_emissions::get_scheduled_tokens_for_mappers(duration),
+                ),
+                (
+                    "service_providers",
+                    Some(&[RewardType::MobileServiceProvider]),
+                    mobile_emissions::get_scheduled_tokens_for_service_providers(duration),
+                ),
+                (
+                    "total",
+                    None,
+                    mobile_emissions::get_scheduled_tokens_for_poc(duration)
+                        + mobile_emissions::get_scheduled_tokens_for_mappers(duration)
+                        + mobile_emissions::get_scheduled_tokens_for_service_providers(duration)
+                        + mobile_emissions::get_scheduled_tokens_for_oracles(duration),
+                ),
+            ],
+        }
+    }
+}
+
+/// Beacon, witness and data transfer rewards. Any data transfer remainder is
+/// redistributed to beacons and witnesses, so the pool is the same whatever
+/// the remainder
+fn iot_poc_and_dc_tokens(duration: Duration) -> Decimal {
+    let (beacon, witness) = iot_emissions::get_scheduled_poc_tokens(duration, Decimal::ZERO);
+    beacon + witness + iot_emissions::get_scheduled_dc_tokens(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn day() -> Range<DateTime<Utc>> {
+        let start = Utc.timestamp_opt(1_680_000_000, 0).unwrap();
+        start..start + Duration::hours(24)
+    }
+
+    fn tokens(amount: Decimal) -> u64 {
+        amount.floor().to_u64().unwrap()
+    }
+
+    fn reason(result: Result<(), InvalidManifest>) -> Option<String> {
+        result.err().map(|err| err.to_string())
+    }
+
+    #[test]
+    fn iot_poc_and_dc_pool_matches_verifier_with_any_dc_remainder() {
+        let duration = Duration::hours(24);
+        for remainder in [Decimal::ZERO, dec!(1_000_000), dec!(12_345_678.9)] {
+            let (beacon, witness) = iot_emissions::get_scheduled_poc_tokens(duration, remainder);
+            let dc = iot_emissions::get_scheduled_dc_tokens(duration) - remainder;
+            assert_eq!(iot_poc_and_dc_tokens(duration), beacon + witness + dc);
+        }
+    }
+
+    #[test]
+    fn mobile_pools_are_within_verifier_total_emissions() {
+        let duration = Duration::hours(24);
+        let validator = ManifestValidator::new(Mode::Mobile);
+        let (_, _, total) = validator.pools(&day()).pop().unwrap();
+        assert!(total <= mobile_emissions::get_total_scheduled_tokens(duration));
+    }
+
+    #[test]
+    fn accepts_rewards_within_schedule() {
+        let duration = Duration::hours(24);
+        let mut validator = ManifestValidator::new(Mode::Iot);
+        validator.add(
+            "gateway:a".to_string(),
+            &RewardType::IotGateway,
+            tokens(iot_poc_and_dc_tokens(duration)) - 1,
+        );
+        validator.add("gateway:b".to_string(), &RewardType::IotGateway, 1);
+        validator.add(
+            "operational".to_string(),
+            &RewardType::IotOperational,
+            tokens(iot_emissions::get_scheduled_ops_fund_tokens(duration)),
+        );
+        assert!(validator.validate(&day()).is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_shares() {
+        let mut validator = ManifestValidator::new(Mode::Mobile);
+        validator.add("gateway:a".to_string(), &RewardType::MobileGateway, 1);
+        validator.add("gateway:b".to_string(), &RewardType::MobileGateway, 1);
+        validator.add("gateway:a".to_string(), &RewardType::MobileGateway, 1);
+        let result = validator.validate(&day());
+        assert_eq!(
+            Some("duplicate_share"),
+            result.as_ref().err().map(InvalidManifest::reason)
+        );
+        assert_eq!(
+            Some("duplicate reward share gateway:a".to_string()),
+            reason(result)
+        );
+    }
+
+    #[test]
+    fn rejects_pool_over_schedule() {
+        let duration = Duration::hours(24);
+        let mut validator = ManifestValidator::new(Mode::Mobile);
+        validator.add(
+            "subscriber:a".to_string(),
+            &RewardType::MobileSubscriber,
+            tokens(mobile_emissions::get_scheduled_tokens_for_mappers(duration).ceil()) + 1,
+        );
+        let result = validator.validate(&day());
+        assert!(matches!(
+            result,
+            Err(InvalidManifest::ExceedsSchedule {
+                pool: "mappers",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn total_includes_unallocated_rewards() {
+        let duration = Duration::hours(24);
+        let mut validator = ManifestValidator::new(Mode::Iot);
+        validator.add(
+            "gateway:a".to_string(),
+            &RewardType::IotGateway,
+            tokens(iot_poc_and_dc_tokens(duration)),
+        );
+        validator.add(
+            "unallocated:0".to_string(),
+            &RewardType::IotUnallocated,
+            tokens(
+                iot_emissions::get_scheduled_ops_fund_tokens(duration)
+                    + iot_emissions::get_scheduled_oracle_tokens(duration),
+            ) + 3,
+        );
+        assert!(matches!(
+            validator.validate(&day()),
+            Err(InvalidManifest::ExceedsSchedule { pool: "total", .. })
+        ));
+    }
+}
@@ -1,5 +1,30 @@
 use crate::indexer::RewardType;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+/// The reward of a single address and reward type from a single manifest of
+/// a source
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RewardHistory {
+    pub process_name: String,
+    pub manifest: String,
+    pub address: String,
+    pub reward_type: RewardType,
+    pub amount: i64,
+    pub start_period: DateTime<Utc>,
+    pub end_period: DateTime<Utc>,
+}
+
+/// The reward total of a single address and reward type. Totals indexed
+/// before reward types were recorded have no reward type
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RewardIndex {
+    pub address: String,
+    pub reward_type: Option<RewardType>,
+    pub rewards: i64,
+    pub last_reward: Option<DateTime<Utc>>,
+}
 
 pub async fn insert<'c, E>(
     executor: E,
@@ -24,7 +49,7 @@ where
                 last_reward,
                 reward_type
             ) values ($1, $2, $3, $4)
-            on conflict(address) do update set
+            on conflict(address, reward_type) do update set
                 rewards = reward_index.rewards + EXCLUDED.rewards,
                 last_reward = EXCLUDED.last_reward
         "#,
@@ -38,3 +63,346 @@ where
 
     Ok(())
 }
+
+/// Reward totals of the address, one per reward type
+pub async fn fetch<'c, E>(executor: E, address: &str) -> Result<Vec<RewardIndex>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardIndex>(
+        r#"
+        select address, reward_type, rewards, last_reward
+        from reward_index
+        where address = $1
+        order by reward_type
+        "#,
+    )
+    .bind(address)
+    .fetch_all(executor)
+    .await
+}
+
+/// Reward totals of each of the addresses, one per address and reward type
+pub async fn fetch_batch<'c, E>(
+    executor: E,
+    addresses: &[String],
+) -> Result<Vec<RewardIndex>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardIndex>(
+        r#"
+        select address, reward_type, rewards, last_reward
+        from reward_index
+        where address = any($1)
+        order by address, reward_type
+        "#,
+    )
+    .bind(addresses)
+    .fetch_all(executor)
+    .await
+}
+
+/// Position in the list of reward totals, the last entry of the previous page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCursor {
+    pub last_reward: DateTime<Utc>,
+    pub address: String,
+    pub reward_type: String,
+}
+
+impl ListCursor {
+    pub fn after(reward: &RewardIndex) -> Option<Self> {
+        reward.last_reward.map(|last_reward| Self {
+            last_reward,
+            address: reward.address.clone(),
+            reward_type: reward
+                .reward_type
+                .as_ref()
+                .map(RewardType::to_string)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A page of reward totals ordered by most recently rewarded first, starting
+/// after the given cursor
+pub async fn list<'c, E>(
+    executor: E,
+    cursor: Option<&ListCursor>,
+    limit: i64,
+) -> Result<Vec<RewardIndex>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardIndex>(
+        r#"
+        select address, reward_type, rewards, last_reward
+        from reward_index
+        where last_reward is not null
+            and ($1::timestamptz is null
+                or (last_reward, address, coalesce(reward_type::text, '')) < ($1, $2, $3))
+        order by last_reward desc, address desc, coalesce(reward_type::text, '') desc
+        limit $4
+        "#,
+    )
+    .bind(cursor.map(|cursor| cursor.last_reward))
+    .bind(cursor.map(|cursor| cursor.address.as_str()))
+    .bind(cursor.map(|cursor| cursor.reward_type.as_str()))
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+pub async fn insert_history<'c, E>(
+    executor: E,
+    process_name: &str,
+    manifest: &str,
+    address: &str,
+    amount: u64,
+    reward_type: RewardType,
+    start_period: &DateTime<Utc>,
+    end_period: &DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    if amount == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        insert into reward_history (
+                process_name,
+                manifest,
+                address,
+                reward_type,
+                amount,
+                start_period,
+                end_period
+            ) values ($1, $2, $3, $4, $5, $6, $7)
+            on conflict(process_name, manifest, address, reward_type) do update set
+                amount = reward_history.amount + EXCLUDED.amount
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .bind(address)
+    .bind(reward_type)
+    .bind(amount as i64)
+    .bind(start_period)
+    .bind(end_period)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Rewards of the address, most recent first
+pub async fn history<'c, E>(
+    executor: E,
+    address: &str,
+    limit: i64,
+) -> Result<Vec<RewardHistory>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardHistory>(
+        r#"
+        select process_name, manifest, address, reward_type, amount, start_period, end_period
+        from reward_history
+        where address = $1
+        order by end_period desc
+        limit $2
+        "#,
+    )
+    .bind(address)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
+
+/// Rewards of the address for the epoch covering the given timestamp
+pub async fn history_at<'c, E>(
+    executor: E,
+    address: &str,
+    timestamp: &DateTime<Utc>,
+) -> Result<Vec<RewardHistory>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardHistory>(
+        r#"
+        select process_name, manifest, address, reward_type, amount, start_period, end_period
+        from reward_history
+        where address = $1 and start_period <= $2 and end_period > $2
+        "#,
+    )
+    .bind(address)
+    .bind(timestamp)
+    .fetch_all(executor)
+    .await
+}
+
+/// Every reward from the given manifest of the source
+pub async fn manifest_history<'c, E>(
+    executor: E,
+    process_name: &str,
+    manifest: &str,
+) -> Result<Vec<RewardHistory>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_as::<_, RewardHistory>(
+        r#"
+        select process_name, manifest, address, reward_type, amount, start_period, end_period
+        from reward_history
+        where process_name = $1 and manifest = $2
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .fetch_all(executor)
+    .await
+}
+
+/// Flag a manifest which failed validation and was skipped
+pub async fn insert_invalid_manifest<'c, E>(
+    executor: E,
+    process_name: &str,
+    manifest: &str,
+    reason: &str,
+    error: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        insert into invalid_manifests (process_name, manifest, reason, error)
+            values ($1, $2, $3, $4)
+            on conflict(process_name, manifest) do update set
+                reason = EXCLUDED.reason,
+                error = EXCLUDED.error,
+                inserted_at = now()
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .bind(reason)
+    .bind(error)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Subtract the rewards of the given manifest of the source from the reward
+/// totals and remove its history. Returns the number of reward totals rolled
+/// back.
+///
+/// last_reward is reset to the end of the latest remaining reward in the
+/// history. Totals with rewards that predate the history fall back to the
+/// start of the rolled back manifest, and totals left without rewards are
+/// reset to null. The manifest stays recorded as processed and is flagged as
+/// rolled back, so it is only indexed again on request
+pub async fn rollback_manifest(
+    txn: &mut Transaction<'_, Postgres>,
+    process_name: &str,
+    manifest: &str,
+) -> Result<u64, sqlx::Error> {
+    let rolled_back = sqlx::query(
+        r#"
+        update reward_index set
+            rewards = reward_index.rewards - manifest_rewards.amount,
+            last_reward = case
+                when reward_index.rewards = manifest_rewards.amount then null
+                else coalesce(
+                    (
+                        select max(end_period)
+                        from reward_history
+                        where reward_history.address = reward_index.address
+                            and reward_history.reward_type = reward_index.reward_type
+                            and (reward_history.process_name, reward_history.manifest) <> ($1, $2)
+                    ),
+                    manifest_rewards.start_period
+                )
+            end
+        from (
+            select address, reward_type, sum(amount)::bigint as amount, min(start_period) as start_period
+            from reward_history
+            where process_name = $1 and manifest = $2
+            group by address, reward_type
+        ) as manifest_rewards
+        where reward_index.address = manifest_rewards.address
+            and reward_index.reward_type = manifest_rewards.reward_type
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .execute(&mut *txn)
+    .await?
+    .rows_affected();
+
+    sqlx::query("delete from reward_history where process_name = $1 and manifest = $2")
+        .bind(process_name)
+        .bind(manifest)
+        .execute(&mut *txn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        insert into rolled_back_manifests (process_name, manifest) values ($1, $2)
+            on conflict(process_name, manifest) do update set rolled_back_at = now()
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .execute(&mut *txn)
+    .await?;
+
+    Ok(rolled_back)
+}
+
+/// Whether the given manifest of the source has been rolled back and not
+/// indexed again since
+pub async fn is_rolled_back<'c, E>(
+    executor: E,
+    process_name: &str,
+    manifest: &str,
+) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        select exists(
+            select 1 from rolled_back_manifests where process_name = $1 and manifest = $2
+        )
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .fetch_one(executor)
+    .await
+}
+
+/// Clear the rolled back flag of a manifest of the source once it has been
+/// indexed again
+pub async fn clear_rolled_back<'c, E>(
+    executor: E,
+    process_name: &str,
+    manifest: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    sqlx::query("delete from rolled_back_manifests where process_name = $1 and manifest = $2")
+        .bind(process_name)
+        .bind(manifest)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
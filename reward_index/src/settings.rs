@@ -1,7 +1,12 @@
 use chrono::Duration;
 use config::{Config, Environment, File};
 use serde::Deserialize;
-use std::{fmt, path::Path};
+use std::{
+    fmt,
+    net::{AddrParseError, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
 
 /// Mode to start the indexer in. Each mode uses different files from
 /// the verifier
@@ -30,15 +35,81 @@ pub struct Settings {
     /// Check interval in seconds. (Default is 900; 15 minutes)
     #[serde(default = "default_interval")]
     pub interval: i64,
-    /// Mode to run the server in (iot or mobile). Required
-    pub mode: Mode,
+    /// Mode to run the server in (iot or mobile). Required unless sources
+    /// are given
+    pub mode: Option<Mode>,
     pub database: db_store::Settings,
-    pub verifier: file_store::Settings,
+    /// Verifier bucket to index. Required unless sources are given
+    pub verifier: Option<file_store::Settings>,
     pub metrics: poc_metrics::Settings,
     pub operation_fund_key: Option<String>,
     pub unallocated_reward_entity_key: Option<String>,
     #[serde(default = "default_start_after")]
     pub start_after: u64,
+    /// Verifier buckets to index into the one database. When given the
+    /// top level mode and verifier are ignored
+    #[serde(default)]
+    pub sources: Vec<SourceSettings>,
+    /// Check the reward shares of each manifest against the scheduled
+    /// emissions for its reward period. Manifests which fail are skipped and
+    /// flagged in invalid_manifests. Default false
+    #[serde(default)]
+    pub validate_manifests: bool,
+    /// Read api serving reward totals. Disabled if not present
+    pub api: Option<ApiSettings>,
+}
+
+/// process name of the single source formed by the top level settings
+pub const DEFAULT_PROCESS_NAME: &str = "default";
+
+/// A verifier bucket indexed by its own poller and indexer
+#[derive(Debug, Deserialize, Clone)]
+pub struct SourceSettings {
+    /// Mode of the verifier writing the rewards (iot or mobile). Required
+    pub mode: Mode,
+    /// Name under which processed files of this source are recorded, unique
+    /// per source. Default is the mode
+    pub process_name: Option<String>,
+    pub verifier: file_store::Settings,
+    /// Operation fund key, required for iot sources. Default is the top
+    /// level operation_fund_key
+    pub operation_fund_key: Option<String>,
+    /// Default is the top level unallocated_reward_entity_key
+    pub unallocated_reward_entity_key: Option<String>,
+    /// Default is the top level start_after
+    pub start_after: Option<u64>,
+}
+
+impl SourceSettings {
+    pub fn process_name(&self) -> String {
+        self.process_name
+            .clone()
+            .unwrap_or_else(|| self.mode.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiSettings {
+    /// Listen address for http requests. Default "0.0.0.0:8080"
+    #[serde(default = "default_api_listen")]
+    pub listen: String,
+    /// File from which to load the keypair signing api responses
+    pub keypair: String,
+}
+
+impl ApiSettings {
+    pub fn listen_addr(&self) -> Result<SocketAddr, AddrParseError> {
+        SocketAddr::from_str(&self.listen)
+    }
+
+    pub fn signing_keypair(&self) -> Result<helium_crypto::Keypair, Box<helium_crypto::Error>> {
+        let data = std::fs::read(&self.keypair).map_err(helium_crypto::Error::from)?;
+        Ok(helium_crypto::Keypair::try_from(&data[..])?)
+    }
+}
+
+fn default_api_listen() -> String {
+    "0.0.0.0:8080".to_string()
 }
 
 pub fn default_start_after() -> u64 {
@@ -76,12 +147,59 @@ impl Settings {
         Duration::seconds(self.interval)
     }
 
-    pub fn operation_fund_key(&self) -> Option<String> {
-        self.operation_fund_key.clone()
+    /// The sources to index, with defaults taken from the top level
+    /// settings. Without configured sources the top level mode and verifier
+    /// form the single source, recording processed files under the
+    /// "default" process name as the indexer always has
+    pub fn sources(&self) -> Result<Vec<SourceSettings>, config::ConfigError> {
+        let sources = if self.sources.is_empty() {
+            vec![SourceSettings {
+                mode: self
+                    .mode
+                    .ok_or_else(|| config::ConfigError::NotFound("mode".to_string()))?,
+                process_name: Some(DEFAULT_PROCESS_NAME.to_string()),
+                verifier: self
+                    .verifier
+                    .clone()
+                    .ok_or_else(|| config::ConfigError::NotFound("verifier".to_string()))?,
+                operation_fund_key: None,
+                unallocated_reward_entity_key: None,
+                start_after: None,
+            }]
+        } else {
+            self.sources.clone()
+        };
+
+        let mut process_names = std::collections::HashSet::new();
+        sources
+            .into_iter()
+            .map(|source| {
+                if !process_names.insert(source.process_name()) {
+                    return Err(config::ConfigError::Message(format!(
+                        "duplicate source process name {}",
+                        source.process_name()
+                    )));
+                }
+                Ok(SourceSettings {
+                    operation_fund_key: source
+                        .operation_fund_key
+                        .or_else(|| self.operation_fund_key.clone()),
+                    unallocated_reward_entity_key: source
+                        .unallocated_reward_entity_key
+                        .or_else(|| self.unallocated_reward_entity_key.clone()),
+                    start_after: source.start_after.or(Some(self.start_after)),
+                    ..source
+                })
+            })
+            .collect()
     }
 
-    pub fn unallocated_reward_entity_key(&self) -> Option<String> {
-        self.unallocated_reward_entity_key.clone()
+    /// Name the indexer connects to the database with
+    pub fn app_name(&self) -> String {
+        match (self.sources.is_empty(), self.mode) {
+            (true, Some(mode)) => format!("{}_{}", mode, env!("CARGO_PKG_NAME")),
+            _ => env!("CARGO_PKG_NAME").to_string(),
+        }
     }
 }
 
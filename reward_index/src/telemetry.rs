@@ -1,12 +1,16 @@
+use crate::settings::DEFAULT_PROCESS_NAME;
 use chrono::{DateTime, TimeZone, Utc};
 use db_store::meta;
 use sqlx::{Pool, Postgres};
 
 const LAST_REWARD_PROCESSED_TIME: &str = "last_reward_processed_time";
 
-pub async fn initialize(db: &Pool<Postgres>) -> anyhow::Result<()> {
-    match meta::fetch(db, LAST_REWARD_PROCESSED_TIME).await {
-        Ok(timestamp) => last_reward_processed_time(db, to_datetime(timestamp)?).await,
+/// Report the last processed time of the source recorded by a previous run
+pub async fn initialize(db: &Pool<Postgres>, process_name: &str) -> anyhow::Result<()> {
+    match meta::fetch(db, &meta_key(process_name)).await {
+        Ok(timestamp) => {
+            last_reward_processed_time(db, process_name, to_datetime(timestamp)?).await
+        }
         Err(db_store::Error::NotFound(_)) => Ok(()),
         Err(err) => Err(err.into()),
     }
@@ -14,14 +18,29 @@ pub async fn initialize(db: &Pool<Postgres>) -> anyhow::Result<()> {
 
 pub async fn last_reward_processed_time(
     db: &Pool<Postgres>,
+    process_name: &str,
     datetime: DateTime<Utc>,
 ) -> anyhow::Result<()> {
-    metrics::gauge!(LAST_REWARD_PROCESSED_TIME, datetime.timestamp() as f64);
-    meta::store(db, LAST_REWARD_PROCESSED_TIME, datetime.timestamp()).await?;
+    metrics::gauge!(
+        LAST_REWARD_PROCESSED_TIME,
+        datetime.timestamp() as f64,
+        "source" => process_name.to_string()
+    );
+    meta::store(db, &meta_key(process_name), datetime.timestamp()).await?;
 
     Ok(())
 }
 
+/// the default source keeps the key it was stored under before sources were
+/// configurable
+fn meta_key(process_name: &str) -> String {
+    if process_name == DEFAULT_PROCESS_NAME {
+        LAST_REWARD_PROCESSED_TIME.to_string()
+    } else {
+        format!("{LAST_REWARD_PROCESSED_TIME}_{process_name}")
+    }
+}
+
 fn to_datetime(timestamp: i64) -> anyhow::Result<DateTime<Utc>> {
     Utc.timestamp_opt(timestamp, 0)
         .single()
@@ -0,0 +1,183 @@
+use chrono::{Duration, TimeZone, Utc};
+use helium_crypto::{KeyTag, KeyType, Keypair, Network};
+use http_api::hyper::{self, Body, Method, Request, Response, StatusCode};
+use reward_index::{api_server, indexer::RewardType, reward_index};
+use serde_json::Value;
+use sqlx::PgPool;
+
+const ADDRESS: &str = "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf";
+const OTHER_ADDRESS: &str = "11z69eJ3czc92k6snrfR9ek7g2uRWXosFbnG9v4bXgwhfUCivUo";
+
+fn keypair() -> Keypair {
+    Keypair::generate(
+        KeyTag {
+            network: Network::MainNet,
+            key_type: KeyType::Ed25519,
+        },
+        &mut rand::rngs::OsRng,
+    )
+}
+
+async fn seed(pool: &PgPool) -> anyhow::Result<()> {
+    let timestamp = Utc.timestamp_opt(1_680_000_000, 0).unwrap();
+    reward_index::insert(
+        pool,
+        ADDRESS.to_string(),
+        100,
+        RewardType::IotGateway,
+        &timestamp,
+    )
+    .await?;
+    reward_index::insert(
+        pool,
+        ADDRESS.to_string(),
+        10,
+        RewardType::IotOperational,
+        &timestamp,
+    )
+    .await?;
+    reward_index::insert(
+        pool,
+        OTHER_ADDRESS.to_string(),
+        50,
+        RewardType::IotGateway,
+        &(timestamp + Duration::days(1)),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn request(
+    pool: &PgPool,
+    keypair: &Keypair,
+    method: Method,
+    uri: &str,
+    body: Body,
+) -> Response<Body> {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(body)
+        .unwrap();
+    api_server::handle(pool, keypair, req).await
+}
+
+async fn signed_json(keypair: &Keypair, response: Response<Body>) -> Value {
+    assert_eq!(StatusCode::OK, response.status());
+    let headers = response.headers().clone();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    assert_eq!(
+        Some(keypair.public_key()),
+        http_api::verify_signed(&headers, &body).as_ref()
+    );
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[sqlx::test]
+async fn looks_up_signed_reward_totals(pool: PgPool) -> anyhow::Result<()> {
+    seed(&pool).await?;
+    let keypair = keypair();
+
+    let response = request(
+        &pool,
+        &keypair,
+        Method::GET,
+        &format!("/rewards/{ADDRESS}"),
+        Body::empty(),
+    )
+    .await;
+    let json = signed_json(&keypair, response).await;
+    assert_eq!(ADDRESS, json["address"]);
+    assert_eq!(110, json["total"]);
+    assert_eq!(2, json["rewards"].as_array().unwrap().len());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn looks_up_batch_of_addresses(pool: PgPool) -> anyhow::Result<()> {
+    seed(&pool).await?;
+    let keypair = keypair();
+
+    let body = serde_json::to_vec(&[OTHER_ADDRESS, "unknown"])?;
+    let response = request(
+        &pool,
+        &keypair,
+        Method::POST,
+        api_server::BATCH_PATH,
+        Body::from(body),
+    )
+    .await;
+    let json = signed_json(&keypair, response).await;
+    assert_eq!(OTHER_ADDRESS, json[0]["address"]);
+    assert_eq!(50, json[0]["total"]);
+    assert_eq!("unknown", json[1]["address"]);
+    assert_eq!(0, json[1]["total"]);
+
+    let too_many = serde_json::to_vec(&vec![ADDRESS; 101])?;
+    let response = request(
+        &pool,
+        &keypair,
+        Method::POST,
+        api_server::BATCH_PATH,
+        Body::from(too_many),
+    )
+    .await;
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn pages_through_reward_totals(pool: PgPool) -> anyhow::Result<()> {
+    seed(&pool).await?;
+    let keypair = keypair();
+
+    let response = request(
+        &pool,
+        &keypair,
+        Method::GET,
+        "/rewards?limit=2",
+        Body::empty(),
+    )
+    .await;
+    let first = signed_json(&keypair, response).await;
+    assert_eq!(OTHER_ADDRESS, first["rewards"][0]["address"]);
+    assert_eq!(2, first["rewards"].as_array().unwrap().len());
+    let cursor = first["cursor"].as_str().unwrap();
+
+    let response = request(
+        &pool,
+        &keypair,
+        Method::GET,
+        &format!("/rewards?limit=2&cursor={cursor}"),
+        Body::empty(),
+    )
+    .await;
+    let second = signed_json(&keypair, response).await;
+    assert_eq!(1, second["rewards"].as_array().unwrap().len());
+    assert!(second["cursor"].is_null());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rejects_bad_requests(pool: PgPool) -> anyhow::Result<()> {
+    let keypair = keypair();
+    for (method, uri, expected) in [
+        (Method::GET, "/rewards?limit=many", StatusCode::BAD_REQUEST),
+        (
+            Method::GET,
+            "/rewards?cursor=garbage",
+            StatusCode::BAD_REQUEST,
+        ),
+        (Method::GET, "/rewards/", StatusCode::NOT_FOUND),
+        (Method::GET, "/other", StatusCode::NOT_FOUND),
+        (Method::DELETE, "/rewards", StatusCode::NOT_FOUND),
+    ] {
+        let response = request(&pool, &keypair, method, uri, Body::empty()).await;
+        assert_eq!(expected, response.status(), "{uri}");
+        assert!(response.headers().get(http_api::SIGNATURE_HEADER).is_none());
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use file_store::{file_info_poller::FileInfoPollerState, FileInfo};
+use reward_index::{indexer::RewardType, reward_index};
+use sqlx::PgPool;
+
+const ADDRESS: &str = "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf";
+
+fn epoch(day: i64) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = Utc.timestamp_opt(1_680_000_000, 0).unwrap() + Duration::days(day);
+    (start, start + Duration::days(1))
+}
+
+async fn index_manifest(
+    pool: &PgPool,
+    process_name: &str,
+    manifest: &str,
+    day: i64,
+    amount: u64,
+) -> anyhow::Result<()> {
+    let (start, end) = epoch(day);
+    let mut txn = pool.begin().await?;
+    reward_index::insert_history(
+        &mut txn,
+        process_name,
+        manifest,
+        ADDRESS,
+        amount,
+        RewardType::IotGateway,
+        &start,
+        &end,
+    )
+    .await?;
+    reward_index::insert(
+        &mut txn,
+        ADDRESS.to_string(),
+        amount,
+        RewardType::IotGateway,
+        &end,
+    )
+    .await?;
+    sqlx::query(
+        r#"
+        insert into files_processed (process_name, file_name, file_type, file_timestamp, processed_at)
+        values ($1, $2, 'reward_manifest', $3, now())
+        "#,
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .bind(end)
+    .execute(&mut txn)
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+async fn rollback(pool: &PgPool, process_name: &str, manifest: &str) -> anyhow::Result<u64> {
+    let mut txn = pool.begin().await?;
+    let rolled_back = reward_index::rollback_manifest(&mut txn, process_name, manifest).await?;
+    txn.commit().await?;
+    Ok(rolled_back)
+}
+
+async fn is_processed(pool: &PgPool, process_name: &str, manifest: &str) -> anyhow::Result<bool> {
+    Ok(sqlx::query_scalar::<_, i64>(
+        "select count(*) from files_processed where process_name = $1 and file_name = $2",
+    )
+    .bind(process_name)
+    .bind(manifest)
+    .fetch_one(pool)
+    .await?
+        > 0)
+}
+
+#[sqlx::test]
+async fn rollback_resets_last_reward_and_keeps_processed_file(pool: PgPool) -> anyhow::Result<()> {
+    index_manifest(&pool, "iot", "manifest_1", 0, 100).await?;
+    index_manifest(&pool, "iot", "manifest_2", 1, 50).await?;
+
+    assert_eq!(1, rollback(&pool, "iot", "manifest_2").await?);
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(1, totals.len());
+    assert_eq!(100, totals[0].rewards);
+    assert_eq!(Some(epoch(0).1), totals[0].last_reward);
+    assert!(reward_index::manifest_history(&pool, "iot", "manifest_2")
+        .await?
+        .is_empty());
+    assert!(is_processed(&pool, "iot", "manifest_2").await?);
+    assert!(reward_index::is_rolled_back(&pool, "iot", "manifest_2").await?);
+    assert!(!reward_index::is_rolled_back(&pool, "iot", "manifest_1").await?);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rolled_back_manifest_is_not_polled_again(pool: PgPool) -> anyhow::Result<()> {
+    index_manifest(&pool, "iot", "manifest_1", 0, 100).await?;
+    rollback(&pool, "iot", "manifest_1").await?;
+
+    // the file poller skips files it finds recorded as processed
+    let file_info = FileInfo {
+        key: "manifest_1".to_string(),
+        prefix: "reward_manifest".to_string(),
+        timestamp: epoch(0).1,
+        size: 0,
+    };
+    assert!(pool.exists("iot", &file_info).await?);
+    assert!(!pool.exists("iot_2", &file_info).await?);
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(0, totals[0].rewards);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rollback_of_older_manifest_keeps_latest_reward(pool: PgPool) -> anyhow::Result<()> {
+    index_manifest(&pool, "iot", "manifest_1", 0, 100).await?;
+    index_manifest(&pool, "iot", "manifest_2", 1, 50).await?;
+
+    rollback(&pool, "iot", "manifest_1").await?;
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(50, totals[0].rewards);
+    assert_eq!(Some(epoch(1).1), totals[0].last_reward);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rollback_of_only_reward_clears_last_reward(pool: PgPool) -> anyhow::Result<()> {
+    index_manifest(&pool, "iot", "manifest_1", 0, 100).await?;
+
+    rollback(&pool, "iot", "manifest_1").await?;
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(0, totals[0].rewards);
+    assert_eq!(None, totals[0].last_reward);
+    assert!(is_processed(&pool, "iot", "manifest_1").await?);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rollback_is_keyed_by_source(pool: PgPool) -> anyhow::Result<()> {
+    index_manifest(&pool, "iot", "manifest_1", 0, 100).await?;
+    index_manifest(&pool, "iot_2", "manifest_1", 0, 50).await?;
+
+    rollback(&pool, "iot_2", "manifest_1").await?;
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(100, totals[0].rewards);
+    assert_eq!(Some(epoch(0).1), totals[0].last_reward);
+    assert_eq!(
+        1,
+        reward_index::manifest_history(&pool, "iot", "manifest_1")
+            .await?
+            .len()
+    );
+    assert!(!reward_index::is_rolled_back(&pool, "iot", "manifest_1").await?);
+    assert!(reward_index::is_rolled_back(&pool, "iot_2", "manifest_1").await?);
+
+    Ok(())
+}
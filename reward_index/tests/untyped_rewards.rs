@@ -0,0 +1,55 @@
+use chrono::{Duration, TimeZone, Utc};
+use reward_index::{
+    indexer::RewardType,
+    reward_index::{self, ListCursor},
+};
+use sqlx::PgPool;
+
+const ADDRESS: &str = "112bUuQaE7j73THS9ABShHGokm46Miip9L361FSyWv7zSYn8hZWf";
+
+#[sqlx::test]
+async fn untyped_totals_are_kept_apart_from_typed_totals(pool: PgPool) -> anyhow::Result<()> {
+    let legacy = Utc.timestamp_opt(1_680_000_000, 0).unwrap();
+    sqlx::query("insert into reward_index (address, rewards, last_reward) values ($1, 100, $2)")
+        .bind(ADDRESS)
+        .bind(legacy)
+        .execute(&pool)
+        .await?;
+    let latest = legacy + Duration::days(1);
+    reward_index::insert(
+        &pool,
+        ADDRESS.to_string(),
+        50,
+        RewardType::IotGateway,
+        &latest,
+    )
+    .await?;
+    reward_index::insert(
+        &pool,
+        ADDRESS.to_string(),
+        25,
+        RewardType::IotGateway,
+        &latest,
+    )
+    .await?;
+
+    let totals = reward_index::fetch(&pool, ADDRESS).await?;
+    assert_eq!(2, totals.len());
+    assert_eq!(Some(RewardType::IotGateway), totals[0].reward_type);
+    assert_eq!(75, totals[0].rewards);
+    assert_eq!(None, totals[1].reward_type);
+    assert_eq!(100, totals[1].rewards);
+
+    // the untyped total is paged through like any other
+    let first = reward_index::list(&pool, None, 1).await?;
+    assert_eq!(Some(RewardType::IotGateway), first[0].reward_type);
+    let cursor = ListCursor::after(&first[0]);
+    let second = reward_index::list(&pool, cursor.as_ref(), 1).await?;
+    assert_eq!(None, second[0].reward_type);
+    let cursor = ListCursor::after(&second[0]);
+    assert!(reward_index::list(&pool, cursor.as_ref(), 1)
+        .await?
+        .is_empty());
+
+    Ok(())
+}